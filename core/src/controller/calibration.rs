@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use cgmath::{ElementWise, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, warn};
+
+use crate::controller::{Address, Input};
+
+/// Manual correction of the factory calibration of a controller
+///
+/// The correction is applied on top of the readings calibrated by the factory calibration.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Override {
+    #[serde(default = "Vector3::zero")]
+    pub accelerometer_bias: Vector3<f32>,
+
+    #[serde(default = "Override::unit")]
+    pub accelerometer_scale: Vector3<f32>,
+
+    #[serde(default = "Vector3::zero")]
+    pub gyroscope_bias: Vector3<f32>,
+
+    /// Trigger value reported when released
+    #[serde(default)]
+    pub trigger_min: f32,
+
+    /// Trigger value reported when pulled completely
+    #[serde(default = "Override::full")]
+    pub trigger_max: f32,
+}
+
+impl Override {
+    fn unit() -> Vector3<f32> {
+        return Vector3::new(1.0, 1.0, 1.0);
+    }
+
+    fn full() -> f32 {
+        return 1.0;
+    }
+
+    pub fn apply(&self, input: &mut Input) {
+        input.accelerometer = (input.accelerometer - self.accelerometer_bias)
+            .mul_element_wise(self.accelerometer_scale);
+        input.gyroscope -= self.gyroscope_bias;
+
+        if self.trigger_max > self.trigger_min {
+            let trigger = &mut input.buttons.trigger.1;
+            *trigger = ((*trigger - self.trigger_min) / (self.trigger_max - self.trigger_min)).clamp(0.0, 1.0);
+        }
+    }
+}
+
+impl Default for Override {
+    fn default() -> Self {
+        return Self {
+            accelerometer_bias: Vector3::zero(),
+            accelerometer_scale: Self::unit(),
+            gyroscope_bias: Vector3::zero(),
+            trigger_min: 0.0,
+            trigger_max: Self::full(),
+        };
+    }
+}
+
+/// Calibration overrides by controller address persisted as JSON file
+///
+/// The file is loaded once - clones share the overrides, so controllers connected later get the
+/// overrides set in the meantime.
+#[derive(Debug, Clone)]
+pub struct Store {
+    path: PathBuf,
+    overrides: Arc<Mutex<HashMap<Address, Override>>>,
+
+    // Serializes the writes so the file ends up with the latest overrides
+    writing: Arc<Mutex<()>>,
+}
+
+impl Store {
+    /// Loads the store from the given file - a missing file is an empty store
+    #[instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let overrides = match std::fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file)
+                .with_context(|| format!("Failed to parse calibration: {:?}", path))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err)
+                .with_context(|| format!("Failed to open calibration: {:?}", path)),
+        };
+
+        return Ok(Self {
+            path: path.to_owned(),
+            overrides: Arc::new(Mutex::new(overrides)),
+            writing: Arc::new(Mutex::new(())),
+        });
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn save(&self) -> Result<()> {
+        let _writing = self.writing.lock().expect("Calibration writer poisoned");
+        let overrides = self.overrides.lock().expect("Calibration poisoned").clone();
+
+        let file = std::fs::File::create(&self.path)
+            .with_context(|| format!("Failed to create calibration: {:?}", self.path))?;
+
+        serde_json::to_writer_pretty(file, &overrides)
+            .with_context(|| format!("Failed to write calibration: {:?}", self.path))?;
+
+        return Ok(());
+    }
+
+    pub fn get(&self, address: Address) -> Option<Override> {
+        return self.overrides.lock().expect("Calibration poisoned").get(&address).copied();
+    }
+
+    /// Sets the override of a controller and saves the file in the background
+    pub fn set(&self, address: Address, calibration: Override) {
+        self.overrides.lock().expect("Calibration poisoned").insert(address, calibration);
+
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = store.save() {
+                warn!("Failed to save calibration: {:#}", err);
+            }
+        });
+    }
+}
+
+impl PartialEq for Store {
+    fn eq(&self, other: &Self) -> bool {
+        return Arc::ptr_eq(&self.overrides, &other.overrides);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let calibration = Override {
+            accelerometer_bias: Vector3::new(0.1, 0.0, 0.0),
+            accelerometer_scale: Vector3::new(1.0, 1.0, 0.5),
+            gyroscope_bias: Vector3::new(0.0, 0.2, 0.0),
+            trigger_min: 0.2,
+            trigger_max: 0.6,
+        };
+
+        let mut input = Input {
+            accelerometer: Vector3::new(0.1, 0.0, 2.0),
+            gyroscope: Vector3::new(0.0, 0.2, 0.0),
+            ..Input::default()
+        };
+        input.buttons.trigger.1 = 0.4;
+
+        calibration.apply(&mut input);
+
+        assert_eq!(input.accelerometer, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(input.gyroscope, Vector3::zero());
+        assert!((input.buttons.trigger.1 - 0.5).abs() < 1e-6);
+    }
+}
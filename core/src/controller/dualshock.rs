@@ -0,0 +1,466 @@
+use std::os::unix::prelude::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::task::Poll;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
+
+use crate::controller::{Address, Battery, Button, Buttons, Device, Feedback, hid, Input, Model, Reading};
+use crate::controller::frame::Frame;
+use crate::controller::hid::Bus;
+
+// Nominal resolution of both models in counts per g and per degree per second - only used if the
+// calibration of the controller can not be read
+const ACCELEROMETER_RESOLUTION: f32 = 8192.0;
+const GYROSCOPE_RESOLUTION: f32 = 16.4;
+
+// Length of the output reports sent via bluetooth including the trailing checksum
+const BLUETOOTH_OUTPUT_LENGTH: usize = 78;
+
+/// The input report fields required for a reading
+struct RawInput {
+    // Bit 4-7: square, cross, circle, triangle
+    face: u8,
+
+    // Bit 1: R1, Bit 3: R2, Bit 4: share / create, Bit 5: options
+    shoulder: u8,
+
+    // Bit 0: PS
+    system: u8,
+
+    trigger: u8,
+
+    accel: [i16; 3],
+    gyro: [i16; 3],
+
+    battery: Battery,
+}
+
+impl RawInput {
+    fn i16(data: &[u8], offset: usize) -> i16 {
+        return i16::from_le_bytes([data[offset], data[offset + 1]]);
+    }
+
+    fn vector(data: &[u8], offset: usize) -> [i16; 3] {
+        return [Self::i16(data, offset), Self::i16(data, offset + 2), Self::i16(data, offset + 4)];
+    }
+
+    /// Parses the DualShock 4 input report starting after the report header
+    fn dualshock4(data: &[u8]) -> Option<Self> {
+        if data.len() < 31 {
+            return None;
+        }
+
+        let level = data[29] & 0x0F;
+        let cable = data[29] & 0x10 != 0;
+
+        let battery = match (cable, level) {
+            (true, 11..) => Battery::Charged,
+            (true, _) => Battery::Charging,
+            (false, level) => Battery::Draining((level as f32 / 9.0).min(1.0)),
+        };
+
+        return Some(Self {
+            face: data[4],
+            shoulder: data[5],
+            system: data[6],
+            trigger: data[8],
+            gyro: Self::vector(data, 12),
+            accel: Self::vector(data, 18),
+            battery,
+        });
+    }
+
+    /// Parses the DualSense input report starting after the report header
+    fn dualsense(data: &[u8]) -> Option<Self> {
+        if data.len() < 53 {
+            return None;
+        }
+
+        let level = data[52] & 0x0F;
+        let battery = match data[52] >> 4 {
+            0x0 => Battery::Draining((level as f32 / 10.0).min(1.0)),
+            0x1 => Battery::Charging,
+            0x2 => Battery::Charged,
+            _ => Battery::Unknown,
+        };
+
+        return Some(Self {
+            face: data[7],
+            shoulder: data[8],
+            system: data[9],
+            trigger: data[5],
+            gyro: Self::vector(data, 15),
+            accel: Self::vector(data, 21),
+            battery,
+        });
+    }
+}
+
+/// Per-axis scales of the sensors as calibrated in the factory
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Calibration {
+    // Bias and g per count
+    accel: [(f32, f32); 3],
+
+    // Degrees per second per count - the firmware already removes the bias
+    gyro: [f32; 3],
+}
+
+impl Calibration {
+    const NOMINAL: Self = Self {
+        accel: [(0.0, 1.0 / ACCELEROMETER_RESOLUTION); 3],
+        gyro: [1.0 / GYROSCOPE_RESOLUTION; 3],
+    };
+
+    /// Parses the calibration feature report including the report ID
+    ///
+    /// The DualShock 4 lists the plus and minus references of the gyroscope interleaved per axis
+    /// when read via bluetooth but all plus references first otherwise.
+    fn parse(data: &[u8], interleaved: bool) -> Option<Self> {
+        if data.len() < 35 {
+            return None;
+        }
+
+        let value = |offset: usize| RawInput::i16(data, offset) as f32;
+
+        let bias = [value(1), value(3), value(5)];
+        let (plus, minus) = if interleaved {
+            ([value(7), value(11), value(15)], [value(9), value(13), value(17)])
+        } else {
+            ([value(7), value(9), value(11)], [value(13), value(15), value(17)])
+        };
+
+        // Rate in degrees per second the references have been taken at - on both directions
+        let speed = value(19) + value(21);
+
+        let mut gyro = [0.0; 3];
+        for axis in 0..3 {
+            let range = (plus[axis] - bias[axis]).abs() + (minus[axis] - bias[axis]).abs();
+            if range == 0.0 || speed <= 0.0 {
+                return None;
+            }
+            gyro[axis] = speed / range;
+        }
+
+        let mut accel = [(0.0, 0.0); 3];
+        for axis in 0..3 {
+            let (plus, minus) = (value(23 + axis * 4), value(25 + axis * 4));
+            let range = plus - minus;
+            if range <= 0.0 {
+                return None;
+            }
+            accel[axis] = (plus - range / 2.0, 2.0 / range);
+        }
+
+        return Some(Self { accel, gyro });
+    }
+
+    /// Acceleration in g
+    fn accelerometer(&self, raw: [i16; 3]) -> cgmath::Vector3<f32> {
+        let [x, y, z] = [0, 1, 2].map(|axis| (raw[axis] as f32 - self.accel[axis].0) * self.accel[axis].1);
+        return cgmath::Vector3::new(x, y, z);
+    }
+
+    /// Rotation rate in rad/s
+    fn gyroscope(&self, raw: [i16; 3]) -> cgmath::Vector3<f32> {
+        let [x, y, z] = [0, 1, 2].map(|axis| (raw[axis] as f32 * self.gyro[axis]).to_radians());
+        return cgmath::Vector3::new(x, y, z);
+    }
+}
+
+/// A DualShock 4 or DualSense controller connected via hidraw
+///
+/// The lightbar shows the LED color and both rumble motors are driven by the rumble intensity.
+/// The face buttons map to their PS Move counterparts, options and share / create to start and
+/// select, R1 to swoosh and R2 to the trigger.
+pub struct DualShock {
+    path: PathBuf,
+    bus: Bus,
+    file: File,
+    address: Address,
+    model: Model,
+
+    calibration: Calibration,
+
+    // Sequence number of the bluetooth output reports
+    seq: u8,
+}
+
+impl DualShock {
+    // Feature reports containing the address of the controller when connected via USB
+    const DUALSHOCK4_ADDRESS_REPORT: u8 = 0x12;
+    const DUALSENSE_ADDRESS_REPORT: u8 = 0x09;
+
+    // Feature reports containing the sensor calibration - reading it via bluetooth also switches the
+    // DualShock 4 to full input reports
+    const DUALSHOCK4_CALIBRATION_REPORT: u8 = 0x02;
+    const DUALSHOCK4_BLUETOOTH_CALIBRATION_REPORT: u8 = 0x05;
+    const DUALSENSE_CALIBRATION_REPORT: u8 = 0x05;
+
+    pub async fn open(device: &hid::Device) -> Result<Self> {
+        let path = device.path.clone();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .await?;
+
+        let model = device.model()
+            .filter(|model| matches!(model, Model::DualShock4 | Model::DualSense))
+            .ok_or_else(|| anyhow!("Not a DualShock 4 or DualSense controller: {:?}", path))?;
+
+        let report = match (model, device.bus) {
+            (Model::DualShock4, Bus::BLUETOOTH) => Self::feature(&file, Self::DUALSHOCK4_BLUETOOTH_CALIBRATION_REPORT, 41),
+            (Model::DualShock4, _) => Self::feature(&file, Self::DUALSHOCK4_CALIBRATION_REPORT, 37),
+            _ => Self::feature(&file, Self::DUALSENSE_CALIBRATION_REPORT, 41),
+        };
+
+        let interleaved = model == Model::DualShock4 && device.bus == Bus::BLUETOOTH;
+        let calibration = match report.map(|report| Calibration::parse(&report, interleaved)) {
+            Ok(Some(calibration)) => calibration,
+            Ok(None) => {
+                warn!("Invalid sensor calibration on {:?} - using nominal resolution", path);
+                Calibration::NOMINAL
+            }
+            Err(err) => {
+                warn!("Failed to read sensor calibration on {:?}: {}", path, err);
+                Calibration::NOMINAL
+            }
+        };
+
+        // The address is only known by the kernel if connected via bluetooth
+        let address = match device.address.parse() {
+            Ok(address) => address,
+            Err(_) => {
+                let report = match model {
+                    Model::DualShock4 => Self::feature(&file, Self::DUALSHOCK4_ADDRESS_REPORT, 16),
+                    _ => Self::feature(&file, Self::DUALSENSE_ADDRESS_REPORT, 20),
+                };
+
+                // The report holds the address in reverse byte order
+                match report {
+                    Ok(report) => Address::from([report[6], report[5], report[4], report[3], report[2], report[1]]),
+                    Err(err) => {
+                        warn!("Failed to get address from {:?}: {}", path, err);
+                        Address::derived(device.identifier())
+                    }
+                }
+            }
+        };
+
+        return Ok(Self {
+            path,
+            bus: device.bus,
+            file,
+            address,
+            model,
+            calibration,
+            seq: 0,
+        });
+    }
+
+    /// Reads a feature report of the given length including the report ID
+    fn feature(file: &File, id: u8, len: usize) -> Result<Vec<u8>> {
+        let ioc = nix::ioc!(nix::sys::ioctl::READ | nix::sys::ioctl::WRITE, 'H', 0x07, len);
+
+        let mut data = vec![0u8; len];
+        data[0] = id;
+
+        nix::errno::Errno::result(unsafe {
+            nix::libc::ioctl(file.as_raw_fd(), ioc, data.as_mut_ptr())
+        })?;
+
+        return Ok(data);
+    }
+
+    fn output(&mut self, feedback: &Feedback) -> Vec<u8> {
+        let (r, g, b) = feedback.rgb;
+
+        return match (self.model, self.bus) {
+            (Model::DualShock4, Bus::BLUETOOTH) => {
+                let mut report = vec![0u8; BLUETOOTH_OUTPUT_LENGTH];
+                report[0] = 0x11;
+                report[1] = 0xC0; // Enables HID and checksum
+                report[3] = 0x03; // Rumble and lightbar are valid
+                report[6] = feedback.rumble;
+                report[7] = feedback.rumble;
+                report[8..11].copy_from_slice(&[r, g, b]);
+                checksum(&mut report);
+                report
+            }
+
+            (Model::DualShock4, _) => {
+                let mut report = vec![0u8; 32];
+                report[0] = 0x05;
+                report[1] = 0x03; // Rumble and lightbar are valid
+                report[4] = feedback.rumble;
+                report[5] = feedback.rumble;
+                report[6..9].copy_from_slice(&[r, g, b]);
+                report
+            }
+
+            (_, bus) => {
+                // The common part is shared by both buses
+                let mut common = [0u8; 47];
+                common[0] = 0x03; // Compatible rumble
+                common[1] = 0x04; // Lightbar is valid
+                common[2] = feedback.rumble;
+                common[3] = feedback.rumble;
+                common[44..47].copy_from_slice(&[r, g, b]);
+
+                if bus == Bus::BLUETOOTH {
+                    let mut report = vec![0u8; BLUETOOTH_OUTPUT_LENGTH];
+                    report[0] = 0x31;
+                    report[1] = self.seq << 4;
+                    report[2] = 0x10;
+                    report[3..50].copy_from_slice(&common);
+                    checksum(&mut report);
+
+                    self.seq = (self.seq + 1) % 16;
+
+                    report
+                } else {
+                    let mut report = vec![0x02];
+                    report.extend_from_slice(&common);
+                    report
+                }
+            }
+        };
+    }
+}
+
+/// Appends the CRC32 of the output report seeded by the bluetooth HID header
+fn checksum(report: &mut [u8]) {
+    let len = report.len() - 4;
+
+    let crc = crc32(std::iter::once(0xA2).chain(report[..len].iter().copied()));
+    report[len..].copy_from_slice(&crc.to_le_bytes());
+}
+
+fn crc32(data: impl Iterator<Item=u8>) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+
+    return !crc;
+}
+
+#[async_trait]
+impl Device for DualShock {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return self.model;
+    }
+
+    fn bus(&self) -> Bus {
+        return self.bus;
+    }
+
+    fn path(&self) -> Option<&Path> {
+        return Some(&self.path);
+    }
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()> {
+        let report = self.output(feedback);
+        self.file.write_all(&report).await?;
+
+        return Ok(());
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        let mut buffer = [0u8; 128];
+        let len = match futures::poll!(std::pin::pin!(self.file.read(&mut buffer))) {
+            Poll::Ready(len) => len?,
+            Poll::Pending => return Ok(None),
+        };
+
+        let report = &buffer[..len];
+
+        // Reports with other IDs do not carry sensor data
+        let input = match (self.model, report.first()) {
+            (Model::DualShock4, Some(0x01)) => RawInput::dualshock4(&report[1..]),
+            (Model::DualShock4, Some(0x11)) => RawInput::dualshock4(&report[3..]),
+            (_, Some(0x01)) => RawInput::dualsense(&report[1..]),
+            (_, Some(0x31)) => RawInput::dualsense(&report[2..]),
+            _ => None,
+        };
+
+        let input = match input {
+            Some(input) => input,
+            None => return Ok(None),
+        };
+
+        fn bit(byte: u8, bit: usize) -> Button {
+            return Button::new(byte & (1 << bit) != 0);
+        }
+
+        let buttons = Buttons {
+            square: bit(input.face, 4),
+            triangle: bit(input.face, 7),
+            cross: bit(input.face, 5),
+            circle: bit(input.face, 6),
+            start: bit(input.shoulder, 5),
+            select: bit(input.shoulder, 4),
+            logo: bit(input.system, 0),
+            swoosh: bit(input.shoulder, 1),
+            trigger: (bit(input.shoulder, 3), input.trigger as f32 / (0xFF as f32)),
+        };
+
+        let accelerometer = Frame::DUALSHOCK.apply(self.calibration.accelerometer(input.accel));
+        let gyroscope = Frame::DUALSHOCK.apply(self.calibration.gyroscope(input.gyro));
+
+        return Ok(Some(Reading {
+            input: Input {
+                accelerometer,
+                gyroscope,
+                buttons,
+                ..Input::default()
+            },
+            battery: input.battery,
+            temperature: None,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789".iter().copied()), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_calibration() {
+        let mut report = vec![0x02];
+        for value in [
+            10i16, -5, 0, // Gyroscope bias
+            8800, 8810, 8790, -8780, -8795, -8800, // Gyroscope references
+            540, 540, // Reference rate
+            8200, -8180, 8192, -8192, 8300, -8100, // Accelerometer references
+        ] {
+            report.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let calibration = Calibration::parse(&report, false).unwrap();
+
+        // One g up to the plus reference and 540 degrees per second at the gyroscope reference
+        assert!((calibration.accelerometer([8200, 0, 0]).x - 1.0).abs() < 1e-3);
+        assert!((calibration.gyroscope([8790, 0, 0]).x - 540f32.to_radians()).abs() < 0.01);
+
+        assert_eq!(Calibration::parse(&report[..20], false), None);
+    }
+}
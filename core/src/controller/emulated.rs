@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::controller::{Address, Battery, Button, Buttons, Controller, feed, Input, Model, Reading};
+use crate::controller::feed::Feed;
+
+/// Emulated controllers for developing and demoing without hardware
+#[derive(clap::Args, Debug)]
+#[group(id = "emulated")]
+pub struct Config {
+    /// Uses this many emulated controllers instead of the connected hardware
+    #[arg(long = "emulate", id = "emulate", value_name = "COUNT", default_value_t = 0)]
+    pub count: usize,
+
+    /// Drives the emulated controllers by a script with lines of SECONDS CONTROLLER ACTION instead
+    /// of commands of CONTROLLER ACTION read from stdin
+    #[arg(long = "emulate-script", value_name = "FILE")]
+    pub script: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        return self.count > 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Square,
+    Triangle,
+    Cross,
+    Circle,
+    Start,
+    Select,
+    Logo,
+    Swoosh,
+    Trigger,
+
+    /// Shakes the controller
+    Jolt,
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return Ok(match s.to_lowercase().as_str() {
+            "square" => Self::Square,
+            "triangle" => Self::Triangle,
+            "cross" => Self::Cross,
+            "circle" => Self::Circle,
+            "start" => Self::Start,
+            "select" => Self::Select,
+            "logo" => Self::Logo,
+            "swoosh" => Self::Swoosh,
+            "trigger" => Self::Trigger,
+            "jolt" => Self::Jolt,
+            _ => return Err(anyhow!("Unknown action: {}", s)),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+    pub controller: usize,
+    pub action: Action,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (controller, action) = s.trim().split_once(char::is_whitespace)
+            .ok_or_else(|| anyhow!("Expected CONTROLLER ACTION: {}", s))?;
+
+        return Ok(Self {
+            controller: controller.parse()?,
+            action: action.trim().parse()?,
+        });
+    }
+}
+
+/// The state of an emulated controller
+struct Emulated {
+    feed: Feed,
+
+    // The time until which an action is active
+    active: HashMap<Action, Instant>,
+}
+
+impl Emulated {
+    // Time an action stays active
+    const DURATION: Duration = Duration::from_millis(200);
+
+    fn apply(&mut self, action: Action, now: Instant) {
+        self.active.insert(action, now + Self::DURATION);
+    }
+
+    fn reading(&self, now: Instant) -> Reading {
+        let active = |action| self.active.get(&action).map_or(false, |until| now < *until);
+
+        let trigger = active(Action::Trigger);
+
+        return Reading {
+            input: Input {
+                accelerometer: if active(Action::Jolt) {
+                    cgmath::Vector3::new(1.5, 0.0, 1.0)
+                } else {
+                    cgmath::Vector3::unit_z()
+                },
+                buttons: Buttons {
+                    square: Button::new(active(Action::Square)),
+                    triangle: Button::new(active(Action::Triangle)),
+                    cross: Button::new(active(Action::Cross)),
+                    circle: Button::new(active(Action::Circle)),
+                    start: Button::new(active(Action::Start)),
+                    select: Button::new(active(Action::Select)),
+                    logo: Button::new(active(Action::Logo)),
+                    swoosh: Button::new(active(Action::Swoosh)),
+                    trigger: (Button::new(trigger), if trigger { 1.0 } else { 0.0 }),
+                },
+                ..Input::default()
+            },
+            battery: Battery::Draining(1.0),
+            temperature: None,
+        };
+    }
+}
+
+/// Creates the emulated controllers and starts driving them
+pub fn spawn(config: Config) -> Result<Vec<Controller>> {
+    let mut emulated = Vec::new();
+    let mut controllers = Vec::new();
+
+    for i in 0..config.count {
+        let address: Address = format!("02:00:00:00:EE:{:02X}", i).parse()?;
+        let (feed, device) = feed::channel(address, Model::CECH_ZCM1);
+
+        emulated.push(Emulated {
+            feed,
+            active: HashMap::new(),
+        });
+        controllers.push(Controller::new(Box::new(device)));
+    }
+
+    let commands = match config.script {
+        Some(ref path) => script(path)?,
+        None => stdin(),
+    };
+
+    tokio::spawn(drive(emulated, commands));
+
+    return Ok(controllers);
+}
+
+/// Plays the commands of the script at their time relative to the start
+fn script(path: &Path) -> Result<mpsc::UnboundedReceiver<Command>> {
+    let file = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read script: {:?}", path))?;
+
+    let mut script = file.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (time, command) = line.split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("Expected SECONDS CONTROLLER ACTION: {}", line))?;
+            return Ok((Duration::from_secs_f64(time.parse()?), command.parse()?));
+        })
+        .collect::<Result<Vec<(Duration, Command)>>>()
+        .with_context(|| format!("Failed to parse script: {:?}", path))?;
+
+    script.sort_by_key(|(time, _)| *time);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now();
+        for (time, command) in script {
+            tokio::time::sleep_until(start + time).await;
+            if tx.send(command).is_err() {
+                return;
+            }
+        }
+
+        info!("Emulation script finished");
+    });
+
+    return Ok(rx);
+}
+
+/// Reads commands from stdin - an empty line repeats the last command
+fn stdin() -> mpsc::UnboundedReceiver<Command> {
+    info!("Emulated controllers are controlled by lines of CONTROLLER ACTION on stdin");
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut last = None;
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+
+            let command = if line.trim().is_empty() {
+                last
+            } else {
+                match line.parse::<Command>() {
+                    Ok(command) => Some(command),
+                    Err(err) => {
+                        warn!("Invalid command: {:#}", err);
+                        None
+                    }
+                }
+            };
+
+            if let Some(command) = command {
+                if tx.send(command).is_err() {
+                    return;
+                }
+                last = Some(command);
+            }
+        }
+    });
+
+    return rx;
+}
+
+async fn drive(mut emulated: Vec<Emulated>, mut commands: mpsc::UnboundedReceiver<Command>) {
+    let mut interval = tokio::time::interval(Duration::from_millis(10));
+    loop {
+        interval.tick().await;
+        let now = Instant::now();
+
+        while let Ok(command) = commands.try_recv() {
+            match emulated.get_mut(command.controller) {
+                Some(controller) => controller.apply(command.action, now),
+                None => warn!("No such emulated controller: {}", command.controller),
+            }
+        }
+
+        for controller in emulated.iter() {
+            controller.feed.replace(controller.reading(now));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let command: Command = "2  Trigger".parse().unwrap();
+        assert_eq!(command.controller, 2);
+        assert_eq!(command.action, Action::Trigger);
+
+        assert!("2".parse::<Command>().is_err());
+        assert!("2 dance".parse::<Command>().is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::ErrorKind;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ::evdev::{AbsoluteAxisType, BusType, Key};
+use futures::{Stream, TryStreamExt};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use udev::EventType;
+
+use crate::controller::{Address, Battery, Button, Buttons, Device, Feedback, Input, Model, Reading};
+use crate::controller::hid::{Bus, Monitor};
+
+#[derive(clap::Args, Debug)]
+#[group(id = "evdev")]
+pub struct Config {
+    /// Uses gamepads connected via evdev as controllers in addition to the PS Move controllers
+    #[arg(long)]
+    pub gamepads: bool,
+}
+
+/// An ordinary gamepad mapped onto a controller
+///
+/// Gamepads have no accelerometer. Instead, the deflection of the left stick is reported as
+/// acceleration so moving the stick has the same effect as moving a controller. The LEDs and the
+/// rumble are ignored.
+pub struct Gamepad {
+    path: PathBuf,
+    device: ::evdev::Device,
+
+    address: Address,
+    bus: Bus,
+}
+
+impl Gamepad {
+    pub fn open(path: &Path) -> Result<Self> {
+        let device = ::evdev::Device::open(path)
+            .with_context(|| format!("Failed to open gamepad: {:?}", path))?;
+
+        // Events are fetched without waiting for them
+        fcntl(device.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+
+        // Bluetooth gamepads report their address as unique name - others get one derived from
+        // their serial or the port they are plugged into
+        let address = match device.unique_name().filter(|name| !name.is_empty()) {
+            Some(name) => name.parse().unwrap_or_else(|_| Address::derived(name)),
+            None => match device.physical_path() {
+                Some(phys) => Address::derived(phys),
+                None => Address::derived(&path.to_string_lossy()),
+            },
+        };
+
+        let bus = match device.input_id().bus_type() {
+            BusType::BUS_USB => Bus::USB,
+            BusType::BUS_BLUETOOTH => Bus::BLUETOOTH,
+            _ => Bus::UNKNOWN,
+        };
+
+        return Ok(Self {
+            path: path.to_owned(),
+            device,
+            address,
+            bus,
+        });
+    }
+
+    /// The position of an axis scaled to [-1, 1]
+    fn axis(&self, axis: AbsoluteAxisType) -> f32 {
+        let info = match self.device.cached_state().abs_vals() {
+            Some(abs) => abs[axis.0 as usize],
+            None => return 0.0,
+        };
+
+        if info.maximum <= info.minimum {
+            return 0.0;
+        }
+
+        return (info.value - info.minimum) as f32 / (info.maximum - info.minimum) as f32 * 2.0 - 1.0;
+    }
+
+    fn key(&self, key: Key) -> Button {
+        return Button::new(self.device.cached_state().key_vals()
+            .map_or(false, |keys| keys.contains(key)));
+    }
+}
+
+#[async_trait]
+impl Device for Gamepad {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return Model::Gamepad;
+    }
+
+    fn bus(&self) -> Bus {
+        return self.bus;
+    }
+
+    fn path(&self) -> Option<&Path> {
+        return Some(&self.path);
+    }
+
+    async fn send(&mut self, _feedback: &Feedback) -> Result<()> {
+        return Ok(());
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        // Events update the cached state of the device
+        match self.device.fetch_events() {
+            Ok(events) => events.for_each(drop),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+
+        // Pushing the stick forward lowers its value but points along y of the common frame
+        let stick = cgmath::Vector3::new(self.axis(AbsoluteAxisType::ABS_X), -self.axis(AbsoluteAxisType::ABS_Y), 0.0);
+
+        // The analog trigger rests at the minimum
+        let trigger = (self.axis(AbsoluteAxisType::ABS_RZ) + 1.0) / 2.0;
+
+        let buttons = Buttons {
+            square: self.key(Key::BTN_WEST),
+            triangle: self.key(Key::BTN_NORTH),
+            cross: self.key(Key::BTN_SOUTH),
+            circle: self.key(Key::BTN_EAST),
+            start: self.key(Key::BTN_START),
+            select: self.key(Key::BTN_SELECT),
+            logo: self.key(Key::BTN_MODE),
+            swoosh: self.key(Key::BTN_TR),
+            trigger: (self.key(Key::BTN_TR2), trigger),
+        };
+
+        return Ok(Some(Reading {
+            input: Input {
+                accelerometer: cgmath::Vector3::unit_z() + stick,
+                buttons,
+                ..Input::default()
+            },
+            battery: Battery::Unknown,
+            temperature: None,
+        }));
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Added(PathBuf),
+    Removed(PathBuf),
+}
+
+pub type Events = impl Stream<Item=Result<Event>>;
+
+/// The event device node of a gamepad - `None` for other input devices
+fn gamepad(device: &udev::Device) -> Option<PathBuf> {
+    if !device.sysname().to_string_lossy().starts_with("event") {
+        return None;
+    }
+
+    if device.property_value("ID_INPUT_JOYSTICK").map_or(true, |value| value != "1") {
+        return None;
+    }
+
+    return device.devnode().map(Path::to_path_buf);
+}
+
+/// Lists the gamepads connected already and watches for gamepads being connected and disconnected
+pub fn monitor() -> Result<(Vec<PathBuf>, Events)> {
+    let mut enumerator = udev::Enumerator::new()?;
+    enumerator.match_subsystem("input")?;
+
+    let initial = enumerator.scan_devices()?
+        .filter_map(|device| gamepad(&device))
+        .collect();
+
+    let monitor = Monitor::new("input")?
+        .try_filter_map(|event| async move {
+            let path = match gamepad(&event) {
+                Some(path) => path,
+                None => return Ok(None),
+            };
+
+            return Ok(match event.event_type() {
+                EventType::Add => Some(Event::Added(path)),
+                EventType::Remove => Some(Event::Removed(path)),
+                _ => None,
+            });
+        });
+
+    return Ok((initial, Box::pin(monitor)));
+}
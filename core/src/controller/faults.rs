@@ -0,0 +1,186 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use tracing::{info, warn};
+
+use crate::controller::{Address, Device, Extension, Feedback, Firmware, Model, Reading};
+use crate::controller::hid::Bus;
+use crate::controller::pairing::Outcome;
+
+/// Developer mode injecting faults into the controller communication to exercise error handling
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "faults")]
+pub struct Config {
+    /// Adds a random latency up to this duration to every controller update
+    #[arg(long = "fault-latency", value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub latency: Option<Duration>,
+
+    /// Probability of an input report getting lost
+    #[arg(long = "fault-drop", value_name = "PROBABILITY", default_value_t = 0.0)]
+    pub drop: f32,
+
+    /// Average number of random disconnects per controller and minute
+    #[arg(long = "fault-disconnects", value_name = "RATE", default_value_t = 0.0)]
+    pub disconnects: f32,
+
+    /// Time a controller stays silent after a disconnect
+    #[arg(long = "fault-disconnect-duration", value_name = "DURATION", default_value = "2s", value_parser = humantime::parse_duration)]
+    pub disconnect_duration: Duration,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        return self.latency.is_some() || self.drop > 0.0 || self.disconnects > 0.0;
+    }
+}
+
+/// A device wrapper injecting faults into the communication with the wrapped device
+///
+/// A disconnected controller goes silent - nothing is received and feedback gets lost - until it
+/// reconnects. Disconnects are timed by the frames passed to the device and all decisions are
+/// drawn from a seeded generator to make them reproducible.
+pub struct Faulty {
+    device: Box<dyn Device>,
+    config: Config,
+
+    rng: StdRng,
+
+    // The remaining time until the controller reconnects
+    disconnected: Option<Duration>,
+}
+
+impl Faulty {
+    pub fn new(device: Box<dyn Device>, config: Config, seed: u64) -> Self {
+        return Self {
+            device,
+            config,
+            rng: StdRng::seed_from_u64(seed),
+            disconnected: None,
+        };
+    }
+
+    /// Delays the communication by a random latency
+    async fn delay(&mut self) {
+        if let Some(latency) = self.config.latency {
+            tokio::time::sleep(latency.mul_f32(self.rng.gen())).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Device for Faulty {
+    fn address(&self) -> Address {
+        return self.device.address();
+    }
+
+    fn model(&self) -> Model {
+        return self.device.model();
+    }
+
+    fn bus(&self) -> Bus {
+        return self.device.bus();
+    }
+
+    fn path(&self) -> Option<&Path> {
+        return self.device.path();
+    }
+
+    fn firmware(&self) -> Option<Firmware> {
+        return self.device.firmware();
+    }
+
+    fn extension(&self) -> Option<Extension> {
+        return self.device.extension();
+    }
+
+    fn advance(&mut self, duration: Duration) {
+        self.device.advance(duration);
+
+        match self.disconnected {
+            Some(remaining) if remaining > duration => {
+                self.disconnected = Some(remaining - duration);
+            }
+            Some(_) => {
+                info!("Reconnecting controller {} after injected disconnect", self.device.address().as_string());
+                self.disconnected = None;
+            }
+            None => if self.rng.gen::<f32>() < self.config.disconnects * duration.as_secs_f32() / 60.0 {
+                warn!("Injecting disconnect of controller {}", self.device.address().as_string());
+                self.disconnected = Some(self.config.disconnect_duration);
+            }
+        }
+    }
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()> {
+        self.delay().await;
+
+        if self.disconnected.is_some() {
+            return Ok(());
+        }
+
+        return self.device.send(feedback).await;
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        self.delay().await;
+
+        // Readings keep piling up at the device and are lost while disconnected
+        let reading = self.device.receive().await?;
+        if self.disconnected.is_some() || self.rng.gen::<f32>() < self.config.drop {
+            return Ok(None);
+        }
+
+        return Ok(reading);
+    }
+
+    async fn pair(&mut self, host: Address) -> Result<Outcome> {
+        return self.device.pair(host).await;
+    }
+
+    async fn set_led_pwm(&mut self, frequency: u32) -> Result<()> {
+        return self.device.set_led_pwm(frequency).await;
+    }
+
+    async fn power_off(&mut self) -> Result<()> {
+        return self.device.power_off().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::{Battery, feed, Input};
+
+    fn reading() -> Reading {
+        return Reading {
+            input: Input::default(),
+            battery: Battery::Charged,
+            temperature: None,
+        };
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let (feed, device) = feed::channel("02:00:00:00:00:01".parse().unwrap(), Model::CECH_ZCM1);
+        let mut faulty = Faulty::new(Box::new(device), Config {
+            latency: None,
+            drop: 0.0,
+            disconnects: 1000.0,
+            disconnect_duration: Duration::from_secs(2),
+        }, 42);
+
+        // Disconnected controllers go silent instead of failing
+        faulty.advance(Duration::from_millis(100));
+        feed.push(reading());
+        assert!(faulty.receive().await.unwrap().is_none());
+
+        faulty.advance(Duration::from_secs(2));
+        feed.push(reading());
+        assert!(faulty.receive().await.unwrap().is_some());
+    }
+}
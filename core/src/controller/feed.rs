@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::controller::{Address, Device, Feedback, Model, Reading};
+use crate::controller::hid::Bus;
+
+/// Creates a controller device which receives its input from software instead of hardware
+pub fn channel(address: Address, model: Model) -> (Feed, FeedDevice) {
+    let readings = Arc::new(Mutex::new(VecDeque::new()));
+    let feedback = Arc::new(Mutex::new(Feedback::default()));
+
+    return (Feed {
+        readings: readings.clone(),
+        feedback: feedback.clone(),
+    }, FeedDevice {
+        address,
+        model,
+        readings,
+        feedback,
+    });
+}
+
+/// The sending side of a fed controller
+#[derive(Clone)]
+pub struct Feed {
+    readings: Arc<Mutex<VecDeque<Reading>>>,
+    feedback: Arc<Mutex<Feedback>>,
+}
+
+impl Feed {
+    /// Queues a reading to be received by the controller
+    pub fn push(&self, reading: Reading) {
+        self.readings.lock().expect("Lock poisoned").push_back(reading);
+    }
+
+    /// Replaces all queued readings so the controller does not lag behind if it is updated slower
+    pub fn replace(&self, reading: Reading) {
+        let mut readings = self.readings.lock().expect("Lock poisoned");
+        readings.clear();
+        readings.push_back(reading);
+    }
+
+    /// The feedback last sent to the controller
+    pub fn feedback(&self) -> Feedback {
+        return self.feedback.lock().expect("Lock poisoned").clone();
+    }
+}
+
+pub struct FeedDevice {
+    address: Address,
+    model: Model,
+
+    readings: Arc<Mutex<VecDeque<Reading>>>,
+    feedback: Arc<Mutex<Feedback>>,
+}
+
+#[async_trait]
+impl Device for FeedDevice {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return self.model;
+    }
+
+    fn bus(&self) -> Bus {
+        return Bus::UNKNOWN;
+    }
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()> {
+        *self.feedback.lock().expect("Lock poisoned") = feedback.clone();
+        return Ok(());
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        return Ok(self.readings.lock().expect("Lock poisoned").pop_front());
+    }
+}
@@ -0,0 +1,125 @@
+use cgmath::{InnerSpace, Vector3};
+
+use crate::controller::Input;
+
+/// Filtering of sensor noise which is especially strong on worn-out controllers
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "filter")]
+pub struct Config {
+    /// Weight of the previous sensor value for low-pass filtering - 0 disables smoothing
+    #[arg(long = "filter-smoothing", value_name = "WEIGHT", default_value_t = 0.0, value_parser = weight)]
+    pub smoothing: f32,
+
+    /// Rotation rates in rad/s and deviations from gravity in g below this are ignored
+    #[arg(long = "filter-dead-zone", value_name = "THRESHOLD", default_value_t = 0.0)]
+    pub dead_zone: f32,
+
+    /// Changes of a sensor axis below this are suppressed
+    #[arg(long = "filter-jitter", value_name = "THRESHOLD", default_value_t = 0.0)]
+    pub jitter: f32,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        return self.smoothing > 0.0 || self.dead_zone > 0.0 || self.jitter > 0.0;
+    }
+}
+
+/// Parses a smoothing weight - the filter would diverge or stop following the sensors otherwise
+fn weight(value: &str) -> Result<f32, String> {
+    let weight: f32 = value.parse().map_err(|err| format!("{}", err))?;
+    if !(0.0..1.0).contains(&weight) {
+        return Err(format!("weight must be at least 0 and less than 1: {}", weight));
+    }
+
+    return Ok(weight);
+}
+
+/// Filters the accelerometer and gyroscope readings of a controller
+#[derive(Debug, Clone)]
+pub struct Filter {
+    config: Config,
+
+    // The last filtered values
+    accelerometer: Option<Vector3<f32>>,
+    gyroscope: Option<Vector3<f32>>,
+}
+
+impl Filter {
+    pub fn new(config: Config) -> Self {
+        return Self {
+            config,
+            accelerometer: None,
+            gyroscope: None,
+        };
+    }
+
+    pub fn apply(&mut self, input: &mut Input) {
+        input.accelerometer = Self::filter(&self.config, &mut self.accelerometer, input.accelerometer);
+        input.gyroscope = Self::filter(&self.config, &mut self.gyroscope, input.gyroscope);
+
+        // A controller at rest measures exactly gravity
+        let magnitude = input.accelerometer.magnitude();
+        if magnitude > 0.0 && (magnitude - 1.0).abs() < self.config.dead_zone {
+            input.accelerometer /= magnitude;
+        }
+
+        input.gyroscope = input.gyroscope.map(|v| if v.abs() < self.config.dead_zone { 0.0 } else { v });
+    }
+
+    fn filter(config: &Config, last: &mut Option<Vector3<f32>>, value: Vector3<f32>) -> Vector3<f32> {
+        let filtered = match *last {
+            Some(last) => {
+                let smoothed = last * config.smoothing + value * (1.0 - config.smoothing);
+                Vector3::new(
+                    if (smoothed.x - last.x).abs() < config.jitter { last.x } else { smoothed.x },
+                    if (smoothed.y - last.y).abs() < config.jitter { last.y } else { smoothed.y },
+                    if (smoothed.z - last.z).abs() < config.jitter { last.z } else { smoothed.z },
+                )
+            }
+            None => value,
+        };
+
+        *last = Some(filtered);
+        return filtered;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Zero;
+
+    use super::*;
+
+    #[test]
+    fn test_suppresses_noise() {
+        let mut filter = Filter::new(Config {
+            smoothing: 0.5,
+            dead_zone: 0.05,
+            jitter: 0.01,
+        });
+
+        let noise = [0.0, 0.02, -0.03, 0.01, 0.03, -0.02];
+        for n in noise {
+            let mut input = Input {
+                accelerometer: Vector3::new(n, 0.0, 1.0 + n),
+                gyroscope: Vector3::new(n, -n, 0.0),
+                ..Input::default()
+            };
+
+            filter.apply(&mut input);
+
+            assert!((input.accelerometer.magnitude() - 1.0).abs() < 1e-6, "{:?}", input.accelerometer);
+            assert_eq!(input.gyroscope, Vector3::zero());
+        }
+    }
+
+    #[test]
+    fn test_weight() {
+        assert_eq!(weight("0.5"), Ok(0.5));
+        assert_eq!(weight("0"), Ok(0.0));
+        assert!(weight("1").is_err());
+        assert!(weight("-0.5").is_err());
+        assert!(weight("NaN").is_err());
+    }
+}
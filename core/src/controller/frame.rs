@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use cgmath::Vector3;
+
+/// Maps the sensor axes of a controller into the common frame of `Input`
+///
+/// Looking at the buttons with the sphere pointing away, the x axis points to the right, the y axis
+/// along the long axis towards the sphere and the z axis out of the face with the buttons. A
+/// controller lying still with the buttons facing upwards measures an acceleration of 1g along z.
+///
+/// The PS Move models mount their sensors in different orientations. Instead of relying on a table
+/// per model, the mapping is derived from the factory calibration which contains accelerometer
+/// readings with each axis of the common frame pointing upwards. Gamepads count the long axis as
+/// pointing away from the player. Their sensors are mounted the same for all devices of a model, so
+/// fixed mappings are used for them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    // The sensor axis and its sign for each axis of the common frame
+    axes: [(usize, f32); 3],
+}
+
+impl Frame {
+    pub const IDENTITY: Self = Self {
+        axes: [(0, 1.0), (1, 1.0), (2, 1.0)],
+    };
+
+    /// DualShock 4 and DualSense sensors - x to the right, y out of the face and z towards the player
+    pub const DUALSHOCK: Self = Self {
+        axes: [(0, 1.0), (2, -1.0), (1, 1.0)],
+    };
+
+    /// Left Joy-Con sensors - x towards the top, y to the left and z out of the face
+    pub const JOYCON_LEFT: Self = Self {
+        axes: [(1, -1.0), (0, 1.0), (2, 1.0)],
+    };
+
+    /// Right Joy-Con sensors - mounted like the left one but turned around the x axis
+    pub const JOYCON_RIGHT: Self = Self {
+        axes: [(1, 1.0), (0, 1.0), (2, -1.0)],
+    };
+
+    /// Derives the frame from the accelerometer readings of the factory calibration
+    ///
+    /// The readings are taken with +z, -x, -z, +x, +y and -y of the common frame pointing upwards.
+    pub fn detect(accel: &[Vector3<f32>; 6]) -> Result<Self> {
+        let mut axes = [(0, 0.0); 3];
+
+        for (axis, (up, down)) in [(3, 1), (4, 5), (0, 2)].into_iter().enumerate() {
+            let delta = accel[up] - accel[down];
+
+            // The sensor axis seeing the largest change while turning the controller around
+            let sensor = (0..3)
+                .max_by(|a, b| delta[*a].abs().total_cmp(&delta[*b].abs()))
+                .expect("Vector has three axes");
+
+            if axes[..axis].iter().any(|(other, _)| *other == sensor) {
+                bail!("Calibration readings do not match any sensor orientation");
+            }
+
+            axes[axis] = (sensor, delta[sensor].signum());
+        }
+
+        return Ok(Self { axes });
+    }
+
+    /// Converts a reading of the sensor into the common frame
+    pub fn apply(&self, raw: Vector3<f32>) -> Vector3<f32> {
+        let [x, y, z] = self.axes.map(|(sensor, sign)| raw[sensor] * sign);
+        return Vector3::new(x, y, z);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cgmath::InnerSpace;
+
+    // Calibration readings in the order of the factory calibration with the sensor mounted as the
+    // common frame (ZCM1) and rotated by 90° around the z axis (ZCM2)
+    const ZCM1: [[f32; 3]; 6] = [
+        [0.01, -0.02, 0.26],
+        [-0.24, 0.01, 0.02],
+        [0.02, -0.01, -0.25],
+        [0.27, 0.02, 0.01],
+        [0.01, 0.25, -0.02],
+        [-0.01, -0.26, 0.01],
+    ];
+
+    const ZCM2: [[f32; 3]; 6] = [
+        [0.02, 0.01, 0.51],
+        [0.01, 0.49, -0.01],
+        [-0.01, 0.02, -0.50],
+        [0.01, -0.51, 0.02],
+        [0.50, 0.01, 0.01],
+        [-0.49, -0.02, 0.02],
+    ];
+
+    #[test]
+    fn test_detect() {
+        let zcm1 = Frame::detect(&ZCM1.map(Vector3::from)).unwrap();
+        assert_eq!(zcm1, Frame::IDENTITY);
+
+        let zcm2 = Frame::detect(&ZCM2.map(Vector3::from)).unwrap();
+        assert_eq!(zcm2.apply(Vector3::new(0.0, -1.0, 0.0)), Vector3::unit_x());
+        assert_eq!(zcm2.apply(Vector3::new(1.0, 0.0, 0.0)), Vector3::unit_y());
+        assert_eq!(zcm2.apply(Vector3::new(0.0, 0.0, 1.0)), Vector3::unit_z());
+
+        // Both report the same orientation in the common frame
+        for i in 0..6 {
+            let a = zcm1.apply(Vector3::from(ZCM1[i]));
+            let b = zcm2.apply(Vector3::from(ZCM2[i]));
+
+            let axis = |v: Vector3<f32>| (0..3).max_by(|x, y| v[*x].abs().total_cmp(&v[*y].abs())).unwrap();
+            assert_eq!(axis(a), axis(b));
+            assert_eq!(a[axis(a)].signum(), b[axis(b)].signum());
+        }
+
+        assert!(Frame::detect(&[Vector3::unit_z(); 6]).is_err());
+    }
+
+    #[test]
+    fn test_fixed() {
+        // Raw readings of the gamepads lying flat with their face upwards
+        let flat = [
+            (Frame::DUALSHOCK, Vector3::unit_y()),
+            (Frame::JOYCON_LEFT, Vector3::unit_z()),
+            (Frame::JOYCON_RIGHT, -Vector3::unit_z()),
+        ];
+
+        for (frame, raw) in flat {
+            assert_eq!(frame.apply(raw), Vector3::unit_z());
+
+            // Mirroring would turn the gyroscope readings around
+            let x = frame.apply(Vector3::unit_x());
+            let y = frame.apply(Vector3::unit_y());
+            let z = frame.apply(Vector3::unit_z());
+            assert_eq!(x.cross(y).dot(z), 1.0);
+        }
+    }
+}
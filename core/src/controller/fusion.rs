@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use cgmath::{InnerSpace, One, Quaternion, Rotation, Vector3, Zero};
+
+/// Estimates the orientation of a controller from its sensors
+///
+/// This is a complementary filter in the style of Mahony: the gyroscope is integrated to track fast
+/// rotations while the drift of the integration is corrected towards the direction of gravity
+/// measured by the accelerometer and, if available, the magnetic north measured by the
+/// magnetometer. The magnetometer only corrects the heading as the tilt is given by gravity.
+#[derive(Debug, Clone)]
+pub struct Fusion {
+    orientation: Quaternion<f32>,
+}
+
+impl Fusion {
+    // Strength of the drift correction
+    const GAIN: f32 = 1.0;
+
+    pub fn new() -> Self {
+        return Self {
+            orientation: Quaternion::one(),
+        };
+    }
+
+    /// The rotation from the controller frame into the world frame with Z pointing upwards
+    pub fn orientation(&self) -> Quaternion<f32> {
+        return self.orientation;
+    }
+
+    /// Integrates a reading with accelerometer in g, gyroscope in rad/s and magnetometer in any unit
+    pub fn update(&mut self,
+                  accelerometer: Vector3<f32>,
+                  gyroscope: Vector3<f32>,
+                  magnetometer: Option<Vector3<f32>>,
+                  duration: Duration) {
+        let q = self.orientation;
+        let mut error = Vector3::zero();
+
+        // Gravity is the only acceleration if the controller is not moved
+        if !accelerometer.is_zero() {
+            let expected = q.conjugate().rotate_vector(Vector3::unit_z());
+            error += accelerometer.normalize().cross(expected);
+        }
+
+        // Only the horizontal part of the magnetic field indicates the heading, which is corrected by
+        // a rotation around the vertical axis of the world
+        if let Some(magnetometer) = magnetometer.filter(|m| !m.is_zero()) {
+            let world = q.rotate_vector(magnetometer);
+            let horizontal = (world.x * world.x + world.y * world.y).sqrt();
+            if horizontal > f32::EPSILON {
+                error += q.conjugate().rotate_vector(Vector3::new(0.0, 0.0, -world.y / horizontal));
+            }
+        }
+
+        let rate = gyroscope + error * Self::GAIN;
+
+        let q = q + q * Quaternion::from_sv(0.0, rate) * (0.5 * duration.as_secs_f32());
+        self.orientation = q.normalize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_follows_gravity() {
+        let mut fusion = Fusion::new();
+
+        // Controller lying on its side
+        for _ in 0..2000 {
+            fusion.update(Vector3::unit_x(), Vector3::zero(), None, Duration::from_millis(10));
+        }
+
+        let up = fusion.orientation().rotate_vector(Vector3::unit_x());
+        assert!((up - Vector3::unit_z()).magnitude() < 0.01, "{:?}", up);
+    }
+
+    #[test]
+    fn test_corrects_heading_only() {
+        let mut fusion = Fusion::new();
+
+        // Level controller pointing east with a steep magnetic field
+        for _ in 0..2000 {
+            fusion.update(Vector3::unit_z(), Vector3::zero(), Some(Vector3::new(0.0, 1.0, -2.0)), Duration::from_millis(10));
+        }
+
+        let forward = fusion.orientation().rotate_vector(Vector3::unit_y());
+        assert!((forward - Vector3::unit_x()).magnitude() < 0.01, "{:?}", forward);
+
+        let up = fusion.orientation().rotate_vector(Vector3::unit_z());
+        assert!((up - Vector3::unit_z()).magnitude() < 0.01, "{:?}", up);
+    }
+
+    #[test]
+    fn test_integrates_rotation() {
+        let mut fusion = Fusion::new();
+
+        // Quarter turn around the vertical axis within one second
+        for _ in 0..100 {
+            fusion.update(Vector3::unit_z(), Vector3::new(0.0, 0.0, std::f32::consts::FRAC_PI_2), None, Duration::from_millis(10));
+        }
+
+        let forward = fusion.orientation().rotate_vector(Vector3::unit_x());
+        assert!((forward - Vector3::unit_y()).magnitude() < 0.01, "{:?}", forward);
+    }
+}
@@ -7,14 +7,22 @@ use futures::{Stream, TryStreamExt};
 use tokio::io::unix::AsyncFd;
 use udev::EventType;
 
+use crate::controller::Model;
+
 const BUS_USB: u8 = 0x03;
 const BUS_BLUETOOTH: u8 = 0x05;
 
-const PSMOVE_VID: u16 = 0x054c;
+const SONY_VID: u16 = 0x054c;
+const NINTENDO_VID: u16 = 0x057e;
 const PSMOVE_PS3_PID: u16 = 0x03d5;
 const PSMOVE_PS4_PID: u16 = 0x0c5e;
+const DUALSHOCK4_V1_PID: u16 = 0x05c4;
+const DUALSHOCK4_V2_PID: u16 = 0x09cc;
+const DUALSENSE_PID: u16 = 0x0ce6;
+const JOYCON_LEFT_PID: u16 = 0x2006;
+const JOYCON_RIGHT_PID: u16 = 0x2007;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Bus {
     USB,
     BLUETOOTH,
@@ -34,6 +42,31 @@ pub struct Device {
     pub controller: String,
 }
 
+impl Device {
+    /// The controller model derived from the product id - `None` if not a controller
+    pub fn model(&self) -> Option<Model> {
+        return match (self.vendor_id, self.product_id) {
+            (SONY_VID, PSMOVE_PS3_PID) => Some(Model::CECH_ZCM1),
+            (SONY_VID, PSMOVE_PS4_PID) => Some(Model::CECH_ZCM2),
+            (SONY_VID, DUALSHOCK4_V1_PID | DUALSHOCK4_V2_PID) => Some(Model::DualShock4),
+            (SONY_VID, DUALSENSE_PID) => Some(Model::DualSense),
+            (NINTENDO_VID, JOYCON_LEFT_PID) => Some(Model::JoyConLeft),
+            (NINTENDO_VID, JOYCON_RIGHT_PID) => Some(Model::JoyConRight),
+            _ => None,
+        };
+    }
+
+    /// An identifier which stays the same across reconnects - the unique name if reported, the
+    /// physical port otherwise
+    pub fn identifier(&self) -> &str {
+        if self.address.is_empty() {
+            return &self.controller;
+        }
+
+        return &self.address;
+    }
+}
+
 #[derive(Debug)]
 pub enum Event {
     Added(Device),
@@ -43,7 +76,7 @@ pub enum Event {
 pub type Events = impl Stream<Item=Result<Event>>;
 
 fn is_controller(device: &Device) -> bool {
-    return device.vendor_id == PSMOVE_VID && (device.product_id == PSMOVE_PS3_PID || device.product_id == PSMOVE_PS4_PID);
+    return device.model().is_some();
 }
 
 pub fn monitor() -> Result<(Vec<Device>, Events)> {
@@ -69,7 +102,7 @@ pub fn monitor() -> Result<(Vec<Device>, Events)> {
         }).filter_map(Result::transpose)
         .collect::<Result<_>>()?;
 
-    let monitor = Monitor::new()?
+    let monitor = Monitor::new("hidraw")?
         .try_filter_map(|event| async move {
             let path = if let Some(path) = event.devnode() {
                 path.to_path_buf()
@@ -102,14 +135,15 @@ pub fn monitor() -> Result<(Vec<Device>, Events)> {
     return Ok((initial, Box::pin(monitor)));
 }
 
-struct Monitor {
+/// Watches udev for devices of a subsystem being added or removed
+pub(crate) struct Monitor {
     fd: AsyncFd<udev::MonitorSocket>,
 }
 
 impl Monitor {
-    pub fn new() -> Result<Self> {
+    pub fn new(subsystem: &str) -> Result<Self> {
         let socket = udev::MonitorBuilder::new()?
-            .match_subsystem("hidraw")?
+            .match_subsystem(subsystem)?
             .listen()?;
 
         return Ok(Self {
@@ -0,0 +1,297 @@
+use std::path::{Path, PathBuf};
+use std::task::Poll;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::controller::{Address, Battery, Button, Buttons, Device, Feedback, hid, Input, Model, Reading};
+use crate::controller::frame::Frame;
+use crate::controller::hid::Bus;
+
+// Sensor resolution in the default ranges of +-8g and +-2000 degree per second
+const ACCELEROMETER_RESOLUTION: f32 = 4096.0;
+const GYROSCOPE_RESOLUTION: f32 = 16.384;
+
+const REPORT_SUBCOMMAND: u8 = 0x01;
+const REPORT_RUMBLE: u8 = 0x10;
+const REPORT_INPUT: u8 = 0x30;
+
+const SUBCOMMAND_INPUT_MODE: u8 = 0x03;
+const SUBCOMMAND_HCI_STATE: u8 = 0x06;
+const SUBCOMMAND_PLAYER_LEDS: u8 = 0x30;
+const SUBCOMMAND_IMU: u8 = 0x40;
+const SUBCOMMAND_VIBRATION: u8 = 0x48;
+
+// The frequency used for both bands of the HD rumble
+const RUMBLE_FREQUENCY: f32 = 160.0;
+
+/// Encodes the rumble of a single Joy-Con as HD rumble data
+fn rumble(amplitude: f32) -> [u8; 4] {
+    if amplitude <= 0.0 {
+        return [0x00, 0x01, 0x40, 0x40];
+    }
+
+    let amplitude = amplitude.min(1.0);
+
+    let freq = ((RUMBLE_FREQUENCY / 10.0).log2() * 32.0).round() as u16;
+    let hf = (freq - 0x60) * 4;
+    let lf = (freq - 0x40) as u8;
+
+    let amp = if amplitude > 0.23 {
+        ((amplitude * 8.7).log2() * 32.0).round()
+    } else if amplitude > 0.12 {
+        ((amplitude * 17.0).log2() * 16.0).round()
+    } else {
+        ((amplitude.log2() * 32.0 - 96.0) / (4.0 - 2.0 * amplitude)).round()
+    }.max(0.0) as u8;
+
+    let hf_amp = amp * 2;
+    let lf_amp = amp / 2 + 0x40;
+
+    return [
+        (hf & 0xFF) as u8,
+        hf_amp + (hf >> 8) as u8,
+        lf,
+        lf_amp,
+    ];
+}
+
+/// Approximates a color by the pattern of the four player LEDs
+///
+/// Each primary color and each mix of two of them lights a distinct pattern while white lights all
+/// LEDs.
+fn player_leds((r, g, b): (u8, u8, u8)) -> u8 {
+    let max = r.max(g).max(b);
+    if max == 0 {
+        return 0b0000;
+    }
+
+    // Channels with at least half the brightness of the strongest one count as lit
+    let lit = |c: u8| c as u16 * 2 >= max as u16;
+
+    return match (lit(r), lit(g), lit(b)) {
+        (true, false, false) => 0b0001,
+        (true, true, false) => 0b0011,
+        (false, true, false) => 0b0010,
+        (false, true, true) => 0b0110,
+        (false, false, true) => 0b0100,
+        (true, false, true) => 0b1001,
+        _ => 0b1111,
+    };
+}
+
+/// A Nintendo Joy-Con connected via hidraw
+///
+/// The Joy-Con is held sideways so the buttons next to the stick map to the face buttons by their
+/// position. Plus and minus map to start and select, home and capture to the logo, the shoulder
+/// button to swoosh and the rear shoulder button to the trigger.
+pub struct JoyCon {
+    path: PathBuf,
+    bus: Bus,
+    file: File,
+    address: Address,
+    model: Model,
+
+    // Packet counter of the output reports
+    counter: u8,
+
+    // Pattern of the player LEDs last sent to the device
+    leds: Option<u8>,
+}
+
+impl JoyCon {
+    pub async fn open(device: &hid::Device) -> Result<Self> {
+        let path = device.path.clone();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .await?;
+
+        let model = device.model()
+            .filter(|model| matches!(model, Model::JoyConLeft | Model::JoyConRight))
+            .ok_or_else(|| anyhow!("Not a Joy-Con: {:?}", path))?;
+
+        let address = device.address.parse()
+            .unwrap_or_else(|_| Address::derived(device.identifier()));
+
+        let mut joycon = Self {
+            path,
+            bus: device.bus,
+            file,
+            address,
+            model,
+            counter: 0,
+            leds: None,
+        };
+
+        // Switch to full input reports containing the IMU readings and enable vibration
+        joycon.subcommand(SUBCOMMAND_IMU, &[0x01]).await?;
+        joycon.subcommand(SUBCOMMAND_VIBRATION, &[0x01]).await?;
+        joycon.subcommand(SUBCOMMAND_INPUT_MODE, &[REPORT_INPUT]).await?;
+
+        return Ok(joycon);
+    }
+
+    async fn output(&mut self, id: u8, rumble: u8, subcommand: Option<(u8, &[u8])>) -> Result<()> {
+        let rumble = self::rumble(rumble as f32 / (0xFF as f32));
+
+        let mut report = vec![id, self.counter];
+        report.extend_from_slice(&rumble);
+        report.extend_from_slice(&rumble);
+
+        if let Some((subcommand, args)) = subcommand {
+            report.push(subcommand);
+            report.extend_from_slice(args);
+        }
+
+        self.counter = (self.counter + 1) % 16;
+
+        self.file.write_all(&report).await?;
+
+        return Ok(());
+    }
+
+    async fn subcommand(&mut self, subcommand: u8, args: &[u8]) -> Result<()> {
+        self.output(REPORT_SUBCOMMAND, 0, Some((subcommand, args))).await?;
+
+        // The Joy-Con drops subcommands sent in fast succession
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        return Ok(());
+    }
+}
+
+#[async_trait]
+impl Device for JoyCon {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return self.model;
+    }
+
+    fn bus(&self) -> Bus {
+        return self.bus;
+    }
+
+    fn path(&self) -> Option<&Path> {
+        return Some(&self.path);
+    }
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()> {
+        // Changing the LEDs requires a subcommand which is only sent on changes
+        let leds = player_leds(feedback.rgb);
+        if self.leds != Some(leds) {
+            self.output(REPORT_SUBCOMMAND, feedback.rumble, Some((SUBCOMMAND_PLAYER_LEDS, &[leds]))).await?;
+            self.leds = Some(leds);
+        } else {
+            self.output(REPORT_RUMBLE, feedback.rumble, None).await?;
+        }
+
+        return Ok(());
+    }
+
+    async fn power_off(&mut self) -> Result<()> {
+        // Disconnects and enters sleep mode
+        return self.subcommand(SUBCOMMAND_HCI_STATE, &[0x00]).await;
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        let mut buffer = [0u8; 64];
+        let len = match futures::poll!(std::pin::pin!(self.file.read(&mut buffer))) {
+            Poll::Ready(len) => len?,
+            Poll::Pending => return Ok(None),
+        };
+
+        // Replies to subcommands and simple input reports do not carry sensor data
+        let report = &buffer[..len];
+        if report.len() < 49 || report[0] != REPORT_INPUT {
+            return Ok(None);
+        }
+
+        fn bit(byte: u8, bit: usize) -> Button {
+            return Button::new(byte & (1 << bit) != 0);
+        }
+
+        let (right, shared, left) = (report[3], report[4], report[5]);
+
+        let buttons = match self.model {
+            Model::JoyConLeft => Buttons {
+                square: bit(left, 0),
+                triangle: bit(left, 3),
+                cross: bit(left, 2),
+                circle: bit(left, 1),
+                start: bit(shared, 1),
+                select: bit(shared, 0),
+                logo: bit(shared, 5),
+                swoosh: bit(left, 6),
+                trigger: (bit(left, 7), if left & 0x80 != 0 { 1.0 } else { 0.0 }),
+            },
+            _ => Buttons {
+                square: bit(right, 1),
+                triangle: bit(right, 3),
+                cross: bit(right, 0),
+                circle: bit(right, 2),
+                start: bit(shared, 1),
+                select: bit(shared, 0),
+                logo: bit(shared, 4),
+                swoosh: bit(right, 6),
+                trigger: (bit(right, 7), if right & 0x80 != 0 { 1.0 } else { 0.0 }),
+            },
+        };
+
+        // The report contains three IMU samples - only the latest one is used
+        let sample = &report[37..49];
+        let value = |i: usize| i16::from_le_bytes([sample[i * 2], sample[i * 2 + 1]]) as f32;
+
+        let frame = match self.model {
+            Model::JoyConLeft => Frame::JOYCON_LEFT,
+            _ => Frame::JOYCON_RIGHT,
+        };
+
+        let accelerometer = frame.apply(cgmath::Vector3::new(value(0), value(1), value(2)) / ACCELEROMETER_RESOLUTION);
+        let gyroscope = frame.apply(cgmath::Vector3::new(value(3), value(4), value(5)) / GYROSCOPE_RESOLUTION * std::f32::consts::PI / 180.0);
+
+        // Level in the upper three bits and charging in the next one
+        let battery = match (report[2] >> 4 & 0x01 != 0, report[2] >> 5) {
+            (true, 4) => Battery::Charged,
+            (true, _) => Battery::Charging,
+            (false, level) => Battery::Draining(level as f32 / 4.0),
+        };
+
+        return Ok(Some(Reading {
+            input: Input {
+                accelerometer,
+                gyroscope,
+                buttons,
+                ..Input::default()
+            },
+            battery,
+            temperature: None,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rumble() {
+        assert_eq!(rumble(0.0), [0x00, 0x01, 0x40, 0x40]);
+        assert_eq!(rumble(1.0), [0x80, 0xC8, 0x40, 0x72]);
+    }
+
+    #[test]
+    fn test_player_leds() {
+        assert_eq!(player_leds((0, 0, 0)), 0b0000);
+        assert_eq!(player_leds((200, 0, 10)), 0b0001);
+        assert_eq!(player_leds((255, 255, 255)), 0b1111);
+    }
+}
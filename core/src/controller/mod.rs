@@ -0,0 +1,771 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use cgmath::{One, Quaternion, Zero};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{info, instrument, warn};
+
+use calibration::Override;
+use dualshock::DualShock;
+use faults::Faulty;
+use filter::Filter;
+use fusion::Fusion;
+use hid::Bus;
+use joycon::JoyCon;
+use pairing::Outcome;
+pub use proto::Address;
+use psmove::PSMove;
+use rate::Rate;
+use recording::Recorder;
+use trigger::Curve;
+
+mod proto;
+pub mod calibration;
+pub mod dualshock;
+pub mod emulated;
+pub mod evdev;
+pub mod faults;
+pub mod feed;
+pub mod filter;
+pub mod frame;
+pub mod fusion;
+pub mod hid;
+pub mod joycon;
+pub mod pairing;
+pub mod playback;
+pub mod psmove;
+pub mod quirks;
+pub mod rate;
+pub mod recording;
+pub mod signal;
+pub mod trigger;
+
+/// The state of a button including the changes since the last update of the controller
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Button {
+    down: bool,
+
+    // Whether the state has changed with the latest reading
+    changed: bool,
+}
+
+impl Button {
+    pub fn new(down: bool) -> Self {
+        return Self {
+            down,
+            changed: false,
+        };
+    }
+
+    pub fn is_down(&self) -> bool {
+        return self.down;
+    }
+
+    /// Whether the button has been pressed since the last update
+    pub fn pressed(&self) -> bool {
+        return self.down && self.changed;
+    }
+
+    /// Whether the button has been released since the last update
+    pub fn released(&self) -> bool {
+        return !self.down && self.changed;
+    }
+
+    fn track(&mut self, previous: Button) {
+        self.changed = self.down != previous.down;
+    }
+}
+
+// Only the state is recorded as the changes are tracked by the controller
+impl Serialize for Button {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_bool(self.down)
+    }
+}
+
+impl<'de> Deserialize<'de> for Button {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+        return Ok(Self::new(bool::deserialize(deserializer)?));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Buttons {
+    pub square: Button,
+    pub triangle: Button,
+    pub cross: Button,
+    pub circle: Button,
+
+    pub start: Button,
+    pub select: Button,
+
+    pub logo: Button,
+    pub swoosh: Button,
+
+    pub trigger: (Button, f32),
+}
+
+impl Buttons {
+    pub fn all(&self) -> [&Button; 9] {
+        return [
+            &self.square,
+            &self.triangle,
+            &self.cross,
+            &self.circle,
+            &self.start,
+            &self.select,
+            &self.logo,
+            &self.swoosh,
+            &self.trigger.0,
+        ];
+    }
+
+    fn all_mut(&mut self) -> [&mut Button; 9] {
+        return [
+            &mut self.square,
+            &mut self.triangle,
+            &mut self.cross,
+            &mut self.circle,
+            &mut self.start,
+            &mut self.select,
+            &mut self.logo,
+            &mut self.swoosh,
+            &mut self.trigger.0,
+        ];
+    }
+
+    /// Detects the changes of all buttons since the previous reading
+    fn track(&mut self, mut previous: Buttons) {
+        for (button, previous) in self.all_mut().into_iter().zip(previous.all_mut()) {
+            button.track(*previous);
+        }
+    }
+
+    /// Forgets about the changes once they have been seen for an update
+    fn settle(&mut self) {
+        for button in self.all_mut() {
+            button.changed = false;
+        }
+    }
+}
+
+struct Limiter<T> {
+    value: T,
+    dirty: bool,
+    updated: Instant,
+
+    min_update: Duration,
+    max_update: Duration,
+}
+
+impl<T> Limiter<T>
+    where
+        T: PartialEq,
+{
+    pub fn new(initial: T, rate: Rate) -> Self {
+        return Self {
+            value: initial,
+            dirty: true,
+            updated: Instant::now(),
+            min_update: rate.min,
+            max_update: rate.max,
+        };
+    }
+
+    pub fn set(&mut self, value: T) {
+        if value != self.value {
+            self.value = value;
+            self.dirty = true;
+        }
+    }
+
+    /// Time since the value has been sent last
+    pub(self) fn age(&self) -> Duration {
+        return self.updated.elapsed();
+    }
+
+    /// Checks if value has change but rate limit will not exceed or if value needs resending
+    pub(self) fn pending(&self) -> bool {
+        let elapsed = self.updated.elapsed();
+        return (elapsed >= self.min_update && self.dirty) || elapsed >= self.max_update;
+    }
+
+    /// Returns the value if it is due to be sent - a sent value is only sent again once it changes
+    /// or the maximal interval has passed
+    pub(self) fn update(&mut self) -> Option<&T> {
+        if self.pending() {
+            self.updated = Instant::now();
+            self.dirty = false;
+            return Some(&self.value);
+        }
+
+        return None;
+    }
+}
+
+impl<T> Deref for Limiter<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        return &self.value;
+    }
+}
+
+impl<T> Default for Limiter<T>
+    where
+        T: PartialEq + Default
+{
+    fn default() -> Self {
+        return Self::new(T::default(), Rate::default());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feedback {
+    pub rgb: (u8, u8, u8),
+    pub rumble: u8,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        return Self {
+            rgb: (0, 0, 0),
+            rumble: 0,
+        };
+    }
+
+    pub fn led_color(mut self, color: impl Into<(u8, u8, u8)>) -> Self {
+        let color = color.into();
+        self.rgb = color;
+        return self;
+    }
+
+    pub fn led_off(mut self) -> Self {
+        self.rgb = (0, 0, 0);
+        return self;
+    }
+
+    pub fn rumble(mut self, rumble: u8) -> Self {
+        self.rumble = rumble;
+        return self;
+    }
+}
+
+impl Default for Feedback {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Input {
+    /// Acceleration in g in the common frame described by `frame::Frame`
+    pub accelerometer: cgmath::Vector3<f32>,
+
+    /// Rotation rate in rad/s around the axes of the common frame
+    pub gyroscope: cgmath::Vector3<f32>,
+
+    /// Calibrated magnetic field - zero if the controller has no magnetometer
+    #[serde(default = "cgmath::Vector3::zero")]
+    pub magnetometer: cgmath::Vector3<f32>,
+
+    /// Estimated rotation from the controller frame into the world frame
+    #[serde(default = "Quaternion::one")]
+    pub orientation: Quaternion<f32>,
+
+    pub buttons: Buttons,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        return Self {
+            accelerometer: cgmath::Vector3::zero(),
+            gyroscope: cgmath::Vector3::zero(),
+            magnetometer: cgmath::Vector3::zero(),
+            orientation: Quaternion::one(),
+            buttons: Default::default(),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Battery {
+    Draining(f32),
+
+    Charging,
+    Charged,
+
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    CECH_ZCM1,
+    CECH_ZCM2,
+
+    /// Generic gamepad connected via evdev
+    Gamepad,
+
+    DualShock4,
+    DualSense,
+
+    JoyConLeft,
+    JoyConRight,
+}
+
+impl Model {
+    pub fn is_psmove(&self) -> bool {
+        return matches!(self, Self::CECH_ZCM1 | Self::CECH_ZCM2);
+    }
+}
+
+/// The firmware running on a controller
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Firmware {
+    pub version: u16,
+    pub revision: u16,
+}
+
+/// A device attached to the extension port of a PS Move controller
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Extension {
+    Sharpshooter,
+    RacingWheel,
+
+    /// An extension with an unknown device id
+    Other(u16),
+
+    /// An extension which did not answer the device info request
+    Unidentified,
+}
+
+impl Extension {
+    pub fn from_id(id: u16) -> Self {
+        return match id {
+            0x8081 => Self::Sharpshooter,
+            0x8101 => Self::RacingWheel,
+            id => Self::Other(id),
+        };
+    }
+}
+
+/// A change of the extension attached to a controller
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionEvent {
+    Attached(Extension),
+    Detached(Extension),
+}
+
+/// The state reported by a device
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub input: Input,
+    pub battery: Battery,
+
+    /// Temperature in degrees Celsius if reported by the device
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// The backend driving a controller
+#[async_trait]
+pub trait Device: Send {
+    /// The bluetooth address of the controller
+    fn address(&self) -> Address;
+
+    fn model(&self) -> Model;
+
+    /// The bus the device is connected to
+    fn bus(&self) -> Bus;
+
+    /// Path of the device node if the device is backed by one
+    fn path(&self) -> Option<&Path> {
+        return None;
+    }
+
+    /// The firmware of the controller if the device reports it
+    fn firmware(&self) -> Option<Firmware> {
+        return None;
+    }
+
+    /// The device attached to the extension port as of the latest reading
+    fn extension(&self) -> Option<Extension> {
+        return None;
+    }
+
+    /// Advances devices simulating behaviour over time by the time of a frame
+    fn advance(&mut self, _duration: Duration) {}
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()>;
+
+    /// Returns the latest reading if one is available without waiting for it
+    async fn receive(&mut self) -> Result<Option<Reading>>;
+
+    /// Writes the host address to the controller so it connects to the host via bluetooth
+    async fn pair(&mut self, _host: Address) -> Result<Outcome> {
+        bail!("Pairing not supported by device");
+    }
+
+    /// Sets the PWM frequency of the LEDs in Hz
+    async fn set_led_pwm(&mut self, _frequency: u32) -> Result<()> {
+        bail!("LED PWM frequency not supported by device");
+    }
+
+    /// Turns the controller off - controllers connected via bluetooth power off when disconnected
+    async fn power_off(&mut self) -> Result<()> {
+        if self.bus() != Bus::BLUETOOTH {
+            bail!("Power off not supported by device");
+        }
+
+        return signal::disconnect(self.address());
+    }
+}
+
+pub struct Controller {
+    device: Box<dyn Device>,
+
+    input: Input,
+    battery: Battery,
+
+    // The latest input as received from the device
+    raw: Input,
+
+    temperature: Option<f32>,
+    overheated: bool,
+
+    signal: Option<i8>,
+
+    extension: Option<Extension>,
+
+    // The change of the extension with the latest update
+    extension_event: Option<ExtensionEvent>,
+
+    calibration: Override,
+    filter: Option<Filter>,
+    trigger: Curve,
+
+    fusion: Fusion,
+    received: Option<Instant>,
+
+    feedback: Limiter<Feedback>,
+    rate: Rate,
+
+    // Skips sending feedback on the next update
+    hold: bool,
+
+    recorder: Option<Recorder>,
+}
+
+impl Controller {
+    const OVERHEAT_TEMPERATURE: f32 = 45.0;
+
+    pub fn new(device: Box<dyn Device>) -> Self {
+        return Self {
+            device,
+            input: Default::default(),
+            battery: Battery::Unknown,
+            raw: Default::default(),
+            temperature: None,
+            overheated: false,
+            signal: None,
+            extension: None,
+            extension_event: None,
+            calibration: Override::default(),
+            filter: None,
+            trigger: Curve::Linear,
+            fusion: Fusion::new(),
+            received: None,
+            feedback: Default::default(),
+            rate: Rate::default(),
+            hold: false,
+            recorder: None,
+        };
+    }
+
+    /// Opens a controller connected via hidraw
+    pub async fn open(device: &hid::Device) -> Result<Self> {
+        return Ok(match device.model() {
+            Some(Model::DualShock4 | Model::DualSense) => Self::new(Box::new(DualShock::open(device).await?)),
+            Some(Model::JoyConLeft | Model::JoyConRight) => Self::new(Box::new(JoyCon::open(device).await?)),
+            _ => Self::new(Box::new(PSMove::open(device).await?)),
+        });
+    }
+
+    /// Injects faults into the communication with the device if enabled - faults are drawn from
+    /// a generator with the given seed
+    pub fn inject(mut self, faults: &faults::Config, seed: u64) -> Self {
+        if faults.enabled() {
+            self.device = Box::new(Faulty::new(self.device, faults.clone(), seed));
+        }
+
+        return self;
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        return self.device.path();
+    }
+
+    pub fn bus(&self) -> Bus {
+        return self.device.bus();
+    }
+
+    /// Controllers connected via USB are only charging as their input is unreliable
+    pub fn charging_only(&self) -> bool {
+        return self.bus() == Bus::USB && self.model().is_psmove();
+    }
+
+    pub fn serial(&self) -> Address {
+        return self.device.address();
+    }
+
+    pub fn model(&self) -> Model {
+        return self.device.model();
+    }
+
+    pub fn firmware(&self) -> Option<Firmware> {
+        return self.device.firmware();
+    }
+
+    /// A unique id of that controller
+    pub fn id(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(self.device.address().as_ref());
+        return hasher.finish();
+    }
+
+    #[instrument(level = "trace", name = "Controller::update", skip(self))]
+    pub async fn update(&mut self, duration: Duration) -> Result<()> {
+        self.device.advance(duration);
+
+        // Send updates if required
+        if !std::mem::take(&mut self.hold) {
+            if let Some(feedback) = self.feedback.update() {
+                self.device.send(feedback).await?;
+            }
+        }
+
+        self.input.buttons.settle();
+        self.extension_event = None;
+
+        if let Some(reading) = self.device.receive().await? {
+            if let Some(ref mut recorder) = self.recorder {
+                if let Err(err) = recorder.record(&reading) {
+                    warn!("Stopped recording controller {}: {:#}", self.device.address().as_string(), err);
+                    self.recorder = None;
+                }
+            }
+
+            let now = Instant::now();
+            let elapsed = self.received.map_or(Duration::ZERO, |received| now - received);
+            self.received = Some(now);
+
+            self.raw = reading.input.clone();
+
+            let mut input = reading.input;
+            input.buttons.track(self.input.buttons.clone());
+            self.calibration.apply(&mut input);
+
+            if let Some(ref mut filter) = self.filter {
+                filter.apply(&mut input);
+            }
+
+            input.buttons.trigger.1 = self.trigger.apply(input.buttons.trigger.1);
+
+            self.fusion.update(input.accelerometer, input.gyroscope, Some(input.magnetometer), elapsed);
+
+            self.input = Input {
+                orientation: self.fusion.orientation(),
+                ..input
+            };
+            self.battery = reading.battery;
+            self.temperature = reading.temperature;
+
+            // Warn once per overheating with some hysteresis to avoid flapping
+            match self.temperature {
+                Some(temperature) if temperature >= Self::OVERHEAT_TEMPERATURE && !self.overheated => {
+                    warn!("Controller {} is overheating: {:.1}°C", self.serial().as_string(), temperature);
+                    self.overheated = true;
+                }
+                Some(temperature) if temperature < Self::OVERHEAT_TEMPERATURE - 5.0 => {
+                    self.overheated = false;
+                }
+                _ => {}
+            }
+
+            let extension = self.device.extension();
+            if extension != self.extension {
+                self.extension_event = match (self.extension, extension) {
+                    (_, Some(attached)) => Some(ExtensionEvent::Attached(attached)),
+                    (Some(detached), None) => Some(ExtensionEvent::Detached(detached)),
+                    (None, None) => None,
+                };
+
+                info!("Extension of controller {} changed: {:?}", self.serial().as_string(), self.extension_event);
+                self.extension = extension;
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn input(&self) -> &Input {
+        return &self.input;
+    }
+
+    /// The latest input before applying calibration, filtering and sensor fusion
+    pub fn raw_input(&self) -> &Input {
+        return &self.raw;
+    }
+
+    pub fn battery(&self) -> Battery {
+        return self.battery;
+    }
+
+    /// Temperature in degrees Celsius if reported by the controller
+    pub fn temperature(&self) -> Option<f32> {
+        return self.temperature;
+    }
+
+    /// Whether the controller has exceeded the safe operating temperature
+    pub fn overheated(&self) -> bool {
+        return self.overheated;
+    }
+
+    /// Filters sensor noise of the following readings if enabled
+    pub fn set_filter(&mut self, config: filter::Config) {
+        self.filter = config.enabled().then(|| Filter::new(config));
+    }
+
+    pub fn set_trigger_curve(&mut self, curve: Curve) {
+        self.trigger = curve;
+    }
+
+    /// Strength of the bluetooth link in dB as last probed
+    pub fn signal(&self) -> Option<i8> {
+        return self.signal;
+    }
+
+    pub fn set_signal(&mut self, signal: Option<i8>) {
+        self.signal = signal;
+    }
+
+    /// The device attached to the extension port
+    pub fn extension(&self) -> Option<Extension> {
+        return self.extension;
+    }
+
+    /// Whether an extension has been attached or detached with the last update
+    pub fn extension_event(&self) -> Option<ExtensionEvent> {
+        return self.extension_event;
+    }
+
+    pub fn feedback(&mut self, feedback: Feedback) {
+        self.feedback.set(feedback);
+    }
+
+    pub fn calibration(&self) -> Override {
+        return self.calibration;
+    }
+
+    /// Sets the correction applied on top of the factory calibration
+    pub fn set_calibration(&mut self, calibration: Override) {
+        self.calibration = calibration;
+    }
+
+    /// Sets the limits for sending feedback to the device
+    pub fn set_rate(&mut self, rate: Rate) {
+        self.rate = rate;
+        self.feedback.min_update = rate.min;
+        self.feedback.max_update = rate.max;
+    }
+
+    /// Records the input received from the device from now on
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Whether the next update will send feedback to the device
+    pub fn feedback_pending(&self) -> bool {
+        return !self.hold && self.feedback.pending();
+    }
+
+    /// Time since feedback has been sent to the device last
+    pub fn feedback_age(&self) -> Duration {
+        return self.feedback.age();
+    }
+
+    /// Defers sending pending feedback to a later update
+    pub fn hold_feedback(&mut self) {
+        self.hold = true;
+    }
+
+    /// Sets the minimal interval between feedback updates - resets to the rate if `None`
+    pub fn feedback_interval(&mut self, interval: Option<Duration>) {
+        self.feedback.min_update = interval.map_or(self.rate.min, |interval| interval.max(self.rate.min));
+    }
+
+    #[instrument(level = "debug", name = "Controller::pair", skip(self))]
+    pub async fn pair(&mut self, host: Address) -> Result<Outcome> {
+        return self.device.pair(host).await;
+    }
+
+    /// Sets the PWM frequency of the LEDs in Hz - higher frequencies avoid flicker on cameras
+    pub async fn set_led_pwm(&mut self, frequency: u32) -> Result<()> {
+        return self.device.set_led_pwm(frequency).await;
+    }
+
+    #[instrument(level = "debug", name = "Controller::power_off", skip(self))]
+    pub async fn power_off(&mut self) -> Result<()> {
+        return self.device.power_off().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limiter() {
+        let mut limiter = Limiter::new(0, Rate {
+            min: Duration::ZERO,
+            max: Duration::from_secs(3600),
+        });
+        assert_eq!(limiter.update(), Some(&0));
+
+        // Sent values are not sent again with every update
+        assert_eq!(limiter.update(), None);
+
+        limiter.set(0);
+        assert_eq!(limiter.update(), None);
+
+        limiter.set(1);
+        assert_eq!(limiter.update(), Some(&1));
+        assert_eq!(limiter.update(), None);
+    }
+
+    #[test]
+    fn test_button_edges() {
+        let mut buttons = Buttons::default();
+        buttons.start = Button::new(true);
+        buttons.track(Buttons::default());
+        assert!(buttons.start.pressed());
+        assert!(!buttons.cross.pressed());
+
+        // Held down across readings
+        let mut next = buttons.clone();
+        next.settle();
+        next.track(buttons);
+        assert!(next.start.is_down());
+        assert!(!next.start.pressed());
+
+        let mut released = Buttons::default();
+        released.track(next);
+        assert!(released.start.released());
+    }
+}
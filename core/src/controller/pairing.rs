@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::controller::Address;
+
+/// Directory where bluez keeps a sub-directory named by the address of each local adapter
+const BLUEZ_STORAGE: &str = "/var/lib/bluetooth";
+
+#[derive(clap::Args, Debug)]
+#[group(id = "pairing")]
+pub struct Config {
+    /// Pairs controllers with the local bluetooth adapter as soon as they are plugged in via USB
+    #[arg(long)]
+    pub auto_pair: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Paired,
+    AlreadyPaired,
+}
+
+#[derive(Debug)]
+pub struct PairingResult {
+    pub controller: Address,
+    pub outcome: Result<Outcome>,
+}
+
+/// Looks up the address of the local bluetooth adapter
+#[instrument(level = "debug")]
+pub fn host_address() -> Result<Address> {
+    let entries = Path::new(BLUEZ_STORAGE).read_dir()
+        .with_context(|| format!("Failed to read bluetooth storage: {}", BLUEZ_STORAGE))?;
+
+    for entry in entries {
+        if let Ok(address) = entry?.file_name().to_string_lossy().parse() {
+            return Ok(address);
+        }
+    }
+
+    bail!("No bluetooth adapter found");
+}
@@ -0,0 +1,146 @@
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use tracing::info;
+
+use crate::controller::{Address, Controller, Device, Feedback, Model, Reading};
+use crate::controller::hid::Bus;
+use crate::controller::recording::Recording;
+
+/// Replays recorded input instead of using the connected hardware
+#[derive(clap::Args, Debug)]
+#[group(id = "playback")]
+pub struct Config {
+    /// Replays the input recording as a controller - can be repeated to replay multiple controllers
+    /// recorded in the same session
+    #[arg(long = "playback", value_name = "FILE", conflicts_with = "emulate")]
+    pub recordings: Vec<PathBuf>,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        return !self.recordings.is_empty();
+    }
+}
+
+/// Creates a controller for each recording replaying its samples along the frame time
+///
+/// All recordings must stem from the same session as their samples are timed relative to it.
+pub fn spawn(config: Config) -> Result<Vec<Controller>> {
+    let mut controllers = Vec::new();
+    let mut session = None;
+
+    for path in config.recordings.iter() {
+        let recording = Recording::load(path)?;
+        info!("Replaying {} samples of controller {} from {:?}", recording.samples.len(), recording.address.as_string(), path);
+
+        if *session.get_or_insert(recording.session) != recording.session {
+            bail!("Recording is from another session: {:?}", path);
+        }
+
+        controllers.push(Controller::new(Box::new(Playback {
+            address: recording.address,
+            model: recording.model,
+            samples: recording.samples.into_iter().peekable(),
+            time: Duration::ZERO,
+        })));
+    }
+
+    return Ok(controllers);
+}
+
+/// A controller device returning the recorded samples which are due by the time advanced so far
+///
+/// The time is advanced by the frames only, so a replay gets the same samples each frame
+/// regardless of how long processing the frames takes.
+struct Playback {
+    address: Address,
+    model: Model,
+
+    samples: Peekable<std::vec::IntoIter<(Duration, Reading)>>,
+    time: Duration,
+}
+
+#[async_trait]
+impl Device for Playback {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return self.model;
+    }
+
+    fn bus(&self) -> Bus {
+        return Bus::UNKNOWN;
+    }
+
+    fn advance(&mut self, duration: Duration) {
+        self.time += duration;
+    }
+
+    async fn send(&mut self, _feedback: &Feedback) -> Result<()> {
+        return Ok(());
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        let mut latest = None;
+        while let Some((_, reading)) = self.samples.next_if(|(time, _)| *time <= self.time) {
+            latest = Some(reading);
+
+            if self.samples.peek().is_none() {
+                info!("Replay of controller {} finished", self.address.as_string());
+            }
+        }
+
+        return Ok(latest);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::controller::{Battery, Button, Input};
+
+    fn reading(input: Input) -> Reading {
+        return Reading {
+            input,
+            battery: Battery::Unknown,
+            temperature: None,
+        };
+    }
+
+    #[tokio::test]
+    async fn test_frame_time() {
+        let mut pressed = Input::default();
+        pressed.buttons.cross = Button::new(true);
+
+        let mut playback = Playback {
+            address: Address::from([2, 0, 0, 0, 0, 1]),
+            model: Model::CECH_ZCM2,
+            samples: vec![
+                (Duration::from_millis(10), reading(Input::default())),
+                (Duration::from_millis(15), reading(pressed)),
+                (Duration::from_millis(50), reading(Input::default())),
+            ].into_iter().peekable(),
+            time: Duration::ZERO,
+        };
+
+        assert!(playback.receive().await.unwrap().is_none());
+
+        // Only the latest of the due samples is returned
+        playback.advance(Duration::from_millis(20));
+        assert!(playback.receive().await.unwrap().unwrap().input.buttons.cross.is_down());
+        assert!(playback.receive().await.unwrap().is_none());
+
+        playback.advance(Duration::from_millis(20));
+        assert!(playback.receive().await.unwrap().is_none());
+
+        playback.advance(Duration::from_millis(20));
+        assert!(!playback.receive().await.unwrap().unwrap().input.buttons.cross.is_down());
+    }
+}
@@ -1,12 +1,15 @@
 use std::os::unix::prelude::AsRawFd;
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use packed_struct::prelude::{bits::ByteArray, PackedStruct, PackedStructSlice};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 pub mod zcm1;
+pub mod zcm2;
 
 #[async_trait]
 pub trait Getter<R: Report> {
@@ -124,6 +127,46 @@ impl<R> Setter<R> for Feature
     }
 }
 
+/// Converts a raw temperature reading to degrees Celsius
+///
+/// This is a linear approximation of the lookup table used by psmoveapi which is accurate to about
+/// one degree between 0°C and 60°C.
+pub fn temperature(raw: u16) -> f32 {
+    return (raw as f32 - 0x1F6 as f32) / 17.5 - 10.0;
+}
+
+#[derive(PackedStruct, Debug, Copy, Clone)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct Vector {
+    pub x: u16,
+    pub y: u16,
+    pub z: u16,
+}
+
+impl Vector {
+    pub fn normalize(v: u16) -> f32 {
+        return (v as f32) / (0x8000 as f32) - 1.0;
+    }
+
+    pub fn x(&self) -> f32 {
+        return Self::normalize(self.x);
+    }
+
+    pub fn y(&self) -> f32 {
+        return Self::normalize(self.y);
+    }
+
+    pub fn z(&self) -> f32 {
+        return Self::normalize(self.z);
+    }
+}
+
+impl From<Vector> for cgmath::Vector3<f32> {
+    fn from(vec: Vector) -> Self {
+        return cgmath::Vector3::new(vec.x(), vec.y(), vec.z());
+    }
+}
+
 #[derive(PackedStruct, Debug, Eq, PartialEq, Hash, Copy, Clone)]
 #[packed_struct(bit_numbering = "msb0", endian = "lsb")]
 pub struct Address {
@@ -131,12 +174,61 @@ pub struct Address {
 }
 
 impl Address {
+    /// A locally administered address derived from a stable identifier of a device which has none
+    ///
+    /// The identifier is hashed with FNV-1a to get the same address across restarts and builds.
+    pub fn derived(identifier: &str) -> Self {
+        let hash = identifier.bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+            .to_le_bytes();
+
+        return Self { data: [hash[0], hash[1], hash[2], hash[3], hash[4], 0x02] };
+    }
+
     pub fn as_string(&self) -> String {
         return format!("{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
                        self.data[5], self.data[4], self.data[3], self.data[2], self.data[1], self.data[0]);
     }
 }
 
+impl FromStr for Address {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        if parts.len() != 6 {
+            bail!("Invalid address: {}", s);
+        }
+
+        // Addresses are stored in reverse byte order
+        let mut data = [0u8; 6];
+        for (i, part) in parts.into_iter().enumerate() {
+            data[5 - i] = u8::from_str_radix(part, 16)?;
+        }
+
+        return Ok(Self { data });
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.as_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error> where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        return s.parse().map_err(serde::de::Error::custom);
+    }
+}
+
+impl From<[u8; 6]> for Address {
+    fn from(data: [u8; 6]) -> Self {
+        return Self { data };
+    }
+}
+
 impl AsRef<[u8]> for Address {
     fn as_ref(&self) -> &[u8] {
         return &self.data;
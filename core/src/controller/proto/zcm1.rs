@@ -0,0 +1,356 @@
+use anyhow::Result;
+use packed_struct::prelude::{Integer, packed_bits, PackedStruct};
+
+// TODO: Check out https://crates.io/crates/deku for struct packing
+
+use crate::controller::Feedback;
+use crate::controller::proto::{Address, Feature, Get, Primary, Set, Vector};
+
+use super::Report;
+
+const REPORT_GET_INPUT: u8 = 0x01;
+const REPORT_SET_LED: u8 = 0x06;
+const REPORT_SET_LED_PWM_FREQ: u8 = 0x03;
+const REPORT_GET_BT_ADDR: u8 = 0x04;
+const REPORT_SET_BT_ADDR: u8 = 0x05;
+const REPORT_GET_CALIBRATION: u8 = 0x10;
+// const REPORT_SET_AUTH_CHALLENGE: u8 = 0xA0;
+// const REPORT_GET_AUTH_RESPONSE: u8 = 0xA1;
+const REPORT_EXT_DEVICE_INFO: u8 = 0xE0;
+// const REPORT_SET_DFU_MODE: u8 = 0xF2;
+const REPORT_GET_FIRMWARE_INFO: u8 = 0xF9;
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct GetInput {
+    pub buttons: Integer<u32, packed_bits::Bits<28>>,
+
+    pub seq: Integer<u8, packed_bits::Bits<4>>,
+
+    pub trigger_1: u8,
+    pub trigger_2: u8,
+
+    _reserved: [u8; 4],
+
+    time_high: u8,
+
+    pub battery: u8,
+
+    #[packed_field(element_size_bytes = "6")]
+    pub accel_1: Vector,
+
+    #[packed_field(element_size_bytes = "6")]
+    pub accel_2: Vector,
+
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_1: Vector,
+
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_2: Vector,
+
+    pub temp: Integer<u16, packed_bits::Bits<12>>,
+
+    pub magnet_x: Integer<u16, packed_bits::Bits<12>>,
+    pub magnet_y: Integer<u16, packed_bits::Bits<12>>,
+    pub magnet_z: Integer<u16, packed_bits::Bits<12>>,
+
+    time_low: u8, // TODO: can this be a single field but split using packed_struct magic?
+
+    pub extdata: [u8; 5],
+}
+
+impl GetInput {
+    /// The temperature in degrees Celsius
+    pub fn temperature(&self) -> f32 {
+        return super::temperature(self.temp.into());
+    }
+
+    /// The raw magnetometer reading - the axes are 12 bit two's complement values
+    pub fn magnetometer(&self) -> cgmath::Vector3<f32> {
+        fn signed(v: u16) -> f32 {
+            return ((v << 4) as i16 >> 4) as f32;
+        }
+
+        return cgmath::Vector3::new(
+            signed(self.magnet_x.into()),
+            signed(self.magnet_y.into()),
+            signed(self.magnet_z.into()),
+        );
+    }
+}
+
+impl Report for GetInput {
+    const REPORT_ID: u8 = self::REPORT_GET_INPUT;
+}
+
+impl Get for GetInput { type Getter = Primary; }
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct SetLED {
+    _reserved1: [u8; 1],
+
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+
+    _reserved2: [u8; 1],
+
+    pub rumble: u8,
+
+    _reserved3: [u8; 2],
+}
+
+impl Report for SetLED {
+    const REPORT_ID: u8 = self::REPORT_SET_LED;
+}
+
+impl Set for SetLED {
+    type Setter = Primary;
+}
+
+impl SetLED {
+    pub fn from(feedback: &Feedback) -> Self {
+        return Self {
+            _reserved1: [0],
+            r: feedback.rgb.0,
+            g: feedback.rgb.1,
+            b: feedback.rgb.2,
+            _reserved2: [0],
+            rumble: feedback.rumble,
+            _reserved3: [0, 0],
+        };
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct SetLEDPWMFrequency {
+    // The report is ignored if this is not set to the magic value
+    magic: u8,
+
+    // Command 0x00 sets the frequency
+    command: u8,
+
+    pub frequency: u32,
+}
+
+impl Report for SetLEDPWMFrequency {
+    const REPORT_ID: u8 = self::REPORT_SET_LED_PWM_FREQ;
+}
+
+impl Set for SetLEDPWMFrequency {
+    type Setter = Feature;
+}
+
+impl SetLEDPWMFrequency {
+    /// The range of frequencies in Hz accepted by the controller
+    pub const RANGE: std::ops::RangeInclusive<u32> = 733..=24_000_000;
+
+    pub fn new(frequency: u32) -> Result<Self> {
+        if !Self::RANGE.contains(&frequency) {
+            anyhow::bail!("LED PWM frequency out of range: {}Hz", frequency);
+        }
+
+        return Ok(Self {
+            magic: 0x41,
+            command: 0x00,
+            frequency,
+        });
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct GetCalibration {
+    pub index: u8,
+    pub data: [u8; 47],
+}
+
+impl Report for GetCalibration {
+    const REPORT_ID: u8 = self::REPORT_GET_CALIBRATION;
+}
+
+impl Get for GetCalibration {
+    type Getter = Feature;
+}
+
+impl GetCalibration {
+    pub fn stitch(data: [&Self; 3]) -> Result<GetCalibrationInner> {
+        let data1 = data.iter().find(|report| report.index == 0x00);
+        let data2 = data.iter().find(|report| report.index == 0x01);
+        let data3 = data.iter().find(|report| report.index == 0x82);
+
+        if let (Some(data1), Some(data2), Some(data3)) = (data1, data2, data3) {
+            let mut data = [0; 141];
+            data[0..47].copy_from_slice(&data1.data);
+            data[47..94].copy_from_slice(&data2.data);
+            data[94..141].copy_from_slice(&data3.data);
+
+            return Ok(GetCalibrationInner::unpack(&data)?);
+        } else {
+            anyhow::bail!("Insufficient data");
+        }
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct GetCalibrationInner {
+    _unknown01: [u8; 2],
+
+    #[packed_field(element_size_bytes = "6")]
+    pub accel: [Vector; 6],
+
+    _unknown02: [u8; 2],
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_bias: Vector,
+
+    _unknown04: [u8; 2],
+    #[packed_field(element_size_bytes = "6")]
+    _unknown05: Vector,
+
+    _unknown06: [u8; 7],
+
+    _unknown07: [u8; 1],
+    _unknown08: [u8; 2],
+    _unknown09: [u8; 2],
+    _unknown10: [u8; 2],
+
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_x: Vector,
+
+    _unknown11: [u8; 2],
+
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_y: Vector,
+
+    _unknown12: [u8; 2],
+
+    #[packed_field(element_size_bytes = "6")]
+    pub gyro_z: Vector,
+
+    _unknown13: [u8; 2],
+
+    _unknown14: [u8; 12],
+    _unknown15: [u8; 12],
+    _unknown16: [u8; 4],
+    _unknown17: [u8; 4],
+
+    _unknown18: [u8; 17],
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct GetAddress {
+    #[packed_field(element_size_bytes = "6")]
+    pub controller: Address,
+
+    _unknown: [u8; 3],
+
+    #[packed_field(element_size_bytes = "6")]
+    pub host: Address,
+}
+
+impl Report for GetAddress {
+    const REPORT_ID: u8 = self::REPORT_GET_BT_ADDR;
+}
+
+impl Get for GetAddress {
+    type Getter = Feature;
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct SetAddress {
+    #[packed_field(element_size_bytes = "6")]
+    pub host: Address,
+
+    _reserved: [u8; 16],
+}
+
+impl Report for SetAddress {
+    const REPORT_ID: u8 = self::REPORT_SET_BT_ADDR;
+}
+
+impl Set for SetAddress {
+    type Setter = Feature;
+}
+
+impl SetAddress {
+    pub fn new(host: Address) -> Self {
+        return Self {
+            host,
+            _reserved: [0; 16],
+        };
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb")]
+pub struct GetFirmwareInfo {
+    _unknown01: [u8; 2],
+
+    pub version: u16,
+    pub revision: u16,
+    pub bt_version: u16,
+
+    _unknown02: [u8; 4],
+}
+
+impl Report for GetFirmwareInfo {
+    const REPORT_ID: u8 = self::REPORT_GET_FIRMWARE_INFO;
+}
+
+impl Get for GetFirmwareInfo {
+    type Getter = Feature;
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "lsb")]
+pub struct SetExtDeviceInfo {
+    _reserved: u8,
+
+    // The I2C bus address of the extension and the register and length to read from it
+    address: u8,
+    offset: u8,
+    len: u8,
+}
+
+impl Report for SetExtDeviceInfo {
+    const REPORT_ID: u8 = self::REPORT_EXT_DEVICE_INFO;
+}
+
+impl Set for SetExtDeviceInfo {
+    type Setter = Feature;
+}
+
+impl SetExtDeviceInfo {
+    /// Requests the device info of the extension which is then read by `GetExtDeviceInfo`
+    pub fn new() -> Self {
+        return Self {
+            _reserved: 0x00,
+            address: 0xA0,
+            offset: 0x80,
+            len: 40,
+        };
+    }
+}
+
+#[derive(PackedStruct, Debug)]
+#[packed_struct(bit_numbering = "msb0", endian = "msb")]
+pub struct GetExtDeviceInfo {
+    _unknown: [u8; 8],
+
+    pub id: u16,
+
+    pub info: [u8; 38],
+}
+
+impl Report for GetExtDeviceInfo {
+    const REPORT_ID: u8 = self::REPORT_EXT_DEVICE_INFO;
+}
+
+impl Get for GetExtDeviceInfo {
+    type Getter = Feature;
+}
@@ -0,0 +1,454 @@
+use std::path::{Path, PathBuf};
+use std::task::Poll;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cgmath::{ElementWise, Zero};
+use tokio::fs::{File, OpenOptions};
+use tracing::{instrument, warn};
+
+use crate::controller::{Address, Button, Buttons, Device, Extension, Feedback, Firmware, hid, Input, Model, Reading};
+use crate::controller::frame::Frame;
+use crate::controller::hid::Bus;
+use crate::controller::pairing::Outcome;
+use crate::controller::proto::{Get, Set, zcm1, zcm2};
+use crate::controller::proto::zcm1::{GetAddress, GetExtDeviceInfo, GetFirmwareInfo, SetAddress, SetExtDeviceInfo, SetLEDPWMFrequency};
+use crate::controller::quirks::{self, Quirks};
+
+#[derive(Debug, Clone)]
+struct Calibration {
+    frame: Frame,
+
+    accelerometer_m: cgmath::Vector3<f32>,
+    accelerometer_b: cgmath::Vector3<f32>,
+
+    gyroscope: cgmath::Vector3<f32>,
+}
+
+impl Calibration {
+    /// Derives the calibration from the accelerometer readings in all six orientations and the
+    /// gyroscope readings while rotating around each axis at the given speed
+    fn new(accel: [cgmath::Vector3<f32>; 6],
+           gyro: [cgmath::Vector3<f32>; 3],
+           gyro_bias: cgmath::Vector3<f32>,
+           gyro_rpm: f32) -> Self {
+        let frame = Frame::detect(&accel).unwrap_or_else(|err| {
+            warn!("Failed to detect sensor orientation: {}", err);
+            Frame::IDENTITY
+        });
+
+        let accel = accel.map(|v| frame.apply(v));
+        let gyro = gyro.map(|v| frame.apply(v));
+        let gyro = cgmath::Vector3::new(gyro[0].x, gyro[1].y, gyro[2].z);
+        let gyro_bias = frame.apply(gyro_bias);
+
+        let accel_min = cgmath::Vector3 {
+            x: accel[1].x,
+            y: accel[5].y,
+            z: accel[2].z,
+        };
+
+        let accel_max = cgmath::Vector3 {
+            x: accel[3].x,
+            y: accel[4].y,
+            z: accel[0].z,
+        };
+
+        let accelerometer_m = 2.0 / (accel_max - accel_min);
+        let accelerometer_b = -accelerometer_m.mul_element_wise(accel_min) + cgmath::Vector3::new(-1.0, -1.0, -1.0);
+
+        let factor = gyro_rpm * (2.0 * std::f32::consts::PI) / 60.0;
+        let gyroscope = factor / (gyro - gyro_bias);
+
+        return Self {
+            frame,
+            accelerometer_m,
+            accelerometer_b,
+            gyroscope,
+        };
+    }
+}
+
+impl From<zcm1::GetCalibrationInner> for Calibration {
+    fn from(report: zcm1::GetCalibrationInner) -> Self {
+        return Self::new(
+            report.accel.map(Into::into),
+            [report.gyro_x.into(), report.gyro_y.into(), report.gyro_z.into()],
+            report.gyro_bias.into(),
+            80.0,
+        );
+    }
+}
+
+impl From<zcm2::GetCalibrationInner> for Calibration {
+    fn from(report: zcm2::GetCalibrationInner) -> Self {
+        return Self::new(
+            report.accel.map(Into::into),
+            [report.gyro_x.into(), report.gyro_y.into(), report.gyro_z.into()],
+            report.gyro_bias.into(),
+            90.0,
+        );
+    }
+}
+
+/// The input report fields shared by all models
+struct RawInput {
+    buttons: u32,
+
+    trigger_1: u8,
+    trigger_2: u8,
+
+    battery: u8,
+
+    accel_1: cgmath::Vector3<f32>,
+    accel_2: cgmath::Vector3<f32>,
+
+    gyro_1: cgmath::Vector3<f32>,
+    gyro_2: cgmath::Vector3<f32>,
+
+    // Only available on models with a magnetometer
+    magnet: Option<cgmath::Vector3<f32>>,
+
+    temperature: f32,
+}
+
+impl From<zcm1::GetInput> for RawInput {
+    fn from(input: zcm1::GetInput) -> Self {
+        return Self {
+            buttons: input.buttons.into(),
+            trigger_1: input.trigger_1,
+            trigger_2: input.trigger_2,
+            battery: input.battery,
+            accel_1: input.accel_1.into(),
+            accel_2: input.accel_2.into(),
+            gyro_1: input.gyro_1.into(),
+            gyro_2: input.gyro_2.into(),
+            magnet: Some(input.magnetometer()),
+            temperature: input.temperature(),
+        };
+    }
+}
+
+impl From<zcm2::GetInput> for RawInput {
+    fn from(input: zcm2::GetInput) -> Self {
+        return Self {
+            buttons: input.buttons.into(),
+            trigger_1: input.trigger_1,
+            trigger_2: input.trigger_2,
+            battery: input.battery,
+            accel_1: input.accel_1.into(),
+            accel_2: input.accel_2.into(),
+            gyro_1: input.gyro_1.into(),
+            gyro_2: input.gyro_2.into(),
+            magnet: None,
+            temperature: input.temperature(),
+        };
+    }
+}
+
+/// Calibrates the magnetometer by scaling the range of readings seen so far into [-1, 1]
+///
+/// The controller does not store magnetometer calibration data. Instead the hard iron offset and
+/// the scale of each axis is learned while the controller is moved around. Readings are withheld
+/// until every axis has seen a range wide enough to tell the heading.
+#[derive(Debug, Clone)]
+struct MagnetometerCalibration {
+    min: cgmath::Vector3<f32>,
+    max: cgmath::Vector3<f32>,
+}
+
+impl MagnetometerCalibration {
+    // Range of raw readings of an axis required - the earth's field spans about 300
+    const CONVERGED: f32 = 200.0;
+
+    fn new() -> Self {
+        return Self {
+            min: cgmath::Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: cgmath::Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        };
+    }
+
+    fn apply(&mut self, raw: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+        self.min = cgmath::Vector3::new(self.min.x.min(raw.x), self.min.y.min(raw.y), self.min.z.min(raw.z));
+        self.max = cgmath::Vector3::new(self.max.x.max(raw.x), self.max.y.max(raw.y), self.max.z.max(raw.z));
+
+        let range = self.max - self.min;
+        if range.x < Self::CONVERGED || range.y < Self::CONVERGED || range.z < Self::CONVERGED {
+            return cgmath::Vector3::zero();
+        }
+
+        fn scale(raw: f32, min: f32, max: f32) -> f32 {
+            return (raw - min) / (max - min) * 2.0 - 1.0;
+        }
+
+        return cgmath::Vector3::new(
+            scale(raw.x, self.min.x, self.max.x),
+            scale(raw.y, self.min.y, self.max.y),
+            scale(raw.z, self.min.z, self.max.z),
+        );
+    }
+}
+
+/// The protocols spoken by the PS Move controller generations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    ZCM1,
+    ZCM2,
+}
+
+impl Protocol {
+    /// The protocol of the model - `None` if not a PS Move controller
+    fn of(model: Model) -> Option<Self> {
+        return match model {
+            Model::CECH_ZCM1 => Some(Self::ZCM1),
+            Model::CECH_ZCM2 => Some(Self::ZCM2),
+            _ => None,
+        };
+    }
+
+    fn model(&self) -> Model {
+        return match self {
+            Self::ZCM1 => Model::CECH_ZCM1,
+            Self::ZCM2 => Model::CECH_ZCM2,
+        };
+    }
+}
+
+/// A PlayStation Move controller connected via hidraw
+pub struct PSMove {
+    /// Path of the device
+    path: PathBuf,
+
+    /// The bus the device is connected to
+    bus: Bus,
+
+    /// The device file used for communication
+    file: File,
+
+    /// The bluetooth address of the controller
+    address: Address,
+
+    /// The protocol used for communication
+    protocol: Protocol,
+
+    /// The firmware of the controller if it could be queried
+    firmware: Option<Firmware>,
+
+    /// Calibration data received from the controller
+    calibration: Calibration,
+
+    magnetometer: MagnetometerCalibration,
+
+    quirks: Quirks,
+
+    /// The device attached to the extension port
+    extension: Option<Extension>,
+}
+
+impl PSMove {
+    pub async fn open(device: &hid::Device) -> Result<Self> {
+        let path = device.path.clone();
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .await?;
+
+        let protocol = device.model()
+            .and_then(Protocol::of)
+            .ok_or_else(|| anyhow!("Not a PS Move controller: {:?}", path))?;
+
+        // Delay a bit for things to settle - the firmware is not known before, so the delay of the
+        // model is used
+        tokio::time::sleep(quirks::lookup(protocol.model(), None).settle).await;
+
+        // Not all controllers answer the firmware info request
+        let firmware = match GetFirmwareInfo::get(&mut file).await {
+            Ok(info) => Some(Firmware {
+                version: info.version,
+                revision: info.revision,
+            }),
+            Err(err) => {
+                warn!("Failed to get firmware info from {:?}: {}", path, err);
+                None
+            }
+        };
+
+        let quirks = quirks::lookup(protocol.model(), firmware.map(|firmware| firmware.version));
+
+        // Get device address
+        let address = GetAddress::get(&mut file).await?
+            .controller;
+
+        // Collect calibration data from device
+        let calibration = match protocol {
+            Protocol::ZCM1 => zcm1::GetCalibration::stitch([
+                &zcm1::GetCalibration::get(&mut file).await?,
+                &zcm1::GetCalibration::get(&mut file).await?,
+                &zcm1::GetCalibration::get(&mut file).await?,
+            ])?.into(),
+            Protocol::ZCM2 => zcm2::GetCalibration::stitch([
+                &zcm2::GetCalibration::get(&mut file).await?,
+                &zcm2::GetCalibration::get(&mut file).await?,
+            ])?.into(),
+        };
+
+        return Ok(Self {
+            path,
+            bus: device.bus,
+            file,
+            address,
+            protocol,
+            firmware,
+            calibration,
+            magnetometer: MagnetometerCalibration::new(),
+            quirks,
+            extension: None,
+        });
+    }
+
+    /// Identifies the device attached to the extension port
+    async fn identify_extension(&mut self) -> Result<Extension> {
+        SetExtDeviceInfo::set(&mut self.file, SetExtDeviceInfo::new()).await?;
+        let info = GetExtDeviceInfo::get(&mut self.file).await?;
+
+        return Ok(Extension::from_id(info.id));
+    }
+}
+
+#[async_trait]
+impl Device for PSMove {
+    fn address(&self) -> Address {
+        return self.address;
+    }
+
+    fn model(&self) -> Model {
+        return self.protocol.model();
+    }
+
+    fn bus(&self) -> Bus {
+        return self.bus;
+    }
+
+    fn path(&self) -> Option<&Path> {
+        return Some(&self.path);
+    }
+
+    fn firmware(&self) -> Option<Firmware> {
+        return self.firmware;
+    }
+
+    fn extension(&self) -> Option<Extension> {
+        return self.extension;
+    }
+
+    async fn send(&mut self, feedback: &Feedback) -> Result<()> {
+        let feedback = self.quirks.feedback(feedback);
+        match self.protocol {
+            Protocol::ZCM1 => zcm1::SetLED::set(&mut self.file, zcm1::SetLED::from(&feedback)).await?,
+            Protocol::ZCM2 => zcm2::SetLED::set(&mut self.file, zcm2::SetLED::from(&feedback)).await?,
+        }
+
+        return Ok(());
+    }
+
+    async fn receive(&mut self) -> Result<Option<Reading>> {
+        // Read input report from device if available
+        // TODO: Revisit this: Would it be better to read at least one report?
+        // TODO: This effectively disables the timeout
+        let input: RawInput = match self.protocol {
+            Protocol::ZCM1 => match futures::poll!(zcm1::GetInput::get(&mut self.file)) {
+                Poll::Ready(input) => input?.into(),
+                Poll::Pending => return Ok(None),
+            },
+            Protocol::ZCM2 => match futures::poll!(zcm2::GetInput::get(&mut self.file)) {
+                Poll::Ready(input) => input?.into(),
+                Poll::Pending => return Ok(None),
+            },
+        };
+
+        // The input report only flags whether an extension is attached - identifying it requires
+        // another request which is only sent if the flag changes
+        let attached = input.buttons & (1 << 24) != 0;
+        if attached != self.extension.is_some() {
+            self.extension = if attached {
+                Some(self.identify_extension().await.unwrap_or_else(|err| {
+                    warn!("Failed to identify extension of {:?}: {}", self.path, err);
+                    Extension::Unidentified
+                }))
+            } else {
+                None
+            };
+        }
+
+        fn avg(v1: cgmath::Vector3<f32>, v2: cgmath::Vector3<f32>) -> cgmath::Vector3<f32> {
+            return (v1 + v2) / 2.0;
+        }
+
+        // Readings are converted into the common frame before applying the calibration
+        let accelerometer = self.calibration.frame.apply(avg(input.accel_1, input.accel_2))
+            .mul_element_wise(self.calibration.accelerometer_m)
+            .add_element_wise(self.calibration.accelerometer_b);
+
+        let gyroscope = self.calibration.frame.apply(avg(input.gyro_1, input.gyro_2))
+            .mul_element_wise(self.calibration.gyroscope);
+
+        let magnetometer = input.magnet
+            .map_or(cgmath::Vector3::zero(), |magnet| self.magnetometer.apply(self.calibration.frame.apply(magnet)));
+
+        fn bit(buttons: impl Into<u32>, bit: usize) -> Button {
+            return Button::new(buttons.into() & (1 << bit) != 0);
+        }
+
+        // Each input report contains two readings of the trigger
+        let trigger = (input.trigger_1 as f32 + input.trigger_2 as f32) / 2.0 / (0xFF as f32);
+
+        let buttons = Buttons {
+            square: bit(input.buttons, 15),
+            triangle: bit(input.buttons, 12),
+            cross: bit(input.buttons, 14),
+            circle: bit(input.buttons, 13),
+            start: bit(input.buttons, 3),
+            select: bit(input.buttons, 0),
+            logo: bit(input.buttons, 16),
+            swoosh: bit(input.buttons, 19),
+            trigger: (bit(input.buttons, 20), trigger),
+        };
+
+        let battery = self.quirks.battery.decode(input.battery);
+
+        return Ok(Some(Reading {
+            input: Input {
+                accelerometer,
+                gyroscope,
+                magnetometer,
+                buttons,
+                ..Input::default()
+            },
+            battery,
+            temperature: Some(input.temperature),
+        }));
+    }
+
+    /// Writes the host address to the controller so it connects to the host via bluetooth
+    #[instrument(level = "debug", name = "PSMove::pair", skip(self))]
+    async fn pair(&mut self, host: Address) -> Result<Outcome> {
+        let current = GetAddress::get(&mut self.file).await?
+            .host;
+
+        if current == host {
+            return Ok(Outcome::AlreadyPaired);
+        }
+
+        SetAddress::set(&mut self.file, SetAddress::new(host)).await?;
+
+        return Ok(Outcome::Paired);
+    }
+
+    #[instrument(level = "debug", name = "PSMove::set_led_pwm", skip(self))]
+    async fn set_led_pwm(&mut self, frequency: u32) -> Result<()> {
+        SetLEDPWMFrequency::set(&mut self.file, SetLEDPWMFrequency::new(frequency)?).await?;
+        return Ok(());
+    }
+}
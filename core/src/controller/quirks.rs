@@ -0,0 +1,122 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::controller::{Battery, Feedback, Model};
+
+/// How the battery state is encoded in input reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryEncoding {
+    /// Levels from `0x00` (empty) to `0x05` (full) and `0xEE` / `0xEF` when charging / charged
+    Levels,
+
+    /// Like `Levels`, but without the full level - `0x05` is reported as unknown
+    LevelsWithoutFull,
+}
+
+impl BatteryEncoding {
+    pub fn decode(self, value: u8) -> Battery {
+        return match (self, value) {
+            (_, 0x00) => Battery::Draining(0.0),
+            (_, 0x01) => Battery::Draining(0.2),
+            (_, 0x02) => Battery::Draining(0.4),
+            (_, 0x03) => Battery::Draining(0.6),
+            (_, 0x04) => Battery::Draining(0.8),
+            (BatteryEncoding::Levels, 0x05) => Battery::Draining(1.0),
+            (_, 0xEE) => Battery::Charging,
+            (_, 0xEF) => Battery::Charged,
+            _ => Battery::Unknown,
+        };
+    }
+}
+
+/// Workarounds for the behaviour of specific hardware revisions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub battery: BatteryEncoding,
+
+    /// Time to wait after opening the device before it answers reliably - as this applies before
+    /// the firmware is queried, only the value of the entry matching an unknown firmware is used
+    pub settle: Duration,
+
+    /// Highest LED value accepted - brighter values are scaled down
+    pub led_max: u8,
+}
+
+impl Quirks {
+    /// Applies the LED limits to the feedback
+    pub fn feedback(&self, feedback: &Feedback) -> Feedback {
+        let limit = |v: u8| (v as u16 * self.led_max as u16 / 0xFF) as u8;
+
+        return Feedback {
+            rgb: (limit(feedback.rgb.0), limit(feedback.rgb.1), limit(feedback.rgb.2)),
+            rumble: feedback.rumble,
+        };
+    }
+}
+
+struct Entry {
+    model: Model,
+
+    /// Firmware versions affected - applies to all versions if missing
+    firmware: Option<RangeInclusive<u16>>,
+
+    quirks: Quirks,
+}
+
+/// Known quirks - the first matching entry wins
+const TABLE: &[Entry] = &[
+    Entry {
+        model: Model::CECH_ZCM1,
+        firmware: None,
+        quirks: Quirks {
+            battery: BatteryEncoding::LevelsWithoutFull,
+            settle: Duration::from_millis(100),
+            led_max: 0xFF,
+        },
+    },
+    Entry {
+        model: Model::CECH_ZCM2,
+        firmware: None,
+        quirks: Quirks {
+            battery: BatteryEncoding::Levels,
+            settle: Duration::from_millis(250),
+            led_max: 0xFF,
+        },
+    },
+];
+
+/// Looks up the quirks of a controller - the firmware version may be unknown
+pub fn lookup(model: Model, firmware: Option<u16>) -> Quirks {
+    return TABLE.iter()
+        .find(|entry| entry.model == model && match (&entry.firmware, firmware) {
+            (None, _) => true,
+            (Some(range), Some(firmware)) => range.contains(&firmware),
+            (Some(_), None) => false,
+        })
+        .map(|entry| entry.quirks)
+        .expect("Quirks for every model");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_battery_encoding() {
+        assert_eq!(BatteryEncoding::Levels.decode(0x05), Battery::Draining(1.0));
+        assert_eq!(BatteryEncoding::LevelsWithoutFull.decode(0x05), Battery::Unknown);
+        assert_eq!(BatteryEncoding::LevelsWithoutFull.decode(0xEE), Battery::Charging);
+    }
+
+    #[test]
+    fn test_led_limit() {
+        let quirks = Quirks {
+            battery: BatteryEncoding::Levels,
+            settle: Duration::ZERO,
+            led_max: 0x80,
+        };
+
+        let feedback = quirks.feedback(&Feedback::new().led_color((0xFF, 0x00, 0x80)));
+        assert_eq!(feedback.rgb, (0x80, 0x00, 0x40));
+    }
+}
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::controller::hid::Bus;
+use crate::controller::Model;
+
+#[derive(clap::Args, Debug)]
+#[group(id = "rate")]
+pub struct Config {
+    /// Overrides the feedback update rate for a transport and optionally a controller model - can be repeated
+    #[arg(long = "feedback-rate", value_name = "BUS[:MODEL]=MIN[/MAX]")]
+    pub rules: Vec<Rule>,
+}
+
+/// Limits for sending feedback to a controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    /// Minimal interval between changed feedback being sent
+    pub min: Duration,
+
+    /// Maximal interval after which unchanged feedback is sent again
+    pub max: Duration,
+}
+
+impl Default for Rate {
+    fn default() -> Self {
+        return Self {
+            min: Duration::from_millis(50),
+            max: Duration::from_millis(1000),
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub bus: Bus,
+
+    /// Applies to all models if missing
+    pub model: Option<Model>,
+
+    pub rate: Rate,
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (target, rate) = s.split_once('=')
+            .ok_or_else(|| anyhow!("Expected BUS[:MODEL]=MIN[/MAX]: {}", s))?;
+
+        let (bus, model) = match target.split_once(':') {
+            Some((bus, model)) => (bus, Some(model)),
+            None => (target, None),
+        };
+
+        let bus = match bus.to_lowercase().as_str() {
+            "usb" => Bus::USB,
+            "bluetooth" | "bt" => Bus::BLUETOOTH,
+            _ => return Err(anyhow!("Unknown bus: {}", bus)),
+        };
+
+        let model = match model.map(str::to_lowercase).as_deref() {
+            None => None,
+            Some("zcm1") => Some(Model::CECH_ZCM1),
+            Some("zcm2") => Some(Model::CECH_ZCM2),
+            Some("gamepad") => Some(Model::Gamepad),
+            Some("ds4") => Some(Model::DualShock4),
+            Some("dualsense") => Some(Model::DualSense),
+            Some("joycon-left") => Some(Model::JoyConLeft),
+            Some("joycon-right") => Some(Model::JoyConRight),
+            Some(model) => return Err(anyhow!("Unknown model: {}", model)),
+        };
+
+        let rate = match rate.split_once('/') {
+            Some((min, max)) => Rate {
+                min: humantime::parse_duration(min)?,
+                max: humantime::parse_duration(max)?,
+            },
+            None => Rate {
+                min: humantime::parse_duration(rate)?,
+                ..Rate::default()
+            },
+        };
+
+        if rate.min > rate.max {
+            return Err(anyhow!("Minimal interval exceeds maximal interval: {}", s));
+        }
+
+        return Ok(Self { bus, model, rate });
+    }
+}
+
+/// The feedback rates for all transports and models
+#[derive(Debug, Clone)]
+pub struct Rates {
+    rules: Vec<Rule>,
+}
+
+impl Rates {
+    /// Creates the builtin rates overridden by the configured rules
+    pub fn new(config: &Config) -> Self {
+        let mut rules = vec![
+            // Fast LED updates destabilize some bluetooth stacks
+            Rule {
+                bus: Bus::BLUETOOTH,
+                model: None,
+                rate: Rate::default(),
+            },
+            Rule {
+                bus: Bus::USB,
+                model: None,
+                rate: Rate {
+                    min: Duration::from_millis(10),
+                    max: Duration::from_millis(1000),
+                },
+            },
+        ];
+
+        rules.extend(config.rules.iter().cloned());
+
+        return Self { rules };
+    }
+
+    /// Returns the rate of the most specific rule matching - later rules take precedence
+    pub fn get(&self, bus: Bus, model: Model) -> Rate {
+        let matching = || self.rules.iter().rev()
+            .filter(move |rule| rule.bus == bus);
+
+        return matching().find(|rule| rule.model == Some(model))
+            .or_else(|| matching().find(|rule| rule.model.is_none()))
+            .map(|rule| rule.rate)
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rates() {
+        let rates = Rates::new(&Config {
+            rules: vec![
+                "bt:zcm2=100ms/2s".parse().unwrap(),
+                "usb=5ms".parse().unwrap(),
+            ],
+        });
+
+        assert_eq!(rates.get(Bus::BLUETOOTH, Model::CECH_ZCM1), Rate::default());
+        assert_eq!(rates.get(Bus::BLUETOOTH, Model::CECH_ZCM2), Rate {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(2),
+        });
+        assert_eq!(rates.get(Bus::USB, Model::CECH_ZCM1).min, Duration::from_millis(5));
+        assert_eq!(rates.get(Bus::UNKNOWN, Model::CECH_ZCM1), Rate::default());
+
+        assert!("usb=2s/1s".parse::<Rule>().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::controller::{Address, Model, Reading};
+
+/// Records the input of each controller for tuning and reproducing games offline
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "recording")]
+pub struct Config {
+    /// Appends the input of each controller to a file in this directory
+    #[arg(long = "record-input", value_name = "DIR")]
+    pub directory: Option<PathBuf>,
+}
+
+// Version of the recording format
+const VERSION: u32 = 3;
+
+// Interval to write the buffered samples to disk to lose little when crashing
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The common time base of all recordings written by one run
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    started: Instant,
+
+    /// Start of the session in microseconds since the UNIX epoch
+    epoch: u64,
+}
+
+impl Session {
+    pub fn start() -> Self {
+        return Self {
+            started: Instant::now(),
+            epoch: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |epoch| epoch.as_micros() as u64),
+        };
+    }
+}
+
+/// A single line of a recording file
+///
+/// A recording starts with a header followed by the readings of the controller. The time is given in
+/// seconds since the start of the session.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+enum Entry {
+    Header {
+        version: u32,
+        session: u64,
+        address: Address,
+        model: Model,
+    },
+
+    Sample {
+        time: f64,
+        reading: Reading,
+    },
+}
+
+/// Writes the readings received from a controller as timestamped samples to a file
+pub struct Recorder {
+    file: BufWriter<File>,
+    session: Session,
+
+    flushed: Instant,
+}
+
+impl Recorder {
+    pub fn create(directory: &Path, session: Session, address: Address, model: Model) -> Result<Self> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create recording directory: {:?}", directory))?;
+
+        let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+        let name = format!("{}-{}", address.as_string().replace(':', ""), time);
+
+        // Reconnecting within the same second gets a new file instead of replacing the previous one
+        let mut sequence = 0;
+        let file = loop {
+            let path = match sequence {
+                0 => directory.join(format!("{}.input", name)),
+                n => directory.join(format!("{}-{}.input", name, n)),
+            };
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break file,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => sequence += 1,
+                Err(err) => return Err(err).with_context(|| format!("Failed to create recording: {:?}", path)),
+            }
+        };
+
+        let mut recorder = Self {
+            file: BufWriter::new(file),
+            session,
+            flushed: Instant::now(),
+        };
+
+        recorder.write(&Entry::Header {
+            version: VERSION,
+            session: session.epoch,
+            address,
+            model,
+        })?;
+        recorder.file.flush()?;
+
+        return Ok(recorder);
+    }
+
+    pub fn record(&mut self, reading: &Reading) -> Result<()> {
+        self.write(&Entry::Sample {
+            time: self.session.started.elapsed().as_secs_f64(),
+            reading: reading.clone(),
+        })?;
+
+        if self.flushed.elapsed() >= FLUSH_INTERVAL {
+            self.flushed = Instant::now();
+            self.file.flush()?;
+        }
+
+        return Ok(());
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<()> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")?;
+        return Ok(());
+    }
+}
+
+/// A recording loaded from a file
+pub struct Recording {
+    /// Start of the recorded session in microseconds since the UNIX epoch
+    pub session: u64,
+
+    pub address: Address,
+    pub model: Model,
+
+    /// The readings with their time since the start of the session
+    pub samples: Vec<(Duration, Reading)>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = BufReader::new(File::open(path)
+            .with_context(|| format!("Failed to open recording: {:?}", path))?);
+
+        let mut lines = file.lines();
+
+        let (session, address, model) = match lines.next() {
+            Some(line) => match serde_json::from_str(&line?) {
+                Ok(Entry::Header { version: VERSION, session, address, model }) => (session, address, model),
+                Ok(Entry::Header { version, .. }) => bail!("Unsupported recording version {}: {:?}", version, path),
+                _ => bail!("Not a recording: {:?}", path),
+            },
+            None => bail!("Empty recording: {:?}", path),
+        };
+
+        let mut samples = Vec::new();
+        for line in lines {
+            // A recording may end with a partially written sample
+            match serde_json::from_str(&line?) {
+                Ok(Entry::Sample { time, reading }) => samples.push((Duration::from_secs_f64(time.max(0.0)), reading)),
+                Ok(Entry::Header { .. }) => bail!("Unexpected header in recording: {:?}", path),
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err).with_context(|| format!("Invalid sample in recording: {:?}", path)),
+            }
+        }
+
+        return Ok(Self {
+            session,
+            address,
+            model,
+            samples,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cgmath::Vector3;
+
+    use crate::controller::{Battery, Button, Input};
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let directory = std::env::temp_dir().join(format!("hastilude-recording-{}", std::process::id()));
+        let address: Address = "02:00:00:00:00:01".parse().unwrap();
+
+        let mut input = Input {
+            accelerometer: Vector3::new(0.1, 0.2, 1.0),
+            gyroscope: Vector3::new(-1.0, 0.5, 0.0),
+            ..Input::default()
+        };
+        input.buttons.cross = Button::new(true);
+        input.buttons.trigger = (Button::new(true), 0.75);
+
+        let reading = Reading {
+            input,
+            battery: Battery::Draining(0.5),
+            temperature: None,
+        };
+
+        let session = Session::start();
+        let mut recorder = Recorder::create(&directory, session, address, Model::CECH_ZCM2).unwrap();
+        recorder.record(&Reading { input: Input::default(), ..reading.clone() }).unwrap();
+        recorder.record(&reading).unwrap();
+        drop(recorder);
+
+        // Reconnecting right away does not replace the previous recording
+        drop(Recorder::create(&directory, session, address, Model::CECH_ZCM2).unwrap());
+
+        let mut recordings = std::fs::read_dir(&directory).unwrap()
+            .map(|entry| Recording::load(&entry.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        recordings.sort_by_key(|recording| recording.samples.len());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(recordings.len(), 2);
+        assert_eq!(recordings[0].samples.len(), 0);
+
+        let recording = &recordings[1];
+        assert_eq!(recording.session, session.epoch);
+        assert_eq!(recording.address, address);
+        assert_eq!(recording.model, Model::CECH_ZCM2);
+        assert_eq!(recording.samples.len(), 2);
+        assert_eq!(recording.samples[1].1, reading);
+    }
+}
@@ -0,0 +1,207 @@
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use nix::libc;
+
+use crate::controller::Address;
+
+// Bluetooth HCI socket constants from the kernel headers
+const BTPROTO_HCI: libc::c_int = 1;
+const SOL_HCI: libc::c_int = 0;
+const HCI_FILTER: libc::c_int = 2;
+const HCI_MAX_DEV: u16 = 16;
+
+const HCI_COMMAND_PKT: u8 = 0x01;
+const HCI_EVENT_PKT: u8 = 0x04;
+const EVT_CMD_COMPLETE: u8 = 0x0E;
+const ACL_LINK: u8 = 0x01;
+
+// OGF 0x05 (status parameters), OCF 0x0005 (read RSSI)
+const OPCODE_READ_RSSI: u16 = 0x05 << 10 | 0x0005;
+
+// OGF 0x01 (link control), OCF 0x0006 (disconnect)
+const OPCODE_DISCONNECT: u16 = 0x01 << 10 | 0x0006;
+
+// Reason for disconnecting: remote device powered off
+const REASON_POWER_OFF: u8 = 0x15;
+
+const IOC_HCI_MAGIC: u8 = b'H';
+const IOC_HCI_GET_CONN_INFO: u8 = 213;
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+struct SockAddr {
+    family: libc::sa_family_t,
+    dev: u16,
+    channel: u16,
+}
+
+#[repr(C)]
+struct Filter {
+    type_mask: u32,
+    event_mask: [u32; 2],
+    opcode: u16,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ConnInfoRequest {
+    address: [u8; 6],
+    link: u8,
+
+    // struct hci_conn_info
+    handle: u16,
+    info_address: [u8; 6],
+    info_link: u8,
+    out: u8,
+    state: u16,
+    link_mode: u32,
+}
+
+/// A raw HCI socket closed on drop
+struct Socket(RawFd);
+
+impl Socket {
+    fn open() -> Result<Self> {
+        let fd = nix::errno::Errno::result(unsafe {
+            libc::socket(libc::AF_BLUETOOTH, libc::SOCK_RAW | libc::SOCK_CLOEXEC, BTPROTO_HCI)
+        })?;
+
+        return Ok(Self(fd));
+    }
+
+    fn bind(&self, dev: u16) -> Result<()> {
+        let addr = SockAddr {
+            family: libc::AF_BLUETOOTH as libc::sa_family_t,
+            dev,
+            channel: 0,
+        };
+
+        nix::errno::Errno::result(unsafe {
+            libc::bind(self.0, &addr as *const SockAddr as *const libc::sockaddr, std::mem::size_of::<SockAddr>() as libc::socklen_t)
+        })?;
+
+        return Ok(());
+    }
+
+    /// Looks up the handle of the ACL connection to the given address on the bound adapter
+    fn connection(&self, address: Address) -> Result<u16> {
+        let ioc = nix::request_code_read!(IOC_HCI_MAGIC, IOC_HCI_GET_CONN_INFO, std::mem::size_of::<libc::c_int>());
+
+        let mut request = ConnInfoRequest::default();
+        request.address.copy_from_slice(address.as_ref());
+        request.link = ACL_LINK;
+
+        nix::errno::Errno::result(unsafe {
+            libc::ioctl(self.0, ioc, &mut request as *mut ConnInfoRequest)
+        })?;
+
+        return Ok(request.handle);
+    }
+
+    fn read_rssi(&self, handle: u16) -> Result<i8> {
+        let filter = Filter {
+            type_mask: 1 << HCI_EVENT_PKT,
+            event_mask: [1 << EVT_CMD_COMPLETE, 0],
+            opcode: OPCODE_READ_RSSI,
+        };
+
+        let timeout = libc::timeval {
+            tv_sec: TIMEOUT.as_secs() as libc::time_t,
+            tv_usec: TIMEOUT.subsec_micros() as libc::suseconds_t,
+        };
+
+        unsafe {
+            nix::errno::Errno::result(libc::setsockopt(self.0, SOL_HCI, HCI_FILTER,
+                                                       &filter as *const Filter as *const libc::c_void,
+                                                       std::mem::size_of::<Filter>() as libc::socklen_t))?;
+            nix::errno::Errno::result(libc::setsockopt(self.0, libc::SOL_SOCKET, libc::SO_RCVTIMEO,
+                                                       &timeout as *const libc::timeval as *const libc::c_void,
+                                                       std::mem::size_of::<libc::timeval>() as libc::socklen_t))?;
+        }
+
+        let opcode = OPCODE_READ_RSSI.to_le_bytes();
+        let handle = handle.to_le_bytes();
+        let command = [HCI_COMMAND_PKT, opcode[0], opcode[1], 2, handle[0], handle[1]];
+
+        nix::errno::Errno::result(unsafe {
+            libc::write(self.0, command.as_ptr() as *const libc::c_void, command.len())
+        })?;
+
+        // Skip completions of commands issued by others
+        loop {
+            let mut event = [0u8; 260];
+            let len = nix::errno::Errno::result(unsafe {
+                libc::read(self.0, event.as_mut_ptr() as *mut libc::c_void, event.len())
+            })? as usize;
+
+            // Packet type, event, length, credits, opcode, status, handle, rssi
+            if len < 10 || event[0] != HCI_EVENT_PKT || event[1] != EVT_CMD_COMPLETE || event[4..6] != opcode {
+                continue;
+            }
+
+            if event[6] != 0 {
+                bail!("Reading RSSI failed with status {:#04x}", event[6]);
+            }
+
+            return Ok(event[9] as i8);
+        }
+    }
+}
+
+impl Socket {
+    fn disconnect(&self, handle: u16) -> Result<()> {
+        let opcode = OPCODE_DISCONNECT.to_le_bytes();
+        let handle = handle.to_le_bytes();
+        let command = [HCI_COMMAND_PKT, opcode[0], opcode[1], 3, handle[0], handle[1], REASON_POWER_OFF];
+
+        nix::errno::Errno::result(unsafe {
+            libc::write(self.0, command.as_ptr() as *const libc::c_void, command.len())
+        })?;
+
+        return Ok(());
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0) };
+    }
+}
+
+/// Reads the signal strength of the bluetooth link to the controller in dB relative to the golden
+/// receive power range of the adapter
+///
+/// This blocks while waiting for the adapter to answer.
+pub fn rssi(address: Address) -> Result<i8> {
+    for dev in 0..HCI_MAX_DEV {
+        let socket = Socket::open()?;
+        if socket.bind(dev).is_err() {
+            continue;
+        }
+
+        if let Ok(handle) = socket.connection(address) {
+            return socket.read_rssi(handle);
+        }
+    }
+
+    bail!("No connection to {}", address.as_string());
+}
+
+/// Terminates the bluetooth link to the controller which makes it power off
+pub fn disconnect(address: Address) -> Result<()> {
+    for dev in 0..HCI_MAX_DEV {
+        let socket = Socket::open()?;
+        if socket.bind(dev).is_err() {
+            continue;
+        }
+
+        if let Ok(handle) = socket.connection(address) {
+            return socket.disconnect(handle);
+        }
+    }
+
+    bail!("No connection to {}", address.as_string());
+}
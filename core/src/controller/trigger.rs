@@ -0,0 +1,97 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "trigger")]
+pub struct Config {
+    /// Response curve of the analog trigger: linear, squared or keypoints like 0:0,0.5:0.2,1:1
+    #[arg(long = "trigger-curve", value_name = "CURVE", default_value = "linear")]
+    pub curve: Curve,
+}
+
+/// Maps the analog trigger position to the reported value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    Linear,
+
+    /// Finer control for slightly pulled triggers
+    Squared,
+
+    /// Interpolates linearly between keypoints sorted by the position
+    Keypoints(Vec<(f32, f32)>),
+}
+
+impl Curve {
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+
+        return match self {
+            Self::Linear => value,
+            Self::Squared => value * value,
+            Self::Keypoints(keypoints) => {
+                let upper = keypoints.iter()
+                    .position(|(x, _)| *x >= value)
+                    .unwrap_or(keypoints.len() - 1);
+
+                if upper == 0 {
+                    return keypoints[0].1;
+                }
+
+                let (x0, y0) = keypoints[upper - 1];
+                let (x1, y1) = keypoints[upper];
+
+                if x1 <= x0 {
+                    return y1;
+                }
+
+                y0 + (y1 - y0) * ((value - x0) / (x1 - x0)).clamp(0.0, 1.0)
+            }
+        };
+    }
+}
+
+impl FromStr for Curve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s {
+            "linear" => Ok(Self::Linear),
+            "squared" => Ok(Self::Squared),
+            _ => {
+                let mut keypoints = s.split(',')
+                    .map(|keypoint| {
+                        let (x, y) = keypoint.split_once(':')
+                            .ok_or_else(|| anyhow!("Expected POSITION:VALUE: {}", keypoint))?;
+                        return Ok((x.trim().parse()?, y.trim().parse()?));
+                    })
+                    .collect::<Result<Vec<(f32, f32)>>>()?;
+
+                if keypoints.is_empty() {
+                    bail!("Curve requires at least one keypoint");
+                }
+
+                keypoints.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+                Ok(Self::Keypoints(keypoints))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keypoints() {
+        let curve: Curve = "1:1, 0:0, 0.5:0.2".parse().unwrap();
+
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(0.25), 0.1);
+        assert_eq!(curve.apply(0.5), 0.2);
+        assert_eq!(curve.apply(0.75), 0.6);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_eq!(curve.apply(2.0), 1.0);
+    }
+}
@@ -0,0 +1,725 @@
+use std::collections::VecDeque;
+use std::f64::consts::{PI, TAU};
+use std::time::Duration;
+
+use scarlet::color::RGBColor;
+use scarlet::colorpoint::ColorPoint;
+
+/// Curve mapping the progress through a keyframe (0.0 - 1.0) to the progress between the values
+#[derive(Debug, Clone, Copy)]
+pub enum Interpolation {
+    /// Any easing function like the ones in [`interpolations`]
+    Easing(fn(f64) -> f64),
+
+    /// Cubic bézier curve from (0, 0) to (1, 1) with the two control points in between - like in CSS
+    CubicBezier { x1: f64, y1: f64, x2: f64, y2: f64 },
+
+    /// Overshoots the target and oscillates around it while settling
+    Elastic { amplitude: f64, period: f64 },
+
+    /// Bounces off the target the given number of times before settling
+    Bounce { bounces: u32 },
+}
+
+impl Interpolation {
+    /// Shortest oscillation period of the elastic easing - shorter ones would divide by zero
+    pub const MIN_PERIOD: f64 = 0.01;
+
+    pub fn apply(&self, i: f64) -> f64 {
+        return match *self {
+            Self::Easing(easing) => easing(i),
+
+            Self::CubicBezier { x1, y1, x2, y2 } => {
+                let t = Self::bezier_solve(x1, x2, i.clamp(0.0, 1.0));
+                Self::bezier(y1, y2, t)
+            }
+
+            Self::Elastic { amplitude, period } => {
+                if i <= 0.0 || i >= 1.0 {
+                    return i.clamp(0.0, 1.0);
+                }
+
+                // Amplitudes below one can not reach the target
+                let amplitude = amplitude.max(1.0);
+                let period = period.max(Self::MIN_PERIOD);
+                let shift = period / TAU * (1.0 / amplitude).asin();
+                amplitude * 2f64.powf(-10.0 * i) * ((i - shift) * TAU / period).sin() + 1.0
+            }
+
+            Self::Bounce { bounces } => {
+                let i = i.clamp(0.0, 1.0);
+                1.0 - (i * PI * (bounces as f64 + 0.5)).cos().abs() * (1.0 - i).powi(2)
+            }
+        };
+    }
+
+    /// One coordinate of the bézier curve at `t` given the coordinates of the control points
+    fn bezier(p1: f64, p2: f64, t: f64) -> f64 {
+        let u = 1.0 - t;
+        return 3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t;
+    }
+
+    /// Finds `t` where the curve is at `x` - the curve is monotonic in x as long as the control
+    /// points stay within 0.0 - 1.0
+    fn bezier_solve(x1: f64, x2: f64, x: f64) -> f64 {
+        let (mut lower, mut upper) = (0.0, 1.0);
+        let mut t = x;
+
+        for _ in 0..32 {
+            let error = Self::bezier(x1, x2, t) - x;
+            if error.abs() < 1e-7 {
+                break;
+            }
+
+            if error > 0.0 { upper = t } else { lower = t };
+            t = (lower + upper) / 2.0;
+        }
+
+        return t;
+    }
+}
+
+impl From<fn(f64) -> f64> for Interpolation {
+    fn from(easing: fn(f64) -> f64) -> Self {
+        return Self::Easing(easing);
+    }
+}
+
+pub trait Lerp {
+    fn lerp(a: Self, b: Self, i: f64) -> Self;
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe<V> {
+    pub duration: Duration,
+    pub value: V,
+
+    pub interpolation: Interpolation,
+}
+
+impl<V, I> From<(Duration, I, Interpolation)> for Keyframe<V>
+    where I: Into<V> {
+    fn from((duration, value, interpolation): (Duration, I, Interpolation)) -> Self {
+        return Keyframe {
+            duration,
+            value: value.into(),
+            interpolation,
+        };
+    }
+}
+
+impl<V, I> From<(Duration, I)> for Keyframe<V>
+    where I: Into<V> {
+    fn from((duration, value): (Duration, I)) -> Self {
+        return Keyframe {
+            duration,
+            value: value.into(),
+            interpolation: Interpolation::Easing(interpolations::end),
+        };
+    }
+}
+
+impl<V, I> From<(f64, I, Interpolation)> for Keyframe<V>
+    where I: Into<V> {
+    fn from((duration, value, interpolation): (f64, I, Interpolation)) -> Self {
+        return Keyframe {
+            duration: Duration::from_secs_f64(duration),
+            value: value.into(),
+            interpolation,
+        };
+    }
+}
+
+impl<V, I> From<(f64, I)> for Keyframe<V>
+    where I: Into<V> {
+    fn from((duration, value): (f64, I)) -> Self {
+        return Keyframe {
+            duration: Duration::from_secs_f64(duration),
+            value: value.into(),
+            interpolation: Interpolation::Easing(interpolations::end),
+        };
+    }
+}
+
+impl<V> Keyframe<V> {
+    pub fn new(duration: Duration, value: V, interpolation: impl Into<Interpolation>) -> Self {
+        return Self {
+            duration,
+            value,
+            interpolation: interpolation.into(),
+        };
+    }
+}
+
+
+#[macro_export]
+macro_rules! keyframe {
+    (@ $duration:expr => { $value:expr } @ { $interpolation:expr }) => {
+        (($duration, $value, $interpolation).into())
+    };
+
+    ($duration:expr => { $value:expr } @ $interpolation:ident ( $($arg:expr),* )) => {
+        $crate::keyframe!(@ $duration => { $value } @ { $crate::engine::animation::interpolations::$interpolation($($arg),*) })
+    };
+
+    ($duration:expr => $value:literal @ $interpolation:ident ( $($arg:expr),* )) => {
+        $crate::keyframe!(@ $duration => { $value } @ { $crate::engine::animation::interpolations::$interpolation($($arg),*) })
+    };
+
+    ($duration:expr => { $value:expr } @ $interpolation:ident) => {
+        $crate::keyframe!(@ $duration => { $value } @ { $crate::engine::animation::Interpolation::Easing($crate::engine::animation::interpolations::$interpolation) })
+    };
+
+    ($duration:expr => $value:literal @ $interpolation:ident) => {
+        $crate::keyframe!(@ $duration => { $value } @ { $crate::engine::animation::Interpolation::Easing($crate::engine::animation::interpolations::$interpolation) })
+    };
+
+    ($duration:expr => { $value:expr }) => {
+        $crate::keyframe!($duration => { $value } @ end)
+    };
+
+    ($duration:expr => $value:literal) => {
+        $crate::keyframe!($duration => { $value } @ end)
+    };
+}
+
+#[macro_export]
+macro_rules! keyframes {
+    (@expr $e:expr) => { $e };
+
+    (@rec [ ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@expr [ $($body)* ])
+    };
+
+    (@rec [ $duration:expr => { $value:expr } @ $interpolation:ident ( $($arg:expr),* ), $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => { $value } @ $interpolation ( $($arg),* )),))
+    };
+
+    (@rec [ $duration:expr => $value:literal @ $interpolation:ident ( $($arg:expr),* ), $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => $value @ $interpolation ( $($arg),* )),))
+    };
+
+    (@rec [ $duration:expr => { $value:expr } @ $interpolation:ident, $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => { $value } @ $interpolation),))
+    };
+
+    (@rec [ $duration:expr => $value:literal @ $interpolation:ident, $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => $value @ $interpolation),))
+    };
+
+    (@rec [ $duration:expr => { $value:expr }, $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => { $value }),))
+    };
+
+    (@rec [ $duration:expr => $value:literal, $($r:tt)* ] -> ($($body:tt)*)) => {
+        $crate::keyframes!(@rec [ $($r)* ] -> ($($body)* $crate::keyframe!($duration => $value),))
+    };
+
+    ($($frame:tt)*) => {
+        $crate::keyframes!(@rec [ $($frame)* ] -> ())
+    };
+}
+
+/// How often a looping animation passes through its timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+/// Where a looping animation continues after passing through its timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Starts over with the first keyframe
+    Restart,
+
+    /// Passes through the timeline backwards to where it started from and forwards again
+    PingPong,
+}
+
+struct Looping<V> {
+    timeline: Vec<Keyframe<V>>,
+
+    // Value before the first keyframe - the end of a backward pass
+    origin: V,
+
+    mode: LoopMode,
+
+    // Passes left to play
+    remaining: Option<u32>,
+
+    // The current keyframe, the direction it is played in and the time already spent in it
+    index: usize,
+    backwards: bool,
+    elapsed: Duration,
+
+    // Keyframes played once the loop has finished
+    then: VecDeque<Keyframe<V>>,
+}
+
+impl<V> Looping<V>
+    where
+        V: Copy,
+{
+    /// The value the current keyframe is played towards
+    fn target(&self) -> V {
+        if !self.backwards {
+            return self.timeline[self.index].value;
+        }
+
+        return match self.index {
+            0 => self.origin,
+            index => self.timeline[index - 1].value,
+        };
+    }
+
+    /// Continues with the next keyframe and returns whether the loop has finished
+    fn advance(&mut self) -> bool {
+        let last = if self.backwards { self.index == 0 } else { self.index + 1 == self.timeline.len() };
+        if !last {
+            if self.backwards { self.index -= 1 } else { self.index += 1 };
+            return false;
+        }
+
+        if let Some(ref mut remaining) = self.remaining {
+            *remaining = remaining.saturating_sub(1);
+            if *remaining == 0 {
+                return true;
+            }
+        }
+
+        match self.mode {
+            LoopMode::Restart => self.index = 0,
+            LoopMode::PingPong => self.backwards = !self.backwards,
+        }
+
+        return false;
+    }
+}
+
+enum State<V> {
+    Running {
+        // Sequence of keyframes in this animation
+        timeline: VecDeque<Keyframe<V>>,
+
+        // Time already spent in the current keyframe
+        elapsed: Duration,
+    },
+
+    Looping(Looping<V>),
+
+    Idle,
+}
+
+/// Token of queued keyframes telling when they have been played
+///
+/// Animations replaced before being played count as completed, too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion(u64);
+
+pub struct Animated<V> {
+    state: State<V>,
+    value: V,
+
+    // Number of keyframes ever queued and played - a loop counts as a single keyframe
+    queued: u64,
+    played: u64,
+}
+
+
+impl<V> Animated<V>
+    where
+        V: Lerp + Copy,
+{
+    pub fn idle(value: impl Into<V>) -> Self {
+        return Self {
+            state: State::Idle,
+            value: value.into(),
+            queued: 0,
+            played: 0,
+        };
+    }
+
+    pub fn set(&mut self, value: V) {
+        self.state = State::Idle;
+        self.value = value;
+        self.played = self.queued;
+    }
+
+    /// Plays the keyframes after the running animation - a loop must finish or be stopped first
+    pub fn animate(&mut self, keyframes: impl IntoIterator<Item=Keyframe<V>>) -> Completion {
+        let keyframes = keyframes.into_iter().collect::<Vec<_>>();
+        self.queued += keyframes.len() as u64;
+
+        match self.state {
+            State::Running { ref mut timeline, .. } => {
+                timeline.extend(keyframes);
+            }
+            State::Looping(ref mut looping) => {
+                looping.then.extend(keyframes);
+            }
+            State::Idle => {
+                self.state = State::Running {
+                    elapsed: Duration::ZERO,
+                    timeline: keyframes.into_iter().collect(),
+                };
+            }
+        }
+
+        return Completion(self.queued);
+    }
+
+    pub fn set_and_animate(&mut self, value: V, keyframes: impl IntoIterator<Item=Keyframe<V>>) -> Completion {
+        self.set(value);
+        return self.animate(keyframes);
+    }
+
+    /// Plays the keyframes repeatedly starting from the current value - replaces any running
+    /// animation
+    ///
+    /// Endless loops complete once stopped and blended back.
+    pub fn animate_looping(&mut self, keyframes: impl IntoIterator<Item=Keyframe<V>>, repeat: Repeat, mode: LoopMode) -> Completion {
+        let timeline = keyframes.into_iter().collect::<Vec<_>>();
+
+        // A loop without any duration would never advance in time
+        if timeline.iter().all(|keyframe| keyframe.duration.is_zero()) || repeat == Repeat::Times(0) {
+            self.set(timeline.last().map_or(self.value(), |keyframe| keyframe.value));
+            return Completion(self.queued);
+        }
+
+        self.played = self.queued;
+        self.queued += 1;
+
+        self.value = self.value();
+        self.state = State::Looping(Looping {
+            timeline,
+            origin: self.value,
+            mode,
+            remaining: match repeat {
+                Repeat::Times(times) => Some(times),
+                Repeat::Forever => None,
+            },
+            index: 0,
+            backwards: false,
+            elapsed: Duration::ZERO,
+            then: VecDeque::new(),
+        });
+
+        return Completion(self.queued);
+    }
+
+    /// Stops a loop right away and blends back to the value it started from - keyframes queued
+    /// after the loop are played afterwards and the loop completes with the blend
+    pub fn stop_looping(&mut self, blend: Duration) {
+        let value = self.value();
+
+        if let State::Looping(ref mut looping) = self.state {
+            let mut timeline = std::mem::take(&mut looping.then);
+            timeline.push_front(Keyframe::new(blend, looping.origin, interpolations::linear as fn(f64) -> f64));
+
+            self.value = value;
+            self.state = State::Running {
+                timeline,
+                elapsed: Duration::ZERO,
+            };
+        }
+    }
+
+    pub fn update(&mut self, duration: Duration) {
+        if let State::Running { ref mut elapsed, ref mut timeline } = self.state {
+            // Continue in time
+            *elapsed += duration;
+
+            while let Some(keyframe) = timeline.front() {
+                if *elapsed >= keyframe.duration {
+                    // The current keyframe has completed
+                    self.value = keyframe.value;
+
+                    *elapsed -= keyframe.duration;
+                    timeline.pop_front();
+                    self.played += 1;
+                } else {
+                    // Stay in the current frame
+                    break;
+                }
+            }
+
+            if timeline.is_empty() {
+                // Timeline depleted - idling
+                self.state = State::Idle;
+            }
+        }
+
+        if let State::Looping(ref mut looping) = self.state {
+            looping.elapsed += duration;
+
+            let mut finished = false;
+            while looping.elapsed >= looping.timeline[looping.index].duration {
+                looping.elapsed -= looping.timeline[looping.index].duration;
+                self.value = looping.target();
+
+                if looping.advance() {
+                    finished = true;
+                    break;
+                }
+            }
+
+            if finished {
+                self.played += 1;
+
+                let timeline = std::mem::take(&mut looping.then);
+                self.state = State::Running {
+                    elapsed: looping.elapsed,
+                    timeline,
+                };
+
+                // Continue with the keyframes queued after the loop
+                self.update(Duration::ZERO);
+            }
+        }
+    }
+
+    pub fn value(&self) -> V {
+        match &self.state {
+            State::Running { elapsed, timeline } => {
+                if let Some(keyframe) = timeline.front() {
+                    let delta = elapsed.as_secs_f64() / keyframe.duration.as_secs_f64();
+                    let delta = keyframe.interpolation.apply(delta);
+                    return V::lerp(self.value, keyframe.value, delta);
+                } else {
+                    return self.value;
+                }
+            }
+            State::Looping(looping) => {
+                let keyframe = &looping.timeline[looping.index];
+                let delta = looping.elapsed.as_secs_f64() / keyframe.duration.as_secs_f64();
+
+                // Backward passes mirror the keyframe in time
+                return if looping.backwards {
+                    V::lerp(looping.target(), keyframe.value, keyframe.interpolation.apply(1.0 - delta))
+                } else {
+                    V::lerp(self.value, keyframe.value, keyframe.interpolation.apply(delta))
+                };
+            }
+            State::Idle => {
+                return self.value;
+            }
+        };
+    }
+
+    pub fn is_idle(&self) -> bool {
+        return match self.state {
+            State::Idle => true,
+            State::Running { .. } | State::Looping(_) => false,
+        };
+    }
+
+    pub fn is_looping(&self) -> bool {
+        return matches!(self.state, State::Looping(_));
+    }
+
+    /// Whether all keyframes queued up to the token have been played
+    pub fn completed(&self, completion: Completion) -> bool {
+        return self.played >= completion.0;
+    }
+}
+
+impl Lerp for u8 {
+    fn lerp(a: Self, b: Self, i: f64) -> Self {
+        if a < b {
+            return a + ((b - a) as f64 * i) as u8;
+        } else {
+            return a - ((a - b) as f64 * i) as u8;
+        }
+    }
+}
+
+impl Lerp for f32 {
+    fn lerp(a: Self, b: Self, i: f64) -> Self {
+        return a + ((b - a) as f64 * i) as f32;
+    }
+}
+
+impl Lerp for RGBColor {
+    fn lerp(a: Self, b: Self, i: f64) -> Self {
+        return RGBColor::weighted_midpoint(b, a, i);
+    }
+}
+
+pub mod interpolations {
+    pub use easings::*;
+
+    use super::Interpolation;
+
+    pub fn end(i: f64) -> f64 {
+        if i < 1.0 { 0.0 } else { 1.0 }
+    }
+
+    pub fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64) -> Interpolation {
+        return Interpolation::CubicBezier {
+            x1: x1.clamp(0.0, 1.0),
+            y1,
+            x2: x2.clamp(0.0, 1.0),
+            y2,
+        };
+    }
+
+    /// Elastic easing with the given overshoot (1.0 and up) and oscillation period (fraction of the keyframe)
+    pub fn elastic(amplitude: f64, period: f64) -> Interpolation {
+        return Interpolation::Elastic { amplitude, period: period.max(Interpolation::MIN_PERIOD) };
+    }
+
+    pub fn bounce(bounces: u32) -> Interpolation {
+        return Interpolation::Bounce { bounces };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_idle_value() {
+        let animation: Animated<u8> = Animated::idle(7);
+        assert!(animation.is_idle());
+        assert_eq!(animation.value(), 7);
+    }
+
+    #[test]
+    fn test_animation_value() {
+        let mut animation: Animated<u8> = Animated::idle(7);
+        assert_eq!(animation.value(), 7);
+
+        animation.animate(keyframes!(
+            1.0 => 9 @ linear,
+            2.0 => 8 @ end,
+            3.0 => 5 @ linear,
+        ));
+
+        assert!(!animation.is_idle());
+
+        assert_eq!(animation.value(), 7);
+
+        // 1. Frame: 1.0 => 9 @ linear
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.value(), 8);
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.value(), 9);
+
+        // 2. Frame: 2.0 => 8 @ end
+        animation.update(Duration::from_millis(1500));
+        assert_eq!(animation.value(), 9);
+        animation.update(Duration::from_millis(400));
+        assert_eq!(animation.value(), 9);
+        animation.update(Duration::from_millis(100));
+        assert_eq!(animation.value(), 8);
+
+        // 3. Frame: 3.0 => 4 @ linear
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 7);
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 6);
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 5);
+
+        assert!(animation.is_idle());
+    }
+
+    #[test]
+    fn test_looping() {
+        let mut animation: Animated<u8> = Animated::idle(0);
+        animation.animate_looping(keyframes!(
+            1.0 => 10 @ linear,
+            1.0 => 20 @ linear,
+        ), Repeat::Times(3), LoopMode::PingPong);
+        animation.animate(keyframes!(
+            1.0 => 50 @ end,
+        ));
+
+        animation.update(Duration::from_millis(1500));
+        assert_eq!(animation.value(), 15);
+
+        // Backwards to where the loop started
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 15);
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 5);
+        assert!(animation.is_looping());
+
+        // The queued keyframes follow the last pass
+        animation.update(Duration::from_millis(2500));
+        assert_eq!(animation.value(), 20);
+        assert!(!animation.is_looping());
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 50);
+        assert!(animation.is_idle());
+
+        // Endless loops run until stopped and blend back
+        animation.animate_looping(keyframes!(
+            1.0 => 100 @ linear,
+        ), Repeat::Forever, LoopMode::PingPong);
+        animation.update(Duration::from_millis(10_500));
+        assert_eq!(animation.value(), 75);
+
+        animation.stop_looping(Duration::from_secs(1));
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.value(), 63);
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.value(), 50);
+        assert!(animation.is_idle());
+    }
+
+    #[test]
+    fn test_interpolations() {
+        let ease = interpolations::cubic_bezier(0.25, 0.1, 0.25, 1.0);
+        assert!((ease.apply(0.5) - 0.8024).abs() < 1e-3);
+        assert!((interpolations::cubic_bezier(0.0, 0.0, 1.0, 1.0).apply(0.3) - 0.3).abs() < 1e-6);
+
+        // Parameterized easings start and end on the values
+        for interpolation in [interpolations::elastic(1.5, 0.3), interpolations::bounce(3), ease] {
+            assert!(interpolation.apply(0.0).abs() < 1e-6);
+            assert!((interpolation.apply(1.0) - 1.0).abs() < 1e-6);
+        }
+        assert!(interpolations::elastic(1.5, 0.3).apply(0.1) > 1.0);
+        assert!(interpolations::elastic(1.5, 0.0).apply(0.5).is_finite());
+
+        let mut animation: Animated<u8> = Animated::idle(0);
+        animation.animate(keyframes!(
+            1.0 => 100 @ cubic_bezier(0.0, 0.0, 1.0, 1.0),
+            1.0 => { 0 } @ bounce(2),
+        ));
+
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.value(), 50);
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.value(), 18);
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut animation: Animated<u8> = Animated::idle(0);
+
+        let first = animation.animate(keyframes!(
+            1.0 => 10 @ linear,
+        ));
+        let looping = animation.animate_looping(keyframes!(
+            1.0 => 20 @ linear,
+        ), Repeat::Times(2), LoopMode::Restart);
+
+        // Replaced keyframes complete right away
+        assert!(animation.completed(first));
+        assert!(!animation.completed(looping));
+
+        let last = animation.animate(keyframes!(
+            1.0 => 30 @ linear,
+            1.0 => 40 @ linear,
+        ));
+
+        animation.update(Duration::from_millis(2000));
+        assert!(animation.completed(looping));
+        animation.update(Duration::from_millis(1500));
+        assert!(!animation.completed(last));
+        animation.update(Duration::from_millis(500));
+        assert!(animation.completed(last));
+    }
+}
\ No newline at end of file
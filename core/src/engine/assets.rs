@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use tracing::{trace_span, instrument, warn};
+
+use crate::engine::sound::{Music, Sfx};
+use std::fmt::Debug;
+
+pub trait AssetLoader: Sized {
+    type Asset;
+
+    /// Data read when loading the bundle to avoid reading it on every use
+    type Preloaded;
+
+    fn preload(path: impl AsRef<Path>) -> Result<Self::Preloaded>;
+
+    fn load(path: impl AsRef<Path>, preloaded: &Self::Preloaded) -> Result<Self::Asset>;
+}
+
+pub struct Asset<L: AssetLoader> {
+    pub path: PathBuf,
+    pub name: String,
+
+    preloaded: L::Preloaded,
+}
+
+impl<L: AssetLoader> Asset<L> {
+    #[instrument(level = "debug", name = "Asset::load")]
+    pub fn load(&self) -> L::Asset {
+        return trace_span!("Loading asset", path=?self.path)
+            .in_scope(|| L::load(&self.path, &self.preloaded))
+            .with_context(|| format!("Loading asset: {:?}", self.path))
+            .expect("Failed to load asset");
+    }
+}
+
+impl<L: AssetLoader> fmt::Debug for Asset<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Asset")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct AssetBundle<L: AssetLoader> {
+    assets: Vec<Asset<L>>,
+}
+
+impl<L: AssetLoader> AssetBundle<L> {
+    #[instrument(level = "debug")]
+    pub(self) fn load(path: impl AsRef<Path> + Debug) -> Result<Self> {
+        let assets = path.as_ref().read_dir()
+            .with_context(|| format!("Failed to open asset directory: {:?}", path.as_ref()))?
+            .map(|entry| {
+                let entry = entry?;
+                let name = entry.path().file_stem()
+                    .ok_or(anyhow!("Invalid filename: {:?}", entry.path()))?
+                    .to_string_lossy().to_string();
+
+                let preloaded = L::preload(entry.path())
+                    .with_context(|| format!("Failed to load asset: {:?}", entry.path()))?;
+
+                return Ok(Asset {
+                    path: entry.path(),
+                    name,
+                    preloaded,
+                });
+            })
+            .collect::<Result<_>>()?;
+
+        return Ok(Self {
+            assets
+        });
+    }
+
+    /// Loads the bundle like `load` but treats a missing directory as an empty bundle
+    #[instrument(level = "debug")]
+    pub(self) fn load_optional(path: impl AsRef<Path> + Debug) -> Result<Self> {
+        if !path.as_ref().exists() {
+            warn!("Asset directory does not exist: {:?}", path.as_ref());
+            return Ok(Self {
+                assets: Vec::new(),
+            });
+        }
+
+        return Self::load(path);
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item=&Asset<L>> {
+        return self.assets.iter();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Asset<L>> {
+        return self.assets.iter()
+            .find(|asset| asset.name == name);
+    }
+
+    pub fn random(&self, rng: &mut impl Rng) -> Option<&Asset<L>> {
+        return self.assets.choose(rng);
+    }
+}
+
+/// Voice announcements with a pack of clips for each language
+///
+/// Each language is a sub-directory named by the language code containing the clips named by the
+/// announcement they are used for: `countdown`, `go`, `faster`, `slower` and `winner`.
+pub struct Voices {
+    packs: HashMap<String, AssetBundle<Sfx>>,
+}
+
+impl Voices {
+    /// The language used for clips missing in other languages
+    pub const DEFAULT: &'static str = "en";
+
+    #[instrument(level = "debug")]
+    fn load(path: impl AsRef<Path> + Debug) -> Result<Self> {
+        let mut packs = HashMap::new();
+
+        if path.as_ref().exists() {
+            for entry in path.as_ref().read_dir()
+                .with_context(|| format!("Failed to open voice directory: {:?}", path.as_ref()))? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+
+                let language = entry.file_name().to_string_lossy().to_string();
+                packs.insert(language, AssetBundle::load(entry.path())?);
+            }
+        } else {
+            warn!("Voice directory does not exist: {:?}", path.as_ref());
+        }
+
+        return Ok(Self { packs });
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item=&str> {
+        return self.packs.keys().map(String::as_str);
+    }
+
+    pub fn has_language(&self, language: &str) -> bool {
+        return self.packs.contains_key(language);
+    }
+
+    /// Looks up a clip in the given language and falls back to the default language if missing
+    pub fn get(&self, language: &str, name: &str) -> Option<&Asset<Sfx>> {
+        return self.packs.get(language)
+            .and_then(|pack| pack.get(name))
+            .or_else(|| self.packs.get(Self::DEFAULT)
+                .and_then(|pack| pack.get(name)));
+    }
+}
+
+pub struct Assets {
+    pub music: AssetBundle<Music>,
+    pub sfx: AssetBundle<Sfx>,
+    pub voice: Voices,
+}
+
+impl Assets {
+    #[instrument(level = "debug")]
+    pub fn init(path: impl AsRef<Path> + Debug) -> Result<Self> {
+        let music = AssetBundle::load(path.as_ref().join("music"))
+            .context("Failed to load music assets")?;
+
+        let sfx = AssetBundle::load_optional(path.as_ref().join("sfx"))
+            .context("Failed to load sfx assets")?;
+
+        let voice = Voices::load(path.as_ref().join("voice"))
+            .context("Failed to load voice assets")?;
+
+        return Ok(Self {
+            music,
+            sfx,
+            voice,
+        });
+    }
+
+    /// Assets without any content
+    pub fn empty() -> Self {
+        return Self {
+            music: AssetBundle { assets: Vec::new() },
+            sfx: AssetBundle { assets: Vec::new() },
+            voice: Voices { packs: HashMap::new() },
+        };
+    }
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+use serde::Serialize;
+
+use crate::controller::Input;
+
+/// Gestures recognized from the motion of a controller
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Gesture {
+    /// Fast rotation like swinging a sword
+    Swing,
+
+    /// Short thrust without much rotation
+    Jab,
+
+    /// Repeated back and forth movement
+    Shake,
+
+    /// Turning the controller around its long axis
+    Flip,
+}
+
+/// Thresholds for recognizing gestures - lower values make gestures easier to perform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Peak rotation rate in rad/s across the long axis (y) of a swing
+    pub swing: f32,
+
+    /// Peak acceleration in g beside gravity of a jab
+    pub jab: f32,
+
+    /// Acceleration in g a shake must reverse its direction with
+    pub shake: f32,
+
+    /// Number of direction reversals of a shake
+    pub shake_reversals: usize,
+
+    /// Rotation in rad around the long axis of a flip
+    pub flip: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        return Self {
+            swing: 8.0,
+            jab: 1.5,
+            shake: 0.8,
+            shake_reversals: 4,
+            flip: 0.75 * std::f32::consts::PI,
+        };
+    }
+}
+
+struct Sample {
+    accelerometer: Vector3<f32>,
+    gyroscope: Vector3<f32>,
+    elapsed: Duration,
+}
+
+/// Classifies the recent motion of a controller into gestures
+pub struct Detector {
+    thresholds: Thresholds,
+
+    // Samples within the window and their total duration
+    samples: VecDeque<Sample>,
+    duration: Duration,
+
+    // Gestures recognized by the last update
+    detected: Vec<Gesture>,
+
+    // Remaining time until the next gesture can be recognized
+    cooldown: Duration,
+}
+
+impl Detector {
+    // Time span of the motion considered for a gesture
+    const WINDOW: Duration = Duration::from_millis(500);
+
+    pub fn new(thresholds: Thresholds) -> Self {
+        return Self {
+            thresholds,
+            samples: VecDeque::new(),
+            duration: Duration::ZERO,
+            detected: Vec::new(),
+            cooldown: Duration::ZERO,
+        };
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        return self.thresholds;
+    }
+
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Gestures recognized by the last update
+    pub fn detected(&self) -> &[Gesture] {
+        return &self.detected;
+    }
+
+    pub fn update(&mut self, input: &Input, elapsed: Duration) {
+        self.detected.clear();
+
+        self.samples.push_back(Sample {
+            accelerometer: input.accelerometer,
+            gyroscope: input.gyroscope,
+            elapsed,
+        });
+        self.duration += elapsed;
+
+        while self.duration > Self::WINDOW {
+            match self.samples.pop_front() {
+                Some(sample) => self.duration -= sample.elapsed,
+                None => break,
+            }
+        }
+
+        if !self.cooldown.is_zero() {
+            self.cooldown = self.cooldown.saturating_sub(elapsed);
+            return;
+        }
+
+        if let Some(gesture) = self.classify() {
+            self.detected.push(gesture);
+
+            // Each motion is only recognized once
+            self.samples.clear();
+            self.duration = Duration::ZERO;
+            self.cooldown = Self::WINDOW;
+        }
+    }
+
+    fn classify(&self) -> Option<Gesture> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        // Reversals of the acceleration around its mean on any axis
+        let mean = self.samples.iter()
+            .fold(Vector3::zero(), |sum, sample| sum + sample.accelerometer) / self.samples.len() as f32;
+
+        let mut reversals = 0;
+        for axis in 0..3 {
+            let mut last = 0.0f32;
+            for sample in self.samples.iter() {
+                let deviation = sample.accelerometer[axis] - mean[axis];
+                if deviation.abs() < self.thresholds.shake {
+                    continue;
+                }
+
+                if last != 0.0 && deviation.signum() != last {
+                    reversals += 1;
+                }
+                last = deviation.signum();
+            }
+        }
+
+        if reversals >= self.thresholds.shake_reversals {
+            return Some(Gesture::Shake);
+        }
+
+        // The long axis of the controller is the y axis
+        let roll = self.samples.iter()
+            .map(|sample| sample.gyroscope.y * sample.elapsed.as_secs_f32())
+            .sum::<f32>();
+
+        if roll.abs() >= self.thresholds.flip {
+            return Some(Gesture::Flip);
+        }
+
+        let rotation = self.samples.iter()
+            .map(|sample| Vector2::new(sample.gyroscope.x, sample.gyroscope.z).magnitude())
+            .fold(0.0, f32::max);
+
+        if rotation >= self.thresholds.swing {
+            return Some(Gesture::Swing);
+        }
+
+        let acceleration = self.samples.iter()
+            .map(|sample| (sample.accelerometer.magnitude() - 1.0).abs())
+            .fold(0.0, f32::max);
+
+        if acceleration >= self.thresholds.jab && rotation < self.thresholds.swing / 2.0 {
+            return Some(Gesture::Jab);
+        }
+
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn detect(samples: &[(Vector3<f32>, Vector3<f32>)]) -> Vec<Gesture> {
+        let mut detector = Detector::new(Thresholds::default());
+        let mut detected = Vec::new();
+
+        for (accelerometer, gyroscope) in samples {
+            detector.update(&Input {
+                accelerometer: *accelerometer,
+                gyroscope: *gyroscope,
+                ..Input::default()
+            }, Duration::from_millis(10));
+            detected.extend_from_slice(detector.detected());
+        }
+
+        return detected;
+    }
+
+    #[test]
+    fn test_gestures() {
+        let rest = (Vector3::unit_z(), Vector3::zero());
+
+        assert_eq!(detect(&[rest; 50]), vec![]);
+
+        assert_eq!(detect(&[rest, (Vector3::unit_z(), Vector3::new(10.0, 0.0, 0.0)), rest]), vec![Gesture::Swing]);
+
+        assert_eq!(detect(&[rest, (Vector3::new(0.0, 0.0, 3.0), Vector3::zero()), rest]), vec![Gesture::Jab]);
+
+        assert_eq!(detect(&[(Vector3::unit_z(), Vector3::new(0.0, 12.0, 0.0)); 30]), vec![Gesture::Flip]);
+
+        let shake = (0..12)
+            .map(|i| (Vector3::new(if i % 2 == 0 { 1.5 } else { -1.5 }, 0.0, 1.0), Vector3::zero()))
+            .collect::<Vec<_>>();
+        assert_eq!(detect(&shake), vec![Gesture::Shake]);
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use crate::engine::animation::{interpolations, Keyframe};
+
+/// A rumble pattern played on the rumble of a player
+///
+/// Patterns always start from silence and end silent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HapticPattern {
+    /// Rumbles with a constant intensity for the duration
+    Pulse { intensity: u8, duration: Duration },
+
+    /// Changes the intensity linearly over the duration
+    Ramp { from: u8, to: u8, duration: Duration },
+
+    /// Double beats like a heart with the given intensity
+    Heartbeat { intensity: u8, beats: usize },
+
+    /// Toggles between the intensity and silence
+    Stutter { intensity: u8, count: usize, interval: Duration },
+}
+
+/// Named patterns games can trigger by name
+const LIBRARY: &[(&str, HapticPattern)] = &[
+    ("buzz", HapticPattern::BUZZ),
+    ("tap", HapticPattern::TAP),
+    ("hit", HapticPattern::HIT),
+    ("shake", HapticPattern::SHAKE),
+    ("charge", HapticPattern::CHARGE),
+    ("heartbeat", HapticPattern::HEARTBEAT),
+    ("alarm", HapticPattern::ALARM),
+];
+
+impl HapticPattern {
+    pub const BUZZ: Self = Self::Pulse { intensity: 0xFF, duration: Duration::from_secs(1) };
+    pub const TAP: Self = Self::Pulse { intensity: 0xC0, duration: Duration::from_millis(80) };
+    pub const HIT: Self = Self::Ramp { from: 0xFF, to: 0x00, duration: Duration::from_secs(1) };
+    pub const SHAKE: Self = Self::Ramp { from: 0xFF, to: 0x00, duration: Duration::from_millis(500) };
+    pub const CHARGE: Self = Self::Ramp { from: 0x00, to: 0xFF, duration: Duration::from_secs(2) };
+    pub const HEARTBEAT: Self = Self::Heartbeat { intensity: 0xC0, beats: 3 };
+    pub const ALARM: Self = Self::Stutter { intensity: 0xFF, count: 6, interval: Duration::from_millis(150) };
+
+    /// Looks up a pattern from the library
+    pub fn named(name: &str) -> Option<Self> {
+        return LIBRARY.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, pattern)| *pattern);
+    }
+
+    pub fn names() -> impl Iterator<Item=&'static str> {
+        return LIBRARY.iter().map(|(name, _)| *name);
+    }
+
+    /// Compiles the pattern to keyframes animating the rumble from silence
+    pub fn keyframes(&self) -> Vec<Keyframe<u8>> {
+        fn jump(duration: Duration, value: u8) -> Keyframe<u8> {
+            return Keyframe::new(duration, value, interpolations::end as fn(f64) -> f64);
+        }
+
+        fn fade(duration: Duration, value: u8) -> Keyframe<u8> {
+            return Keyframe::new(duration, value, interpolations::linear as fn(f64) -> f64);
+        }
+
+        return match *self {
+            Self::Pulse { intensity, duration } => vec![
+                jump(Duration::ZERO, intensity),
+                jump(duration, 0),
+            ],
+
+            Self::Ramp { from, to, duration } => vec![
+                jump(Duration::ZERO, from),
+                fade(duration, to),
+                jump(Duration::ZERO, 0),
+            ],
+
+            Self::Heartbeat { intensity, beats } => (0..beats)
+                .flat_map(|_| [
+                    jump(Duration::ZERO, intensity),
+                    fade(Duration::from_millis(120), 0),
+                    jump(Duration::from_millis(100), intensity),
+                    fade(Duration::from_millis(180), 0),
+                    jump(Duration::from_millis(600), 0),
+                ])
+                .collect(),
+
+            Self::Stutter { intensity, count, interval } => (0..count)
+                .flat_map(|_| [
+                    jump(Duration::ZERO, intensity),
+                    jump(interval, 0),
+                    jump(interval, 0),
+                ])
+                .collect(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::animation::Animated;
+
+    #[test]
+    fn test_library() {
+        for name in HapticPattern::names() {
+            let mut rumble: Animated<u8> = Animated::idle(0);
+            rumble.animate(HapticPattern::named(name).unwrap().keyframes());
+
+            // Every pattern must end silent
+            rumble.update(Duration::from_secs(60));
+            assert!(rumble.is_idle(), "{}", name);
+            assert_eq!(rumble.value(), 0, "{}", name);
+        }
+
+        assert_eq!(HapticPattern::named("unknown"), None);
+    }
+
+    #[test]
+    fn test_stutter() {
+        let mut rumble: Animated<u8> = Animated::idle(0);
+        rumble.animate(HapticPattern::Stutter { intensity: 0xFF, count: 2, interval: Duration::from_millis(100) }.keyframes());
+
+        rumble.update(Duration::from_millis(50));
+        assert_eq!(rumble.value(), 0xFF);
+        rumble.update(Duration::from_millis(100));
+        assert_eq!(rumble.value(), 0x00);
+        rumble.update(Duration::from_millis(100));
+        assert_eq!(rumble.value(), 0xFF);
+        rumble.update(Duration::from_millis(100));
+        assert_eq!(rumble.value(), 0x00);
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use futures::{StreamExt, task::Poll};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use tracing::{debug, info, instrument, warn};
+
+use crate::controller::{Address, Controller, evdev, faults, filter, hid, pairing, recording, trigger};
+use crate::controller::calibration::Store;
+use crate::controller::evdev::Gamepad;
+use crate::controller::hid::Bus;
+use crate::controller::pairing::Outcome;
+use crate::controller::rate::Rates;
+use crate::controller::recording::{Recorder, Session};
+
+pub enum Change {
+    Added(Controller),
+    Removed(PathBuf),
+}
+
+#[derive(clap::Args, Debug)]
+#[group(id = "hotplug")]
+pub struct Config {
+    /// PWM frequency of the controller LEDs in Hz - raise it to avoid flickering on camera recordings
+    #[arg(long)]
+    pub led_pwm_frequency: Option<u32>,
+
+    #[command(flatten)]
+    pub filter: filter::Config,
+
+    #[command(flatten)]
+    pub trigger: trigger::Config,
+
+    #[command(flatten)]
+    pub recording: recording::Config,
+
+    #[command(flatten)]
+    pub gamepads: evdev::Config,
+}
+
+/// Watches for controllers being connected and disconnected
+pub struct Hotplug {
+    events: hid::Events,
+
+    // Only watched if gamepads are enabled
+    gamepads: Option<evdev::Events>,
+
+    config: Config,
+
+    calibration: Option<Store>,
+
+    rates: Rates,
+    faults: faults::Config,
+
+    // Faults of real controllers can not be reproduced anyway
+    rng: StdRng,
+
+    // The host to pair controllers plugged in via USB with
+    host: Option<Address>,
+
+    // Time base shared by the recordings of all controllers
+    session: Session,
+}
+
+impl Hotplug {
+    /// Starts watching and opens all controllers connected already
+    #[instrument(level = "debug")]
+    pub async fn init(config: Config, calibration: Option<Store>, rates: Rates, faults: faults::Config, pairing: pairing::Config) -> Result<(Self, Vec<Controller>)> {
+        let (devices, events) = hid::monitor()?;
+
+        let (pads, gamepads) = if config.gamepads.gamepads {
+            let (pads, gamepads) = evdev::monitor()?;
+            (pads, Some(gamepads))
+        } else {
+            (Vec::new(), None)
+        };
+
+        let host = if pairing.auto_pair {
+            match pairing::host_address() {
+                Ok(host) => Some(host),
+                Err(err) => {
+                    warn!("Automatic pairing disabled: {:#}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut hotplug = Self { events, gamepads, config, calibration, rates, faults, rng: StdRng::from_entropy(), host, session: Session::start() };
+
+        let mut controllers = Vec::new();
+        for device in devices {
+            debug!("Added controller: {:?}", device.path);
+            controllers.push(hotplug.open(&device).await?);
+        }
+
+        for path in pads {
+            debug!("Added gamepad: {:?}", path);
+            controllers.extend(hotplug.open_gamepad(&path).await);
+        }
+
+        return Ok((hotplug, controllers));
+    }
+
+    async fn open(&mut self, device: &hid::Device) -> Result<Controller> {
+        let controller = Controller::open(device).await?;
+        return Ok(self.prepare(controller).await);
+    }
+
+    /// Opens a gamepad - gamepads failing to open are skipped
+    async fn open_gamepad(&mut self, path: &Path) -> Option<Controller> {
+        let gamepad = match Gamepad::open(path) {
+            Ok(gamepad) => gamepad,
+            Err(err) => {
+                warn!("Skipping gamepad {:?}: {:#}", path, err);
+                return None;
+            }
+        };
+
+        return Some(self.prepare(Controller::new(Box::new(gamepad))).await);
+    }
+
+    /// Applies the configuration to a newly connected controller
+    async fn prepare(&mut self, controller: Controller) -> Controller {
+        let mut controller = controller.inject(&self.faults, self.rng.gen());
+        controller.set_rate(self.rates.get(controller.bus(), controller.model()));
+        controller.set_filter(self.config.filter.clone());
+        controller.set_trigger_curve(self.config.trigger.curve.clone());
+
+        if let Some(calibration) = self.calibration.as_ref().and_then(|store| store.get(controller.serial())) {
+            debug!("Applying calibration override to controller {}", controller.serial().as_string());
+            controller.set_calibration(calibration);
+        }
+
+        if let Some(ref directory) = self.config.recording.directory {
+            match Recorder::create(directory, self.session, controller.serial(), controller.model()) {
+                Ok(recorder) => controller.record(recorder),
+                Err(err) => warn!("Failed to record controller {}: {:#}", controller.serial().as_string(), err),
+            }
+        }
+
+        if let Some(frequency) = self.config.led_pwm_frequency {
+            if let Err(err) = controller.set_led_pwm(frequency).await {
+                warn!("Failed to set LED PWM frequency of controller {}: {:#}", controller.serial().as_string(), err);
+            }
+        }
+
+        if let Some(host) = self.host.filter(|_| controller.bus() == Bus::USB && controller.model().is_psmove()) {
+            match controller.pair(host).await {
+                Ok(Outcome::Paired) => info!("Paired controller {} to {}", controller.serial().as_string(), host.as_string()),
+                Ok(Outcome::AlreadyPaired) => debug!("Controller {} already paired to {}", controller.serial().as_string(), host.as_string()),
+                Err(err) => warn!("Failed to pair controller {}: {:#}", controller.serial().as_string(), err),
+            }
+        }
+
+        return controller;
+    }
+
+    /// Returns the next change if available
+    #[instrument(level = "trace", name = "Hotplug::poll", skip(self))]
+    pub async fn poll(&mut self) -> Result<Option<Change>> {
+        if let Poll::Ready(Some(event)) = futures::poll(self.events.next()).await {
+            return match event? {
+                hid::Event::Added(device) => {
+                    debug!("Added controller: {:?}", device.path);
+                    Ok(Some(Change::Added(self.open(&device).await?)))
+                }
+
+                hid::Event::Removed(path) => {
+                    debug!("Removed controller: {:?}", &path);
+                    Ok(Some(Change::Removed(path)))
+                }
+            };
+        }
+
+        if let Some(ref mut gamepads) = self.gamepads {
+            if let Poll::Ready(Some(event)) = futures::poll(gamepads.next()).await {
+                return match event? {
+                    evdev::Event::Added(path) => {
+                        debug!("Added gamepad: {:?}", path);
+                        Ok(self.open_gamepad(&path).await.map(Change::Added))
+                    }
+
+                    evdev::Event::Removed(path) => {
+                        debug!("Removed gamepad: {:?}", &path);
+                        Ok(Some(Change::Removed(path)))
+                    }
+                };
+            }
+        }
+
+        return Ok(None);
+    }
+}
@@ -0,0 +1,47 @@
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+use scarlet::color::{Color, RGBColor};
+use scarlet::colors::HSVColor;
+
+/// A slow color wave running across the controllers not taking part in the running game
+pub struct Wave;
+
+impl Wave {
+    // Time for the wave to pass each controller and for the hue to make a full rotation
+    const PERIOD: Duration = Duration::from_secs(6);
+    const HUE_ROTATION: Duration = Duration::from_secs(90);
+
+    // Brightness between and on the crest of the wave
+    const DIM: f64 = 0.05;
+    const BRIGHT: f64 = 0.4;
+
+    /// Brightness of the controller at `position` between 0 and 1 along the wave
+    fn brightness(elapsed: Duration, position: f64) -> f64 {
+        let phase = elapsed.as_secs_f64() / Self::PERIOD.as_secs_f64() - position;
+        return Self::DIM + (Self::BRIGHT - Self::DIM) * (0.5 + 0.5 * (phase * TAU).cos());
+    }
+
+    /// Color of the controller at `position` between 0 and 1 along the wave
+    pub fn color(elapsed: Duration, position: f64) -> RGBColor {
+        return HSVColor {
+            h: elapsed.as_secs_f64() / Self::HUE_ROTATION.as_secs_f64() * 360.0 % 360.0,
+            s: 1.0,
+            v: Self::brightness(elapsed, position),
+        }.convert::<RGBColor>();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_brightness() {
+        assert_eq!(Wave::brightness(Duration::ZERO, 0.0), Wave::BRIGHT);
+        assert!((Wave::brightness(Duration::ZERO, 0.5) - Wave::DIM).abs() < 1e-9);
+
+        // The crest moves along the controllers
+        assert!((Wave::brightness(Wave::PERIOD / 4, 0.25) - Wave::BRIGHT).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,143 @@
+use std::ops::Sub;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::engine::players::Players;
+use crate::engine::timing::{Timing, TimingReport};
+
+/// Running totals of the controller I/O
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counters {
+    /// Updates of individual controllers
+    pub updates: u64,
+
+    /// Controller updates which failed or timed out
+    pub errors: u64,
+
+    /// Feedback writes to the controllers
+    pub feedback: u64,
+
+    /// Feedback writes deferred to keep within the feedback budget
+    pub held: u64,
+}
+
+impl Counters {
+    /// Fraction of the controller updates which failed
+    pub fn error_rate(&self) -> f64 {
+        if self.updates == 0 {
+            return 0.0;
+        }
+
+        return self.errors as f64 / self.updates as f64;
+    }
+}
+
+impl Sub for Counters {
+    type Output = Counters;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        return Self {
+            updates: self.updates.saturating_sub(rhs.updates),
+            errors: self.errors.saturating_sub(rhs.errors),
+            feedback: self.feedback.saturating_sub(rhs.feedback),
+            held: self.held.saturating_sub(rhs.held),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsReport {
+    /// Frames since startup
+    pub frames: u64,
+
+    pub timing: TimingReport,
+
+    /// Number of connected controllers
+    pub controllers: usize,
+
+    pub counters: Counters,
+}
+
+impl MetricsReport {
+    pub fn of(timing: &Timing, players: &Players) -> Self {
+        return Self {
+            frames: timing.count,
+            timing: TimingReport {
+                frames: timing.frames.percentiles(),
+                work: timing.work.percentiles(),
+                controllers: players.latency().percentiles(),
+                backlog: timing.backlog.percentiles(),
+            },
+            controllers: players.count(),
+            counters: players.counters(),
+        };
+    }
+}
+
+/// Logs the metrics periodically with the rates since the previous log
+pub struct MetricsLog {
+    interval: Duration,
+
+    logged: Instant,
+    previous: (u64, Counters),
+}
+
+impl MetricsLog {
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        return Self {
+            interval,
+            logged: now,
+            previous: (0, Counters::default()),
+        };
+    }
+
+    pub fn due(&self, now: Instant) -> bool {
+        return now.saturating_duration_since(self.logged) >= self.interval;
+    }
+
+    pub fn log(&mut self, name: &str, now: Instant, report: &MetricsReport) {
+        let elapsed = now.saturating_duration_since(self.logged).as_secs_f64().max(f64::EPSILON);
+        let frames = report.frames.saturating_sub(self.previous.0);
+        let counters = report.counters - self.previous.1;
+
+        info!("Arena {}: {:.1} fps (work p99 {:?}), {} controllers (latency p99 {:?}), {:.1} feedback/s, {} held, {:.2}% errors",
+            name,
+            frames as f64 / elapsed,
+            report.timing.work.p99,
+            report.controllers,
+            report.timing.controllers.p99,
+            counters.feedback as f64 / elapsed,
+            counters.held,
+            counters.error_rate() * 100.0);
+
+        self.logged = now;
+        self.previous = (report.frames, report.counters);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let earlier = Counters {
+            updates: 100,
+            errors: 2,
+            feedback: 40,
+            held: 0,
+        };
+        let later = Counters {
+            updates: 300,
+            errors: 12,
+            feedback: 90,
+            held: 5,
+        };
+
+        let delta = later - earlier;
+        assert_eq!(delta.updates, 200);
+        assert_eq!(delta.error_rate(), 0.05);
+        assert_eq!(Counters::default().error_rate(), 0.0);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Instant;
+
+use tracing::debug;
+
+use crate::engine::assets::Assets;
+use crate::engine::players::Players;
+use crate::engine::random::Random;
+use crate::engine::scope::Scope;
+use crate::engine::sound::{Priority, Sound};
+use crate::engine::timer::Timers;
+use crate::engine::timing::Timing;
+use crate::events::Events;
+
+pub mod hotplug;
+pub mod players;
+pub mod sound;
+pub mod assets;
+pub mod animation;
+pub mod haptics;
+pub mod gestures;
+pub mod idle;
+pub mod scope;
+pub mod timing;
+pub mod metrics;
+pub mod timer;
+pub mod random;
+
+/// Everything a game interacts with during a frame
+pub struct World<'a, S, E, R> {
+    // Current time of the frame
+    pub now: Instant,
+
+    pub players: &'a mut Players,
+
+    pub sound: &'a mut Sound,
+
+    pub assets: &'a Assets,
+
+    pub settings: &'a mut S,
+
+    pub timing: &'a Timing,
+
+    // Timers scheduled by the running game
+    pub timers: &'a mut Timers,
+
+    pub rng: &'a mut Random,
+
+    // Sensor samples streamed for inspecting controllers
+    pub scope: &'a Scope,
+
+    pub events: &'a Events<E>,
+
+    // Results recorded over the running session
+    pub scoring: &'a mut R,
+
+    // Scale of the movement threshold on top of the settings - changed by the running round for the
+    // current frame
+    pub threshold_scale: f32,
+}
+
+/// Settings of the application the engine depends on
+pub trait Settings {
+    /// Language of voice announcements
+    fn language(&self) -> &str;
+
+    /// Movement threshold of all players
+    fn threshold(&self) -> f32;
+}
+
+impl<S, E, R> World<'_, S, E, R>
+    where
+        S: Settings,
+{
+    /// Movement threshold of all players in the current frame
+    pub fn threshold(&self) -> f32 {
+        return self.settings.threshold() * self.threshold_scale;
+    }
+
+    /// Plays a voice announcement in the active language
+    pub fn announce(&mut self, clip: &str) {
+        if let Some(asset) = self.assets.voice.get(self.settings.language(), clip) {
+            self.sound.cue(self.now, Priority::Announcement, clip, asset);
+        } else {
+            debug!("Voice clip not available: {}", clip);
+        }
+    }
+}
@@ -0,0 +1,1003 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cgmath::InnerSpace;
+use heapless::HistoryBuffer;
+use scarlet::color::RGBColor;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::controller::{Address, Battery, Controller, Feedback, Input, signal};
+use crate::controller::calibration::Override;
+use crate::controller::hid::Bus;
+use crate::controller::pairing::PairingResult;
+use crate::engine::animation::{Animated, interpolations, Keyframe, Lerp, LoopMode, Repeat};
+use crate::engine::gestures::{Detector, Gesture, Thresholds};
+use crate::engine::haptics::HapticPattern;
+use crate::engine::idle::Wave;
+use crate::engine::metrics::Counters;
+use crate::engine::timing::DurationSamples;
+
+pub type PlayerId = u64;
+
+struct Discharge {
+    // Battery level when the controller was first seen draining
+    level: f32,
+    since: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatteryReport {
+    pub player: PlayerId,
+    pub address: Address,
+    pub battery: Battery,
+    pub remaining: Option<Duration>,
+}
+
+/// Movement of a group of players taken together
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupMetrics {
+    /// Number of connected players in the group
+    pub count: usize,
+
+    /// Sum of the average acceleration of all players
+    pub total: f32,
+
+    /// Highest average acceleration of a single player
+    pub peak: f32,
+}
+
+impl GroupMetrics {
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        return self.total / self.count as f32;
+    }
+}
+
+/// Color animation shown on top of the color of a player and blended in by its opacity
+///
+/// Overlays are dropped once they are fully transparent and not animated anymore.
+pub struct Overlay {
+    pub color: Animated<RGBColor>,
+    pub opacity: Animated<f32>,
+}
+
+impl Overlay {
+    fn new() -> Self {
+        return Self {
+            color: Animated::idle(RGBColor { r: 0.0, g: 0.0, b: 0.0 }),
+            opacity: Animated::idle(0.0f32),
+        };
+    }
+
+    fn update(&mut self, duration: Duration) {
+        self.color.update(duration);
+        self.opacity.update(duration);
+    }
+
+    fn finished(&self) -> bool {
+        return self.opacity.is_idle() && self.opacity.value() <= 0.0;
+    }
+
+    fn blend(&self, color: RGBColor) -> RGBColor {
+        return RGBColor::lerp(color, self.color.value(), self.opacity.value().clamp(0.0, 1.0) as f64);
+    }
+}
+
+pub struct Player {
+    controller: Controller,
+
+    acceleration: HistoryBuffer<f32, 4>,
+
+    discharge: Option<Discharge>,
+
+    // Last time the player moved or pressed a button
+    active: Instant,
+
+    gestures: Detector,
+
+    // Since when the battery is below the warning level
+    low_battery: Option<Instant>,
+
+    // Scales the movement thresholds of games for this player
+    threshold: f32,
+
+    pub rumble: Animated<u8>,
+    pub color: Animated<RGBColor>,
+
+    // Layers composited on top of the color in the order they were added
+    overlays: Vec<(&'static str, Overlay)>,
+
+    failed: usize,
+}
+
+impl Player {
+    const TIMEOUT: Duration = Duration::from_millis(1000);
+
+    // Deviation from gravity counted as movement
+    const ACTIVE_ACCELERATION: f32 = 0.1;
+
+    // Nominal runtime of a fully charged controller
+    const BATTERY_RUNTIME: Duration = Duration::from_secs(8 * 60 * 60);
+
+    // Blinking shown on top of the color while the battery is low
+    const LOW_BATTERY_LAYER: &'static str = "low-battery";
+    const LOW_BATTERY_COLOR: (u8, u8, u8) = (0xFF, 0x60, 0x00);
+    const LOW_BATTERY_PERIOD: Duration = Duration::from_secs(5);
+    const LOW_BATTERY_BLINK: Duration = Duration::from_millis(250);
+
+    const FLASH_LAYER: &'static str = "flash";
+
+    // Bounds of the per-player threshold
+    const THRESHOLD_MIN: f32 = 0.5;
+    const THRESHOLD_MAX: f32 = 3.0;
+
+    pub fn id(&self) -> PlayerId {
+        return self.controller.id();
+    }
+
+    pub fn input(&self) -> &Input {
+        return self.controller.input();
+    }
+
+    pub fn battery(&self) -> Battery {
+        return self.controller.battery();
+    }
+
+    /// Whether the player is only charging and must not take part in games
+    pub fn charging_only(&self) -> bool {
+        return self.controller.charging_only();
+    }
+
+    /// Strength of the bluetooth link in dB - `None` if unknown or not connected via bluetooth
+    pub fn signal(&self) -> Option<i8> {
+        return self.controller.signal();
+    }
+
+    /// Estimates the remaining runtime from the observed discharge rate, falling back to the nominal
+    /// runtime as long as the level has not changed yet. Returns `None` if the battery is not draining.
+    pub fn battery_remaining(&self) -> Option<Duration> {
+        let level = match self.battery() {
+            Battery::Draining(level) => level,
+            _ => return None,
+        };
+
+        if let Some(discharge) = &self.discharge {
+            let drained = discharge.level - level;
+            if drained > 0.0 {
+                let rate = drained / discharge.since.elapsed().as_secs_f32();
+                return Some(Duration::from_secs_f32(level / rate));
+            }
+        }
+
+        return Some(Self::BATTERY_RUNTIME.mul_f32(level));
+    }
+
+    /// Whether the battery has dropped below the warning level
+    pub fn low_battery(&self) -> bool {
+        return self.low_battery.is_some();
+    }
+
+    pub fn set_calibration(&mut self, calibration: Override) {
+        self.controller.set_calibration(calibration);
+    }
+
+    /// Gestures performed since the last update
+    pub fn gestures(&self) -> &[Gesture] {
+        return self.gestures.detected();
+    }
+
+    pub fn set_gesture_thresholds(&mut self, thresholds: Thresholds) {
+        self.gestures.set_thresholds(thresholds);
+    }
+
+    /// Scale of the movement thresholds of games for this player - higher values tolerate more
+    /// movement. Applies on top of the global threshold.
+    pub fn threshold(&self) -> f32 {
+        return self.threshold;
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold.clamp(Self::THRESHOLD_MIN, Self::THRESHOLD_MAX);
+    }
+
+    /// Time since the player last moved or pressed a button
+    pub fn idle(&self) -> Duration {
+        return self.active.elapsed();
+    }
+
+    pub async fn power_off(&mut self) -> Result<()> {
+        return self.controller.power_off().await;
+    }
+
+    /// Plays a rumble pattern replacing the current rumble animation
+    pub fn haptic(&mut self, pattern: HapticPattern) {
+        self.rumble.set_and_animate(0, pattern.keyframes());
+    }
+
+    /// The overlay layer of the given name shown on top of the color - created transparent if missing
+    pub fn overlay(&mut self, layer: &'static str) -> &mut Overlay {
+        let index = match self.overlays.iter().position(|(name, _)| *name == layer) {
+            Some(index) => index,
+            None => {
+                self.overlays.push((layer, Overlay::new()));
+                self.overlays.len() - 1
+            }
+        };
+
+        return &mut self.overlays[index].1;
+    }
+
+    pub fn remove_overlay(&mut self, layer: &str) -> bool {
+        let count = self.overlays.len();
+        self.overlays.retain(|(name, _)| *name != layer);
+        return self.overlays.len() != count;
+    }
+
+    /// Flashes the color on top of the current color fading out over the given time
+    pub fn flash(&mut self, color: RGBColor, duration: Duration) {
+        let overlay = self.overlay(Self::FLASH_LAYER);
+        overlay.color.set(color);
+        overlay.opacity.set_and_animate(1.0, [Keyframe::new(duration, 0.0, interpolations::linear as fn(f64) -> f64)]);
+    }
+
+    /// Advances the animations and passes the resulting feedback to the controller
+    fn feedback(&mut self, duration: Duration, output: Output, idle: Option<RGBColor>) {
+        // The battery warning keeps blinking while the other animations are frozen
+        let animated = if output.frozen { Duration::ZERO } else { duration };
+
+        self.rumble.update(animated);
+        self.color.update(animated);
+
+        for (layer, overlay) in self.overlays.iter_mut() {
+            overlay.update(if *layer == Self::LOW_BATTERY_LAYER { duration } else { animated });
+        }
+        self.overlays.retain(|(_, overlay)| !overlay.finished());
+
+        let low_battery = match (self.battery(), output.low_battery) {
+            (Battery::Draining(level), Some(warning)) if level <= warning => self.low_battery.or_else(|| Some(Instant::now())),
+            _ => None,
+        };
+
+        // Blink at the start of each period while the battery is low
+        match (self.low_battery, low_battery) {
+            (None, Some(_)) => {
+                let overlay = self.overlay(Self::LOW_BATTERY_LAYER);
+                overlay.color.set(Self::LOW_BATTERY_COLOR.into());
+                overlay.opacity.set(1.0);
+                overlay.opacity.animate_looping([
+                    Keyframe::new(Self::LOW_BATTERY_BLINK, 0.0, interpolations::end as fn(f64) -> f64),
+                    Keyframe::new(Self::LOW_BATTERY_PERIOD - Self::LOW_BATTERY_BLINK, 1.0, interpolations::end as fn(f64) -> f64),
+                ], Repeat::Forever, LoopMode::Restart);
+            }
+            (Some(_), None) => {
+                self.remove_overlay(Self::LOW_BATTERY_LAYER);
+            }
+            _ => {}
+        }
+        self.low_battery = low_battery;
+
+        // Spectators show the idle wave instead of the color of the game
+        let color = self.overlays.iter()
+            .fold(idle.unwrap_or_else(|| self.color.value()), |color, (_, overlay)| overlay.blend(color));
+
+        let (r, g, b) = color.int_rgb_tup();
+
+        let dim = |v: u8| (v as f32 * output.brightness) as u8;
+
+        self.controller.feedback_interval(output.interval);
+        self.controller.feedback(Feedback {
+            rgb: (dim(r), dim(g), dim(b)),
+            rumble: (self.rumble.value() as f32 * output.rumble) as u8,
+        });
+    }
+
+    #[instrument(level = "trace", name = "Player::update", skip(self), fields(id = self.id()))]
+    async fn update(&mut self, duration: Duration) -> Duration {
+        let started = Instant::now();
+
+        let update = self.controller.update(duration);
+        let update = timeout(Self::TIMEOUT, update);
+
+        if let Err(err) = update.await
+            .map_err(Into::into)
+            .flatten() {
+            warn!("Updating controller {} failed: {}", self.controller.id(), err);
+            self.failed += 1;
+        } else {
+            // TODO: Do not reset immediately but require multiple successful before resetting
+            // TODO: Report flaky devices
+            self.failed = 0;
+        }
+
+        // Update acceleration data history
+        self.acceleration.write((1.0 - self.controller.input().accelerometer.magnitude()).abs());
+
+        self.gestures.update(self.controller.input(), duration);
+
+        let buttons = &self.controller.input().buttons;
+        if self.acceleration(false) >= Self::ACTIVE_ACCELERATION || buttons.all().iter().any(|button| button.is_down()) {
+            self.active = Instant::now();
+        }
+
+        // Track discharge since the controller was last charged
+        match self.controller.battery() {
+            Battery::Draining(level) => match self.discharge {
+                Some(ref discharge) if discharge.level >= level => {}
+                _ => self.discharge = Some(Discharge {
+                    level,
+                    since: Instant::now(),
+                }),
+            },
+            _ => self.discharge = None,
+        }
+
+        return started.elapsed();
+    }
+
+    pub fn controller(&self) -> &Controller {
+        return &self.controller;
+    }
+
+    pub fn acceleration(&self, avg: bool) -> f32 {
+        return if avg {
+            self.acceleration.iter().sum::<f32>() / self.acceleration.len() as f32
+        } else {
+            self.acceleration.recent().copied().unwrap_or(0.0)
+        };
+    }
+}
+
+/// Global adjustments of the feedback sent to all controllers
+#[derive(Debug, Clone, Copy)]
+struct Output {
+    brightness: f32,
+    rumble: f32,
+    interval: Option<Duration>,
+
+    // Battery level below which players blink a warning
+    low_battery: Option<f32>,
+
+    // Halts the animations of the players
+    frozen: bool,
+}
+
+pub struct Players {
+    players: Vec<Player>,
+
+    latency: DurationSamples,
+    counters: Counters,
+
+    // Scales the LED output of all controllers
+    brightness: f32,
+
+    power_save: bool,
+
+    // Players idle for this long are powered off
+    idle_power_off: Option<Duration>,
+
+    // Maximum number of controllers receiving feedback per frame
+    feedback_budget: Option<usize>,
+
+    low_battery_warning: Option<f32>,
+
+    frozen: bool,
+
+    // Players taking part in the running game - all others show the idle wave
+    participants: Option<HashSet<PlayerId>>,
+
+    // Frame time the idle wave has been running for
+    idle_elapsed: Duration,
+
+    // Running probe of the signal strengths
+    signal: Option<JoinHandle<Vec<(PlayerId, Option<i8>)>>>,
+    signal_probed: Instant,
+
+    // Players which lost their controller recently and since when
+    disconnected: Vec<(Player, Instant)>,
+}
+
+impl Players {
+    const MAX_FAILS: usize = 10;
+
+    // Reductions applied while saving power
+    const POWER_SAVE_BRIGHTNESS: f32 = 0.4;
+    const POWER_SAVE_RUMBLE: f32 = 0.5;
+    const POWER_SAVE_INTERVAL: Duration = Duration::from_millis(200);
+
+    // Battery level considered low
+    const LOW_BATTERY: f32 = 0.2;
+
+    // Interval between probing the signal strengths
+    const SIGNAL_INTERVAL: Duration = Duration::from_secs(5);
+
+    // Time a disconnected player is kept to re-attach it if the controller reconnects
+    const RECONNECT_GRACE: Duration = Duration::from_secs(10);
+
+    pub fn new() -> Self {
+        return Self {
+            players: Vec::new(),
+            latency: DurationSamples::new(),
+            counters: Counters::default(),
+            brightness: 1.0,
+            power_save: false,
+            idle_power_off: None,
+            feedback_budget: None,
+            low_battery_warning: None,
+            frozen: false,
+            participants: None,
+            idle_elapsed: Duration::ZERO,
+            signal: None,
+            signal_probed: Instant::now(),
+            disconnected: Vec::new(),
+        };
+    }
+
+    pub fn power_save(&self) -> bool {
+        return self.power_save;
+    }
+
+    /// Dims the LEDs, reduces rumble and sends feedback less often to extend battery life
+    pub fn set_power_save(&mut self, power_save: bool) {
+        if power_save != self.power_save {
+            info!("Power saving {}", if power_save { "enabled" } else { "disabled" });
+            self.power_save = power_save;
+        }
+    }
+
+    /// Checks if at least half of the draining controllers are low on battery
+    pub fn batteries_low(&self) -> bool {
+        let levels = self.players.iter()
+            .filter_map(|player| match player.battery() {
+                Battery::Draining(level) => Some(level),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let low = levels.iter()
+            .filter(|level| **level <= Self::LOW_BATTERY)
+            .count();
+
+        return low > 0 && low * 2 >= levels.len();
+    }
+
+    /// Powers off controllers which have been idle for the given time - `None` disables it
+    pub fn set_idle_power_off(&mut self, idle: Option<Duration>) {
+        self.idle_power_off = idle;
+    }
+
+    /// Limits the number of controllers receiving feedback per frame - `None` for no limit
+    ///
+    /// The feedback of the other controllers is deferred to the following frames, favoring the
+    /// controllers waiting the longest. This avoids congesting the bluetooth adapters when lots of
+    /// controllers change at once. At least one controller receives feedback per frame.
+    pub fn set_feedback_budget(&mut self, budget: Option<usize>) {
+        self.feedback_budget = budget.map(|budget| budget.max(1));
+    }
+
+    /// Lets players blink orange periodically if their battery drops to the given level - `None`
+    /// disables the warning
+    pub fn set_low_battery_warning(&mut self, level: Option<f32>) {
+        self.low_battery_warning = level;
+    }
+
+    /// Shows a synchronized color wave on all controllers not taking part in the running game -
+    /// `None` if there is no such game
+    pub fn set_participants(&mut self, participants: Option<HashSet<PlayerId>>) {
+        self.participants = participants;
+    }
+
+    /// Halts the color and rumble animations of all players, e.g. while the game is paused - the
+    /// controllers stop rumbling until the animations continue
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub fn brightness(&self) -> f32 {
+        return self.brightness;
+    }
+
+    pub fn set_brightness(&mut self, brightness: f32) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    #[instrument(level = "trace", name = "Players::update", skip(self))]
+    pub async fn update(&mut self, duration: Duration) -> Result<()> {
+        let output = if self.power_save {
+            Output {
+                brightness: self.brightness * Self::POWER_SAVE_BRIGHTNESS,
+                rumble: if self.frozen { 0.0 } else { Self::POWER_SAVE_RUMBLE },
+                interval: Some(Self::POWER_SAVE_INTERVAL),
+                low_battery: self.low_battery_warning,
+                frozen: self.frozen,
+            }
+        } else {
+            Output {
+                brightness: self.brightness,
+                rumble: if self.frozen { 0.0 } else { 1.0 },
+                interval: None,
+                low_battery: self.low_battery_warning,
+                frozen: self.frozen,
+            }
+        };
+
+        // Spectators are ordered by their id to keep the wave stable and to find them quickly
+        let mut spectators = match self.participants {
+            Some(ref participants) => self.players.iter()
+                .map(Player::id)
+                .filter(|id| !participants.contains(id))
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        spectators.sort_unstable();
+
+        self.idle_elapsed += duration;
+        for player in self.players.iter_mut() {
+            let idle = spectators.binary_search(&player.id())
+                .ok()
+                .map(|i| Wave::color(self.idle_elapsed, i as f64 / spectators.len() as f64));
+
+            player.feedback(duration, output, idle);
+        }
+
+        if let Some(budget) = self.feedback_budget {
+            let mut pending = self.players.iter_mut()
+                .filter(|player| player.controller.feedback_pending())
+                .collect::<Vec<_>>();
+
+            pending.sort_by_key(|player| std::cmp::Reverse(player.controller.feedback_age()));
+
+            self.counters.held += pending.len().saturating_sub(budget) as u64;
+            for player in pending.into_iter().skip(budget) {
+                player.controller.hold_feedback();
+            }
+        }
+
+        self.counters.feedback += self.players.iter()
+            .filter(|player| player.controller.feedback_pending())
+            .count() as u64;
+
+        // Update all controllers
+        let latencies = futures::future::join_all(
+            self.players.iter_mut()
+                .map(|player| player.update(duration))
+        ).await;
+
+        for latency in latencies {
+            self.latency.record(latency);
+        }
+
+        // The fail count of a player is reset by every successful update
+        self.counters.updates += self.players.len() as u64;
+        self.counters.errors += self.players.iter()
+            .filter(|player| player.failed > 0)
+            .count() as u64;
+
+        // Probing blocks on the adapter and is therefore done in the background
+        if let Some(ref mut probe) = self.signal {
+            if let Poll::Ready(signals) = futures::poll!(probe) {
+                self.signal = None;
+
+                for (id, signal) in signals? {
+                    if let Some(player) = self.get_mut(id) {
+                        player.controller.set_signal(signal);
+                    }
+                }
+            }
+        } else if self.signal_probed.elapsed() >= Self::SIGNAL_INTERVAL {
+            self.signal_probed = Instant::now();
+
+            let controllers = self.players.iter()
+                .filter(|player| player.controller.bus() == Bus::BLUETOOTH)
+                .map(|player| (player.id(), player.controller.serial()))
+                .collect::<Vec<_>>();
+
+            self.signal = Some(tokio::task::spawn_blocking(move || {
+                return controllers.into_iter()
+                    .map(|(id, address)| (id, signal::rssi(address)
+                        .map_err(|err| debug!("Failed to probe signal of {}: {:#}", address.as_string(), err))
+                        .ok()))
+                    .collect();
+            }));
+        }
+
+        if let Some(idle) = self.idle_power_off {
+            for player in self.players.iter_mut().filter(|player| player.idle() >= idle) {
+                info!("Powering off idle player {}", player.id());
+                if let Err(err) = player.power_off().await {
+                    warn!("Failed to power off player {}: {:#}", player.id(), err);
+                }
+
+                // Do not try again until idle for another period
+                player.active = Instant::now();
+            }
+        }
+
+        // Drop controllers with high error count
+        for player in self.players
+            .drain_filter(|player| player.failed >= Self::MAX_FAILS) {
+            error!("Dropping player {} because of to many errors", player.id());
+            self.disconnected.push((player, Instant::now()));
+        }
+
+        // Forget players which did not reconnect in time
+        for (player, _) in self.disconnected
+            .drain_filter(|(_, since)| since.elapsed() >= Self::RECONNECT_GRACE) {
+            info!("Player {} did not reconnect", player.id());
+        }
+
+        return Ok(());
+    }
+
+    /// Recent update latencies of individual controllers
+    pub fn latency(&self) -> &DurationSamples {
+        return &self.latency;
+    }
+
+    /// Totals of the controller I/O since startup
+    pub fn counters(&self) -> Counters {
+        return self.counters;
+    }
+
+    pub fn count(&self) -> usize {
+        return self.players.len();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&Player> + ExactSizeIterator {
+        return self.players.iter();
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=&mut Player> + ExactSizeIterator {
+        return self.players.iter_mut();
+    }
+
+    pub fn get(&self, id: PlayerId) -> Option<&Player> {
+        return self.players.iter().find(|player| player.id() == id);
+    }
+
+    pub fn get_mut(&mut self, id: PlayerId) -> Option<&mut Player> {
+        return self.players.iter_mut().find(|player| player.id() == id);
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item=PlayerId> + '_ {
+        return self.players.iter().map(Player::id);
+    }
+
+    /// Aggregates the movement of the given players scaled by their thresholds - players not
+    /// connected are skipped
+    pub fn group(&self, players: impl IntoIterator<Item=PlayerId>) -> GroupMetrics {
+        return players.into_iter()
+            .filter_map(|id| self.get(id))
+            .map(|player| player.acceleration(true) / player.threshold())
+            .fold(GroupMetrics::default(), |metrics, accel| GroupMetrics {
+                count: metrics.count + 1,
+                total: metrics.total + accel,
+                peak: metrics.peak.max(accel),
+            });
+    }
+
+    /// Reports the battery state of all controllers, sorted from the most urgent to the least urgent
+    pub fn batteries(&self) -> Vec<BatteryReport> {
+        let mut reports = self.players.iter()
+            .map(|player| BatteryReport {
+                player: player.id(),
+                address: player.controller.serial(),
+                battery: player.battery(),
+                remaining: player.battery_remaining(),
+            })
+            .collect::<Vec<_>>();
+
+        reports.sort_by_key(|report| match report.battery {
+            Battery::Draining(_) => (0, report.remaining),
+            Battery::Unknown => (1, None),
+            Battery::Charging => (2, None),
+            Battery::Charged => (3, None),
+        });
+
+        return reports;
+    }
+
+    /// Pairs all controllers connected via USB to the given host
+    #[instrument(level = "debug", skip(self))]
+    pub async fn pair(&mut self, host: Address) -> Vec<PairingResult> {
+        let mut results = Vec::new();
+
+        for player in self.players.iter_mut()
+            .filter(|player| player.controller.bus() == Bus::USB) {
+            let outcome = player.controller.pair(host).await;
+            debug!("Pairing controller {} to {}: {:?}", player.controller.serial().as_string(), host.as_string(), outcome);
+
+            results.push(PairingResult {
+                controller: player.controller.serial(),
+                outcome,
+            });
+        }
+
+        return results;
+    }
+
+    pub fn with_data<'a, D>(&'a mut self, data: &'a mut PlayerData<D>) -> WithData<'a, D> {
+        return WithData {
+            players: self,
+            data,
+        };
+    }
+
+    pub fn add(&mut self, controller: Controller) {
+        // Must ensure IDs are unique
+        assert!(self.players.iter()
+            .map(Player::id)
+            .find(|id| *id == controller.id())
+            .is_none());
+
+        // Re-attach a recently disconnected player keeping its state
+        if let Some(index) = self.disconnected.iter().position(|(player, _)| player.id() == controller.id()) {
+            let (mut player, since) = self.disconnected.swap_remove(index);
+            info!("Player {} reconnected after {:?}", player.id(), since.elapsed());
+
+            player.controller = controller;
+            player.failed = 0;
+
+            self.players.push(player);
+            return;
+        }
+
+        self.players.push(Player {
+            controller,
+            acceleration: HistoryBuffer::new_with(0.0),
+            discharge: None,
+            active: Instant::now(),
+            gestures: Detector::new(Thresholds::default()),
+            low_battery: None,
+            threshold: 1.0,
+            rumble: Animated::idle(0),
+            color: Animated::idle(RGBColor { r: 0.0, g: 0.0, b: 0.0 }),
+            overlays: Vec::new(),
+            failed: 0,
+        });
+    }
+
+    pub fn remove(&mut self, id: PlayerId) -> bool {
+        let count = self.players.len();
+        self.players.retain(|player| player.id() != id);
+        return self.players.len() != count;
+    }
+
+    /// Disconnects the player using the controller with the given device path
+    ///
+    /// The player is kept for a grace period and re-attached if the same controller reconnects.
+    pub fn remove_path(&mut self, path: &Path) -> bool {
+        let count = self.players.len();
+        for player in self.players.drain_filter(|player| player.controller.path() == Some(path)) {
+            self.disconnected.push((player, Instant::now()));
+        }
+        return self.players.len() != count;
+    }
+
+    /// Whether the player is disconnected but may still reconnect
+    pub fn is_disconnected(&self, id: PlayerId) -> bool {
+        return self.disconnected.iter().any(|(player, _)| player.id() == id);
+    }
+}
+
+pub struct PlayerData<D> {
+    data: HashMap<PlayerId, D>,
+}
+
+impl<D> PlayerData<D> {
+    pub fn init(players: HashSet<PlayerId>, f: impl Fn() -> D) -> Self {
+        let data = players.into_iter()
+            .map(|id| (id, f()))
+            .collect();
+
+        return Self { data };
+    }
+
+    pub fn init_with(data: HashMap<PlayerId, D>) -> Self {
+        return Self { data };
+    }
+
+    pub fn new() -> Self {
+        return Self { data: HashMap::new() };
+    }
+
+    pub fn reset(&mut self) {
+        self.data.clear();
+    }
+
+    pub fn get(&mut self, player: PlayerId) -> Option<&D> {
+        return self.data.get(&player);
+    }
+
+    pub fn get_mut(&mut self, player: PlayerId) -> Option<&mut D> {
+        return self.data.get_mut(&player);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(PlayerId, &D)> {
+        return self.data.iter()
+            .map(|(id, data)| (*id, data));
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(PlayerId, &mut D)> {
+        return self.data.iter_mut()
+            .map(|(id, data)| (*id, data));
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item=PlayerId> + '_ {
+        return self.data.keys().copied();
+    }
+
+    /// Adds the data of a player. Returns whether the player was not known before.
+    pub fn insert(&mut self, player: PlayerId, data: D) -> bool {
+        if self.data.contains_key(&player) {
+            return false;
+        }
+
+        self.data.insert(player, data);
+        return true;
+    }
+
+    pub fn remove(&mut self, player: PlayerId) -> bool {
+        return self.data.remove(&player).is_some();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len();
+    }
+}
+
+pub struct WithData<'a, D> {
+    players: &'a mut Players,
+    data: &'a mut PlayerData<D>,
+}
+
+impl<'a, D> WithData<'a, D> {
+    pub fn update(self, mut f: impl FnMut(&'a mut Player, &'a mut D) -> bool) {
+        self.data.data.retain(|id, data| {
+            if let Some(player) = self.players.get_mut(*id) {
+                // SAFETY: This is save because the underlying `self.iter` is guaranteed to yield unique
+                // serials and therefore this will never hand out two references to the same element
+                // from `self.data`.
+                let data: &'a mut D = unsafe { std::mem::transmute(data) };
+                let player: &'a mut Player = unsafe { std::mem::transmute(player) };
+                return f(player, data);
+            }
+
+            // Keep the data of players which may still reconnect
+            return self.players.is_disconnected(*id);
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::controller::{feed, Model, Reading};
+    use crate::controller::rate::Rate;
+
+    use super::*;
+
+    #[test]
+    fn test_reconnect() {
+        let address: Address = "02:00:00:00:00:01".parse().unwrap();
+        let controller = || Controller::new(Box::new(feed::channel(address, Model::CECH_ZCM1).1));
+
+        let mut players = Players::new();
+        players.add(controller());
+
+        let id = players.keys().next().unwrap();
+        let mut data = PlayerData::init(HashSet::from([id]), || 42);
+
+        // Disconnect the player
+        let player = players.players.pop().unwrap();
+        players.disconnected.push((player, Instant::now()));
+
+        players.with_data(&mut data).update(|_, _| true);
+        assert_eq!(players.count(), 0);
+        assert_eq!(data.get(id), Some(&42));
+
+        players.add(controller());
+        assert_eq!(players.count(), 1);
+        assert!(!players.is_disconnected(id));
+        assert_eq!(players.keys().next(), Some(id));
+    }
+
+    #[test]
+    fn test_group() {
+        let mut players = Players::new();
+        players.add(Controller::new(Box::new(feed::channel("02:00:00:00:00:01".parse().unwrap(), Model::CECH_ZCM1).1)));
+
+        let id = players.keys().next().unwrap();
+
+        let metrics = players.group([id, id + 1]);
+        assert_eq!(metrics.count, 1);
+        assert_eq!(metrics.total, 0.0);
+
+        assert_eq!(players.group([]).mean(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_budget() {
+        let mut players = Players::new();
+        players.set_feedback_budget(Some(2));
+
+        let mut feeds = Vec::new();
+        for i in 0..5 {
+            let (feed, device) = feed::channel(format!("02:00:00:00:00:{:02X}", i).parse().unwrap(), Model::CECH_ZCM1);
+
+            // Without a minimal interval all controllers have feedback pending right away
+            let mut controller = Controller::new(Box::new(device));
+            controller.set_rate(Rate { min: Duration::ZERO, ..Rate::default() });
+
+            players.add(controller);
+            feeds.push(feed);
+        }
+
+        for player in players.iter_mut() {
+            player.rumble.set(255);
+        }
+
+        let sent = || feeds.iter().filter(|feed| feed.feedback().rumble != 0).count();
+
+        players.update(Duration::ZERO).await.unwrap();
+        assert_eq!(sent(), 2);
+
+        players.update(Duration::ZERO).await.unwrap();
+        assert_eq!(sent(), 4);
+
+        players.update(Duration::ZERO).await.unwrap();
+        assert_eq!(sent(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_low_battery_warning() {
+        let mut players = Players::new();
+        players.set_low_battery_warning(Some(0.1));
+
+        let (feed, device) = feed::channel("02:00:00:00:00:01".parse().unwrap(), Model::CECH_ZCM1);
+        let mut controller = Controller::new(Box::new(device));
+        controller.set_rate(Rate { min: Duration::ZERO, ..Rate::default() });
+        players.add(controller);
+
+        feed.push(Reading {
+            input: Input::default(),
+            battery: Battery::Draining(0.05),
+            temperature: None,
+        });
+
+        players.update(Duration::ZERO).await.unwrap();
+        assert!(!players.iter().next().unwrap().low_battery());
+
+        players.update(Duration::ZERO).await.unwrap();
+        assert!(players.iter().next().unwrap().low_battery());
+        assert_eq!(feed.feedback().rgb, Player::LOW_BATTERY_COLOR);
+    }
+
+    #[tokio::test]
+    async fn test_overlay() {
+        let mut players = Players::new();
+
+        let (feed, device) = feed::channel("02:00:00:00:00:01".parse().unwrap(), Model::CECH_ZCM1);
+        let mut controller = Controller::new(Box::new(device));
+        controller.set_rate(Rate { min: Duration::ZERO, ..Rate::default() });
+        players.add(controller);
+
+        let player = players.iter_mut().next().unwrap();
+        player.color.set(RGBColor { r: 1.0, g: 0.0, b: 0.0 });
+        player.flash(RGBColor { r: 0.0, g: 0.0, b: 1.0 }, Duration::from_secs(1));
+
+        players.update(Duration::from_millis(500)).await.unwrap();
+        assert_eq!(feed.feedback().rgb, (128, 0, 128));
+
+        // The color of the game is back once the flash has faded out
+        players.update(Duration::from_millis(500)).await.unwrap();
+        assert_eq!(feed.feedback().rgb, (255, 0, 0));
+        assert!(players.iter().next().unwrap().overlays.is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand::rngs::StdRng;
+
+/// Source of all randomness of the games - seeding it makes sessions reproducible
+pub struct Random {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Random {
+    /// Creates a generator from the given seed or from a random one if none is given
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+
+        return Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        };
+    }
+
+    /// The seed to reproduce the session with
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        return self.rng.next_u32();
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return self.rng.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        return self.rng.try_fill_bytes(dest);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::Rng;
+
+    #[test]
+    fn test_seeded() {
+        let mut a = Random::new(Some(42));
+        let mut b = Random::new(Some(42));
+
+        let a = (0..16).map(|_| a.gen::<u32>()).collect::<Vec<_>>();
+        let b = (0..16).map(|_| b.gen::<u32>()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+
+        assert_ne!(Random::new(None).seed(), Random::new(None).seed());
+    }
+}
@@ -0,0 +1,71 @@
+use cgmath::Vector3;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::controller::Input;
+use crate::engine::players::PlayerId;
+
+/// Motion sensor values of a single reading
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Readings {
+    pub accelerometer: Vector3<f32>,
+    pub gyroscope: Vector3<f32>,
+}
+
+impl From<&Input> for Readings {
+    fn from(input: &Input) -> Self {
+        return Self {
+            accelerometer: input.accelerometer,
+            gyroscope: input.gyroscope,
+        };
+    }
+}
+
+/// The sensor values of a controller at one point in time
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub player: PlayerId,
+
+    /// Seconds since sampling has started
+    pub time: f32,
+
+    /// Values as received from the device
+    pub raw: Readings,
+
+    /// Values after applying the calibration and the noise filter
+    pub calibrated: Readings,
+}
+
+/// Streams sensor samples of inspected controllers to subscribers at the full frame rate
+#[derive(Clone)]
+pub struct Scope(broadcast::Sender<Sample>);
+
+impl Scope {
+    // Samples kept for slow subscribers - about a second of readings of a handful of controllers
+    const CAPACITY: usize = 1024;
+
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        return Self(tx);
+    }
+
+    /// Whether anybody is subscribed - sampling can be skipped otherwise
+    pub fn watched(&self) -> bool {
+        return self.0.receiver_count() > 0;
+    }
+
+    pub fn publish(&self, sample: Sample) {
+        // Having no subscribers is fine
+        let _ = self.0.send(sample);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Sample> {
+        return self.0.subscribe();
+    }
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
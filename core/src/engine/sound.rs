@@ -0,0 +1,608 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI8, AtomicU16, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sample, Source};
+use rodio::buffer::SamplesBuffer;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::source::SineWave;
+use tracing::instrument;
+
+use crate::engine::assets::{Asset, AssetLoader};
+
+struct DynamicSource<I> {
+    input: I,
+
+    speed: Arc<AtomicI8>,
+    stopped: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+
+    // Nanoseconds of the input played so far
+    position: Arc<AtomicU64>,
+
+    // Speed shared by all music on top of the own speed in 1/256 - 256 plays at normal speed
+    boost: Arc<AtomicU16>,
+}
+
+impl<I> DynamicSource<I>
+    where
+        I: Source,
+        I::Item: Sample,
+{
+    const MAX_FRAME_LEN: usize = 1024;
+
+    pub fn new(input: I) -> Self {
+        return Self {
+            input,
+            speed: Arc::new(AtomicI8::new(0)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            position: Arc::new(AtomicU64::new(0)),
+            boost: Arc::new(AtomicU16::new(Sound::BOOST_NORMAL)),
+        };
+    }
+
+    /// Applies the given shared speed on top of the own speed
+    fn boosted(self, boost: Arc<AtomicU16>) -> Self {
+        return Self {
+            boost,
+            ..self
+        };
+    }
+
+    fn stopped_handle(&self) -> Arc<AtomicBool> {
+        return self.stopped.clone();
+    }
+
+    fn playback(&self) -> Playback {
+        return Playback {
+            speed: self.speed.clone(),
+            stopped: self.stopped.clone(),
+            paused: self.paused.clone(),
+            position: self.position.clone(),
+        };
+    }
+}
+
+impl<I> Iterator for DynamicSource<I>
+    where
+        I: Source,
+        I::Item: Sample,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        // Keep the output running with silence while paused
+        if self.paused.load(Ordering::Relaxed) {
+            return Some(I::Item::zero_value());
+        }
+
+        let sample = self.input.next();
+
+        if sample.is_some() {
+            let rate = self.input.sample_rate() as u64 * self.input.channels() as u64;
+            if rate != 0 {
+                self.position.fetch_add(1_000_000_000 / rate, Ordering::Relaxed);
+            }
+        }
+
+        return sample;
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.input.size_hint();
+    }
+}
+
+impl<I> ExactSizeIterator for DynamicSource<I>
+    where
+        I: Source + ExactSizeIterator,
+        I::Item: Sample,
+{}
+
+impl<I> Source for DynamicSource<I>
+    where
+        I: Source,
+        I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        return Some(self.input.current_frame_len()
+            .map_or(Self::MAX_FRAME_LEN,
+                    |frame_len| frame_len.max(Self::MAX_FRAME_LEN)));
+    }
+
+    fn channels(&self) -> u16 {
+        return self.input.channels();
+    }
+
+    fn sample_rate(&self) -> u32 {
+        let speed = self.speed.load(Ordering::Relaxed) as i32; // [-128, 127]: 0 => 0
+        let speed = (speed + 256) as u64;                            // [128, 383]: 0 => 256
+        let boost = self.boost.load(Ordering::Relaxed) as u64;
+        return (self.input.sample_rate() as u64 * speed * boost / (256 * 256)) as u32;
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        return None;
+    }
+}
+
+/// Importance of an audio cue - more important cues preempt less important ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Background effects which are skipped if anything else is playing
+    Ambient,
+
+    /// Short sounds accompanying game events
+    Stinger,
+
+    /// Voice announcements
+    Announcement,
+}
+
+struct Cue<S> {
+    priority: Priority,
+    key: String,
+    source: S,
+    duration: Duration,
+    queued: Instant,
+}
+
+struct Current {
+    priority: Priority,
+    key: String,
+    until: Instant,
+}
+
+/// Queue of audio cues making sure only a single cue is playing at a time
+///
+/// Cues with the same key as a cue waiting or playing are merged into it. A cue preempts the cue
+/// currently playing if it is more important, otherwise it waits for the current cue to finish.
+/// Waiting cues are played in order of importance and dropped if they waited for too long.
+struct Cues<S> {
+    queue: Vec<Cue<S>>,
+    current: Option<Current>,
+}
+
+impl<S> Cues<S> {
+    // Cues waiting longer than this are outdated
+    const MAX_WAIT: Duration = Duration::from_secs(3);
+
+    fn new() -> Self {
+        return Self {
+            queue: Vec::new(),
+            current: None,
+        };
+    }
+
+    fn push(&mut self, now: Instant, priority: Priority, key: &str, source: S, duration: Duration) {
+        let busy = self.current.as_ref().map_or(false, |current| current.until > now);
+
+        if busy && self.current.as_ref().map_or(false, |current| current.key == key) {
+            return;
+        }
+
+        if self.queue.iter().any(|cue| cue.key == key) {
+            return;
+        }
+
+        if priority == Priority::Ambient && (busy || !self.queue.is_empty()) {
+            return;
+        }
+
+        self.queue.push(Cue {
+            priority,
+            key: key.to_owned(),
+            source,
+            duration,
+            queued: now,
+        });
+    }
+
+    /// Returns the cue to play next and if it preempts the cue currently playing
+    fn next(&mut self, now: Instant) -> Option<(S, bool)> {
+        self.queue.retain(|cue| now.duration_since(cue.queued) <= Self::MAX_WAIT);
+
+        if self.current.as_ref().map_or(false, |current| current.until <= now) {
+            self.current = None;
+        }
+
+        let priority = self.queue.iter().map(|cue| cue.priority).max()?;
+        if self.current.as_ref().map_or(false, |current| current.priority >= priority) {
+            return None;
+        }
+
+        let index = self.queue.iter().position(|cue| cue.priority == priority)?;
+        let cue = self.queue.remove(index);
+
+        let preempted = self.current.replace(Current {
+            priority: cue.priority,
+            key: cue.key,
+            until: now + cue.duration,
+        }).is_some();
+
+        return Some((cue.source, preempted));
+    }
+}
+
+pub struct Sound {
+    // Both are absent if running without sound output
+    #[allow(unused)]
+    output: Option<OutputStream>,
+    handle: Option<OutputStreamHandle>,
+
+    volume: f32,
+
+    // Speed of all music on top of the speed of the individual playbacks
+    boost: Arc<AtomicU16>,
+
+    cues: Cues<SamplesBuffer<i16>>,
+
+    // Stops the cue currently playing
+    cue: Option<Arc<AtomicBool>>,
+}
+
+pub struct Playback {
+    speed: Arc<AtomicI8>,
+    stopped: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    position: Arc<AtomicU64>,
+}
+
+impl Playback {
+    /// A playback not backed by any sound
+    pub fn silent() -> Self {
+        return Self {
+            speed: Arc::new(AtomicI8::new(0)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            position: Arc::new(AtomicU64::new(0)),
+        };
+    }
+
+    pub fn speed(&mut self, speed: f32) {
+        let speed = speed.clamp(0.5, 1.5) * 256.0 - 256.0;
+        self.speed.store(speed as i8, Ordering::Relaxed);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&mut self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn paused(&self) -> bool {
+        return self.paused.load(Ordering::Relaxed);
+    }
+
+    /// Time into the music played so far - does not advance while paused
+    pub fn position(&self) -> Duration {
+        return Duration::from_nanos(self.position.load(Ordering::Relaxed));
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+pub type Music = Decoder<BufReader<File>>;
+
+impl AssetLoader for Music {
+    type Asset = Music;
+
+    // Music is streamed from the file as it plays
+    type Preloaded = ();
+
+    fn preload(_path: impl AsRef<Path>) -> anyhow::Result<()> {
+        return Ok(());
+    }
+
+    fn load(path: impl AsRef<Path>, _preloaded: &()) -> anyhow::Result<Self> {
+        return Ok(Decoder::new(BufReader::new(File::open(path)?))?);
+    }
+}
+
+/// A clip decoded in advance
+pub struct Clip {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+}
+
+/// Short sound effects played once - decoded when loading the assets to play them without delay
+pub struct Sfx;
+
+impl AssetLoader for Sfx {
+    type Asset = SamplesBuffer<i16>;
+    type Preloaded = Clip;
+
+    fn preload(path: impl AsRef<Path>) -> anyhow::Result<Clip> {
+        let source = Decoder::new(BufReader::new(File::open(path)?))?;
+        return Ok(Clip {
+            channels: source.channels(),
+            sample_rate: source.sample_rate(),
+            samples: source.collect(),
+        });
+    }
+
+    fn load(_path: impl AsRef<Path>, clip: &Clip) -> anyhow::Result<Self::Asset> {
+        return Ok(SamplesBuffer::new(clip.channels, clip.sample_rate, clip.samples.to_vec()));
+    }
+}
+
+impl Sound {
+    const BOOST_NORMAL: u16 = 256;
+
+    #[instrument(level = "debug")]
+    pub fn init() -> Result<Self> {
+        let (output, handle) = OutputStream::try_default()
+            .context("Failed to open default sound output stream")?;
+
+        return Ok(Self {
+            output: Some(output),
+            handle: Some(handle),
+            volume: 1.0,
+            boost: Arc::new(AtomicU16::new(Self::BOOST_NORMAL)),
+            cues: Cues::new(),
+            cue: None,
+        });
+    }
+
+    /// Opens the output device with the given name
+    #[instrument(level = "debug")]
+    pub fn init_device(name: &str) -> Result<Self> {
+        let device = rodio::cpal::default_host().output_devices()?
+            .find(|device| device.name().map_or(false, |device| device == name))
+            .ok_or_else(|| anyhow!("No such sound output device: {}", name))?;
+
+        let (output, handle) = OutputStream::try_from_device(&device)
+            .with_context(|| format!("Failed to open sound output stream on {}", name))?;
+
+        return Ok(Self {
+            output: Some(output),
+            handle: Some(handle),
+            volume: 1.0,
+            boost: Arc::new(AtomicU16::new(Self::BOOST_NORMAL)),
+            cues: Cues::new(),
+            cue: None,
+        });
+    }
+
+    /// Creates a sound engine without output which plays nothing
+    pub fn silent() -> Self {
+        return Self {
+            output: None,
+            handle: None,
+            volume: 1.0,
+            boost: Arc::new(AtomicU16::new(Self::BOOST_NORMAL)),
+            cues: Cues::new(),
+            cue: None,
+        };
+    }
+
+    pub fn volume(&self) -> f32 {
+        return self.volume;
+    }
+
+    /// Sets the volume for all sounds played from now on
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// Speeds up or slows down all music playing or played from now on (0.5 - 2.0) - applies on top
+    /// of the speed of the individual playbacks
+    pub fn set_speed(&mut self, speed: f32) {
+        let boost = speed.clamp(0.5, 2.0) * Self::BOOST_NORMAL as f32;
+        self.boost.store(boost as u16, Ordering::Relaxed);
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn music(&self, asset: Option<&Asset<Music>>) -> Playback {
+        return self.play(asset, self.volume);
+    }
+
+    /// Plays music in the background quieter than the music of the games by the given level
+    #[instrument(level = "debug", skip(self))]
+    pub fn ambient(&self, asset: Option<&Asset<Music>>, level: f32) -> Playback {
+        return self.play(asset, self.volume * level.clamp(0.0, 1.0));
+    }
+
+    fn play(&self, asset: Option<&Asset<Music>>, volume: f32) -> Playback {
+        let (handle, asset) = match (&self.handle, asset) {
+            (Some(handle), Some(asset)) => (handle, asset),
+            _ => return Playback::silent(),
+        };
+
+        let source = asset
+            .load()
+            .repeat_infinite()
+            .fade_in(Duration::from_secs(1))
+            .amplify(volume);
+
+        let source = DynamicSource::new(source).boosted(self.boost.clone());
+        let music = source.playback();
+
+        handle.play_raw(source.convert_samples())
+            .expect("Output dropped");
+
+        return music;
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn effect(&self, asset: &Asset<Sfx>) {
+        let handle = match self.handle {
+            Some(ref handle) => handle,
+            None => return,
+        };
+
+        let source = asset
+            .load()
+            .amplify(self.volume);
+
+        handle.play_raw(source.convert_samples())
+            .expect("Output dropped");
+    }
+
+    /// Queues a sound effect to be played according to its priority
+    #[instrument(level = "debug", skip(self, now, asset))]
+    pub fn cue(&mut self, now: Instant, priority: Priority, key: &str, asset: &Asset<Sfx>) {
+        if self.handle.is_none() {
+            return;
+        }
+
+        let source = asset.load();
+        let duration = source.total_duration().unwrap_or_default();
+
+        self.cues.push(now, priority, key, source, duration);
+    }
+
+    /// Starts playing the next queued cue if due
+    #[instrument(level = "trace", skip(self))]
+    pub fn update(&mut self, now: Instant) {
+        let handle = match self.handle {
+            Some(ref handle) => handle,
+            None => return,
+        };
+
+        if let Some((source, preempted)) = self.cues.next(now) {
+            if preempted {
+                if let Some(stopped) = self.cue.take() {
+                    stopped.store(true, Ordering::SeqCst);
+                }
+            }
+
+            let source = DynamicSource::new(source.amplify(self.volume));
+            self.cue = Some(source.stopped_handle());
+
+            handle.play_raw(source.convert_samples())
+                .expect("Output dropped");
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn tone(&self, frequency: f32, duration: Duration) {
+        let handle = match self.handle {
+            Some(ref handle) => handle,
+            None => return,
+        };
+
+        let source = SineWave::new(frequency)
+            .take_duration(duration)
+            .amplify(self.volume);
+
+        handle.play_raw(source.convert_samples())
+            .expect("Output dropped");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Constant;
+
+    impl Iterator for Constant {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            return Some(1.0);
+        }
+    }
+
+    impl Source for Constant {
+        fn current_frame_len(&self) -> Option<usize> {
+            return None;
+        }
+
+        fn channels(&self) -> u16 {
+            return 2;
+        }
+
+        fn sample_rate(&self) -> u32 {
+            return 500;
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            return None;
+        }
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut source = DynamicSource::new(Constant);
+        let mut playback = source.playback();
+
+        assert_eq!(source.by_ref().take(500).sum::<f32>(), 500.0);
+        assert_eq!(playback.position(), Duration::from_millis(500));
+
+        // Paused playback is silent and keeps its position
+        playback.pause();
+        assert!(playback.paused());
+        assert_eq!(source.by_ref().take(500).sum::<f32>(), 0.0);
+        assert_eq!(playback.position(), Duration::from_millis(500));
+
+        playback.resume();
+        assert_eq!(source.next(), Some(1.0));
+
+        drop(playback);
+        assert_eq!(source.next(), None);
+    }
+
+    #[test]
+    fn test_boost() {
+        let mut sound = Sound::silent();
+
+        let source = DynamicSource::new(Constant).boosted(sound.boost.clone());
+        let mut playback = source.playback();
+        assert_eq!(source.sample_rate(), 500);
+
+        // The shared speed applies on top of the speed of the playback
+        sound.set_speed(2.0);
+        assert_eq!(source.sample_rate(), 1000);
+
+        playback.speed(1.5);
+        assert_eq!(source.sample_rate(), 1496);
+    }
+
+    #[test]
+    fn test_cues() {
+        let now = Instant::now();
+        let second = Duration::from_secs(1);
+
+        let mut cues = Cues::new();
+        cues.push(now, Priority::Stinger, "eliminated", 1, second);
+        cues.push(now, Priority::Stinger, "eliminated", 2, second);
+        cues.push(now, Priority::Ambient, "crowd", 3, second);
+        cues.push(now, Priority::Announcement, "countdown", 4, second);
+
+        // Announcements first, eliminations are merged and ambient sounds skipped
+        assert_eq!(cues.next(now), Some((4, false)));
+        assert_eq!(cues.next(now), None);
+        assert_eq!(cues.next(now + second), Some((1, false)));
+        assert_eq!(cues.next(now + second * 2), None);
+
+        // Announcements preempt less important cues
+        cues.push(now + second * 2, Priority::Stinger, "eliminated", 5, second);
+        assert_eq!(cues.next(now + second * 2), Some((5, false)));
+        cues.push(now + second * 2, Priority::Announcement, "go", 6, second);
+        assert_eq!(cues.next(now + second * 2), Some((6, true)));
+
+        // Outdated cues are dropped
+        cues.push(now + second * 2, Priority::Stinger, "eliminated", 7, second);
+        assert_eq!(cues.next(now + second * 10), None);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct Timer {
+    started: Instant,
+    duration: Duration,
+    repeat: bool,
+}
+
+impl Timer {
+    fn due(&self) -> Instant {
+        return self.started + self.duration;
+    }
+}
+
+/// One-shot and repeating timers of the running game keyed by name
+///
+/// Timers do not call back but are polled by the game - a timer fires once per frame at most, even
+/// if a repeating timer elapsed multiple times since.
+#[derive(Default)]
+pub struct Timers {
+    timers: HashMap<String, Timer>,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Schedules a timer firing once after the given duration - replaces any timer of the same name
+    pub fn once(&mut self, name: &str, now: Instant, duration: Duration) {
+        self.timers.insert(name.to_owned(), Timer {
+            started: now,
+            duration,
+            repeat: false,
+        });
+    }
+
+    /// Schedules a timer firing every period - replaces any timer of the same name
+    pub fn repeat(&mut self, name: &str, now: Instant, period: Duration) {
+        self.timers.insert(name.to_owned(), Timer {
+            started: now,
+            duration: period,
+            repeat: true,
+        });
+    }
+
+    /// Removes the timer and returns whether it was pending
+    pub fn cancel(&mut self, name: &str) -> bool {
+        return self.timers.remove(name).is_some();
+    }
+
+    pub fn pending(&self, name: &str) -> bool {
+        return self.timers.contains_key(name);
+    }
+
+    /// Time since the timer was scheduled or last fired
+    pub fn elapsed(&self, name: &str, now: Instant) -> Option<Duration> {
+        return self.timers.get(name)
+            .map(|timer| now.saturating_duration_since(timer.started));
+    }
+
+    /// Time until the timer fires
+    pub fn remaining(&self, name: &str, now: Instant) -> Option<Duration> {
+        return self.timers.get(name)
+            .map(|timer| timer.due().saturating_duration_since(now));
+    }
+
+    /// Whether the timer is due - one-shot timers are removed when fired while repeating timers
+    /// start their next period
+    pub fn fired(&mut self, name: &str, now: Instant) -> bool {
+        let timer = match self.timers.get_mut(name) {
+            Some(timer) if timer.due() <= now => timer,
+            _ => return false,
+        };
+
+        if timer.repeat {
+            // Keep the period stable even if the timer is polled late
+            while timer.due() <= now {
+                timer.started += timer.duration.max(Duration::from_millis(1));
+            }
+        } else {
+            self.timers.remove(name);
+        }
+
+        return true;
+    }
+
+    /// Postpones all timers - used to skip the time a game was paused
+    pub fn delay(&mut self, duration: Duration) {
+        for timer in self.timers.values_mut() {
+            timer.started += duration;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timers() {
+        let now = Instant::now();
+        let mut timers = Timers::new();
+
+        timers.once("phase", now, Duration::from_secs(5));
+        timers.repeat("beat", now, Duration::from_secs(2));
+
+        assert!(!timers.fired("phase", now + Duration::from_secs(4)));
+        assert_eq!(timers.remaining("phase", now + Duration::from_secs(4)), Some(Duration::from_secs(1)));
+
+        assert!(timers.fired("phase", now + Duration::from_secs(5)));
+        assert!(!timers.pending("phase"));
+
+        // Repeating timers fire once even if polled late
+        assert!(timers.fired("beat", now + Duration::from_secs(5)));
+        assert!(!timers.fired("beat", now + Duration::from_secs(5)));
+        assert_eq!(timers.remaining("beat", now + Duration::from_secs(5)), Some(Duration::from_secs(1)));
+
+        // Paused time does not count
+        timers.delay(Duration::from_secs(10));
+        assert!(!timers.fired("beat", now + Duration::from_secs(7)));
+
+        assert!(timers.cancel("beat"));
+        assert!(!timers.cancel("beat"));
+    }
+}
@@ -0,0 +1,194 @@
+use std::time::{Duration, Instant};
+
+use heapless::HistoryBuffer;
+use tracing::debug;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Percentiles<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+    pub max: T,
+}
+
+impl<T> Percentiles<T>
+    where
+        T: Copy + Ord + Default,
+{
+    /// Calculates the percentiles of the given values
+    pub fn of(mut values: Vec<T>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        values.sort_unstable();
+
+        let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+
+        return Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: percentile(100),
+        };
+    }
+}
+
+/// Keeps the most recent `N` samples of a measurement
+pub struct Samples<T, const N: usize> {
+    buffer: HistoryBuffer<T, N>,
+}
+
+impl<T, const N: usize> Samples<T, N>
+    where
+        T: Copy + Ord + Default,
+{
+    pub fn new() -> Self {
+        return Self {
+            buffer: HistoryBuffer::new(),
+        };
+    }
+
+    pub fn record(&mut self, value: T) {
+        self.buffer.write(value);
+    }
+
+    pub fn percentiles(&self) -> Percentiles<T> {
+        return Percentiles::of(self.buffer.as_slice().to_vec());
+    }
+}
+
+impl<T, const N: usize> Default for Samples<T, N>
+    where
+        T: Copy + Ord + Default,
+{
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+pub type DurationSamples = Samples<Duration, 1024>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimingReport {
+    pub frames: Percentiles<Duration>,
+    pub work: Percentiles<Duration>,
+    pub controllers: Percentiles<Duration>,
+    pub backlog: Percentiles<usize>,
+}
+
+/// Timing information about the main loop
+#[derive(Default)]
+pub struct Timing {
+    /// Number of frames since startup
+    pub count: u64,
+
+    /// Time simulated by each frame
+    pub frames: DurationSamples,
+
+    /// Time spent updating each frame - must stay below the tick to keep up
+    pub work: DurationSamples,
+
+    pub backlog: Samples<usize, 1024>,
+}
+
+/// Paces the main loop to a fixed tick rate
+///
+/// Elapsed time accumulates and is consumed in whole ticks while the remainder carries over to the
+/// next frame. Each tick is simulated on its own with a time advancing by exactly one tick. Falling
+/// behind by more than a few ticks drops the excess instead of catching up.
+pub struct Pacer {
+    tick: Duration,
+
+    last: Instant,
+    accumulated: Duration,
+
+    // Time of the latest simulated tick
+    time: Instant,
+}
+
+impl Pacer {
+    // Maximum number of ticks simulated by a single frame
+    const MAX_TICKS: u32 = 5;
+
+    pub fn new(rate: u32, now: Instant) -> Self {
+        return Self {
+            tick: Duration::from_secs(1) / rate.max(1),
+            last: now,
+            accumulated: Duration::ZERO,
+            time: now,
+        };
+    }
+
+    pub fn tick(&self) -> Duration {
+        return self.tick;
+    }
+
+    /// Accumulates the time elapsed until now and returns the number of whole ticks taken from it
+    pub fn advance(&mut self, now: Instant) -> u32 {
+        self.accumulated += now.saturating_duration_since(self.last);
+        self.last = now;
+
+        let ticks = (self.accumulated.as_nanos() / self.tick.as_nanos()) as u32;
+        if ticks > Self::MAX_TICKS {
+            debug!("Main loop fell behind - dropping {} ticks", ticks - Self::MAX_TICKS);
+
+            // The dropped ticks are skipped to keep the time in line with the clock
+            self.time += self.tick * (ticks - Self::MAX_TICKS);
+            self.accumulated = Duration::ZERO;
+            return Self::MAX_TICKS;
+        }
+
+        self.accumulated -= self.tick * ticks;
+        return ticks;
+    }
+
+    /// Advances the time by a single tick and returns it
+    pub fn step(&mut self) -> Instant {
+        self.time += self.tick;
+        return self.time;
+    }
+
+    /// Sleeps until the next tick is due and returns the number of ticks to simulate
+    pub async fn wait(&mut self) -> u32 {
+        loop {
+            let due = self.tick.saturating_sub(self.accumulated + self.last.elapsed());
+            if !due.is_zero() {
+                tokio::time::sleep(due).await;
+            }
+
+            let ticks = self.advance(Instant::now());
+            if ticks > 0 {
+                return ticks;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pacer() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(100, start);
+        assert_eq!(pacer.tick(), Duration::from_millis(10));
+
+        assert_eq!(pacer.advance(start + Duration::from_millis(4)), 0);
+
+        // The remainder of a frame carries over to the next one
+        assert_eq!(pacer.advance(start + Duration::from_millis(15)), 1);
+        assert_eq!(pacer.advance(start + Duration::from_millis(36)), 2);
+
+        // Every tick advances the time by exactly one tick
+        assert_eq!(pacer.step(), start + Duration::from_millis(10));
+        assert_eq!(pacer.step(), start + Duration::from_millis(20));
+        assert_eq!(pacer.step(), start + Duration::from_millis(30));
+
+        // Long stalls are not caught up with - the dropped ticks are skipped
+        assert_eq!(pacer.advance(start + Duration::from_secs(1)), 5);
+        assert_eq!((0..5).map(|_| pacer.step()).last(), Some(start + Duration::from_secs(1)));
+        assert_eq!(pacer.advance(start + Duration::from_millis(1005)), 0);
+    }
+}
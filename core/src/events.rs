@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+use tokio::sync::broadcast;
+use tracing::trace;
+
+/// Distributes events of type `E` to all interested subscribers
+pub struct Events<E>(broadcast::Sender<E>);
+
+impl<E> Events<E>
+    where
+        E: Clone + Debug,
+{
+    const CAPACITY: usize = 64;
+
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(Self::CAPACITY);
+        return Self(tx);
+    }
+
+    pub fn emit(&self, event: E) {
+        trace!("Event: {:?}", event);
+
+        // Having no subscribers is fine
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<E> {
+        return self.0.subscribe();
+    }
+}
+
+impl<E> Clone for Events<E> {
+    fn clone(&self) -> Self {
+        return Self(self.0.clone());
+    }
+}
+
+impl<E> Default for Events<E>
+    where
+        E: Clone + Debug,
+{
+    fn default() -> Self {
+        return Self::new();
+    }
+}
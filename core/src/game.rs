@@ -0,0 +1,181 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::players::{PlayerData, PlayerId};
+use crate::engine::World;
+
+pub struct Session {
+    // The time when the session was started
+    pub started: Instant,
+
+    // Total time the session was suspended before and the start of the current suspension
+    suspended: Duration,
+    since: Option<Instant>,
+}
+
+impl Session {
+    pub fn new(now: Instant) -> Self {
+        return Self {
+            started: now,
+            suspended: Duration::ZERO,
+            since: None,
+        };
+    }
+
+    /// Time the session was running - not counting the time it was suspended
+    pub fn age(&self, now: Instant) -> Duration {
+        let current = self.since.map_or(Duration::ZERO, |since| now - since);
+        return (now - self.started).saturating_sub(self.suspended + current);
+    }
+
+    /// Stops the clock of the session until it is resumed
+    pub fn suspend(&mut self, now: Instant) {
+        self.since.get_or_insert(now);
+    }
+
+    /// Continues the clock of the session. Returns the time the session was suspended.
+    pub fn resume(&mut self, now: Instant) -> Duration {
+        let paused = self.since.take().map_or(Duration::ZERO, |since| now - since);
+        self.suspended += paused;
+        return paused;
+    }
+
+    pub fn suspended(&self) -> bool {
+        return self.since.is_some();
+    }
+
+    /// Time left until the session has lasted for the given duration
+    pub fn remaining(&self, now: Instant, duration: Duration) -> Duration {
+        return duration.saturating_sub(self.age(now));
+    }
+}
+
+/// Points accumulated by each player over the course of a game
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Scores<T> {
+    scores: HashMap<PlayerId, T>,
+}
+
+impl<T> Scores<T>
+    where
+        T: AddAssign + Default + PartialOrd + Copy
+{
+    /// Starts all players with no points
+    pub fn new(players: impl IntoIterator<Item=PlayerId>) -> Self {
+        return Self {
+            scores: players.into_iter()
+                .map(|player| (player, T::default()))
+                .collect(),
+        };
+    }
+
+    pub fn add(&mut self, player: PlayerId, points: T) {
+        *self.scores.entry(player).or_default() += points;
+    }
+
+    pub fn get(&self, player: PlayerId) -> T {
+        return self.scores.get(&player).copied().unwrap_or_default();
+    }
+
+    /// Drops the points of a player leaving - they are not ranked anymore
+    pub fn remove(&mut self, player: PlayerId) {
+        self.scores.remove(&player);
+    }
+
+    /// The players sharing the highest score
+    pub fn leaders(&self) -> HashSet<PlayerId> {
+        let best = self.scores.values()
+            .copied()
+            .fold(None, |best: Option<T>, score| match best {
+                Some(best) if best >= score => Some(best),
+                _ => Some(score),
+            });
+
+        return self.scores.iter()
+            .filter(|(_, score)| Some(**score) == best)
+            .map(|(player, _)| *player)
+            .collect();
+    }
+}
+
+/// A game played in a world with settings `S` emitting events `E` and recording results in `R`
+pub trait Game<S, E, R> {
+    /// What the game turns into once it is over
+    type Next;
+
+    fn update(&mut self, world: &mut World<S, E, R>, duration: Duration, session: &Session) -> Option<Self::Next>;
+
+    /// Removes a player form the game. Returns whether the player was part of the game.
+    fn kick_player(&mut self, player: PlayerId, world: &mut World<S, E, R>) -> bool;
+
+    /// Adds a player arriving late to the running game. Returns whether the game accepted the
+    /// player - games not supporting late joins refuse all players.
+    fn join_player(&mut self, _player: PlayerId, _world: &mut World<S, E, R>) -> bool {
+        return false;
+    }
+
+    /// Halts the game while its session is suspended - e.g. by pausing the music
+    fn suspend(&mut self, _world: &mut World<S, E, R>) {}
+
+    /// Continues the game after being suspended for the given time. Games must postpone all
+    /// their deadlines by this time to continue exactly where they stopped.
+    fn resume(&mut self, _paused: Duration, _world: &mut World<S, E, R>) {}
+
+    /// Applies settings changed while the game is running - games reading their settings from the
+    /// world in every update have nothing to do
+    fn reconfigure(&mut self, _world: &mut World<S, E, R>) {}
+
+    /// Whether controllers not taking part in the game show the idle animation
+    fn idle_animation(&self) -> bool {
+        return true;
+    }
+}
+
+/// A game keeping data for each of its players
+pub trait GameData<S, E, R>: Game<S, E, R> {
+    type Data;
+
+    fn data(&mut self) -> &mut PlayerData<Self::Data>;
+
+    fn create(players: HashSet<PlayerId>, world: &mut World<S, E, R>) -> Self
+        where Self: Sized;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scores() {
+        let mut scores = Scores::new([1, 2, 3]);
+        assert_eq!(scores.leaders(), HashSet::from([1, 2, 3]));
+
+        scores.add(1, Duration::from_secs(3));
+        scores.add(2, Duration::from_secs(5));
+        assert_eq!(scores.get(2), Duration::from_secs(5));
+        assert_eq!(scores.leaders(), HashSet::from([2]));
+
+        scores.add(1, Duration::from_secs(2));
+        assert_eq!(scores.leaders(), HashSet::from([1, 2]));
+
+        scores.remove(2);
+        assert_eq!(scores.leaders(), HashSet::from([1]));
+    }
+
+    #[test]
+    fn test_session_suspend() {
+        let now = Instant::now();
+        let mut session = Session::new(now);
+
+        session.suspend(now + Duration::from_secs(10));
+        assert_eq!(session.age(now + Duration::from_secs(15)), Duration::from_secs(10));
+
+        assert_eq!(session.resume(now + Duration::from_secs(20)), Duration::from_secs(10));
+        assert_eq!(session.age(now + Duration::from_secs(25)), Duration::from_secs(15));
+        assert!(!session.suspended());
+    }
+}
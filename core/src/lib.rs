@@ -0,0 +1,61 @@
+//! Reusable core of hastilude: drives PlayStation Move controllers and provides an engine for
+//! games played with them.
+//!
+//! The crate is split into the following parts:
+//!
+//! * [`controller`] talks to the controllers via hidraw. Controllers are wrapped in a
+//!   [`Controller`](controller::Controller) which rate limits feedback and keeps the latest input.
+//!   Custom backends can be plugged in by implementing [`Device`](controller::Device).
+//! * [`engine`] manages the connected [`Players`](engine::players::Players), animates their LEDs
+//!   and rumble using [`Animated`](engine::animation::Animated) values and the [`keyframes!`]
+//!   macro, plays sounds and loads assets.
+//! * [`game`] defines the traits implemented by games. Games are updated every frame with a
+//!   [`World`](engine::World) holding everything they can interact with. The type of the settings,
+//!   the events and the results recorded in the world is chosen by the application.
+//! * [`events`] is a bus distributing events emitted by games to all interested subscribers.
+//!
+//! A minimal main loop opens the controllers, adds them to the players and updates the players
+//! and the game every frame:
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use std::time::Duration;
+//!
+//! use hastilude_core::controller::{evdev, faults, filter, pairing, rate::{self, Rates}, recording, trigger};
+//! use hastilude_core::engine::hotplug::{self, Hotplug};
+//! use hastilude_core::engine::players::Players;
+//!
+//! let rates = Rates::new(&rate::Config { rules: vec![] });
+//! let faults = faults::Config { latency: None, drop: 0.0, disconnects: 0.0, disconnect_duration: Duration::ZERO };
+//!
+//! let pairing = pairing::Config { auto_pair: false };
+//! let filter = filter::Config { smoothing: 0.0, dead_zone: 0.0, jitter: 0.0 };
+//! let trigger = trigger::Config { curve: trigger::Curve::Linear };
+//! let recording = recording::Config { directory: None };
+//! let gamepads = evdev::Config { gamepads: false };
+//! let config = hotplug::Config { led_pwm_frequency: None, filter, trigger, recording, gamepads };
+//!
+//! let (mut hotplug, controllers) = Hotplug::init(config, None, rates, faults, pairing).await?;
+//!
+//! let mut players = Players::new();
+//! for controller in controllers {
+//!     players.add(controller);
+//! }
+//!
+//! loop {
+//!     players.update(Duration::from_millis(10)).await?;
+//!     // Update the game using a `World` ...
+//! #   break;
+//! }
+//! # return Ok(());
+//! # }
+//! ```
+
+#![feature(type_alias_impl_trait)]
+#![feature(result_flattening)]
+#![feature(drain_filter)]
+
+pub mod controller;
+pub mod engine;
+pub mod events;
+pub mod game;
@@ -0,0 +1,468 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::watch;
+use tracing::{debug, info, instrument, warn};
+
+use hastilude_core::controller::{Address, Controller};
+use hastilude_core::engine::assets::Assets;
+use hastilude_core::engine::hotplug::Change;
+use hastilude_core::engine::metrics::{MetricsLog, MetricsReport};
+use hastilude_core::engine::players::Players;
+use hastilude_core::engine::random::Random;
+use hastilude_core::engine::scope::Scope;
+use hastilude_core::engine::sound::Sound;
+use hastilude_core::engine::timer::Timers;
+use hastilude_core::engine::timing::Timing;
+use hastilude_core::engine::World;
+
+use crate::capture;
+use crate::events::Events;
+use crate::games::mutators::Mutators;
+use crate::games::scoring::Scoring;
+use crate::journal::Journal;
+use crate::meta::lobby::Lobby;
+use crate::meta::pause::Paused;
+use crate::recorder::Recorder;
+use crate::snapshot::{self, Snapshot};
+use crate::state::{PowerSave, Settings, State};
+use crate::state::request::{Requests, Stub};
+use crate::web::{ArenaHandle, ControllerInfoDTO, InfoPublisher, StateDTO};
+
+#[derive(clap::Args, Debug)]
+#[group(id = "arena")]
+pub struct Config {
+    /// Runs an independent arena - can be repeated to run multiple arenas at once
+    #[arg(long = "arena", value_name = "NAME[=SOUND_DEVICE]")]
+    pub arenas: Vec<Definition>,
+
+    /// Assigns a controller to an arena - unassigned controllers join the first arena
+    #[arg(long = "arena-controller", value_name = "ADDRESS=ARENA")]
+    pub assignments: Vec<Assignment>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+
+    /// The sound output device - uses the default device if missing
+    pub sound: Option<String>,
+}
+
+impl FromStr for Definition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, sound) = match s.split_once('=') {
+            Some((name, sound)) => (name, Some(sound.to_owned())),
+            None => (s, None),
+        };
+
+        if name.is_empty() {
+            return Err(anyhow!("Arena name must not be empty"));
+        }
+
+        return Ok(Self {
+            name: name.to_owned(),
+            sound,
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub controller: Address,
+    pub arena: String,
+}
+
+impl FromStr for Assignment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (controller, arena) = s.rsplit_once('=')
+            .ok_or_else(|| anyhow!("Expected ADDRESS=ARENA: {}", s))?;
+
+        return Ok(Self {
+            controller: controller.parse()?,
+            arena: arena.to_owned(),
+        });
+    }
+}
+
+/// An independent play area with its own controllers, game and sound output
+pub struct Arena {
+    pub name: String,
+
+    players: Players,
+    sound: Sound,
+
+    settings: Settings,
+    timing: Timing,
+    metrics: MetricsLog,
+    timers: Timers,
+    rng: Random,
+    scope: Scope,
+    scoring: Scoring,
+
+    // Always present except while the state is updated
+    state: Option<State>,
+
+    events: Events,
+    recorder: Recorder,
+
+    stub: Stub,
+    requests: Requests,
+
+    info: InfoPublisher,
+
+    // Notifies about settings changed at runtime
+    published: watch::Sender<Settings>,
+
+    capture: Option<capture::Writer>,
+    snapshot: Option<snapshot::Store>,
+    journal: Option<Journal>,
+}
+
+impl Arena {
+    // Interval between logging the metrics
+    const METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+    #[instrument(level = "debug", skip(settings))]
+    pub fn new(definition: &Definition, mut settings: Settings) -> Result<Self> {
+        let sound = match definition.sound {
+            Some(ref device) => Sound::init_device(device),
+            None => Sound::init(),
+        }.with_context(|| format!("Failed to initialize sound for arena {}", definition.name))?;
+
+        let mut players = Players::new();
+        let state = State::lobby(&mut players);
+
+        let events = Events::new();
+        let recorder = Recorder::start(events.subscribe());
+        let scoring = Scoring::new(events.subscribe());
+
+        let (stub, requests) = Stub::create();
+
+        // Keep the seed actually used to have it in captures
+        let rng = Random::new(settings.seed);
+        info!("Arena {} uses random seed {}", definition.name, rng.seed());
+        settings.seed = Some(rng.seed());
+        let (published, _) = watch::channel(settings.clone());
+
+        let mut arena = Self {
+            name: definition.name.clone(),
+            players,
+            sound,
+            settings,
+            timing: Timing::default(),
+            metrics: MetricsLog::new(Self::METRICS_INTERVAL, Instant::now()),
+            timers: Timers::new(),
+            rng,
+            scope: Scope::new(),
+            scoring,
+            state: Some(state),
+            events,
+            recorder,
+            stub,
+            requests,
+            info: InfoPublisher::new(),
+            published,
+            capture: None,
+            snapshot: None,
+            journal: None,
+        };
+
+        arena.configure();
+
+        return Ok(arena);
+    }
+
+    /// Hands the settings handled by the engine over to it - required whenever they have changed
+    fn configure(&mut self) {
+        self.sound.set_volume(self.settings.volume);
+        self.players.set_feedback_budget(self.settings.feedback_budget);
+        self.players.set_low_battery_warning(self.settings.low_battery_warning);
+    }
+
+    pub fn stub(&self) -> Stub {
+        return self.stub.clone();
+    }
+
+    pub fn events(&self) -> &Events {
+        return &self.events;
+    }
+
+    pub fn info(&self) -> &InfoPublisher {
+        return &self.info;
+    }
+
+    pub fn recorder(&self) -> Recorder {
+        return self.recorder.clone();
+    }
+
+    /// The current settings updated whenever they are changed
+    pub fn settings(&self) -> watch::Receiver<Settings> {
+        return self.published.subscribe();
+    }
+
+    /// Everything the web interface requires to serve this arena
+    pub fn handle(&self) -> ArenaHandle {
+        return ArenaHandle {
+            name: self.name.clone(),
+            stub: self.stub(),
+            info: self.info.subscribe(),
+            recorder: self.recorder(),
+            events: self.events.clone(),
+            scope: self.scope.clone(),
+            settings: self.settings(),
+        };
+    }
+
+    /// Starts writing a capture of this arena
+    pub fn capture(&mut self, path: &Path) -> Result<()> {
+        self.capture = Some(capture::Writer::create(path, &self.settings, self.events.subscribe())?);
+        return Ok(());
+    }
+
+    /// Starts writing an event log of this arena to the data directory
+    pub fn journal(&mut self, directory: &Path) -> Result<()> {
+        self.journal = Some(Journal::create(directory, &self.name, self.events.subscribe())?);
+        return Ok(());
+    }
+
+    /// Keeps the session of this arena in a file and continues the session kept there, if any
+    pub fn snapshot(&mut self, path: &Path) -> Result<()> {
+        let store = snapshot::Store::open(path)?;
+
+        if let Some(snapshot) = store.last().cloned() {
+            info!("Restoring session of arena {} from {:?}", self.name, path);
+
+            self.settings.game_mode = snapshot.game_mode;
+            self.scoring.restore(snapshot.scoring);
+            self.state = Some(match snapshot.progress {
+                Some(progress) => progress.restore(&mut self.players),
+                None => State::Lobby(Lobby::restore(&mut self.players, snapshot.ready)),
+            });
+        }
+
+        self.snapshot = Some(store);
+        return Ok(());
+    }
+
+    #[instrument(level = "trace", name = "Arena::update", skip(self, assets), fields(arena = self.name))]
+    pub async fn update(&mut self, assets: &Assets, now: Instant, duration: Duration) -> Result<()> {
+        let started = Instant::now();
+
+        self.timing.count += 1;
+        self.timing.frames.record(duration);
+        self.timing.backlog.record(self.requests.backlog());
+
+        // Apply the output depending on the state of the game
+        let mutators = self.state.as_ref().and_then(State::mutators);
+        self.players.set_brightness(match self.state {
+            Some(ref state) if state.paused() => self.settings.brightness * Paused::BRIGHTNESS,
+            _ => self.settings.brightness * mutators.map_or(1.0, Mutators::brightness),
+        });
+        self.players.set_frozen(self.state.as_ref().map_or(false, State::paused));
+        self.sound.set_speed(mutators.map_or(1.0, Mutators::music_speed));
+        self.players.set_power_save(match self.settings.power_save {
+            PowerSave::Off => false,
+            PowerSave::On => true,
+            PowerSave::Auto => self.players.batteries_low(),
+        });
+        self.players.set_idle_power_off(match self.state {
+            Some(State::Lobby(_)) | Some(State::Attract(_)) => self.settings.idle_power_off,
+            _ => None,
+        });
+        self.players.set_participants(self.state.as_ref()
+            .and_then(State::participants)
+            .cloned());
+
+        // Update controller information
+        self.players.update(duration).await
+            .context("Failed to update players")?;
+
+        let mut world = World {
+            now,
+            players: &mut self.players,
+            sound: &mut self.sound,
+            assets,
+            settings: &mut self.settings,
+            timing: &self.timing,
+            timers: &mut self.timers,
+            rng: &mut self.rng,
+            scope: &self.scope,
+            events: &self.events,
+            scoring: &mut self.scoring,
+            threshold_scale: 1.0,
+        };
+
+        let mut state = self.state.take().expect("State present");
+
+        // Handle requests
+        state = state.handle(&mut self.requests, &mut world).await;
+
+        // Play the game
+        state = state.update(&mut world, duration);
+
+        if let Some(ref mut capture) = self.capture {
+            capture.record(&world)
+                .context("Failed to write capture")?;
+        }
+
+        self.sound.update(now);
+        let scored = self.scoring.update(now);
+
+        if let Some(ref mut journal) = self.journal {
+            if let Err(err) = journal.record(&state, now) {
+                warn!("Failed to write event log: {:#}", err);
+            }
+        }
+
+        // Players stay ready while a game is running
+        if let Some(ref mut store) = self.snapshot {
+            let ready = match state {
+                State::Lobby(ref lobby) => lobby.remembered(),
+                State::Attract(ref attract) => attract.remembered(),
+                _ => store.last().map(|snapshot| snapshot.ready.clone()).unwrap_or_default(),
+            };
+
+            let progress = state.progress();
+
+            // The scoring report is only built if anything changed as it sorts all the records
+            let changed = scored || store.last().map_or(true, |last| {
+                last.game_mode != self.settings.game_mode || last.ready != ready || last.progress != progress
+            });
+
+            if changed {
+                store.update(Snapshot {
+                    game_mode: self.settings.game_mode,
+                    ready,
+                    progress,
+                    scoring: self.scoring.report(),
+                });
+            }
+        }
+
+        if *self.published.borrow() != self.settings {
+            self.configure();
+            self.published.send_replace(self.settings.clone());
+        }
+
+        // Publish updated status info
+        self.info.publish(StateDTO {
+            mode: self.settings.game_mode.into(),
+            options: self.settings.options.clone(),
+            mutators: self.settings.mutators.clone(),
+            theme: self.settings.theme,
+            state: (&state).into(),
+            devices: self.players.iter()
+                .map(|player| ControllerInfoDTO {
+                    low_battery: player.low_battery(),
+                    threshold: player.threshold(),
+                    ..player.controller().into()
+                })
+                .collect(),
+        });
+
+        self.state = Some(state);
+
+        self.timing.work.record(started.elapsed());
+
+        if self.metrics.due(now) {
+            self.metrics.log(&self.name, now, &MetricsReport::of(&self.timing, &self.players));
+        }
+
+        return Ok(());
+    }
+}
+
+/// All arenas and the assignment of controllers to them
+pub struct Arenas {
+    arenas: Vec<Arena>,
+    assignments: Vec<Assignment>,
+}
+
+impl Arenas {
+    /// Creates the configured arenas or a single default arena if none is configured
+    pub fn new(config: Config, settings: Settings) -> Result<Self> {
+        let definitions = if config.arenas.is_empty() {
+            vec![Definition {
+                name: "default".to_owned(),
+                sound: None,
+            }]
+        } else {
+            config.arenas
+        };
+
+        for (i, definition) in definitions.iter().enumerate() {
+            if definitions[..i].iter().any(|other| other.name == definition.name) {
+                return Err(anyhow!("Arena defined twice: {}", definition.name));
+            }
+        }
+
+        for assignment in config.assignments.iter() {
+            if !definitions.iter().any(|definition| definition.name == assignment.arena) {
+                return Err(anyhow!("Controller {} assigned to unknown arena: {}", assignment.controller.as_string(), assignment.arena));
+            }
+        }
+
+        let arenas = definitions.iter()
+            .map(|definition| Arena::new(definition, settings.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        return Ok(Self {
+            arenas,
+            assignments: config.assignments,
+        });
+    }
+
+    /// The first arena which also receives all unassigned controllers
+    pub fn primary(&self) -> &Arena {
+        return &self.arenas[0];
+    }
+
+    pub fn primary_mut(&mut self) -> &mut Arena {
+        return &mut self.arenas[0];
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&Arena> {
+        return self.arenas.iter();
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=&mut Arena> {
+        return self.arenas.iter_mut();
+    }
+
+    /// Adds the controller to the arena it is assigned to
+    pub fn add(&mut self, controller: Controller) {
+        let index = self.assignments.iter()
+            .find(|assignment| assignment.controller == controller.serial())
+            .and_then(|assignment| self.arenas.iter().position(|arena| arena.name == assignment.arena))
+            .unwrap_or(0);
+
+        let arena = &mut self.arenas[index];
+
+        debug!("Controller {} joins arena {}", controller.serial().as_string(), arena.name);
+        arena.players.add(controller);
+    }
+
+    pub fn apply(&mut self, change: Change) {
+        match change {
+            Change::Added(controller) => self.add(controller),
+            Change::Removed(path) => for arena in self.arenas.iter_mut() {
+                arena.players.remove_path(&path);
+            },
+        }
+    }
+
+    pub async fn update(&mut self, assets: &Assets, now: Instant, duration: Duration) -> Result<()> {
+        for arena in self.arenas.iter_mut() {
+            arena.update(assets, now, duration).await?;
+        }
+
+        return Ok(());
+    }
+}
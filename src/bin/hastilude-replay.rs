@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use tracing::warn;
+
+use hastilude_core::engine::assets::Assets;
+
+use hastilude::capture;
+
+/// Replays a session capture headlessly through the game logic
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// The capture file to replay
+    capture: PathBuf,
+
+    /// Directory containing the assets - music is not played but required to pick tracks
+    #[arg(long, default_value = "assets")]
+    assets: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter("INFO")
+        .compact()
+        .init();
+
+    let assets = Assets::init(&args.assets).unwrap_or_else(|err| {
+        warn!("Replaying without assets: {:#}", err);
+        return Assets::empty();
+    });
+
+    let entries = capture::read(&args.capture)?;
+    let replay = capture::replay(entries, &assets).await
+        .context("Failed to replay capture")?;
+
+    for (time, event) in replay.replayed.iter() {
+        println!("{:10.3} {}", time, serde_json::to_string(event)?);
+    }
+
+    if replay.diverged() {
+        bail!("Replay diverged from capture: {} events recorded, {} events replayed",
+              replay.recorded.len(), replay.replayed.len());
+    }
+
+    println!("Replay matches capture");
+
+    return Ok(());
+}
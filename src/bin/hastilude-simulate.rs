@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Parser;
+use tracing::warn;
+
+use hastilude_core::controller::faults;
+use hastilude_core::engine::assets::Assets;
+use hastilude_core::engine::timing::Percentiles;
+
+use hastilude::games::GameMode;
+use hastilude::simulation::{self, Config};
+
+/// Simulates rounds of a game mode with virtual players to help balancing the game
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// The game mode to simulate
+    #[arg(long, default_value = "joust")]
+    mode: GameMode,
+
+    /// Number of virtual players
+    #[arg(long, default_value_t = 6)]
+    players: usize,
+
+    /// Number of rounds to simulate
+    #[arg(long, default_value_t = 100)]
+    rounds: usize,
+
+    /// Simulated frame duration
+    #[arg(long, default_value = "10ms", value_parser = humantime::parse_duration)]
+    step: Duration,
+
+    /// Cancel rounds taking longer than this
+    #[arg(long, default_value = "10m", value_parser = humantime::parse_duration)]
+    max_round: Duration,
+
+    /// Directory containing the assets - music is not played but required to pick tracks
+    #[arg(long, default_value = "assets")]
+    assets: PathBuf,
+
+    /// Seed of the random number generator to reproduce a simulation
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[command(flatten)]
+    faults: faults::Config,
+}
+
+fn format(percentiles: &Percentiles<Duration>) -> String {
+    return format!("p50 {:7.1}s  p90 {:7.1}s  p99 {:7.1}s  max {:7.1}s",
+                   percentiles.p50.as_secs_f32(),
+                   percentiles.p90.as_secs_f32(),
+                   percentiles.p99.as_secs_f32(),
+                   percentiles.max.as_secs_f32());
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter("INFO")
+        .compact()
+        .init();
+
+    let assets = Assets::init(&args.assets).unwrap_or_else(|err| {
+        warn!("Simulating without assets: {:#}", err);
+        return Assets::empty();
+    });
+
+    let report = simulation::run(Config {
+        mode: args.mode,
+        players: args.players,
+        rounds: args.rounds,
+        step: args.step,
+        max_round: args.max_round,
+        faults: args.faults,
+        seed: args.seed,
+    }, &assets).await?;
+
+    println!("Rounds:        {} ({} timed out, {} draws)", report.rounds, report.timeouts, report.draws);
+    println!("Round length:  {}", format(&report.length));
+    println!("Eliminations:  {}", format(&report.eliminations));
+    println!("Spread:        {}", format(&report.spread));
+
+    println!("Wins by restlessness:");
+    for (restlessness, wins) in report.wins {
+        println!("  {:5.2}/s  {:5} {}", restlessness, wins, "#".repeat(wins * 50 / report.rounds.max(1)));
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,400 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{debug, instrument, warn};
+
+use hastilude_core::controller::{Address, Controller, feed, Model, Reading};
+use hastilude_core::engine::assets::Assets;
+use hastilude_core::engine::players::{PlayerId, Players};
+use hastilude_core::engine::random::Random;
+use hastilude_core::engine::scope::Scope;
+use hastilude_core::engine::sound::Sound;
+use hastilude_core::engine::timer::Timers;
+use hastilude_core::engine::timing::Timing;
+
+use crate::events::{Event, Events};
+use crate::games::scoring::Scoring;
+use crate::state::{Settings, State, World};
+
+const VERSION: u32 = 2;
+
+/// A single line of a capture file
+///
+/// A capture starts with a header followed by entries ordered by time, which is given in seconds
+/// since the capture was started. Entries sharing the same time belong to the same frame. Every
+/// frame contains a reading for each connected player, even if it did not change.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Entry {
+    Header {
+        version: u32,
+        settings: Settings,
+    },
+
+    Settings {
+        time: f64,
+        settings: Settings,
+    },
+
+    Connected {
+        time: f64,
+        player: PlayerId,
+        address: Address,
+        model: Model,
+    },
+
+    Disconnected {
+        time: f64,
+        player: PlayerId,
+    },
+
+    Reading {
+        time: f64,
+        player: PlayerId,
+        reading: Reading,
+    },
+
+    Event {
+        time: f64,
+        event: Event,
+    },
+}
+
+impl Entry {
+    pub fn time(&self) -> Option<f64> {
+        return match self {
+            Entry::Header { .. } => None,
+            Entry::Settings { time, .. } |
+            Entry::Connected { time, .. } |
+            Entry::Disconnected { time, .. } |
+            Entry::Reading { time, .. } |
+            Entry::Event { time, .. } => Some(*time),
+        };
+    }
+}
+
+/// Writes everything required to replay a session to a capture file
+pub struct Writer {
+    file: BufWriter<File>,
+
+    started: Instant,
+
+    events: broadcast::Receiver<Event>,
+
+    // Last written state used to write changes only
+    settings: Settings,
+    connected: HashSet<PlayerId>,
+}
+
+impl Writer {
+    #[instrument(level = "debug", skip(settings, events))]
+    pub fn create(path: &Path, settings: &Settings, events: broadcast::Receiver<Event>) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create capture file: {:?}", path))?;
+
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            started: Instant::now(),
+            events,
+            settings: settings.clone(),
+            connected: HashSet::new(),
+        };
+
+        writer.write(&Entry::Header {
+            version: VERSION,
+            settings: settings.clone(),
+        })?;
+
+        return Ok(writer);
+    }
+
+    /// Writes the readings of the current frame and all changes since the last one
+    pub fn record(&mut self, world: &World) -> Result<()> {
+        let time = world.now.saturating_duration_since(self.started).as_secs_f64();
+
+        if *world.settings != self.settings {
+            self.settings = world.settings.clone();
+            self.write(&Entry::Settings {
+                time,
+                settings: self.settings.clone(),
+            })?;
+        }
+
+        let disconnected = self.connected.iter()
+            .filter(|player| world.players.get(**player).is_none())
+            .copied()
+            .collect::<Vec<_>>();
+        for player in disconnected {
+            self.connected.remove(&player);
+            self.write(&Entry::Disconnected { time, player })?;
+        }
+
+        for player in world.players.iter() {
+            if self.connected.insert(player.id()) {
+                self.write(&Entry::Connected {
+                    time,
+                    player: player.id(),
+                    address: player.controller().serial(),
+                    model: player.controller().model(),
+                })?;
+            }
+
+            // Write steady readings as well as a frame without a reading differs from one with
+            self.write(&Entry::Reading {
+                time,
+                player: player.id(),
+                reading: Reading {
+                    input: player.input().clone(),
+                    battery: player.battery(),
+                    temperature: player.controller().temperature(),
+                },
+            })?;
+        }
+
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => self.write(&Entry::Event { time, event })?,
+                Err(broadcast::error::TryRecvError::Lagged(n)) => warn!("Capture missed {} events", n),
+                Err(_) => break,
+            }
+        }
+
+        self.file.flush()?;
+
+        return Ok(());
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<()> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")?;
+        return Ok(());
+    }
+}
+
+/// Reads all entries from a capture file
+#[instrument(level = "debug")]
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open capture file: {:?}", path))?;
+
+    let entries = BufReader::new(file).lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line?;
+            return serde_json::from_str(&line)
+                .with_context(|| format!("Invalid capture entry in line {}", i + 1));
+        })
+        .collect::<Result<Vec<Entry>>>()?;
+
+    match entries.first() {
+        Some(Entry::Header { version: VERSION, .. }) => {}
+        Some(Entry::Header { version, .. }) => bail!("Unsupported capture version: {}", version),
+        _ => bail!("Capture does not start with a header"),
+    }
+
+    return Ok(entries);
+}
+
+/// Events of a replay alongside the ones recorded in the capture
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub recorded: Vec<(f64, Event)>,
+    pub replayed: Vec<(f64, Event)>,
+}
+
+impl Replay {
+    /// Checks if the replay produced the same outcome as the recorded session
+    ///
+    /// Music speed changes are ignored as the pacing is chosen randomly. So is the wall clock time
+    /// of eliminations.
+    pub fn diverged(&self) -> bool {
+        fn relevant(events: &[(f64, Event)]) -> impl Iterator<Item=Event> + '_ {
+            return events.iter()
+                .map(|(_, event)| event)
+                .filter(|event| !matches!(event, Event::MusicSpeed { .. }))
+                .map(|event| match event {
+                    Event::PlayerEliminated { player, cause, .. } => Event::PlayerEliminated { player: *player, cause: *cause, time: SystemTime::UNIX_EPOCH },
+                    event => event.clone(),
+                });
+        }
+
+        return !relevant(&self.recorded).eq(relevant(&self.replayed));
+    }
+}
+
+/// Replays a capture headlessly through the game logic
+///
+/// The game is driven by the recorded controller readings using the recorded frame timing. Actions
+/// triggered from outside the game, like starting a game from the web interface, are not part of
+/// the capture and are therefore derived from the recorded events.
+#[instrument(level = "debug", skip(entries, assets))]
+pub async fn replay(entries: Vec<Entry>, assets: &Assets) -> Result<Replay> {
+    let mut entries = entries.into_iter().peekable();
+
+    let mut settings = match entries.next() {
+        Some(Entry::Header { settings, .. }) => settings,
+        _ => bail!("Capture does not start with a header"),
+    };
+
+    let mut players = Players::new();
+    let mut sound = Sound::silent();
+    let timing = Timing::default();
+    let mut timers = Timers::new();
+    let mut rng = Random::new(settings.seed);
+    let scope = Scope::new();
+
+    let events = Events::new();
+    let mut emitted = events.subscribe();
+    let mut scoring = Scoring::new(events.subscribe());
+
+    let mut feeds = HashMap::new();
+
+    let mut state = State::lobby(&mut players);
+    let mut replay = Replay::default();
+
+    let started = Instant::now();
+    let mut last = 0.0;
+
+    while let Some(time) = entries.peek().and_then(Entry::time) {
+        let mut recorded = Vec::new();
+
+        while let Some(entry) = entries.next_if(|entry| entry.time() == Some(time)) {
+            match entry {
+                Entry::Header { .. } => bail!("Unexpected header in capture"),
+
+                Entry::Settings { settings: recorded, .. } => {
+                    settings = recorded;
+                }
+
+                Entry::Connected { player, address, model, .. } => {
+                    let (feed, device) = feed::channel(address, model);
+                    let controller = Controller::new(Box::new(device));
+
+                    if controller.id() != player {
+                        bail!("Player {} has been recorded with an incompatible id", address.as_string());
+                    }
+
+                    players.add(controller);
+                    feeds.insert(player, feed);
+                }
+
+                Entry::Disconnected { player, .. } => {
+                    players.remove(player);
+                    feeds.remove(&player);
+                }
+
+                Entry::Reading { player, reading, .. } => {
+                    if let Some(feed) = feeds.get(&player) {
+                        feed.push(reading);
+                    }
+                }
+
+                Entry::Event { event, .. } => {
+                    recorded.push(event);
+                }
+            }
+        }
+
+        let duration = Duration::from_secs_f64((time - last).max(0.0));
+        last = time;
+
+        players.update(duration).await?;
+
+        let mut world = World {
+            now: started + Duration::from_secs_f64(time),
+            players: &mut players,
+            sound: &mut sound,
+            assets,
+            settings: &mut settings,
+            timing: &timing,
+            timers: &mut timers,
+            rng: &mut rng,
+            scope: &scope,
+            events: &events,
+            scoring: &mut scoring,
+            threshold_scale: 1.0,
+        };
+
+        state = state.update(&mut world, duration);
+
+        let mut replayed = Vec::new();
+        while let Ok(event) = emitted.try_recv() {
+            replayed.push(event);
+        }
+
+        // Apply recorded actions which have not been caused by the game itself
+        for event in recorded.iter() {
+            match event {
+                Event::GameStarted { mode, players } if !replayed.iter().any(|event| matches!(event, Event::GameStarted { .. })) => {
+                    debug!("Starting game from capture: {:?}", mode);
+                    world.settings.game_mode = *mode;
+
+                    let (next, result) = state.start(&mut world, Some(players.clone()));
+                    if let Err(err) = result {
+                        warn!("Failed to start game from capture: {}", err);
+                    }
+                    state = next;
+                }
+
+                Event::GameCancelled => {
+                    let (next, _) = state.cancel(&mut world);
+                    state = next;
+                }
+
+                Event::EmergencyStop => {
+                    state = state.emergency_stop(&mut world);
+                }
+
+                _ => {}
+            }
+        }
+
+        while let Ok(event) = emitted.try_recv() {
+            replayed.push(event);
+        }
+
+        replay.recorded.extend(recorded.into_iter().map(|event| (time, event)));
+        replay.replayed.extend(replayed.into_iter().map(|event| (time, event)));
+    }
+
+    return Ok(replay);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::games::elimination::Cause;
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let entry = Entry::Event {
+            time: 1.5,
+            event: Event::PlayerEliminated { player: 42, cause: Cause::Movement, time: SystemTime::now() },
+        };
+
+        let line = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<Entry>(&line).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_diverged_ignores_music_speed() {
+        let replay = Replay {
+            recorded: vec![
+                (1.0, Event::MusicSpeed { speed: 1.5 }),
+                (2.0, Event::PlayerEliminated { player: 1, cause: Cause::Movement, time: SystemTime::UNIX_EPOCH }),
+            ],
+            replayed: vec![
+                (2.5, Event::PlayerEliminated { player: 1, cause: Cause::Movement, time: SystemTime::now() }),
+            ],
+        };
+
+        assert!(!replay.diverged());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tracing::{debug, info, instrument, warn};
+
+use crate::events::Event;
+use crate::games::GameMode;
+use crate::meta::lobby::Lobby;
+use crate::state::request::Stub;
+
+/// Lets viewers of a Twitch stream vote for the next game mode and trigger chaos events.
+///
+/// Only Twitch is supported as its chat is available via anonymous IRC while YouTube requires API
+/// credentials.
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "chat")]
+pub struct Config {
+    /// Twitch channel to join - chat integration is disabled if not set
+    #[arg(long = "twitch-channel")]
+    pub channel: Option<String>,
+
+    /// Minimum time between two chaos events in seconds
+    #[arg(long = "chat-chaos-cooldown", default_value_t = 30)]
+    pub cooldown: u64,
+}
+
+const SERVER: &str = "irc.chat.twitch.tv:6667";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Vote(GameMode),
+    Chaos,
+}
+
+/// Extracts the sender and command from a chat message
+fn parse(line: &str) -> Option<(&str, Command)> {
+    let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+    let user = prefix.split_once('!')?.0;
+
+    let (_, message) = rest.strip_prefix("PRIVMSG ")?.split_once(" :")?;
+    let mut words = message.split_whitespace();
+
+    let command = match words.next()? {
+        "!vote" => {
+            // Viewers can only vote for the modes the players could pick themselves
+            let mode: GameMode = words.next()?.to_lowercase().parse().ok()?;
+            Command::Vote(Lobby::selectable().find(|selectable| *selectable == mode)?)
+        }
+        "!chaos" => Command::Chaos,
+        _ => return None,
+    };
+
+    return Some((user, command));
+}
+
+struct Chat {
+    stub: Stub,
+
+    // Latest vote of each viewer
+    votes: HashMap<String, GameMode>,
+
+    cooldown: Duration,
+    last_chaos: Option<Instant>,
+}
+
+impl Chat {
+    async fn command(&mut self, user: &str, command: Command) {
+        match command {
+            Command::Vote(mode) => {
+                debug!("{} voted for {:?}", user, mode);
+                self.votes.insert(user.to_owned(), mode);
+            }
+
+            Command::Chaos => {
+                if self.last_chaos.map_or(false, |last| last.elapsed() < self.cooldown) {
+                    return;
+                }
+
+                info!("{} triggered chaos", user);
+                self.last_chaos = Some(Instant::now());
+                self.stub.chaos().await;
+            }
+        }
+    }
+
+    /// Applies the game mode with the most votes and starts a new vote
+    async fn close_vote(&mut self) {
+        if let Some((mode, votes)) = winner(self.votes.drain().map(|(_, mode)| mode)) {
+            info!("Chat voted for {:?} with {} votes", mode, votes);
+            self.stub.game_mode(mode).await;
+        }
+    }
+
+    async fn connect(&mut self, channel: &str, events: &mut broadcast::Receiver<Event>) -> Result<()> {
+        let stream = TcpStream::connect(SERVER).await?;
+        let (reader, mut writer) = stream.into_split();
+
+        // Anonymous login with read-only access
+        writer.write_all(format!("NICK justinfan{}\r\nJOIN #{}\r\n", rand::random::<u16>(), channel.to_lowercase()).as_bytes()).await?;
+
+        info!("Joined Twitch channel {}", channel);
+
+        let mut lines = BufReader::new(reader).lines();
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let line = match line? {
+                        Some(line) => line,
+                        None => bail!("Connection closed"),
+                    };
+
+                    if let Some(ping) = line.strip_prefix("PING ") {
+                        writer.write_all(format!("PONG {}\r\n", ping).as_bytes()).await?;
+                        continue;
+                    }
+
+                    if let Some((user, command)) = parse(&line) {
+                        self.command(user, command).await;
+                    }
+                }
+
+                event = events.recv() => match event {
+                    // Votes are applied whenever the game returns to the lobby
+                    Ok(Event::GameFinished { .. } | Event::GameDrawn | Event::GameAborted | Event::GameCancelled) => self.close_vote().await,
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+}
+
+/// The game mode with the most votes and its vote count - ties go to the mode named first
+fn winner(votes: impl IntoIterator<Item=GameMode>) -> Option<(GameMode, usize)> {
+    let mut tally = HashMap::<GameMode, usize>::new();
+    for mode in votes {
+        *tally.entry(mode).or_default() += 1;
+    }
+
+    return tally.into_iter()
+        .max_by(|(a, a_votes), (b, b_votes)| a_votes.cmp(b_votes).then_with(|| b.name.cmp(a.name)));
+}
+
+#[instrument(level = "debug", skip(stub, events))]
+pub async fn run(config: Config, stub: Stub, mut events: broadcast::Receiver<Event>) {
+    let channel = config.channel.expect("Twitch channel not configured");
+
+    let mut chat = Chat {
+        stub,
+        votes: HashMap::new(),
+        cooldown: Duration::from_secs(config.cooldown),
+        last_chaos: None,
+    };
+
+    loop {
+        match chat.connect(&channel, &mut events).await {
+            Ok(()) => return,
+            Err(err) => warn!("Twitch chat failed: {}", err),
+        }
+
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::games::{joust, king, traitor};
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(":alice!alice@alice.tmi.twitch.tv PRIVMSG #venue :!vote Joust"),
+                   Some(("alice", Command::Vote(GameMode::of(&joust::MODE)))));
+        assert_eq!(parse(":bob!bob@bob.tmi.twitch.tv PRIVMSG #venue :!chaos please"),
+                   Some(("bob", Command::Chaos)));
+    }
+
+    #[test]
+    fn test_parse_ignores_other_messages() {
+        assert_eq!(parse(":alice!alice@alice.tmi.twitch.tv PRIVMSG #venue :hello"), None);
+        assert_eq!(parse(":alice!alice@alice.tmi.twitch.tv PRIVMSG #venue :!vote nonsense"), None);
+        assert_eq!(parse(":alice!alice@alice.tmi.twitch.tv PRIVMSG #venue :!vote debug"), None);
+        assert_eq!(parse(":tmi.twitch.tv 001 justinfan123 :Welcome, GLHF!"), None);
+    }
+
+    #[test]
+    fn test_winner() {
+        let joust = GameMode::of(&joust::MODE);
+        let king = GameMode::of(&king::MODE);
+        let traitor = GameMode::of(&traitor::MODE);
+
+        assert_eq!(winner([traitor, king, traitor]), Some((traitor, 2)));
+        assert_eq!(winner([traitor, king, joust, traitor, king]), Some((king, 2)));
+        assert_eq!(winner([]), None);
+    }
+}
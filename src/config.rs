@@ -0,0 +1,298 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{debug, instrument, warn};
+
+use crate::games::{GameMode, GameOptions};
+use crate::palette::Theme;
+use crate::state::{PowerSave, Settings};
+
+/// Setup of a venue loaded from a TOML file at startup - everything missing keeps its default
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Address the web interface listens on
+    pub bind: Option<SocketAddr>,
+
+    /// Directory containing music, sounds and voices - relative to the working directory
+    pub assets: Option<PathBuf>,
+
+    /// Game mode selected at startup
+    pub game_mode: Option<GameMode>,
+
+    /// Language of voice announcements
+    pub language: Option<String>,
+
+    /// Scales movement thresholds of games - higher values tolerate more movement
+    pub threshold: Option<f32>,
+
+    /// LED brightness of all controllers (0.0 - 1.0)
+    pub brightness: Option<f32>,
+
+    /// Volume of music and sounds (0.0 - 1.0)
+    pub volume: Option<f32>,
+
+    pub power_save: Option<PowerSave>,
+
+    /// Colors shown on the controllers - `standard`, `color_blind` or `high_contrast`
+    pub theme: Option<Theme>,
+
+    /// Seed of the random number generator of the games to reproduce a session
+    pub seed: Option<u64>,
+
+    /// Settings of the individual game modes
+    pub options: Option<GameOptions>,
+
+    pub lobby: Lobby,
+
+    pub controllers: Controllers,
+}
+
+/// How the lobby behaves while nobody is playing
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lobby {
+    /// Powers off controllers idle in the lobby for this long
+    #[serde(with = "duration")]
+    pub idle_power_off: Option<Duration>,
+
+    /// Shows a rainbow and plays music after the lobby was idle for this long
+    #[serde(with = "duration")]
+    pub attract_after: Option<Duration>,
+
+    /// Starts games automatically once enough players are ready and nobody got ready for this long
+    #[serde(with = "duration")]
+    pub auto_start: Option<Duration>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Controllers {
+    /// Maximum number of controllers receiving LED and rumble updates per frame
+    pub feedback_budget: Option<usize>,
+
+    /// Blinks controllers orange from time to time if their battery drops to this level (0.0 - 1.0)
+    pub low_battery_warning: Option<f32>,
+}
+
+/// Durations given in human readable form like `90s` or `5min`
+mod duration {
+    use std::time::Duration;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        return match duration {
+            Some(duration) => serializer.serialize_str(&humantime::format_duration(*duration).to_string()),
+            None => serializer.serialize_none(),
+        };
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        return Option::<String>::deserialize(deserializer)?
+            .map(|value| humantime::parse_duration(&value).map_err(de::Error::custom))
+            .transpose();
+    }
+}
+
+impl Config {
+    const DEFAULT_BIND: ([u8; 4], u16) = ([0, 0, 0, 0], 3000);
+    const DEFAULT_ASSETS: &'static str = "assets";
+
+    #[instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config: {:?}", path))?;
+
+        let config: Self = toml::from_str(&config)
+            .with_context(|| format!("Failed to parse config: {:?}", path))?;
+
+        config.validate()
+            .with_context(|| format!("Invalid config: {:?}", path))?;
+
+        return Ok(config);
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(threshold) = self.threshold {
+            ensure!(threshold > 0.0 && threshold.is_finite(), "Threshold must be positive: {}", threshold);
+        }
+
+        if let Some(budget) = self.controllers.feedback_budget {
+            ensure!(budget >= 1, "Feedback budget must allow at least one controller");
+        }
+
+        if let Some(ref options) = self.options {
+            options.validate()?;
+        }
+
+        return Ok(());
+    }
+
+    pub fn bind(&self) -> SocketAddr {
+        return self.bind.unwrap_or_else(|| Self::DEFAULT_BIND.into());
+    }
+
+    pub fn assets(&self) -> PathBuf {
+        return self.assets.clone().unwrap_or_else(|| Self::DEFAULT_ASSETS.into());
+    }
+
+    /// Applies everything given in the config on top of the settings
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(game_mode) = self.game_mode {
+            settings.game_mode = game_mode;
+        }
+        if let Some(ref language) = self.language {
+            settings.language = language.clone();
+        }
+        if let Some(threshold) = self.threshold {
+            settings.threshold = threshold;
+        }
+        if let Some(brightness) = self.brightness {
+            settings.brightness = brightness.clamp(0.0, 1.0);
+        }
+        if let Some(volume) = self.volume {
+            settings.volume = volume.clamp(0.0, 1.0);
+        }
+        if let Some(power_save) = self.power_save {
+            settings.power_save = power_save;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+        }
+        if let Some(ref options) = self.options {
+            settings.options = options.clone();
+        }
+
+        settings.seed = self.seed.or(settings.seed);
+
+        settings.idle_power_off = self.lobby.idle_power_off.or(settings.idle_power_off);
+        settings.attract_after = self.lobby.attract_after.or(settings.attract_after);
+        settings.auto_start = self.lobby.auto_start.or(settings.auto_start);
+
+        settings.feedback_budget = self.controllers.feedback_budget.or(settings.feedback_budget);
+        settings.low_battery_warning = self.controllers.low_battery_warning.or(settings.low_battery_warning);
+    }
+
+    /// Takes over the settings which can be changed at runtime
+    pub fn update(&mut self, settings: &Settings) {
+        self.game_mode = Some(settings.game_mode);
+        self.language = Some(settings.language.clone());
+        self.threshold = Some(settings.threshold);
+        self.brightness = Some(settings.brightness);
+        self.volume = Some(settings.volume);
+        self.power_save = Some(settings.power_save);
+        self.theme = Some(settings.theme);
+        self.options = Some(settings.options.clone());
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let config = toml::to_string_pretty(self)
+            .context("Failed to serialize config")?;
+
+        // Replace the file at once to never leave a partial config behind
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, config)
+            .with_context(|| format!("Failed to write config: {:?}", temp))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Failed to replace config: {:?}", path))?;
+
+        return Ok(());
+    }
+}
+
+/// Writes the settings back to the config file whenever they are changed at runtime
+pub async fn persist(path: PathBuf, mut config: Config, mut settings: watch::Receiver<Settings>) {
+    while settings.changed().await.is_ok() {
+        let previous = config.clone();
+        config.update(&settings.borrow_and_update());
+
+        if config == previous {
+            continue;
+        }
+
+        debug!("Saving changed settings to {:?}", path);
+
+        let (path, config) = (path.clone(), config.clone());
+        match tokio::task::spawn_blocking(move || config.save(&path)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("Failed to save settings: {:#}", err),
+            Err(err) => warn!("Failed to save settings: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::games::king;
+
+    #[test]
+    fn test_apply() {
+        let config: Config = toml::from_str(r#"
+            bind = "127.0.0.1:8080"
+            game_mode = "king"
+            brightness = 0.6
+
+            [lobby]
+            attract_after = "5min"
+            auto_start = "30s"
+        "#).unwrap();
+
+        assert_eq!(config.bind(), "127.0.0.1:8080".parse().unwrap());
+        assert_eq!(config.assets(), PathBuf::from("assets"));
+
+        let mut settings = Settings::default();
+        config.apply(&mut settings);
+
+        assert_eq!(settings.game_mode, GameMode::of(&king::MODE));
+        assert_eq!(settings.brightness, 0.6);
+        assert_eq!(settings.threshold, 1.0);
+        assert_eq!(settings.attract_after, Some(Duration::from_secs(300)));
+        assert_eq!(settings.auto_start, Some(Duration::from_secs(30)));
+        assert_eq!(settings.idle_power_off, None);
+
+        // Typos are reported instead of silently ignored
+        assert!(toml::from_str::<Config>("brightnes = 0.5").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+        assert!(toml::from_str::<Config>("threshold = 0.0").unwrap().validate().is_err());
+        assert!(toml::from_str::<Config>("[controllers]\nfeedback_budget = 0").unwrap().validate().is_err());
+        assert!(toml::from_str::<Config>("[options.celebration]\nduration = 90.0").unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut settings = Settings::default();
+        settings.brightness = 0.3;
+        settings.auto_start = Some(Duration::from_secs(30));
+
+        let mut config = Config {
+            lobby: Lobby {
+                auto_start: Some(Duration::from_secs(30)),
+                ..Lobby::default()
+            },
+            ..Config::default()
+        };
+        config.update(&settings);
+
+        // Everything survives the way into the file and back
+        let written = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&written).unwrap(), config);
+        assert_eq!(config.brightness, Some(0.3));
+    }
+}
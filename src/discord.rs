@@ -0,0 +1,85 @@
+use reqwest::Client;
+use serde_json::json;
+use tokio::sync::broadcast;
+use tracing::{instrument, warn};
+
+use crate::events::Event;
+use crate::leaderboard::Leaderboard;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "discord")]
+pub struct Config {
+    /// Discord webhook URL to post announcements to - disabled if not set
+    #[arg(long = "discord-webhook")]
+    pub webhook: Option<String>,
+}
+
+/// Number of players shown in the leaderboard
+const LEADERBOARD_SIZE: usize = 5;
+
+fn format_leaderboard(leaderboard: &Leaderboard) -> String {
+    return std::iter::once("📊 **Leaderboard**".to_owned())
+        .chain(leaderboard.ranking(LEADERBOARD_SIZE).into_iter()
+            .enumerate()
+            .map(|(i, (player, wins))| format!("{}. Player `{:016X}` - {} wins", i + 1, player, wins)))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+async fn post(client: &Client, webhook: &str, content: String) {
+    let response = client.post(webhook)
+        .json(&json!({ "content": content }))
+        .send().await
+        .and_then(|response| response.error_for_status());
+
+    if let Err(err) = response {
+        warn!("Failed to post to Discord: {}", err);
+    }
+}
+
+/// Posts announcements about started and finished games to a Discord channel
+#[instrument(level = "debug", skip(config, events))]
+pub async fn run(config: Config, mut events: broadcast::Receiver<Event>) {
+    let webhook = config.webhook.expect("Discord webhook not configured");
+
+    let client = Client::new();
+
+    let mut leaderboard = Leaderboard::new();
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Discord announcements skipped {} events", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                return;
+            }
+        };
+
+        match event {
+            Event::GameStarted { mode, players } => {
+                post(&client, &webhook, format!("🎮 A round of **{}** has started with {} players", mode.to_string(), players.len())).await;
+            }
+
+            Event::GameFinished { winners } => {
+                let names = winners.iter()
+                    .map(|winner| format!("`{:016X}`", winner))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                post(&client, &webhook, format!("🏆 The round was won by {}", names)).await;
+
+                if leaderboard.record(winners, LEADERBOARD_SIZE) {
+                    post(&client, &webhook, format_leaderboard(&leaderboard)).await;
+                }
+            }
+
+            Event::GameDrawn => {
+                post(&client, &webhook, "🤝 The round ended in a draw".to_owned()).await;
+            }
+
+            _ => {}
+        }
+    }
+}
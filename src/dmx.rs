@@ -0,0 +1,228 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use scarlet::color::{Color, RGBColor};
+use scarlet::colors::HSVColor;
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+use tracing::{debug, info, instrument, warn};
+
+use hastilude_core::keyframes;
+use hastilude_core::engine::animation::{Animated, LoopMode, Repeat};
+
+use crate::events::Event;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "dmx")]
+pub struct Config {
+    /// Art-Net node to send DMX data to - lighting is disabled if not set
+    #[arg(long = "dmx-target")]
+    pub target: Option<IpAddr>,
+
+    #[arg(long = "dmx-universe", default_value_t = 0)]
+    pub universe: u16,
+
+    /// JSON file describing the fixtures
+    #[arg(long = "dmx-fixtures", default_value = "fixtures.json")]
+    pub fixtures: PathBuf,
+}
+
+/// Channel layout of a fixture starting at its address
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum Layout {
+    RGB,
+    RGBW,
+
+    /// Dimmer channel followed by RGB
+    DRGB,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Fixture {
+    /// First DMX channel of the fixture (1-based)
+    pub address: u16,
+    pub layout: Layout,
+}
+
+impl Fixture {
+    fn write(&self, universe: &mut [u8; 512], color: RGBColor) {
+        let (r, g, b) = color.int_rgb_tup();
+
+        let values: &[u8] = match self.layout {
+            Layout::RGB => &[r, g, b],
+            Layout::RGBW => &[r, g, b, r.min(g).min(b)],
+            Layout::DRGB => &[0xFF, r, g, b],
+        };
+
+        for (i, value) in values.iter().enumerate() {
+            if let Some(channel) = universe.get_mut(self.address as usize - 1 + i) {
+                *channel = *value;
+            }
+        }
+    }
+}
+
+fn load_fixtures(path: &Path) -> Result<Vec<Fixture>> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read fixtures: {:?}", path))?;
+    let fixtures: Vec<Fixture> = serde_json::from_slice(&data)
+        .with_context(|| format!("Failed to parse fixtures: {:?}", path))?;
+
+    // DMX channels are counted from one
+    for fixture in fixtures.iter() {
+        ensure!((1..=512).contains(&fixture.address), "Invalid fixture address {} in {:?} - must be 1 - 512", fixture.address, path);
+    }
+
+    return Ok(fixtures);
+}
+
+struct ArtDmx {
+    universe: u16,
+    sequence: u8,
+}
+
+impl ArtDmx {
+    const PORT: u16 = 6454;
+
+    fn packet(&mut self, data: &[u8; 512]) -> Vec<u8> {
+        // Sequence zero disables re-ordering on the receiver
+        self.sequence = self.sequence.wrapping_add(1).max(1);
+
+        let mut packet = Vec::with_capacity(18 + data.len());
+        packet.extend_from_slice(b"Art-Net\0");
+        packet.extend_from_slice(&0x5000u16.to_le_bytes()); // OpDmx
+        packet.extend_from_slice(&14u16.to_be_bytes()); // Protocol version
+        packet.push(self.sequence);
+        packet.push(0); // Physical port
+        packet.extend_from_slice(&(self.universe & 0x7FFF).to_le_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+
+        return packet;
+    }
+}
+
+/// Lighting scenes following the game state
+struct Scenes {
+    color: Animated<RGBColor>,
+    hue: f64,
+    playing: bool,
+}
+
+impl Scenes {
+    const COLOR_IDLE: RGBColor = RGBColor { r: 0.2, g: 0.0, b: 0.4 };
+
+    // Speed of the hue rotation while playing (time for a full rotation)
+    const HUE_ROTATION_SPEED: f64 = 1.0 / 60.0;
+
+    fn new() -> Self {
+        let mut scenes = Self {
+            color: Animated::idle(RGBColor { r: 0.0, g: 0.0, b: 0.0 }),
+            hue: 0.0,
+            playing: false,
+        };
+        scenes.idle();
+        return scenes;
+    }
+
+    fn idle(&mut self) {
+        self.playing = false;
+        self.color.set_and_animate(RGBColor { r: 0.0, g: 0.0, b: 0.0 }, keyframes![
+            2.0 => { Self::COLOR_IDLE } @ quadratic_in_out,
+        ]);
+    }
+
+    fn countdown(&mut self) {
+        self.playing = true;
+        self.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+        self.color.animate_looping(keyframes![
+            0.75 => { (255, 255, 255) } @ end,
+            0.25 => { (0, 0, 0) } @ linear,
+        ], Repeat::Times(3), LoopMode::Restart);
+    }
+
+    fn celebration(&mut self) {
+        self.playing = false;
+        self.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+        self.color.animate_looping(keyframes![
+            0.05 => { (255, 255, 255) },
+            0.15 => { (0, 0, 0) },
+        ], Repeat::Times(50), LoopMode::Restart);
+        self.color.animate(keyframes![
+            2.0 => { Self::COLOR_IDLE } @ quadratic_in_out,
+        ]);
+    }
+
+    fn update(&mut self, duration: Duration) -> RGBColor {
+        self.color.update(duration);
+
+        // Slow color wash after the countdown has finished
+        if self.playing && self.color.is_idle() {
+            self.hue = (self.hue + Self::HUE_ROTATION_SPEED * duration.as_secs_f64()) % 1.0;
+            self.color.set(HSVColor {
+                h: self.hue * 360.0,
+                s: 1.0,
+                v: 0.5,
+            }.convert::<RGBColor>());
+        }
+
+        return self.color.value();
+    }
+}
+
+/// Mirrors the game state onto DMX fixtures via Art-Net
+#[instrument(level = "debug", skip(events))]
+pub async fn run(config: Config, mut events: broadcast::Receiver<Event>) -> Result<()> {
+    const FRAME: Duration = Duration::from_millis(25);
+
+    let target = SocketAddr::new(config.target.expect("DMX target not configured"), ArtDmx::PORT);
+    let fixtures = load_fixtures(&config.fixtures)?;
+
+    let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+    socket.set_broadcast(true)?;
+
+    info!("Sending Art-Net to {} for {} fixtures", target, fixtures.len());
+
+    let mut artdmx = ArtDmx {
+        universe: config.universe,
+        sequence: 0,
+    };
+
+    let mut scenes = Scenes::new();
+    let mut universe = [0u8; 512];
+
+    let mut ticker = tokio::time::interval(FRAME);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let color = scenes.update(FRAME);
+                for fixture in fixtures.iter() {
+                    fixture.write(&mut universe, color);
+                }
+
+                if let Err(err) = socket.send_to(&artdmx.packet(&universe), target).await {
+                    warn!("Failed to send Art-Net packet: {}", err);
+                }
+            }
+
+            event = events.recv() => match event {
+                Ok(Event::GameStarted { .. }) => scenes.countdown(),
+                Ok(Event::GameFinished { .. } | Event::GameDrawn) => scenes.celebration(),
+                Ok(Event::GameCancelled | Event::GameAborted | Event::EmergencyStop) => scenes.idle(),
+                Ok(_) => {}
+
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("DMX output skipped {} events", skipped);
+                }
+
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use evdev::InputEventKind;
+use gpio_cdev::{Chip, EventRequestFlags, LineRequestFlags};
+use tracing::{error, info, warn};
+
+use crate::state::request::Stub;
+
+/// Emergency stop buttons working independently of the web interface
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "estop")]
+pub struct Config {
+    /// GPIO chip of the emergency stop button
+    #[arg(long = "estop-gpio-chip", default_value = "/dev/gpiochip0")]
+    pub gpio_chip: PathBuf,
+
+    /// GPIO line of an active-low emergency stop button
+    #[arg(long = "estop-gpio-line")]
+    pub gpio_line: Option<u32>,
+
+    /// Input device (i.e. a USB button) where any key press triggers an emergency stop
+    #[arg(long = "estop-input")]
+    pub input: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn enabled(&self) -> bool {
+        return self.gpio_line.is_some() || self.input.is_some();
+    }
+}
+
+/// Ignores repeated triggers caused by bouncing contacts or buttons held down
+struct Trigger {
+    stubs: Vec<Stub>,
+    last: Option<Instant>,
+}
+
+impl Trigger {
+    const DEBOUNCE: Duration = Duration::from_secs(1);
+
+    fn fire(&mut self, source: &str) {
+        if self.last.map_or(false, |last| last.elapsed() < Self::DEBOUNCE) {
+            return;
+        }
+        self.last = Some(Instant::now());
+
+        warn!("Emergency stop triggered by {}", source);
+        for stub in self.stubs.iter_mut() {
+            futures::executor::block_on(stub.emergency_stop());
+        }
+    }
+}
+
+fn gpio(chip: PathBuf, line: u32, mut trigger: Trigger) -> Result<()> {
+    let mut chip = Chip::new(&chip)
+        .with_context(|| format!("Failed to open GPIO chip: {:?}", chip))?;
+
+    let events = chip.get_line(line)?
+        .events(LineRequestFlags::INPUT, EventRequestFlags::FALLING_EDGE, "hastilude-estop")?;
+
+    info!("Emergency stop on GPIO line {}", line);
+
+    for event in events {
+        event?;
+        trigger.fire("GPIO");
+    }
+
+    return Ok(());
+}
+
+fn input(path: PathBuf, mut trigger: Trigger) -> Result<()> {
+    let mut device = evdev::Device::open(&path)
+        .with_context(|| format!("Failed to open input device: {:?}", path))?;
+
+    info!("Emergency stop on input device {:?}", path);
+
+    loop {
+        for event in device.fetch_events()? {
+            if let InputEventKind::Key(_) = event.kind() {
+                if event.value() == 1 {
+                    trigger.fire("input device");
+                }
+            }
+        }
+    }
+}
+
+/// Watches the configured emergency stop buttons on dedicated threads - a stop affects all arenas
+pub fn run(config: Config, stubs: Vec<Stub>) {
+    if let Some(line) = config.gpio_line {
+        let trigger = Trigger { stubs: stubs.clone(), last: None };
+        std::thread::spawn(move || {
+            if let Err(err) = gpio(config.gpio_chip, line, trigger) {
+                error!("Emergency stop on GPIO failed: {}", err);
+            }
+        });
+    }
+
+    if let Some(path) = config.input {
+        let trigger = Trigger { stubs, last: None };
+        std::thread::spawn(move || {
+            if let Err(err) = input(path, trigger) {
+                error!("Emergency stop on input device failed: {}", err);
+            }
+        });
+    }
+}
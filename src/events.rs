@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::games::GameMode;
+use crate::games::elimination::Cause;
+
+// Time of events logged before they were timestamped
+fn unknown_time() -> SystemTime {
+    return SystemTime::UNIX_EPOCH;
+}
+
+/// Noteworthy things happening in the game
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Event {
+    GameStarted {
+        mode: GameMode,
+        players: HashSet<PlayerId>,
+    },
+
+    GameCancelled,
+
+    GamePaused,
+
+    GameResumed,
+
+    EmergencyStop,
+
+    PlayerJoined {
+        player: PlayerId,
+    },
+
+    PlayerEliminated {
+        player: PlayerId,
+
+        #[serde(default)]
+        cause: Cause,
+
+        /// Wall clock time of the elimination
+        #[serde(default = "unknown_time")]
+        time: SystemTime,
+    },
+
+    GameFinished {
+        winners: HashSet<PlayerId>,
+    },
+
+    GameDrawn,
+
+    /// A round of a match was decided - the match goes on with the next round
+    RoundFinished {
+        winners: HashSet<PlayerId>,
+    },
+
+    GameAborted,
+
+    TournamentFinished {
+        champions: HashSet<PlayerId>,
+    },
+
+    MatchFinished {
+        champions: HashSet<PlayerId>,
+    },
+
+    MusicSpeed {
+        speed: f32,
+    },
+}
+
+/// Distributes events to all interested subscribers
+pub type Events = hastilude_core::events::Events<Event>;
@@ -0,0 +1,372 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use tracing::debug;
+
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::game::Scores;
+
+use crate::events::Event;
+use crate::games::{GameMode, GameOptions};
+use crate::games::mutators::Mutators;
+use crate::meta::celebration::Celebration;
+use crate::meta::intermission::Intermission;
+use crate::snapshot::{self, Goal, Progress};
+use crate::state::{JoinPlayerError, PauseGameError, State, World};
+
+/// The game mode and number of wins deciding a match
+pub struct Setup {
+    /// Game mode played in each round - uses the selected game mode if missing
+    pub mode: Option<GameMode>,
+
+    /// Rounds a player must win to win the match
+    pub wins: u32,
+
+    /// Players taking part instead of the ready players
+    pub players: Option<HashSet<PlayerId>>,
+}
+
+enum Phase {
+    Round(Box<State>),
+    Intermission(Intermission),
+
+    // Waiting for the players to reconnect after a restart
+    Resuming,
+}
+
+/// Chains rounds of a game mode until players have won the required number of rounds - the
+/// standings are shown on the controllers between the rounds
+pub struct Match {
+    mode: GameMode,
+
+    // Options of the game mode for all rounds as set when starting the match
+    options: GameOptions,
+
+    wins: u32,
+
+    players: HashSet<PlayerId>,
+
+    // Rounds won by each player - all members of a winning team score
+    standings: Scores<u32>,
+
+    // Rounds finished so far
+    played: usize,
+
+    phase: Phase,
+}
+
+impl Match {
+    pub fn start(mode: GameMode, options: GameOptions, wins: u32, players: HashSet<PlayerId>, world: &mut World) -> State {
+        debug!("Starting match {:?} to {} wins with players {:?}", mode, wins, players);
+
+        let round = mode.create(players.clone(), &options, world);
+
+        return State::Match(Self {
+            mode,
+            options,
+            wins,
+            standings: Scores::new(players.iter().copied()),
+            players,
+            played: 0,
+            phase: Phase::Round(Box::new(round)),
+        });
+    }
+
+    /// Continues a match kept over a restart with the standings shown before the next round
+    pub fn restore(progress: Progress, wins: u32) -> Self {
+        debug!("Restoring match {:?} to {} wins after round {}", progress.mode, wins, progress.played);
+
+        return Self {
+            mode: progress.mode,
+            options: progress.options,
+            wins,
+            players: progress.players,
+            standings: progress.standings,
+            played: progress.played,
+            phase: Phase::Resuming,
+        };
+    }
+
+    pub fn update(mut self, world: &mut World, duration: Duration) -> State {
+        match self.phase {
+            Phase::Resuming => {
+                if snapshot::resumable(&self.players, world) {
+                    self.phase = Phase::Intermission(Intermission::new(&self.players, &self.standings, &HashSet::new(), world));
+                }
+            }
+
+            Phase::Intermission(ref mut intermission) => {
+                if !intermission.update(world, duration) {
+                    return State::Match(self);
+                }
+
+                // Only players still connected take part in the next round
+                let players = self.players.iter()
+                    .copied()
+                    .filter(|player| world.players.get(*player).is_some())
+                    .collect::<HashSet<_>>();
+
+                if players.len() < 2 || !self.mode.accepts(players.len()) {
+                    debug!("Not enough players left to continue the match");
+                    return State::Celebration(Celebration::match_winners(self.standings.leaders(), world));
+                }
+
+                self.phase = Phase::Round(Box::new(self.mode.create(players, &self.options, world)));
+            }
+
+            Phase::Round(round) => match round.update(world, duration) {
+                // Rounds are decided without starting their celebration - the standings are shown
+                // in the intermission instead
+                State::Celebration(celebration) => {
+                    let winners = celebration.winners().clone();
+                    for winner in winners.iter() {
+                        self.standings.add(*winner, 1);
+                    }
+
+                    self.played += 1;
+                    debug!("Finished match round {}", self.played);
+
+                    world.events.emit(Event::RoundFinished {
+                        winners: winners.clone(),
+                    });
+
+                    // Players having won the required number of rounds
+                    let champions = self.players.iter()
+                        .copied()
+                        .filter(|player| self.standings.get(*player) >= self.wins)
+                        .collect::<HashSet<_>>();
+
+                    if !champions.is_empty() {
+                        return State::Celebration(Celebration::match_winners(champions, world));
+                    }
+
+                    self.phase = Phase::Intermission(Intermission::new(&self.players, &self.standings, &winners, world));
+                }
+
+                // Aborted rounds count as played without scoring
+                State::Lobby(_) => {
+                    self.played += 1;
+                    debug!("Aborted match round {}", self.played);
+
+                    self.phase = Phase::Intermission(Intermission::new(&self.players, &self.standings, &HashSet::new(), world));
+                }
+
+                round => {
+                    self.phase = Phase::Round(Box::new(round));
+                }
+            }
+        }
+
+        return State::Match(self);
+    }
+
+    /// Removes a player from the match and the current round. Returns whether the player was part
+    /// of the match.
+    pub fn kick_player(mut self, player: PlayerId, world: &mut World) -> (State, bool) {
+        if !self.players.remove(&player) {
+            return (State::Match(self), false);
+        }
+
+        // Players gone can not win the match
+        self.standings.remove(player);
+
+        // The player might already be out of the current round
+        if let Phase::Round(round) = self.phase {
+            let (round, _) = round.kick_player(player, world);
+            self.phase = Phase::Round(Box::new(round));
+        }
+
+        return (State::Match(self), true);
+    }
+
+    pub fn pause(mut self, world: &mut World) -> (State, Result<(), PauseGameError>) {
+        return match self.phase {
+            Phase::Round(round) => {
+                let (round, result) = round.pause(world);
+                self.phase = Phase::Round(Box::new(round));
+                (State::Match(self), result)
+            }
+            Phase::Intermission(_) | Phase::Resuming => (State::Match(self), Err(PauseGameError::GameNotRunning)),
+        };
+    }
+
+    pub fn resume(mut self, world: &mut World) -> (State, Result<(), PauseGameError>) {
+        return match self.phase {
+            Phase::Round(round) => {
+                let (round, result) = round.resume(world);
+                self.phase = Phase::Round(Box::new(round));
+                (State::Match(self), result)
+            }
+            Phase::Intermission(_) | Phase::Resuming => (State::Match(self), Err(PauseGameError::NotPaused)),
+        };
+    }
+
+    /// Whether the current round is paused
+    pub fn paused(&self) -> bool {
+        return match &self.phase {
+            Phase::Round(round) => round.paused(),
+            Phase::Intermission(_) | Phase::Resuming => false,
+        };
+    }
+
+    /// Adds a player arriving late to the current round and all following rounds
+    pub fn join_player(mut self, player: PlayerId, world: &mut World) -> (State, Result<(), JoinPlayerError>) {
+        return match self.phase {
+            Phase::Round(round) => {
+                let (round, result) = round.join_player(player, world);
+                self.phase = Phase::Round(Box::new(round));
+
+                if result.is_ok() {
+                    self.players.insert(player);
+                }
+
+                (State::Match(self), result)
+            }
+            Phase::Intermission(_) | Phase::Resuming => (State::Match(self), Err(JoinPlayerError::GameNotRunning)),
+        };
+    }
+
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return match &self.phase {
+            Phase::Round(round) => round.participants(),
+            Phase::Intermission(_) => Some(&self.players),
+            Phase::Resuming => None,
+        };
+    }
+
+    pub fn mutators(&self) -> Option<&Mutators> {
+        return match &self.phase {
+            Phase::Round(round) => round.mutators(),
+            Phase::Intermission(_) | Phase::Resuming => None,
+        };
+    }
+
+    /// Name of the state of the current round
+    pub fn phase(&self) -> String {
+        return match &self.phase {
+            Phase::Round(round) => round.name(),
+            Phase::Intermission(_) => "intermission".to_owned(),
+            Phase::Resuming => "resuming".to_owned(),
+        };
+    }
+
+    pub fn mode(&self) -> GameMode {
+        return self.mode;
+    }
+
+    pub fn wins(&self) -> u32 {
+        return self.wins;
+    }
+
+    pub fn played(&self) -> usize {
+        return self.played;
+    }
+
+    /// Whether the match is between two rounds
+    pub fn intermission(&self) -> bool {
+        return matches!(self.phase, Phase::Intermission(_) | Phase::Resuming);
+    }
+
+    pub fn standings(&self) -> HashMap<PlayerId, u32> {
+        return self.players.iter()
+            .map(|player| (*player, self.standings.get(*player)))
+            .collect();
+    }
+
+    pub fn progress(&self) -> Progress {
+        return Progress {
+            mode: self.mode,
+            options: self.options.clone(),
+            goal: Goal::Wins(self.wins),
+            players: self.players.clone(),
+            standings: self.standings.clone(),
+            played: self.played,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hastilude_core::controller::{Controller, feed, Model};
+    use hastilude_core::engine::assets::Assets;
+    use hastilude_core::game::{Game, Session};
+
+    use crate::games::{GameState, joust};
+    use crate::games::outcome::Outcome;
+    use crate::games::scoring::Scoring;
+    use crate::simulation::Sandbox;
+    use crate::state::Settings;
+
+    // A round won by the given players as soon as it is updated
+    struct Decided(HashSet<PlayerId>);
+
+    impl Game<Settings, Event, Scoring> for Decided {
+        type Next = State;
+
+        fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+            return Some(Outcome::Winners(self.0.clone()).finish(world));
+        }
+
+        fn kick_player(&mut self, _player: PlayerId, _world: &mut World) -> bool {
+            return false;
+        }
+    }
+
+    fn round(players: &[PlayerId], winner: PlayerId, world: &World) -> Phase {
+        let game = Decided(HashSet::from([winner]));
+        return Phase::Round(Box::new(State::Playing(GameState::new(Box::new(game), players.iter().copied().collect(), Mutators::default(), world.now))));
+    }
+
+    #[test]
+    fn test_rounds() {
+        let assets = Assets::empty();
+        let mut sandbox = Sandbox::new(&assets, Settings::default(), Some(1));
+
+        for i in 0..2 {
+            sandbox.players.add(Controller::new(Box::new(feed::channel(Sandbox::address(i), Model::CECH_ZCM1).1)));
+        }
+
+        let mut ids = sandbox.players.keys().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        let mut events = sandbox.events.subscribe();
+
+        let game = Match {
+            mode: GameMode::of(&joust::MODE),
+            options: GameOptions::default(),
+            wins: 2,
+            players: ids.iter().copied().collect(),
+            standings: Scores::new(ids.iter().copied()),
+            played: 0,
+            phase: round(&ids, ids[0], &sandbox.world()),
+        };
+
+        // A round is decided without finishing a game
+        let game = match game.update(&mut sandbox.world(), Duration::from_millis(10)) {
+            State::Match(game) => game,
+            _ => panic!("Expected the match to go on"),
+        };
+        assert!(game.intermission());
+        assert_eq!(game.standings(), HashMap::from([(ids[0], 1), (ids[1], 0)]));
+        assert!(matches!(events.try_recv(), Ok(Event::RoundFinished { .. })));
+        assert!(events.try_recv().is_err());
+
+        // Winning the required number of rounds decides the match
+        let game = Match {
+            phase: round(&ids, ids[0], &sandbox.world()),
+            ..game
+        };
+
+        let celebration = match game.update(&mut sandbox.world(), Duration::from_millis(10)) {
+            State::Celebration(celebration) => celebration,
+            _ => panic!("Expected the match to be decided"),
+        };
+        assert_eq!(celebration.winners(), &HashSet::from([ids[0]]));
+
+        celebration.update(&mut sandbox.world(), Duration::from_millis(10));
+        assert!(matches!(events.try_recv(), Ok(Event::RoundFinished { .. })));
+        assert!(matches!(events.try_recv(), Ok(Event::MatchFinished { .. })));
+    }
+}
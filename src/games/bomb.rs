@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::gestures::Gesture;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{reset_color, start};
+use crate::games::outcome::Outcome;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player;
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.level(0);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "bomb",
+    description: "Players take turns cutting the wires of a bomb while the others keep their movement below a shared budget",
+    min_players: 2,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Bomb>(players, seconds, world),
+    teams: None,
+};
+
+/// Cooperative mode where the players take turns flipping their controller to cut the wires of a
+/// bomb while all others keep their movement below a shared budget - all win or lose together
+pub struct Bomb {
+    data: PlayerData<Player>,
+
+    // Order in which the players cut wires and the player currently cutting
+    order: Vec<PlayerId>,
+    turn: usize,
+
+    wires: usize,
+
+    // Movement spent by the group so far
+    spent: f32,
+
+    // Time the bomb went off
+    exploded: Option<Instant>,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Bomb {
+    const WIRES_PER_PLAYER: usize = 2;
+
+    // Time until the bomb goes off on its own
+    const FUSE: Duration = Duration::from_secs(120);
+
+    // Movement the group can spend in total
+    const BUDGET: f32 = 4.0;
+
+    // Movement of a single player not counting towards the budget
+    const NOISE: f32 = 0.05;
+
+    // Time the explosion is shown before returning to the lobby
+    const EXPLOSION: Duration = Duration::from_secs(3);
+
+    fn cutter(&self) -> Option<PlayerId> {
+        return self.order.get(self.turn).copied();
+    }
+
+    fn explode(&mut self, world: &mut World) {
+        self.exploded = Some(world.now);
+        world.announce("explosion");
+
+        for id in self.data.keys() {
+            if let Some(player) = world.players.get_mut(id) {
+                player.haptic(HapticPattern::HIT);
+            }
+        }
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Bomb {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, duration: Duration, session: &Session) -> Option<State> {
+        // The bomb flashes the controllers of the players red before the game ends without winners
+        if let Some(exploded) = self.exploded {
+            for id in self.data.keys() {
+                if let Some(player) = world.players.get_mut(id) {
+                    player.color.set(RGBColor { r: 1.0, g: 0.0, b: 0.0 });
+                }
+            }
+
+            if world.now - exploded >= Self::EXPLOSION {
+                return Some(Outcome::Draw.finish(world));
+            }
+
+            return None;
+        }
+
+        let cutter = match self.cutter() {
+            Some(cutter) => cutter,
+            None => return Some(Outcome::Aborted.finish(world)),
+        };
+
+        // Everybody but the cutter spends the shared budget by moving
+        let threshold = world.threshold();
+        let group = world.players.group(self.data.keys().filter(|id| *id != cutter));
+        let noise = Self::NOISE * group.count as f32;
+        self.spent += (group.total - noise).max(0.0) / threshold * duration.as_secs_f32();
+
+        // The cutter cuts a wire by flipping the controller
+        let cut = world.players.get(cutter)
+            .map_or(false, |player| player.gestures().contains(&Gesture::Flip));
+
+        if cut {
+            self.wires -= 1;
+            self.turn = (self.turn + 1) % self.order.len();
+
+            if let Some(player) = world.players.get_mut(cutter) {
+                player.haptic(HapticPattern::TAP);
+            }
+
+            if self.wires == 0 {
+                return Some(Outcome::Winners(self.data.keys().collect()).finish(world));
+            }
+
+            if let Some(player) = self.cutter().and_then(|cutter| world.players.get_mut(cutter)) {
+                player.haptic(HapticPattern::BUZZ);
+            }
+        }
+
+        if self.spent >= Self::BUDGET || session.remaining(world.now, Self::FUSE).is_zero() {
+            self.explode(world);
+            return None;
+        }
+
+        // The cutter lights up white while all others show the spent budget in steps from low to high
+        let level = (self.spent / Self::BUDGET * 3.0) as usize;
+        let theme = world.settings.theme;
+        let cutter = self.cutter();
+        for id in self.data.keys() {
+            if let Some(player) = world.players.get_mut(id) {
+                player.color.set(if Some(id) == cutter {
+                    RGBColor { r: 1.0, g: 1.0, b: 1.0 }
+                } else {
+                    theme.level(level)
+                });
+            }
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if !self.data.remove(player) {
+            return false;
+        }
+
+        // Keep the turn with the player following the kicked one
+        if let Some(index) = self.order.iter().position(|id| *id == player) {
+            self.order.remove(index);
+
+            if index < self.turn {
+                self.turn -= 1;
+            }
+
+            if self.turn >= self.order.len() {
+                self.turn = 0;
+            }
+        }
+
+        reset_color(player, world);
+
+        return true;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        if let Some(exploded) = self.exploded.as_mut() {
+            *exploded += paused;
+        }
+
+        self.music.resume();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Bomb {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        // Sorted to shuffle the same way for the same seed
+        let mut order = players.iter().copied().collect::<Vec<_>>();
+        order.sort();
+        order.shuffle(world.rng);
+
+        return Self {
+            data: PlayerData::init(players, || Player),
+            wires: Self::WIRES_PER_PLAYER * order.len(),
+            order,
+            turn: 0,
+            spent: 0.0,
+            exploded: None,
+            music,
+        };
+    }
+}
@@ -1,16 +1,70 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
+use cgmath::{Vector3, Zero};
 use scarlet::color::RGBColor;
 use scarlet::colorpoint::ColorPoint;
+use tracing::info;
+
+use hastilude_core::controller::Battery;
+use hastilude_core::controller::calibration::Override;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::engine::scope::Sample;
+use hastilude_core::game::{Game, Session};
+
+use crate::events::Event;
+use crate::games::GameState;
+use crate::games::mutators::Mutators;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::state::{Settings, State, World};
+
+/// Readings collected while calibrating a controller lying still with the buttons facing upwards
+struct Sampling {
+    count: usize,
+
+    accelerometer: Vector3<f32>,
+    gyroscope: Vector3<f32>,
+}
+
+impl Sampling {
+    fn new() -> Self {
+        return Self {
+            count: 0,
+            accelerometer: Vector3::zero(),
+            gyroscope: Vector3::zero(),
+        };
+    }
+
+    /// The override correcting the average readings to a controller at rest
+    fn calibration(&self) -> Override {
+        let accelerometer = self.accelerometer / self.count as f32;
+        let gyroscope = self.gyroscope / self.count as f32;
 
-use crate::controller::Battery;
-use crate::engine::sound::Playback;
-use crate::games::{Game, Session};
-use crate::state::{State, World};
-use crate::engine::players::PlayerId;
+        return Override {
+            accelerometer_bias: accelerometer - Vector3::unit_z(),
+            gyroscope_bias: gyroscope,
+            ..Override::default()
+        };
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "debug",
+    description: "Shows the sensor values of the controllers for testing and calibration",
+    min_players: 1,
+    max_players: None,
+    options: None,
+    create: |players, _, _, world| State::Playing(GameState::new(Box::new(Debug::new(world)), players, Mutators::default(), world.now)),
+    teams: None,
+};
 
 pub struct Debug {
     music: Playback,
+
+    calibrating: HashMap<PlayerId, Sampling>,
 }
 
 pub fn battery_to_color(battery: Battery) -> RGBColor {
@@ -36,45 +90,99 @@ pub fn vector_to_color(a: cgmath::Vector3<f32>) -> RGBColor {
 
 impl Debug {
     const COLOR_WHITE: RGBColor = RGBColor { r: 1.0, g: 1.0, b: 1.0 };
+    const COLOR_CALIBRATING: RGBColor = RGBColor { r: 1.0, g: 1.0, b: 0.0 };
+
+    // Number of readings averaged for calibration
+    const CALIBRATION_SAMPLES: usize = 200;
 
     pub fn new(world: &mut World) -> Self {
-        let music = world.assets.music.random();
-        let music = world.sound.music(music);
+        let music = world.sound.music(world.assets.music.random(world.rng));
 
-        return Self { music };
+        return Self {
+            music,
+            calibrating: HashMap::new(),
+        };
     }
 }
 
-impl Game for Debug {
-    fn update(&mut self, world: &mut World, _: Duration, _: &Session) -> Option<State> {
+impl Game<Settings, Event, Scoring> for Debug {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _: Duration, session: &Session) -> Option<State> {
         let triangle = world.players.iter()
-            .any(|player| player.input().buttons.triangle);
+            .any(|player| player.input().buttons.triangle.is_down());
 
         for player in world.players.iter_mut() {
+            // Pressing the logo button starts the calibration of a controller lying flat
+            if let Some(sampling) = self.calibrating.get_mut(&player.id()) {
+                player.color.set(Self::COLOR_CALIBRATING);
+
+                sampling.count += 1;
+                sampling.accelerometer += player.input().accelerometer;
+                sampling.gyroscope += player.input().gyroscope;
+
+                if sampling.count >= Self::CALIBRATION_SAMPLES {
+                    let calibration = sampling.calibration();
+                    let address = player.controller().serial();
+                    info!("Calibrated controller {}: {:?}", address.as_string(), calibration);
+
+                    player.set_calibration(calibration);
+                    player.haptic(HapticPattern::TAP);
+
+                    if let Some(ref store) = world.settings.calibration {
+                        store.set(address, calibration);
+                    }
+
+                    self.calibrating.remove(&player.id());
+                }
+
+                continue;
+            }
+
+            if player.input().buttons.logo.pressed() {
+                // Sample the readings without the current correction
+                player.set_calibration(Override::default());
+                self.calibrating.insert(player.id(), Sampling::new());
+                continue;
+            }
+
             if triangle {
                 player.color.set(Self::COLOR_WHITE);
-            } else if player.input().buttons.circle {
+            } else if player.input().buttons.circle.is_down() {
                 player.color.set(battery_to_color(player.battery()));
             } else {
                 player.color.set(vector_to_color(player.input().accelerometer));
             }
 
-            if player.input().buttons.swoosh {
+            if player.input().buttons.swoosh.is_down() {
                 player.rumble.set((player.input().buttons.trigger.1 * 255.0) as u8);
             }
 
-            if player.input().buttons.select {
-                self.music = world.sound.music(world.assets.music.random());
+            if player.input().buttons.select.pressed() {
+                self.music = world.sound.music(world.assets.music.random(world.rng));
+            }
+        }
+
+        // Stream the sensor values of all controllers while somebody is watching
+        if world.scope.watched() {
+            let time = session.age(world.now).as_secs_f32();
+            for player in world.players.iter() {
+                world.scope.publish(Sample {
+                    player: player.id(),
+                    time,
+                    raw: player.controller().raw_input().into(),
+                    calibrated: player.input().into(),
+                });
             }
         }
 
         if world.players.iter()
-            .any(|player| player.input().buttons.start || player.input().buttons.cross) {
+            .any(|player| player.input().buttons.start.pressed() || player.input().buttons.cross.pressed()) {
             return Some(State::lobby(world.players));
         }
 
         if let Some(player) = world.players.iter().next() {
-            let speed = if player.input().buttons.square {
+            let speed = if player.input().buttons.square.is_down() {
                 1.0 + player.input().buttons.trigger.1 * 0.5
             } else {
                 1.0 - player.input().buttons.trigger.1 * 0.5
@@ -89,4 +197,17 @@ impl Game for Debug {
     fn kick_player(&mut self, _player: PlayerId, _world: &mut World) -> bool {
         return false;
     }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, _paused: Duration, _world: &mut World) {
+        self.music.resume();
+    }
+
+    // All controllers are inspected
+    fn idle_animation(&self) -> bool {
+        return false;
+    }
 }
\ No newline at end of file
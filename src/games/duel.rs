@@ -0,0 +1,328 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::gestures::Gesture;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{hues, reset_color, start};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    hue: f64,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pairing {
+    Duel(PlayerId, PlayerId),
+    Champion(PlayerId),
+    Nobody,
+}
+
+/// Pairs the players for successive duels - the winners of a round meet in the next round
+struct Bracket {
+    // Players still waiting for their duel in this round
+    round: VecDeque<PlayerId>,
+
+    // Players which won their duel or got a bye in this round
+    advanced: Vec<PlayerId>,
+}
+
+impl Bracket {
+    fn new(players: Vec<PlayerId>) -> Self {
+        return Self {
+            round: players.into(),
+            advanced: Vec::new(),
+        };
+    }
+
+    fn next(&mut self) -> Pairing {
+        loop {
+            match (self.round.len(), self.advanced.len()) {
+                (0, 0) => return Pairing::Nobody,
+                (0, 1) => return Pairing::Champion(self.advanced[0]),
+                (0, _) => self.round.extend(self.advanced.drain(..)),
+                (1, 0) => return Pairing::Champion(self.round[0]),
+
+                // The last player of an odd round advances without a duel
+                (1, _) => self.advanced.extend(self.round.pop_front()),
+
+                _ => {
+                    let a = self.round.pop_front().expect("Round has two players");
+                    let b = self.round.pop_front().expect("Round has two players");
+                    return Pairing::Duel(a, b);
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, winner: PlayerId) {
+        self.advanced.push(winner);
+    }
+
+    fn remove(&mut self, player: PlayerId) {
+        self.round.retain(|id| *id != player);
+        self.advanced.retain(|id| *id != player);
+    }
+}
+
+struct Match {
+    players: (PlayerId, PlayerId),
+
+    // Movement is not checked before the duel starts
+    starts: Instant,
+}
+
+pub static MODE: Mode = Mode {
+    name: "duel",
+    description: "Successive one-on-one duels where the first to move too much or to get struck loses",
+    min_players: 2,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Duel>(players, seconds, world),
+    teams: None,
+};
+
+/// Successive one-on-one duels where the first to move too much or to get struck by a swing of the
+/// opponent loses until a single champion is left
+pub struct Duel {
+    data: PlayerData<Player>,
+
+    bracket: Bracket,
+    current: Option<Match>,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Duel {
+    // Time for the duelists to get ready
+    const MATCH_DELAY: Duration = Duration::from_secs(3);
+
+    // Movement losing a duel
+    const THRESHOLD: f32 = 0.6;
+
+    // Flash of the winner of a duel
+    const WIN_FLASH: Duration = Duration::from_millis(800);
+
+    fn finish(&mut self, winner: PlayerId, loser: PlayerId, cause: Cause, world: &mut World) {
+        self.current = None;
+        self.bracket.advance(winner);
+        self.data.remove(loser);
+
+        if let Some(player) = world.players.get_mut(winner) {
+            player.haptic(HapticPattern::TAP);
+            player.flash(RGBColor { r: 1.0, g: 1.0, b: 1.0 }, Self::WIN_FLASH);
+        }
+
+        Elimination::new(loser, cause).apply(world);
+    }
+
+    /// Removes a player from the bracket - the opponent of a duelist wins the duel
+    fn forfeit(&mut self, player: PlayerId) {
+        self.bracket.remove(player);
+
+        if let Some((a, b)) = self.current.as_ref().map(|current| current.players) {
+            if player == a || player == b {
+                self.current = None;
+
+                let opponent = if player == a { b } else { a };
+                if self.data.get(opponent).is_some() {
+                    self.bracket.advance(opponent);
+                }
+            }
+        }
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Duel {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+        if self.current.is_none() {
+            match self.bracket.next() {
+                Pairing::Duel(a, b) => {
+                    self.current = Some(Match {
+                        players: (a, b),
+                        starts: world.now + Self::MATCH_DELAY,
+                    });
+
+                    world.announce("duel");
+                }
+
+                Pairing::Champion(champion) => {
+                    return Some(Outcome::Winners(HashSet::from([champion])).finish(world));
+                }
+
+                Pairing::Nobody => {
+                    // The last players went out together
+                    return Some(Outcome::Draw.finish(world));
+                }
+            }
+        }
+
+        let (a, b) = self.current.as_ref().expect("Match is running").players;
+        let started = self.current.as_ref().map_or(false, |current| current.starts <= world.now);
+
+        // Only the duelists light up - white until the duel starts
+        let threshold = Self::THRESHOLD * world.threshold();
+        let theme = world.settings.theme;
+        let mut movement = Vec::new();
+        let mut strikes = Vec::new();
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let id = player.id();
+
+            if id != a && id != b {
+                player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+                return true;
+            }
+
+            if !started {
+                player.color.set(RGBColor { r: 1.0, g: 1.0, b: 1.0 });
+                return true;
+            }
+
+            let accel = player.acceleration(true) / (threshold * player.threshold());
+            movement.push((id, accel));
+
+            if player.gestures().contains(&Gesture::Swing) {
+                strikes.push(id);
+            }
+
+            player.color.set(theme.hue(data.hue, 1.0 - f32::sqrt(accel.min(1.0)) as f64));
+
+            return true;
+        });
+
+        // Players gone for good forfeit their duels
+        let gone = [a, b].into_iter()
+            .chain(self.bracket.round.iter().copied())
+            .chain(self.bracket.advanced.iter().copied())
+            .filter(|id| self.data.get(*id).is_none())
+            .collect::<Vec<_>>();
+        if !gone.is_empty() {
+            for player in gone {
+                self.forfeit(player);
+            }
+            return None;
+        }
+
+        // A duelist swinging strikes the opponent - strikes of both duelists at once cancel out
+        if let [striker] = strikes[..] {
+            let struck = if striker == a { b } else { a };
+            self.finish(striker, struck, Cause::Strike, world);
+            return None;
+        }
+
+        // The duelist moving the most loses once exceeding the threshold
+        let loser = movement.into_iter()
+            .filter(|(_, accel)| *accel >= 1.0)
+            .max_by(|(_, x), (_, y)| x.total_cmp(y))
+            .map(|(id, _)| id);
+
+        if let Some(loser) = loser {
+            let winner = if loser == a { b } else { a };
+            self.finish(winner, loser, Cause::Movement, world);
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if !self.data.remove(player) {
+            return false;
+        }
+
+        // The opponent of a kicked duelist wins the duel
+        self.forfeit(player);
+
+        reset_color(player, world);
+
+        return true;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        if let Some(current) = self.current.as_mut() {
+            current.starts += paused;
+        }
+
+        self.music.resume();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Duel {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        // Sorted to shuffle the same way for the same seed
+        let mut order = players.iter().copied().collect::<Vec<_>>();
+        order.sort();
+        order.shuffle(world.rng);
+
+        let hues = hues(players.len(), world.rng);
+
+        let players = PlayerData::init_with(order.iter()
+            .copied()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            bracket: Bracket::new(order),
+            current: None,
+            music,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bracket() {
+        let mut bracket = Bracket::new(vec![1, 2, 3]);
+
+        assert_eq!(bracket.next(), Pairing::Duel(1, 2));
+        bracket.advance(2);
+
+        // Player 3 gets a bye and meets the winner of the first duel
+        assert_eq!(bracket.next(), Pairing::Duel(2, 3));
+        bracket.advance(3);
+
+        assert_eq!(bracket.next(), Pairing::Champion(3));
+
+        assert_eq!(Bracket::new(vec![]).next(), Pairing::Nobody);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::SystemTime;
+
+use scarlet::color::RGBColor;
+use serde::{Deserialize, Serialize};
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::engine::sound::Priority;
+use hastilude_core::keyframes;
+
+use crate::events::Event;
+use crate::state::World;
+
+/// Why a player was knocked out of a game
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Cause {
+    /// Moved more than allowed
+    #[default]
+    Movement,
+
+    /// Did not move enough
+    Inactivity,
+
+    /// Gave a wrong answer
+    Mistake,
+
+    /// Did not answer in time
+    Timeout,
+
+    /// Voted out by the other players
+    Vote,
+
+    /// Struck by the opponent
+    Strike,
+}
+
+impl ToString for Cause {
+    fn to_string(&self) -> String {
+        return match self {
+            Cause::Movement => "movement",
+            Cause::Inactivity => "inactivity",
+            Cause::Mistake => "mistake",
+            Cause::Timeout => "timeout",
+            Cause::Vote => "vote",
+            Cause::Strike => "strike",
+        }.to_owned();
+    }
+}
+
+/// A player knocked out of the running game
+///
+/// Games collect the eliminations of a frame and apply them once they are done with the players,
+/// which plays the same feedback for all game modes and announces the elimination as an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elimination {
+    pub player: PlayerId,
+    pub cause: Cause,
+}
+
+impl Elimination {
+    const SFX: &'static str = "eliminated";
+
+    pub fn new(player: PlayerId, cause: Cause) -> Self {
+        return Self {
+            player,
+            cause,
+        };
+    }
+
+    /// Plays the death animation and sound of the player and emits the elimination event
+    pub fn apply(self, world: &mut World) {
+        if let Some(player) = world.players.get_mut(self.player) {
+            player.haptic(HapticPattern::HIT);
+
+            // Flash red twice and fade out
+            player.color.set_and_animate(RGBColor { r: 1.0, g: 0.0, b: 0.0 }, keyframes![
+                0.15 => { (0, 0, 0) } @ end,
+                0.15 => { (255, 0, 0) } @ end,
+                0.15 => { (0, 0, 0) } @ end,
+                0.15 => { (255, 0, 0) } @ end,
+                0.8 => { (0, 0, 0) } @ linear,
+            ]);
+        }
+
+        if let Some(asset) = world.assets.sfx.get(Self::SFX) {
+            world.sound.cue(world.now, Priority::Stinger, Self::SFX, asset);
+        }
+
+        world.events.emit(Event::PlayerEliminated {
+            player: self.player,
+            cause: self.cause,
+            time: SystemTime::now(),
+        });
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Duration;
+
+use rand::Rng;
+use scarlet::color::RGBColor;
+use serde::{Deserialize, Serialize};
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{countdown, hues, reset_color};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::{Mode, ModeOptions, OptionsType};
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+/// Settings of the freeze game mode
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Options {
+    /// Number of move phases a player can sit out before being eliminated
+    pub lives: usize,
+}
+
+impl ModeOptions for Options {}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Self {
+            lives: 3,
+        };
+    }
+}
+
+pub struct Player {
+    hue: f64,
+
+    lives: usize,
+
+    // Movement accumulated during the current move phase
+    activity: f32,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Phase {
+    Move,
+    Freeze,
+}
+
+impl Phase {
+    pub fn music(self) -> f32 {
+        return match self {
+            Phase::Move => 1.0,
+            Phase::Freeze => 0.5,
+        };
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "freeze",
+    description: "Keep moving while the music plays normally and freeze as soon as it slows down",
+    min_players: 2,
+    max_players: None,
+    options: Some(OptionsType::of::<Options>()),
+    create: |players, options, seconds, world| countdown(Freeze::configured(players, options.get(&MODE), world), seconds, world),
+    teams: None,
+};
+
+/// Players must keep moving while the music plays normally and freeze as soon as it slows down
+pub struct Freeze {
+    data: PlayerData<Player>,
+
+    // The current phase - starts with the first update
+    phase: Option<Phase>,
+
+    music: Playback,
+
+    lives: usize,
+}
+
+impl Freeze {
+    // Timer ending the current phase
+    const PHASE_TIMER: &'static str = "phase";
+
+    // Minimum / maximum duration of the phases
+    const MOVE_DUR: Range<Duration> = (Duration::from_secs(5) .. Duration::from_secs(12));
+    const FREEZE_DUR: Range<Duration> = (Duration::from_secs(3) .. Duration::from_secs(7));
+
+    // Time to come to a halt after the music slowed down
+    const FREEZE_GRACE: Duration = Duration::from_millis(800);
+
+    // Movement eliminating a player during freeze
+    const FREEZE_THRESHOLD: f32 = 0.15;
+
+    // Average movement a player must keep up during a move phase
+    const MOVE_ACTIVITY: f32 = 0.2;
+
+    fn next(phase: Phase, world: &mut World) -> Phase {
+        let (phase, duration) = match phase {
+            Phase::Move => (Phase::Freeze, Self::FREEZE_DUR),
+            Phase::Freeze => (Phase::Move, Self::MOVE_DUR),
+        };
+
+        world.timers.once(Self::PHASE_TIMER, world.now, world.rng.gen_range(duration));
+
+        return phase;
+    }
+
+    pub fn configured(players: HashSet<PlayerId>, options: Options, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+        let lives = options.lives.max(1);
+
+        let hues = hues(players.len(), world.rng);
+
+        let players = PlayerData::init_with(players.into_iter()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+                lives,
+                activity: 0.0,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            phase: None,
+            music,
+            lives,
+        };
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Freeze {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, duration: Duration, _session: &Session) -> Option<State> {
+        let now = world.now;
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+        let lives = self.lives;
+
+        let phase = match self.phase {
+            Some(phase) => phase,
+            None => *self.phase.insert(Self::next(Phase::Freeze, world)),
+        };
+
+        // Players lose a life if they did not move enough during the move phase
+        let elapsed = world.timers.elapsed(Self::PHASE_TIMER, now).unwrap_or_default();
+        let finished = world.timers.fired(Self::PHASE_TIMER, now);
+        let moved = elapsed.as_secs_f32();
+
+        let mut eliminated = Vec::new();
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let accel = player.acceleration(true) / (threshold * player.threshold());
+
+            let cause = match phase {
+                Phase::Freeze => (elapsed >= Self::FREEZE_GRACE && accel >= Self::FREEZE_THRESHOLD).then_some(Cause::Movement),
+                Phase::Move => {
+                    data.activity += accel * duration.as_secs_f32();
+
+                    if finished && data.activity / moved < Self::MOVE_ACTIVITY {
+                        data.lives -= 1;
+                        player.haptic(HapticPattern::SHAKE);
+                    }
+
+                    (data.lives == 0).then_some(Cause::Inactivity)
+                }
+            };
+
+            if let Some(cause) = cause {
+                eliminated.push(Elimination::new(player.id(), cause));
+                return false;
+            }
+
+            // Remaining lives are shown by the brightness - red while frozen
+            let v = data.lives as f64 / lives as f64;
+            player.color.set(match phase {
+                Phase::Move => theme.hue(data.hue, v),
+                Phase::Freeze => RGBColor { r: v, g: 0.0, b: 0.0 },
+            });
+
+            return true;
+        });
+
+        for elimination in eliminated {
+            elimination.apply(world);
+        }
+
+        if finished {
+            let phase = *self.phase.insert(Self::next(phase, world));
+
+            for (_, data) in self.data.iter_mut() {
+                data.activity = 0.0;
+            }
+
+            self.music.speed(phase.music());
+            world.events.emit(Event::MusicSpeed {
+                speed: phase.music(),
+            });
+
+            match phase {
+                Phase::Move => world.announce("move"),
+                Phase::Freeze => world.announce("freeze"),
+            }
+        }
+
+        if self.data.len() == 1 {
+            return Some(Outcome::Winners(self.data.keys().collect()).finish(world));
+        }
+
+        if self.data.len() == 0 {
+            // The last players went out together
+            return Some(Outcome::Draw.finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, _paused: Duration, _world: &mut World) {
+        self.music.resume();
+    }
+
+    fn reconfigure(&mut self, world: &mut World) {
+        let lives = world.settings.options.get::<Options>(&MODE).lives.max(1);
+
+        // Players keep the lives lost so far but are not eliminated by the change
+        for (_, data) in self.data.iter_mut() {
+            data.lives = (data.lives + lives).saturating_sub(self.lives).max(1);
+        }
+
+        self.lives = lives;
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Freeze {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let options = world.settings.options.get(&MODE);
+        return Self::configured(players, options, world);
+    }
+}
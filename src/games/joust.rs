@@ -1,31 +1,124 @@
 use std::collections::HashSet;
-use std::ops::Range;
+use std::ops::RangeInclusive;
 use std::time::{Duration, Instant};
 
 use rand::Rng;
-use scarlet::color::{Color, RGBColor};
-use scarlet::colors::HSVColor;
-
-use crate::engine::animation::Animated;
-use crate::engine::players::{PlayerData, PlayerId};
-use crate::engine::sound::Playback;
-use crate::games::{Game, GameData, Session};
-use crate::keyframes;
-use crate::meta::celebration::Celebration;
+use scarlet::color::RGBColor;
+use serde::{Deserialize, Serialize};
+
+use hastilude_core::engine::animation::Animated;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+use hastilude_core::keyframes;
+
+use crate::events::Event;
+use crate::games::{countdown, positive, reset_color, seconds};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::{Mode, ModeOptions, OptionsType};
+use crate::games::scoring::Scoring;
 use crate::meta::countdown::PlayerColor;
-use crate::state::{State, World};
+use crate::palette::Theme;
+use crate::state::{InvalidOptionsError, Settings, State, World};
+
+/// Settings of the joust game mode
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Options {
+    /// Number of times a player can move too much before being eliminated
+    pub lives: usize,
+
+    /// Movement eliminating a player at normal, fast and slow pacing
+    pub threshold_normal: f32,
+    pub threshold_fast: f32,
+    pub threshold_slow: f32,
+
+    /// Minimum / maximum duration of a regular and a changed pacing phase in seconds
+    pub pacing_regular: (f32, f32),
+    pub pacing_changed: (f32, f32),
+
+    /// Maximum duration of a round in seconds before sudden death starts - unlimited if missing
+    pub round: Option<f32>,
+
+    /// Time in seconds for the threshold to halve during sudden death
+    pub sudden_death: f32,
+
+    /// Lives of players joining a running round - late joins are refused if zero
+    pub late_lives: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Self {
+            lives: 1,
+            threshold_normal: 0.6,
+            threshold_fast: 0.9,
+            threshold_slow: 0.3,
+            pacing_regular: (10.0, 30.0),
+            pacing_changed: (5.0, 15.0),
+            round: None,
+            sudden_death: 10.0,
+            late_lives: 1,
+        };
+    }
+}
+
+impl ModeOptions for Options {
+    fn validate(&self) -> Result<(), InvalidOptionsError> {
+        positive("joust.threshold_normal", self.threshold_normal)?;
+        positive("joust.threshold_fast", self.threshold_fast)?;
+        positive("joust.threshold_slow", self.threshold_slow)?;
+
+        for (field, (min, max)) in [("joust.pacing_regular", self.pacing_regular), ("joust.pacing_changed", self.pacing_changed)] {
+            if seconds(field, min)? > seconds(field, max)? {
+                return Err(InvalidOptionsError { field, reason: "must not end before it starts" });
+            }
+        }
+
+        if let Some(round) = self.round {
+            seconds("joust.round", round)?;
+        }
+
+        positive("joust.sudden_death", self.sudden_death)?;
+
+        return Ok(());
+    }
+}
+
+fn pacing((min, max): (f32, f32)) -> RangeInclusive<Duration> {
+    let min = Duration::try_from_secs_f32(min).unwrap_or_default();
+    let max = Duration::try_from_secs_f32(max).unwrap_or_default();
+    return min ..= max.max(min);
+}
+
+/// Factor applied to the threshold after the round is running for the given time
+fn sudden_death(options: &Options, age: Duration) -> f32 {
+    let overtime = match options.round {
+        Some(round) => age.as_secs_f32() - round.max(0.0),
+        None => return 1.0,
+    };
+
+    if overtime <= 0.0 {
+        return 1.0;
+    }
+
+    return 0.5f32.powf(overtime / options.sudden_death.max(f32::EPSILON));
+}
 
 pub struct Player {
     hue: f64,
+
+    lives: usize,
+
+    // Players losing a life are dimmed and not checked for movement until then
+    recovering: Option<Instant>,
 }
 
 impl PlayerColor for Player {
-    fn color(&self) -> RGBColor {
-        return HSVColor {
-            h: self.hue * 360.0 % 360.0,
-            s: 1.0,
-            v: 1.0,
-        }.convert::<RGBColor>();
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
     }
 }
 
@@ -45,15 +138,25 @@ impl Speed {
         };
     }
 
-    pub fn threshold(self) -> f32 {
+    pub fn threshold(self, options: &Options) -> f32 {
         return match self {
-            Speed::NORMAL => 0.6,
-            Speed::FAST => 0.9,
-            Speed::SLOW => 0.3,
+            Speed::NORMAL => options.threshold_normal,
+            Speed::FAST => options.threshold_fast,
+            Speed::SLOW => options.threshold_slow,
         };
     }
 }
 
+pub static MODE: Mode = Mode {
+    name: "joust",
+    description: "Jostle the controllers of the others while keeping your own still - the last one standing wins",
+    min_players: 2,
+    max_players: None,
+    options: Some(OptionsType::of::<Options>()),
+    create: |players, options, seconds, world| countdown(Joust::configured(players, options.get(&MODE), world), seconds, world),
+    teams: None,
+};
+
 pub struct Joust {
     data: PlayerData<Player>,
 
@@ -65,6 +168,11 @@ pub struct Joust {
     threshold: Animated<f32>,
 
     hue_base: f64,
+
+    // Whether sudden death was already announced
+    sudden_death: bool,
+
+    options: Options,
 }
 
 impl Joust {
@@ -74,20 +182,53 @@ impl Joust {
     // Slack for slowing down movement detection
     const PACING_CHANGE_SLACK: Duration = Duration::from_millis(3000);
 
-    // Minimum / maximum duration of a regular pacing phase
-    const PACING_REGULAR_DUR: Range<Duration> = (Duration::from_secs(10) .. Duration::from_secs(30));
-
-    // Minimum / maximum duration of a changed pacing phase
-    const PACING_CHANGED_DUR: Range<Duration> = (Duration::from_secs(5) .. Duration::from_secs(15));
-
     // Speed of hue rotation (time for a full rotation)
     const HUE_ROTATION_SPEED: f64 = 1.0 / 120.0;
 
     // Speed of hue adoption when hue must change
     const HUE_ADOPTION_SPEED: f64 = 1.0 / 10.0;
+
+    // Time to recover after losing a life and the brightness while recovering
+    const RECOVERY_DUR: Duration = Duration::from_secs(2);
+    const RECOVERY_DIM: f64 = 0.2;
+
+    // Flash of a player losing a life
+    const HIT_FLASH: Duration = Duration::from_millis(500);
+
+    pub fn configured(players: HashSet<PlayerId>, options: Options, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        // Create players and assign colors
+        let hue_base: f64 = world.rng.gen();
+        let hue_step: f64 = 1.0 / world.players.count() as f64;
+
+        let lives = options.lives.max(1);
+
+        let players = PlayerData::init_with(players.into_iter()
+            .enumerate()
+            .map(|(i, id)| (id, Player {
+                hue: hue_base + hue_step * i as f64,
+                lives,
+                recovering: None,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            speed: (Speed::NORMAL, world.now + *pacing(options.pacing_regular).end()),
+            music,
+            music_speed: Animated::idle(Speed::NORMAL.music()),
+            threshold: Animated::idle(Speed::NORMAL.threshold(&options)),
+            hue_base,
+            sudden_death: false,
+            options,
+        };
+    }
 }
 
-impl Game for Joust {
+impl Game<Settings, Event, Scoring> for Joust {
+    type Next = State;
+
     fn update(&mut self, world: &mut World, duration: Duration, session: &Session) -> Option<State> {
         self.music_speed.update(duration);
         self.threshold.update(duration);
@@ -95,7 +236,7 @@ impl Game for Joust {
         // Check if speed is about to change
         if self.speed.1 < world.now {
             let (speed, slack) = match self.speed.0 {
-                Speed::NORMAL => if rand::thread_rng().gen() {
+                Speed::NORMAL => if world.rng.gen() {
                     (Speed::FAST, false)
                 } else {
                     (Speed::SLOW, true)
@@ -108,19 +249,29 @@ impl Game for Joust {
                 Self::PACING_CHANGE_SPEED => { speed.music() } @ linear,
             ]);
 
+            world.events.emit(Event::MusicSpeed {
+                speed: speed.music(),
+            });
+
+            match speed {
+                Speed::FAST => world.announce("faster"),
+                Speed::SLOW => world.announce("slower"),
+                Speed::NORMAL => {}
+            }
+
             // Apply slack in threshold
             if slack {
                 self.threshold.animate(keyframes![
-                    Self::PACING_CHANGE_SLACK => { speed.threshold() } @ linear,
+                    Self::PACING_CHANGE_SLACK => { speed.threshold(&self.options) } @ linear,
                 ]);
             } else {
-                self.threshold.set(speed.threshold());
+                self.threshold.set(speed.threshold(&self.options));
             }
 
             // Roll a dice for duration of the next phase
-            let duration = rand::thread_rng().gen_range(match speed {
-                Speed::NORMAL => Self::PACING_REGULAR_DUR,
-                _ => Self::PACING_CHANGED_DUR,
+            let duration = world.rng.gen_range(match speed {
+                Speed::NORMAL => pacing(self.options.pacing_regular),
+                _ => pacing(self.options.pacing_changed),
             });
 
             self.speed = (speed, world.now + duration);
@@ -138,38 +289,61 @@ impl Game for Joust {
             data.hue += delta_hue.signum() * (Self::HUE_ADOPTION_SPEED * duration.as_secs_f64()).min(delta_hue.abs());
         }
 
+        // Movement detection gets ever more sensitive once the round is over
+        let factor = sudden_death(&self.options, session.age(world.now));
+        if factor < 1.0 && !self.sudden_death {
+            world.announce("sudden-death");
+            self.sudden_death = true;
+        }
+
         // Update players
+        let now = world.now;
+        let mut eliminated = Vec::new();
+        let threshold = world.threshold() * factor;
+        let theme = world.settings.theme;
         world.players.with_data(&mut self.data).update(|player, data| {
-            let accel = player.acceleration(true) / self.threshold.value();
+            if data.recovering.map_or(false, |recovering| recovering > now) {
+                player.color.set(theme.hue(data.hue, Self::RECOVERY_DIM));
+
+                return true;
+            }
+
+            let accel = player.acceleration(true) / (self.threshold.value() * threshold * player.threshold());
 
             // Check if player has moved to much
             if accel >= 1.0 {
-                player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
-                player.rumble.animate(keyframes![
-                    0.0 => 255,
-                    1.0 => 0 @ linear,
-                ]);
+                data.lives = data.lives.saturating_sub(1);
+
+                if data.lives == 0 {
+                    eliminated.push(Elimination::new(player.id(), Cause::Movement));
 
-                return false;
+                    return false;
+                }
+
+                player.haptic(HapticPattern::BUZZ);
+                player.flash(RGBColor { r: 1.0, g: 0.0, b: 0.0 }, Self::HIT_FLASH);
+                data.recovering = Some(now + Self::RECOVERY_DUR);
+
+                return true;
             }
 
             // Update color reflecting players acceleration
-            player.color.set(HSVColor {
-                h: data.hue * 360.0 % 360.0,
-                s: 1.0,
-                v: 1.0 - f32::sqrt(accel) as f64,
-            }.convert::<RGBColor>());
+            player.color.set(theme.hue(data.hue, 1.0 - f32::sqrt(accel) as f64));
 
             return true;
         });
 
+        for elimination in eliminated {
+            elimination.apply(world);
+        }
+
         if self.data.len() == 1 {
-            return Some(State::Celebration(Celebration::new(self.data.keys().collect(), world)));
+            return Some(Outcome::Winners(self.data.keys().collect()).finish(world));
         }
 
         if self.data.len() == 0 {
-            // Got a draw - everybody is winner
-            return Some(State::Celebration(Celebration::new(world.players.keys().collect(), world)));
+            // The last players went out together
+            return Some(Outcome::Draw.finish(world));
         }
 
         return None;
@@ -177,19 +351,58 @@ impl Game for Joust {
 
     fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
         if self.data.remove(player) {
-            // Reset player color
-            if let Some(player) = world.players.get_mut(player) {
-                player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 })
-            }
+            reset_color(player, world);
 
             return true;
         }
 
         return false;
     }
+
+    fn join_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        // Nobody joins if disabled or once sudden death has started
+        let lives = self.options.late_lives.min(self.options.lives.max(1));
+        if lives == 0 || self.sudden_death {
+            return false;
+        }
+
+        // Late arrivals start recovering to get a moment to settle in
+        let joined = self.data.insert(player, Player {
+            hue: world.rng.gen(),
+            lives,
+            recovering: Some(world.now + Self::RECOVERY_DUR),
+        });
+
+        if joined {
+            if let Some(player) = world.players.get_mut(player) {
+                player.haptic(HapticPattern::BUZZ);
+            }
+        }
+
+        return joined;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        self.speed.1 += paused;
+
+        for (_, data) in self.data.iter_mut() {
+            data.recovering = data.recovering.map(|recovering| recovering + paused);
+        }
+
+        self.music.resume();
+    }
+
+    fn reconfigure(&mut self, world: &mut World) {
+        self.options = world.settings.options.get(&MODE);
+        self.threshold.set(self.speed.0.threshold(&self.options));
+    }
 }
 
-impl GameData for Joust {
+impl GameData<Settings, Event, Scoring> for Joust {
     type Data = Player;
 
     fn data(&mut self) -> &mut PlayerData<Player> {
@@ -197,27 +410,23 @@ impl GameData for Joust {
     }
 
     fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
-        let music = world.assets.music.random();
-        let music = world.sound.music(music);
+        let options = world.settings.options.get(&MODE);
+        return Self::configured(players, options, world);
+    }
+}
 
-        // Create players and assign colors
-        let hue_base: f64 = rand::random();
-        let hue_step: f64 = 1.0 / world.players.count() as f64;
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        let players = PlayerData::init_with(players.into_iter()
-            .enumerate()
-            .map(|(i, id)| (id, Player {
-                hue: hue_base + hue_step * i as f64,
-            }))
-            .collect());
+    #[test]
+    fn test_sudden_death() {
+        let options = Options::default();
+        assert_eq!(sudden_death(&options, Duration::from_secs(600)), 1.0);
 
-        return Self {
-            data: players,
-            speed: (Speed::NORMAL, Instant::now() + Self::PACING_REGULAR_DUR.end),
-            music,
-            music_speed: Animated::idle(Speed::NORMAL.music()),
-            threshold: Animated::idle(Speed::NORMAL.threshold()),
-            hue_base,
-        };
+        let options = Options { round: Some(60.0), sudden_death: 10.0, ..Options::default() };
+        assert_eq!(sudden_death(&options, Duration::from_secs(30)), 1.0);
+        assert_eq!(sudden_death(&options, Duration::from_secs(70)), 0.5);
+        assert!(sudden_death(&options, Duration::from_secs(90)) < sudden_death(&options, Duration::from_secs(80)));
     }
 }
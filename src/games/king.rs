@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use scarlet::color::RGBColor;
+use serde::{Deserialize, Serialize};
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Scores, Session};
+
+use crate::events::Event;
+use crate::games::{countdown, hues, reset_color, seconds};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::{Mode, ModeOptions, OptionsType};
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{InvalidOptionsError, Settings, State, World};
+
+/// Settings of the king of the hill game mode
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Options {
+    /// Length of a round in seconds
+    pub round: f32,
+}
+
+impl ModeOptions for Options {
+    fn validate(&self) -> Result<(), InvalidOptionsError> {
+        seconds("king.round", self.round)?;
+        return Ok(());
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Self {
+            round: 90.0,
+        };
+    }
+}
+
+pub struct Player {
+    hue: f64,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "king",
+    description: "The calmest player holds the crown - most crown time at the end of the round wins",
+    min_players: 2,
+    max_players: None,
+    options: Some(OptionsType::of::<Options>()),
+    create: |players, options, seconds, world| countdown(King::configured(players, options.get(&MODE), world), seconds, world),
+    teams: None,
+};
+
+/// The calmest player holds the crown and collects crown time until jostled - the player with the
+/// most crown time at the end of the round wins
+pub struct King {
+    data: PlayerData<Player>,
+
+    // Time each player has held the crown
+    scores: Scores<Duration>,
+
+    crown: Option<PlayerId>,
+
+    round: Duration,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl King {
+    // Movement of the crown holder passing the crown on
+    const JOSTLE_THRESHOLD: f32 = 0.3;
+
+    // Movement eliminating a player
+    const ELIMINATION_THRESHOLD: f32 = 0.8;
+
+    pub fn configured(players: HashSet<PlayerId>, options: Options, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        let hues = hues(players.len(), world.rng);
+
+        let scores = Scores::new(players.iter().copied());
+
+        let players = PlayerData::init_with(players.into_iter()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            scores,
+            crown: None,
+            round: Duration::try_from_secs_f32(options.round).unwrap_or_default(),
+            music,
+        };
+    }
+}
+
+impl Game<Settings, Event, Scoring> for King {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, duration: Duration, session: &Session) -> Option<State> {
+        let mut eliminated = Vec::new();
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+
+        // Eliminate players moving too much and collect the movement of the others
+        let mut movement = Vec::new();
+        world.players.with_data(&mut self.data).update(|player, _| {
+            let accel = player.acceleration(true) / (threshold * player.threshold());
+
+            if accel >= Self::ELIMINATION_THRESHOLD {
+                eliminated.push(Elimination::new(player.id(), Cause::Movement));
+
+                return false;
+            }
+
+            movement.push((player.id(), accel));
+            return true;
+        });
+
+        for elimination in eliminated {
+            elimination.apply(world);
+        }
+
+        // The crown is lost by moving too much or by being eliminated
+        let jostled = self.crown
+            .filter(|crown| movement.iter().all(|(id, accel)| id != crown || *accel >= Self::JOSTLE_THRESHOLD));
+
+        if let Some(jostled) = jostled {
+            self.crown = None;
+
+            if let Some(player) = world.players.get_mut(jostled) {
+                player.haptic(HapticPattern::SHAKE);
+            }
+        }
+
+        // Pass the crown on to the calmest other player
+        if self.crown.is_none() {
+            self.crown = movement.iter()
+                .filter(|(id, _)| Some(*id) != jostled)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(id, _)| *id);
+
+            if let Some(player) = self.crown.and_then(|crown| world.players.get_mut(crown)) {
+                player.haptic(HapticPattern::TAP);
+            }
+        }
+
+        if let Some(crown) = self.crown {
+            self.scores.add(crown, duration);
+        }
+
+        // The crown holder lights up white while the others show their movement
+        for (id, accel) in movement {
+            let data = match self.data.get(id) {
+                Some(data) => data,
+                None => continue,
+            };
+
+            if let Some(player) = world.players.get_mut(id) {
+                player.color.set(if Some(id) == self.crown {
+                    RGBColor { r: 1.0, g: 1.0, b: 1.0 }
+                } else {
+                    theme.hue(data.hue, 1.0 - f32::sqrt(accel / Self::ELIMINATION_THRESHOLD) as f64)
+                });
+            }
+        }
+
+        if session.remaining(world.now, self.round).is_zero() || self.data.len() <= 1 {
+            return Some(Outcome::Winners(self.scores.leaders()).finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            if self.crown == Some(player) {
+                self.crown = None;
+            }
+
+            // Crown time of players gone does not count towards the round
+            self.scores.remove(player);
+
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, _paused: Duration, _world: &mut World) {
+        self.music.resume();
+    }
+
+    fn reconfigure(&mut self, world: &mut World) {
+        self.round = Duration::try_from_secs_f32(world.settings.options.get::<Options>(&MODE).round).unwrap_or_default();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for King {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let options = world.settings.options.get(&MODE);
+        return Self::configured(players, options, world);
+    }
+}
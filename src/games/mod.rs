@@ -1,53 +1,96 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
 use std::str::FromStr;
 use std::time::{Duration, Instant};
 
-use serde::{Deserialize, Serialize};
+use rand::Rng;
+use scarlet::color::RGBColor;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 use tracing::debug;
 
-use crate::engine::players::{PlayerData, PlayerId};
-use crate::games::debug::Debug;
-use crate::games::joust::Joust;
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::game;
+pub use hastilude_core::game::Session;
+
+use crate::events::Event;
+use crate::games::mutators::Mutators;
+use crate::games::registry::{Mode, ModeOptions};
+use crate::games::scoring::Scoring;
+use crate::meta::celebration;
 use crate::meta::countdown::{Countdown, PlayerColor};
-use crate::state::{State, World};
+use crate::meta::pause::{self, Paused};
+use crate::state::{InvalidOptionsError, JoinPlayerError, Settings, State, World};
 
-pub mod debug;
-pub mod joust;
+pub mod best_of;
+pub mod elimination;
+pub mod mutators;
+pub mod outcome;
+pub mod registry;
+pub mod scoring;
+pub mod tournament;
 
-pub struct Session {
-    // The time when the session was started
-    pub started: Instant,
-}
+/// Declares the modules of the game modes and lists their modes in the given order
+macro_rules! modes {
+    ($($game:ident),* $(,)?) => {
+        $(pub mod $game;)*
 
-impl Session {
-    pub fn new() -> Self {
-        return Self {
-            started: Instant::now(),
-        };
-    }
+        /// All game modes in the order they are offered
+        pub static MODES: &[&Mode] = &[$(&$game::MODE),*];
+    };
+}
 
-    pub fn age(&self, now: Instant) -> Duration {
-        return now - self.started;
-    }
+// Adding a game mode takes a module exporting its `MODE` and an entry here
+modes! {
+    bomb,
+    debug,
+    duel,
+    freeze,
+    joust,
+    king,
+    practice,
+    relay,
+    simon,
+    statues,
+    territory,
+    traitor,
 }
 
 pub struct GameState {
     game: Box<dyn Game>,
     session: Session,
+
+    // Players the game was started with and not kicked since
+    players: HashSet<PlayerId>,
+
+    mutators: Mutators,
 }
 
 impl GameState {
-    pub fn new(game: Box<dyn Game>) -> Self {
-        let session = Session::new();
+    pub fn new(game: Box<dyn Game>, players: HashSet<PlayerId>, mutators: Mutators, now: Instant) -> Self {
+        let session = Session::new(now);
         return Self {
             game,
             session,
+            players,
+            mutators,
         };
     }
 
     pub fn update(mut self, world: &mut World, duration: Duration) -> State {
-        if let Some(state) = self.game.update(world, duration, &self.session) {
+        if pause::requested(world, &self.players) {
+            return State::Paused(Paused::new(self, world));
+        }
+
+        self.mutators.update(duration);
+
+        // The world only lasts for the current frame
+        world.threshold_scale = self.mutators.threshold();
+        let next = self.game.update(world, duration, &self.session);
+
+        if let Some(state) = next {
             return state;
         } else {
             return State::Playing(self);
@@ -55,44 +98,121 @@ impl GameState {
     }
 
     pub fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        self.players.remove(&player);
         return self.game.kick_player(player, world);
     }
-}
 
-pub trait GameData: Game {
-    type Data;
+    pub fn join_player(&mut self, player: PlayerId, world: &mut World) -> Result<(), JoinPlayerError> {
+        if self.players.contains(&player) {
+            return Err(JoinPlayerError::AlreadyPlaying(player));
+        }
+
+        if !self.game.join_player(player, world) {
+            return Err(JoinPlayerError::NotSupported);
+        }
 
-    fn data(&mut self) -> &mut PlayerData<Self::Data>;
+        self.players.insert(player);
+        world.events.emit(Event::PlayerJoined { player });
 
-    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self
-        where Self: Sized;
+        return Ok(());
+    }
+
+    /// Stops the game clock and halts the game until resumed
+    pub fn suspend(&mut self, world: &mut World) {
+        self.session.suspend(world.now);
+        self.game.suspend(world);
+    }
+
+    pub fn resume(&mut self, world: &mut World) {
+        let paused = self.session.resume(world.now);
+        world.timers.delay(paused);
+        self.game.resume(paused, world);
+    }
+
+    /// The players taking part in the game if the others show the idle animation
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return self.game.idle_animation().then_some(&self.players);
+    }
+
+    /// The players the game was started with and not kicked since
+    pub fn players(&self) -> &HashSet<PlayerId> {
+        return &self.players;
+    }
+
+    pub fn mutators(&self) -> &Mutators {
+        return &self.mutators;
+    }
+
+    pub fn reconfigure(&mut self, world: &mut World) {
+        self.game.reconfigure(world);
+    }
 }
 
-pub trait Game {
-    fn update(&mut self, world: &mut World, duration: Duration, session: &Session) -> Option<State>;
+/// A game played with the settings, events and scoring of this application
+pub trait Game: game::Game<Settings, Event, Scoring, Next=State> {}
+
+impl<T> Game for T
+    where T: game::Game<Settings, Event, Scoring, Next=State> {}
+
+pub trait GameData: Game + game::GameData<Settings, Event, Scoring> {}
+
+impl<T> GameData for T
+    where T: Game + game::GameData<Settings, Event, Scoring> {}
 
-    /// Removes a player form the game. Returns whether the player was part of the game.
-    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool;
+/// A game mode from the list of all modes - identified by its name
+///
+/// Modes are serialized by their lowercase name as listed by `/api/modes`. The capitalized names
+/// used before are still accepted when parsing.
+#[derive(Copy, Clone)]
+pub struct GameMode(&'static Mode);
+
+impl GameMode {
+    pub const fn of(mode: &'static Mode) -> Self {
+        return Self(mode);
+    }
+
+    pub fn all() -> impl Iterator<Item=Self> {
+        return MODES.iter().map(|mode| Self(mode));
+    }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub enum GameMode {
-    Debug,
-    Joust,
+impl Deref for GameMode {
+    type Target = Mode;
+
+    fn deref(&self) -> &Self::Target {
+        return self.0;
+    }
 }
 
 impl Default for GameMode {
     fn default() -> Self {
-            return Self::Joust;
+        return Self(&joust::MODE);
+    }
+}
+
+impl PartialEq for GameMode {
+    fn eq(&self, other: &Self) -> bool {
+        return self.name == other.name;
+    }
+}
+
+impl Eq for GameMode {}
+
+impl Hash for GameMode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl fmt::Debug for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str(self.name);
     }
 }
 
 impl ToString for GameMode {
     fn to_string(&self) -> String {
-        return match self {
-            GameMode::Debug => "debug",
-            GameMode::Joust => "joust",
-        }.to_owned();
+        return self.name.to_owned();
     }
 }
 
@@ -100,14 +220,138 @@ impl FromStr for GameMode {
     type Err = ParseGameTypeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        return match s {
-            "debug" => Ok(Self::Debug),
-            "joust" => Ok(Self::Joust),
-            _ => Err(ParseGameTypeError),
+        // Names are matched ignoring the case to accept the capitalized names used before
+        return Self::all()
+            .find(|mode| mode.name.eq_ignore_ascii_case(s))
+            .ok_or(ParseGameTypeError);
+    }
+}
+
+impl Serialize for GameMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        return serializer.serialize_str(self.name);
+    }
+}
+
+impl<'de> Deserialize<'de> for GameMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        return name.parse().map_err(de::Error::custom);
+    }
+}
+
+/// Settings of the individual game modes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GameOptions {
+    pub celebration: celebration::Options,
+
+    /// Seconds counted down before a game starts for modes not using the default
+    pub countdowns: HashMap<GameMode, u32>,
+
+    /// Settings of the modes having any by the name of the mode
+    #[serde(flatten, deserialize_with = "deserialize_modes")]
+    modes: BTreeMap<String, Value>,
+}
+
+/// Drops unset fields - they take their defaults anyway and can not be written to TOML
+fn settled(value: Value) -> Value {
+    return match value {
+        Value::Object(fields) => Value::Object(fields.into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| (name, settled(value)))
+            .collect()),
+        value => value,
+    };
+}
+
+fn deserialize_modes<'de, D>(deserializer: D) -> Result<BTreeMap<String, Value>, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    let modes = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    return Ok(modes.into_iter()
+        .map(|(name, value)| (name, settled(value)))
+        .collect());
+}
+
+impl GameOptions {
+    const COUNTDOWN: u32 = 3;
+
+    /// Seconds counted down before a game of the given mode starts
+    pub fn countdown(&self, mode: GameMode) -> u32 {
+        return self.countdowns.get(&mode).copied().unwrap_or(Self::COUNTDOWN);
+    }
+
+    /// The settings of a mode - the defaults if not set
+    pub fn get<T>(&self, mode: &Mode) -> T
+        where
+            T: ModeOptions,
+    {
+        return self.modes.get(mode.name)
+            .and_then(|value| T::deserialize(value).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn set<T>(&mut self, mode: &Mode, options: &T)
+        where
+            T: ModeOptions,
+    {
+        let value = serde_json::to_value(options).expect("Failed to serialize options");
+        self.modes.insert(mode.name.to_owned(), settled(value));
+    }
+
+    /// Checks that the games can be played with these settings
+    pub fn validate(&self) -> Result<(), InvalidOptionsError> {
+        let known = |name: &String| MODES.iter().any(|mode| mode.name == name && mode.options.is_some());
+        if !self.modes.keys().all(known) {
+            return Err(InvalidOptionsError { field: "options", reason: "must only contain modes having settings" });
+        }
+
+        for mode in MODES.iter() {
+            if let (Some(options), Some(value)) = (mode.options.as_ref(), self.modes.get(mode.name)) {
+                (options.validate)(mode.name, value)?;
+            }
+        }
+
+        self.celebration.validate()?;
+        return Ok(());
+    }
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        return Self {
+            celebration: celebration::Options::default(),
+            countdowns: HashMap::new(),
+            modes: MODES.iter()
+                .filter_map(|mode| Some((mode.name.to_owned(), settled((mode.options.as_ref()?.defaults)()))))
+                .collect(),
         };
     }
 }
 
+/// Checks a setting to be a positive number
+pub fn positive(field: &'static str, value: f32) -> Result<f32, InvalidOptionsError> {
+    if !(value > 0.0 && value.is_finite()) {
+        return Err(InvalidOptionsError { field, reason: "must be positive" });
+    }
+
+    return Ok(value);
+}
+
+/// Checks a setting in seconds to be a valid duration
+pub fn seconds(field: &'static str, value: f32) -> Result<Duration, InvalidOptionsError> {
+    return Duration::try_from_secs_f32(value)
+        .map_err(|_| InvalidOptionsError { field, reason: "must be a duration in seconds" });
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseGameTypeError;
 
@@ -117,20 +361,132 @@ impl fmt::Display for ParseGameTypeError {
     }
 }
 
-fn start<T>(players: HashSet<PlayerId>, world: &mut World) -> State
+impl std::error::Error for ParseGameTypeError {}
+
+/// Players playing together in a team and the hue of their team color
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lineup {
+    pub hue: f64,
+    pub roster: Vec<PlayerId>,
+}
+
+/// Splits the players into teams by dealing them out in the given order
+pub fn teams(players: &[PlayerId], count: usize) -> Vec<Vec<PlayerId>> {
+    let mut teams = vec![Vec::new(); count];
+    for (i, player) in players.iter().enumerate() {
+        teams[i % count].push(*player);
+    }
+
+    return teams;
+}
+
+/// Hues evenly spread around the color wheel starting at a random position
+pub fn hues(count: usize, rng: &mut impl Rng) -> impl Iterator<Item=f64> {
+    let base: f64 = rng.gen();
+    let step = 1.0 / count.max(1) as f64;
+
+    return (0..count).map(move |i| base + step * i as f64);
+}
+
+/// Turns off the color of a player leaving the game
+pub fn reset_color(player: PlayerId, world: &mut World) {
+    if let Some(player) = world.players.get_mut(player) {
+        player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+    }
+}
+
+fn start<T>(players: HashSet<PlayerId>, seconds: u32, world: &mut World) -> State
     where T: Game + GameData + 'static,
           T::Data: PlayerColor {
     let game = T::create(players, world);
+    return countdown(game, seconds, world);
+}
+
+fn countdown<T>(game: T, seconds: u32, world: &mut World) -> State
+    where T: Game + GameData + 'static,
+          T::Data: PlayerColor {
     debug!("Game created");
 
-    return State::Countdown(Countdown::new(game, world));
+    return State::Countdown(Countdown::new(game, seconds, world));
 }
 
 impl GameMode {
-    pub fn create(self, players: HashSet<PlayerId>, world: &mut World) -> State {
-        return match self {
-            Self::Debug => State::Playing(GameState::new(Box::new(Debug::new(world)))),
-            Self::Joust => start::<Joust>(players, world),
-        };
+    pub fn create(self, players: HashSet<PlayerId>, options: &GameOptions, world: &mut World) -> State {
+        world.events.emit(Event::GameStarted {
+            mode: self,
+            players: players.clone(),
+        });
+
+        let seconds = options.countdown(self);
+
+        return (self.create)(players, options, seconds, world);
+    }
+
+    /// Creates a game of a team mode for the teams picked by the players
+    pub fn create_teams(self, teams: Vec<Lineup>, options: &GameOptions, world: &mut World) -> State {
+        let create = self.teams.as_ref().expect("Game mode played in teams").create;
+
+        world.events.emit(Event::GameStarted {
+            mode: self,
+            players: teams.iter().flat_map(|team| team.roster.iter().copied()).collect(),
+        });
+
+        let seconds = options.countdown(self);
+
+        return create(teams, options, seconds, world);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_teams() {
+        assert_eq!(teams(&[1, 2, 3, 4, 5], 2), vec![vec![1, 3, 5], vec![2, 4]]);
+        assert_eq!(teams(&[1], 2), vec![vec![1], vec![]]);
+    }
+
+    #[test]
+    fn test_countdown() {
+        let options: GameOptions = serde_json::from_str(r#"{"countdowns": {"Simon": 5}}"#).unwrap();
+        assert_eq!(options.countdown(GameMode::of(&simon::MODE)), 5);
+        assert_eq!(options.countdown(GameMode::of(&joust::MODE)), GameOptions::COUNTDOWN);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(GameOptions::default().validate().is_ok());
+
+        let mut options = GameOptions::default();
+        options.set(&joust::MODE, &joust::Options { threshold_fast: 0.0, ..joust::Options::default() });
+        assert_eq!(options.validate().unwrap_err().field, "joust.threshold_fast");
+
+        let mut options = GameOptions::default();
+        options.set(&king::MODE, &king::Options { round: -1.0 });
+        assert_eq!(options.validate().unwrap_err().field, "king.round");
+
+        let mut options = GameOptions::default();
+        options.set(&joust::MODE, &joust::Options { pacing_regular: (10.0, 1e30), ..joust::Options::default() });
+        assert_eq!(options.validate().unwrap_err().field, "joust.pacing_regular");
+
+        let options: GameOptions = serde_json::from_str(r#"{"freeze": {"lives": "many"}}"#).unwrap();
+        assert_eq!(options.validate().unwrap_err().field, "freeze");
+
+        // Settings not given take the defaults of the mode
+        let options: GameOptions = serde_json::from_str(r#"{"king": {}}"#).unwrap();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.get::<king::Options>(&king::MODE), king::Options::default());
+    }
+
+    #[test]
+    fn test_modes() {
+        for mode in GameMode::all() {
+            assert_eq!(mode.name.parse(), Ok(mode));
+            assert!(mode.max_players.map_or(true, |max| max >= mode.min_players), "{}", mode.name);
+        }
+
+        assert_eq!("Joust".parse(), Ok(GameMode::of(&joust::MODE)));
+        assert_eq!("tag".parse::<GameMode>(), Err(ParseGameTypeError));
     }
 }
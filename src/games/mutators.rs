@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Rule modifier applicable to the rounds of any game mode
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutator {
+    /// The movement threshold of everyone wobbles periodically
+    Earthquake,
+
+    /// All controllers go dark for a few seconds from time to time
+    Blackout,
+
+    /// The music plays at double speed for a short while from time to time
+    SpeedDemon,
+}
+
+/// The mutators applied to a round and how they currently affect it
+#[derive(Debug, Clone, Default)]
+pub struct Mutators {
+    enabled: HashSet<Mutator>,
+
+    // Time the round was played so far
+    elapsed: Duration,
+}
+
+impl Mutators {
+    // Time for the threshold to wobble up and down and how far it wobbles
+    const EARTHQUAKE_PERIOD: f32 = 10.0;
+    const EARTHQUAKE_AMPLITUDE: f32 = 0.4;
+
+    // Time between blackouts and how long they last
+    const BLACKOUT_PERIOD: Duration = Duration::from_secs(30);
+    const BLACKOUT_DURATION: Duration = Duration::from_secs(5);
+
+    // Time between bursts of fast music, how long they last and how fast the music plays
+    const SPEED_DEMON_PERIOD: Duration = Duration::from_secs(40);
+    const SPEED_DEMON_DURATION: Duration = Duration::from_secs(5);
+    const SPEED_DEMON_SPEED: f32 = 2.0;
+
+    pub fn new(enabled: &HashSet<Mutator>) -> Self {
+        if !enabled.is_empty() {
+            debug!("Round mutated by {:?}", enabled);
+        }
+
+        return Self {
+            enabled: enabled.clone(),
+            elapsed: Duration::ZERO,
+        };
+    }
+
+    pub fn update(&mut self, duration: Duration) {
+        self.elapsed += duration;
+    }
+
+    /// Whether the effect repeating every `period` is active - each lasts for `duration` at the
+    /// end of the period
+    fn burst(&self, period: Duration, duration: Duration) -> bool {
+        let phase = self.elapsed.as_secs_f64() % period.as_secs_f64();
+        return phase >= (period - duration).as_secs_f64();
+    }
+
+    /// Scale of the movement threshold of the round
+    pub fn threshold(&self) -> f32 {
+        if !self.enabled.contains(&Mutator::Earthquake) {
+            return 1.0;
+        }
+
+        let phase = self.elapsed.as_secs_f32() / Self::EARTHQUAKE_PERIOD * TAU;
+        return 1.0 + Self::EARTHQUAKE_AMPLITUDE * phase.sin();
+    }
+
+    /// Scale of the LED brightness of all controllers
+    pub fn brightness(&self) -> f32 {
+        if self.enabled.contains(&Mutator::Blackout) && self.burst(Self::BLACKOUT_PERIOD, Self::BLACKOUT_DURATION) {
+            return 0.0;
+        }
+
+        return 1.0;
+    }
+
+    /// Speed of all music on top of the speed chosen by the game
+    pub fn music_speed(&self) -> f32 {
+        if self.enabled.contains(&Mutator::SpeedDemon) && self.burst(Self::SPEED_DEMON_PERIOD, Self::SPEED_DEMON_DURATION) {
+            return Self::SPEED_DEMON_SPEED;
+        }
+
+        return 1.0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_effects() {
+        let mut mutators = Mutators::new(&HashSet::from([Mutator::Blackout, Mutator::Earthquake]));
+        assert_eq!(mutators.brightness(), 1.0);
+        assert_eq!(mutators.threshold(), 1.0);
+
+        mutators.update(Duration::from_secs_f32(Mutators::EARTHQUAKE_PERIOD / 4.0));
+        assert!((mutators.threshold() - 1.4).abs() < 0.001);
+
+        // Blackouts last until the end of each period
+        mutators.update(Duration::from_secs(25) - mutators.elapsed);
+        assert_eq!(mutators.brightness(), 0.0);
+
+        mutators.update(Duration::from_secs(5));
+        assert_eq!(mutators.brightness(), 1.0);
+
+        // Mutators not enabled have no effect
+        mutators.update(Duration::from_secs(5) * 7);
+        assert_eq!(mutators.music_speed(), 1.0);
+    }
+}
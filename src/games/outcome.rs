@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+
+use tracing::debug;
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::events::Event;
+use crate::meta::celebration::Celebration;
+use crate::state::{State, World};
+
+/// How a game has ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The game was decided in favour of the given players
+    Winners(HashSet<PlayerId>),
+
+    /// The last players went out at the same time
+    Draw,
+
+    /// Nobody is left to decide the game - e.g. because all players disconnected
+    Aborted,
+}
+
+impl Outcome {
+    /// Ends the running game by celebrating the outcome - aborted games return to the lobby
+    pub fn finish(self, world: &mut World) -> State {
+        return match self {
+            Outcome::Winners(winners) => State::Celebration(Celebration::new(winners, world)),
+            Outcome::Draw => State::Celebration(Celebration::draw(world)),
+            Outcome::Aborted => {
+                debug!("Game aborted - back to lobby");
+                world.events.emit(Event::GameAborted);
+
+                State::lobby(world.players)
+            }
+        };
+    }
+}
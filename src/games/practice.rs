@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{hues, joust, reset_color, start};
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    hue: f64,
+
+    // Whether the player exceeded the threshold in the last update
+    exceeded: bool,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "practice",
+    description: "Warm-up with the feedback of a joust but without eliminating anybody",
+    min_players: 1,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Practice>(players, seconds, world),
+    teams: None,
+};
+
+/// Warm-up with the feedback of a joust at normal pacing but without eliminating anybody - runs
+/// until cancelled
+pub struct Practice {
+    data: PlayerData<Player>,
+
+    // Threshold of a joust at normal pacing
+    threshold: f32,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Game<Settings, Event, Scoring> for Practice {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+        let threshold = self.threshold * world.threshold();
+        let theme = world.settings.theme;
+
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let accel = player.acceleration(true) / (threshold * player.threshold());
+
+            // Buzz once whenever the player would have been eliminated
+            let exceeded = accel >= 1.0;
+            if exceeded && !data.exceeded {
+                player.haptic(HapticPattern::BUZZ);
+            }
+            data.exceeded = exceeded;
+
+            player.color.set(theme.hue(data.hue, 1.0 - f32::sqrt(accel.min(1.0)) as f64));
+
+            return true;
+        });
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, _paused: Duration, _world: &mut World) {
+        self.music.resume();
+    }
+
+    fn reconfigure(&mut self, world: &mut World) {
+        self.threshold = world.settings.options.get::<joust::Options>(&joust::MODE).threshold_normal;
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Practice {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        let hues = hues(players.len(), world.rng);
+
+        let players = PlayerData::init_with(players.into_iter()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+                exceeded: false,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            threshold: world.settings.options.get::<joust::Options>(&joust::MODE).threshold_normal,
+            music,
+        };
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::games::{GameOptions, Lineup};
+use crate::state::{InvalidOptionsError, State, World};
+
+/// Description of a game mode and how to start it - each game registers one of these in the list
+/// of all modes
+pub struct Mode {
+    /// Unique name used to select the mode
+    pub name: &'static str,
+
+    /// Short explanation of the rules
+    pub description: &'static str,
+
+    /// Number of players required to start the mode
+    pub min_players: usize,
+
+    /// Number of players the mode can handle at most - unlimited if missing
+    pub max_players: Option<usize>,
+
+    /// The mode specific settings in the game options - if the mode has any
+    pub options: Option<OptionsType>,
+
+    /// Creates a game for the players counting down the given seconds before it starts
+    pub create: fn(HashSet<PlayerId>, &GameOptions, u32, &mut World) -> State,
+
+    /// Lets the players pick their teams before the game starts - if the mode is played in teams
+    pub teams: Option<Teams>,
+}
+
+/// The teams of a mode played in teams
+pub struct Teams {
+    /// Number of teams the players can be split into
+    pub min: usize,
+    pub max: usize,
+
+    /// Creates a game for the picked teams counting down the given seconds before it starts
+    pub create: fn(Vec<Lineup>, &GameOptions, u32, &mut World) -> State,
+}
+
+impl Mode {
+    /// Whether the mode can be played by the given number of players
+    pub fn accepts(&self, players: usize) -> bool {
+        return players >= self.min_players && self.max_players.map_or(true, |max| players <= max);
+    }
+}
+
+/// Settings of a mode kept in the game options under the name of the mode
+pub trait ModeOptions: Serialize + DeserializeOwned + Default {
+    /// Checks that the mode can be played with these settings
+    fn validate(&self) -> Result<(), InvalidOptionsError> {
+        return Ok(());
+    }
+}
+
+/// Handles the settings of a mode without knowing their type
+pub struct OptionsType {
+    /// The settings with their default values
+    pub defaults: fn() -> Value,
+
+    /// JSON schema of the settings
+    pub schema: fn() -> Value,
+
+    /// Checks the settings of the mode with the given name
+    pub validate: fn(&'static str, &Value) -> Result<(), InvalidOptionsError>,
+}
+
+impl OptionsType {
+    pub const fn of<T>() -> Self
+        where
+            T: ModeOptions,
+    {
+        return Self {
+            defaults: defaults::<T>,
+            schema: schema::<T>,
+            validate: validate::<T>,
+        };
+    }
+}
+
+fn defaults<T>() -> Value
+    where
+        T: ModeOptions,
+{
+    return serde_json::to_value(T::default()).expect("Failed to serialize default options");
+}
+
+fn validate<T>(name: &'static str, value: &Value) -> Result<(), InvalidOptionsError>
+    where
+        T: ModeOptions,
+{
+    let options = T::deserialize(value)
+        .map_err(|_| InvalidOptionsError { field: name, reason: "must match the schema of the mode" })?;
+    return options.validate();
+}
+
+/// Describes the settings of a mode as JSON schema derived from their default values
+pub fn schema<T>() -> Value
+    where
+        T: Serialize + Default,
+{
+    let defaults = serde_json::to_value(T::default()).expect("Failed to serialize default options");
+    return describe(defaults);
+}
+
+fn describe(value: Value) -> Value {
+    return match value {
+        // Optional settings without a default - the type is unknown
+        Value::Null => json!({}),
+        Value::Bool(_) => json!({ "type": "boolean", "default": value }),
+        Value::Number(ref number) if number.is_f64() => json!({ "type": "number", "default": value }),
+        Value::Number(_) => json!({ "type": "integer", "default": value }),
+        Value::String(_) => json!({ "type": "string", "default": value }),
+        Value::Array(_) => json!({ "type": "array", "default": value }),
+        Value::Object(fields) => json!({
+            "type": "object",
+            "properties": fields.into_iter()
+                .map(|(name, value)| (name, describe(value)))
+                .collect::<Map<_, _>>(),
+        }),
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Serialize, Default)]
+    struct Options {
+        lives: usize,
+        threshold: f32,
+        round: Option<f32>,
+    }
+
+    #[test]
+    fn test_schema() {
+        assert_eq!(schema::<Options>(), json!({
+            "type": "object",
+            "properties": {
+                "lives": { "type": "integer", "default": 0 },
+                "threshold": { "type": "number", "default": 0.0 },
+                "round": {},
+            },
+        }));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{countdown, hues, Lineup, reset_color, start, teams};
+use crate::games::outcome::Outcome;
+use crate::games::registry::{Mode, Teams};
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    hue: f64,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+struct Team {
+    hue: f64,
+
+    // Order in which the team members run their legs
+    roster: Vec<PlayerId>,
+
+    // The leg currently run and the energy collected for it
+    leg: usize,
+    energy: f32,
+}
+
+impl Team {
+    /// The team member holding the baton - smaller teams start over with their first member
+    fn runner(&self) -> Option<PlayerId> {
+        if self.roster.is_empty() {
+            return None;
+        }
+
+        return Some(self.roster[self.leg % self.roster.len()]);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "relay",
+    description: "Teams race against each other by shaking the controllers in turns",
+    min_players: 4,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Relay>(players, seconds, world),
+    teams: Some(Teams {
+        min: Relay::TEAMS_MIN,
+        max: Relay::TEAMS_MAX,
+        create: |teams, _, seconds, world| countdown(Relay::with_teams(teams, world), seconds, world),
+    }),
+};
+
+/// Teams race against each other - the team member holding the baton must shake the controller
+/// until the leg is done and then passes the baton to the next team member
+pub struct Relay {
+    data: PlayerData<Player>,
+
+    teams: Vec<Team>,
+
+    // Legs each team has to run
+    legs: usize,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Relay {
+    // Players per team and bounds of the number of teams
+    const TEAM_SIZE: usize = 3;
+    const TEAMS_MIN: usize = 2;
+    const TEAMS_MAX: usize = 4;
+
+    // Shake energy to finish a leg
+    const LEG_ENERGY: f32 = 4.0;
+
+    // Brightness of the team members waiting for the baton
+    const WAITING_DIM: f64 = 0.1;
+
+    /// Creates a race between the given teams
+    fn with_teams(teams: Vec<Lineup>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        let teams = teams.into_iter()
+            .map(|lineup| Team {
+                hue: lineup.hue,
+                roster: lineup.roster,
+                leg: 0,
+                energy: 0.0,
+            })
+            .collect::<Vec<_>>();
+
+        let legs = teams.iter()
+            .map(|team| team.roster.len())
+            .max()
+            .unwrap_or(0);
+
+        let players = PlayerData::init_with(teams.iter()
+            .flat_map(|team| team.roster.iter().map(|id| (*id, Player {
+                hue: team.hue,
+            })))
+            .collect());
+
+        return Self {
+            data: players,
+            teams,
+            legs,
+            music,
+        };
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Relay {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, duration: Duration, _session: &Session) -> Option<State> {
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+
+        for team in self.teams.iter_mut() {
+            let runner = match team.runner() {
+                Some(runner) => runner,
+                None => continue,
+            };
+
+            if let Some(player) = world.players.get(runner) {
+                team.energy += player.acceleration(false) / (threshold * player.threshold()) * duration.as_secs_f32();
+            }
+
+            // Pass the baton to the next team member
+            if team.energy >= Self::LEG_ENERGY {
+                team.leg += 1;
+                team.energy = 0.0;
+
+                if let Some(player) = world.players.get_mut(runner) {
+                    player.haptic(HapticPattern::TAP);
+                }
+
+                if team.leg >= self.legs {
+                    let winners = team.roster.iter().copied().collect();
+                    return Some(Outcome::Winners(winners).finish(world));
+                }
+
+                if let Some(player) = team.runner().and_then(|runner| world.players.get_mut(runner)) {
+                    player.haptic(HapticPattern::BUZZ);
+                }
+            }
+
+            // The runner shows the progress of the leg while the others wait dimmed
+            let runner = team.runner();
+            let progress = (team.energy / Self::LEG_ENERGY) as f64;
+            for id in team.roster.iter() {
+                if let Some(player) = world.players.get_mut(*id) {
+                    player.color.set(if Some(*id) == runner {
+                        theme.hue(team.hue, 0.3 + 0.7 * progress)
+                    } else {
+                        theme.hue(team.hue, Self::WAITING_DIM)
+                    });
+                }
+            }
+        }
+
+        if self.teams.iter().all(|team| team.roster.is_empty()) {
+            return Some(Outcome::Aborted.finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if !self.data.remove(player) {
+            return false;
+        }
+
+        for team in self.teams.iter_mut() {
+            // The next team member takes over the leg of a kicked runner
+            if team.runner() == Some(player) {
+                team.energy = 0.0;
+            }
+
+            team.roster.retain(|id| *id != player);
+        }
+
+        reset_color(player, world);
+
+        return true;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, _paused: Duration, _world: &mut World) {
+        self.music.resume();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Relay {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        // Sorted to shuffle the same way for the same seed
+        let mut order = players.iter().copied().collect::<Vec<_>>();
+        order.sort();
+        order.shuffle(world.rng);
+
+        let count = (order.len() / Self::TEAM_SIZE).clamp(Self::TEAMS_MIN, Self::TEAMS_MAX);
+
+        let hues = hues(count, world.rng);
+
+        let teams = teams(&order, count).into_iter()
+            .zip(hues)
+            .map(|(roster, hue)| Lineup {
+                hue,
+                roster,
+            })
+            .collect();
+
+        return Self::with_teams(teams, world);
+    }
+}
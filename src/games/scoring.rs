@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::events::Event;
+
+/// Results of a player accumulated over all rounds of the session
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Record {
+    pub rounds: usize,
+    pub wins: usize,
+    pub eliminations: usize,
+
+    /// Time spent in the rounds until eliminated or until the round was finished
+    pub survival: Duration,
+}
+
+/// Snapshot of the session leaderboard
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoringReport {
+    pub rounds: usize,
+    pub leaderboard: Vec<(PlayerId, Record)>,
+}
+
+struct Round {
+    started: Instant,
+    players: HashSet<PlayerId>,
+
+    // The time the round was paused
+    paused: Option<Instant>,
+
+    // Time each eliminated player survived in the round
+    eliminated: HashMap<PlayerId, Duration>,
+}
+
+/// Records the results of every round played in the running session from the emitted events
+pub struct Scoring {
+    events: broadcast::Receiver<Event>,
+
+    // The round currently played
+    round: Option<Round>,
+
+    rounds: usize,
+    records: HashMap<PlayerId, Record>,
+}
+
+impl Scoring {
+    pub fn new(events: broadcast::Receiver<Event>) -> Self {
+        return Self {
+            events,
+            round: None,
+            rounds: 0,
+            records: HashMap::new(),
+        };
+    }
+
+    /// Records all events emitted since the last update. Returns whether there were any.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let mut recorded = false;
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => {
+                    self.record(&event, now);
+                    recorded = true;
+                }
+
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    warn!("Scoring missed {} events", skipped);
+                }
+
+                Err(broadcast::error::TryRecvError::Empty | broadcast::error::TryRecvError::Closed) => {
+                    return recorded;
+                }
+            }
+        }
+    }
+
+    fn record(&mut self, event: &Event, now: Instant) {
+        match event {
+            Event::GameStarted { players, .. } => {
+                self.round = Some(Round {
+                    started: now,
+                    players: players.clone(),
+                    paused: None,
+                    eliminated: HashMap::new(),
+                });
+            }
+
+            Event::GamePaused => {
+                if let Some(round) = self.round.as_mut() {
+                    round.paused.get_or_insert(now);
+                }
+            }
+
+            // Time spent paused does not count as survived
+            Event::GameResumed => {
+                if let Some(round) = self.round.as_mut() {
+                    if let Some(paused) = round.paused.take() {
+                        round.started += now - paused;
+                    }
+                }
+            }
+
+            // Late arrivals take part in the round from now on
+            Event::PlayerJoined { player } => {
+                if let Some(round) = self.round.as_mut() {
+                    round.players.insert(*player);
+                }
+            }
+
+            Event::PlayerEliminated { player, .. } => {
+                if let Some(round) = self.round.as_mut().filter(|round| round.players.contains(player)) {
+                    round.eliminated.entry(*player).or_insert(now - round.started);
+                }
+            }
+
+            Event::GameFinished { winners } | Event::RoundFinished { winners } => {
+                if let Some(round) = self.round.take() {
+                    self.finish(round, winners, now);
+                }
+            }
+
+            Event::GameDrawn => {
+                if let Some(round) = self.round.take() {
+                    self.finish(round, &HashSet::new(), now);
+                }
+            }
+
+            // Aborted rounds do not count
+            Event::GameCancelled | Event::GameAborted | Event::EmergencyStop => {
+                self.round = None;
+            }
+
+            _ => {}
+        }
+    }
+
+    fn finish(&mut self, round: Round, winners: &HashSet<PlayerId>, now: Instant) {
+        let age = now - round.started;
+
+        for player in round.players {
+            let record = self.records.entry(player).or_default();
+            record.rounds += 1;
+
+            if winners.contains(&player) {
+                record.wins += 1;
+            }
+
+            match round.eliminated.get(&player) {
+                Some(survival) => {
+                    record.eliminations += 1;
+                    record.survival += *survival;
+                }
+                None => record.survival += age,
+            }
+        }
+
+        self.rounds += 1;
+    }
+
+    /// Continues the session from an earlier report - the round currently played is dropped
+    pub fn restore(&mut self, report: ScoringReport) {
+        self.round = None;
+        self.rounds = report.rounds;
+        self.records = report.leaderboard.into_iter().collect();
+    }
+
+    /// Number of rounds finished in the session
+    pub fn rounds(&self) -> usize {
+        return self.rounds;
+    }
+
+    pub fn get(&self, player: PlayerId) -> Record {
+        return self.records.get(&player).copied().unwrap_or_default();
+    }
+
+    /// All players of the session ranked by their wins and the time survived
+    pub fn leaderboard(&self) -> Vec<(PlayerId, Record)> {
+        let mut leaderboard = self.records.iter()
+            .map(|(player, record)| (*player, *record))
+            .collect::<Vec<_>>();
+        leaderboard.sort_by(|a, b| b.1.wins.cmp(&a.1.wins)
+            .then(b.1.survival.cmp(&a.1.survival))
+            .then(a.0.cmp(&b.0)));
+        return leaderboard;
+    }
+
+    pub fn report(&self) -> ScoringReport {
+        return ScoringReport {
+            rounds: self.rounds,
+            leaderboard: self.leaderboard(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    use crate::events::Events;
+    use crate::games::{GameMode, joust};
+    use crate::games::elimination::Cause;
+
+    #[test]
+    fn test_scoring() {
+        let events = Events::new();
+        let mut scoring = Scoring::new(events.subscribe());
+
+        let now = Instant::now();
+        events.emit(Event::GameStarted { mode: GameMode::of(&joust::MODE), players: HashSet::from([1, 2, 3]) });
+        scoring.update(now);
+
+        events.emit(Event::PlayerEliminated { player: 3, cause: Cause::Movement, time: SystemTime::UNIX_EPOCH });
+        scoring.update(now + Duration::from_secs(10));
+
+        events.emit(Event::PlayerEliminated { player: 2, cause: Cause::Timeout, time: SystemTime::UNIX_EPOCH });
+        events.emit(Event::GameFinished { winners: HashSet::from([1]) });
+        scoring.update(now + Duration::from_secs(30));
+
+        assert_eq!(scoring.rounds(), 1);
+        assert_eq!(scoring.get(1), Record { rounds: 1, wins: 1, eliminations: 0, survival: Duration::from_secs(30) });
+        assert_eq!(scoring.get(3), Record { rounds: 1, wins: 0, eliminations: 1, survival: Duration::from_secs(10) });
+
+        // Cancelled rounds are not recorded
+        events.emit(Event::GameStarted { mode: GameMode::of(&joust::MODE), players: HashSet::from([1, 2]) });
+        events.emit(Event::GameCancelled);
+        scoring.update(now + Duration::from_secs(40));
+        assert_eq!(scoring.rounds(), 1);
+
+        let ranking = scoring.leaderboard().into_iter()
+            .map(|(player, _)| player)
+            .collect::<Vec<_>>();
+        assert_eq!(ranking, vec![1, 2, 3]);
+    }
+}
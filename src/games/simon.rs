@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+use tracing::debug;
+
+use hastilude_core::engine::gestures::Gesture;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{self, PlayerData, PlayerId};
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{reset_color, start};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+#[derive(Default)]
+pub struct Player {
+    // Reaction time of the current prompt once answered
+    answered: Option<Duration>,
+
+    // Sum and number of all reaction times
+    reaction: Duration,
+    answers: u32,
+}
+
+impl Player {
+    fn average(&self) -> Option<Duration> {
+        return self.reaction.checked_div(self.answers);
+    }
+}
+
+impl PlayerColor for Player {
+    fn color(&self, _theme: Theme) -> RGBColor {
+        return RGBColor { r: 1.0, g: 1.0, b: 1.0 };
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Target {
+    Square,
+    Triangle,
+    Cross,
+    Circle,
+    Shake,
+}
+
+impl Target {
+    const ALL: [Target; 5] = [Target::Square, Target::Triangle, Target::Cross, Target::Circle, Target::Shake];
+
+    /// The color close to the symbol printed on the button - shaking is white
+    fn color(self, theme: Theme) -> RGBColor {
+        return match self {
+            Target::Square => theme.hue(0.92, 1.0),
+            Target::Triangle => theme.hue(0.39, 1.0),
+            Target::Cross => theme.hue(0.7, 1.0),
+            Target::Circle => theme.hue(0.0, 1.0),
+            Target::Shake => RGBColor { r: 1.0, g: 1.0, b: 1.0 },
+        };
+    }
+
+    /// Frequency of the tone played along with the color
+    fn tone(self) -> f32 {
+        return match self {
+            Target::Square => 261.63,
+            Target::Triangle => 329.63,
+            Target::Cross => 392.00,
+            Target::Circle => 523.25,
+            Target::Shake => 196.00,
+        };
+    }
+
+    /// The target a player reacted with in this frame
+    fn answer(player: &players::Player) -> Option<Self> {
+        let buttons = &player.input().buttons;
+
+        if buttons.square.pressed() {
+            return Some(Target::Square);
+        }
+
+        if buttons.triangle.pressed() {
+            return Some(Target::Triangle);
+        }
+
+        if buttons.cross.pressed() {
+            return Some(Target::Cross);
+        }
+
+        if buttons.circle.pressed() {
+            return Some(Target::Circle);
+        }
+
+        if player.gestures().contains(&Gesture::Shake) {
+            return Some(Target::Shake);
+        }
+
+        return None;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Phase {
+    /// All controllers are dark until the next prompt
+    Pause {
+        until: Instant,
+    },
+
+    /// All controllers show the target and players must react before the deadline
+    Prompt {
+        target: Target,
+        shown: Instant,
+        deadline: Instant,
+    },
+}
+
+pub static MODE: Mode = Mode {
+    name: "simon",
+    description: "React to the shown color by pressing the matching button or shaking the controller",
+    min_players: 2,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Simon>(players, seconds, world),
+    teams: None,
+};
+
+/// Players must react to the shown color by pressing the matching button or shaking the controller
+/// - reacting wrong or too late eliminates
+pub struct Simon {
+    data: PlayerData<Player>,
+
+    phase: Option<Phase>,
+
+    // Number of prompts shown so far
+    prompts: u32,
+}
+
+impl Simon {
+    // Random pause between two prompts
+    const PAUSE: Range<Duration> = (Duration::from_millis(1000) .. Duration::from_millis(3000));
+
+    // Time to react to the first prompt and the shortest time to react
+    const WINDOW_START: Duration = Duration::from_millis(2500);
+    const WINDOW_MIN: Duration = Duration::from_millis(600);
+
+    // Shrinking of the reaction window per prompt
+    const WINDOW_RAMP: f32 = 0.92;
+
+    const CUE_DUR: Duration = Duration::from_millis(300);
+
+    /// Time to react to the given prompt
+    fn window(prompt: u32) -> Duration {
+        return Self::WINDOW_START
+            .mul_f32(Self::WINDOW_RAMP.powi(prompt as i32))
+            .max(Self::WINDOW_MIN);
+    }
+
+    fn pause(now: Instant, rng: &mut impl Rng) -> Phase {
+        return Phase::Pause {
+            until: now + rng.gen_range(Self::PAUSE),
+        };
+    }
+
+    fn prompt(&mut self, world: &mut World) -> Phase {
+        let target = *Target::ALL.choose(world.rng).expect("Targets not empty");
+
+        let window = Self::window(self.prompts);
+        self.prompts += 1;
+
+        for (_, data) in self.data.iter_mut() {
+            data.answered = None;
+        }
+
+        debug!("Prompt {}: {:?} within {:?}", self.prompts, target, window);
+        world.sound.tone(target.tone(), Self::CUE_DUR);
+
+        return Phase::Prompt {
+            target,
+            shown: world.now,
+            deadline: world.now + window,
+        };
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Simon {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+        let now = world.now;
+        let theme = world.settings.theme;
+
+        // Players still in the game before this frame
+        let remaining = self.data.iter()
+            .map(|(id, data)| (id, data.average()))
+            .collect::<Vec<_>>();
+
+        match *self.phase.get_or_insert_with(|| Self::pause(now, world.rng)) {
+            Phase::Pause { until } => {
+                for (id, _) in self.data.iter() {
+                    if let Some(player) = world.players.get_mut(id) {
+                        player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+                    }
+                }
+
+                if until <= now {
+                    self.phase = Some(self.prompt(world));
+                }
+            }
+
+            Phase::Prompt { target, shown, deadline } => {
+                let mut eliminated = Vec::new();
+                world.players.with_data(&mut self.data).update(|player, data| {
+                    // Players already answered are dimmed until the next prompt
+                    if data.answered.is_some() {
+                        player.color.set(RGBColor { r: 0.1, g: 0.1, b: 0.1 });
+                        return true;
+                    }
+
+                    let failed = match Target::answer(player) {
+                        Some(answer) if answer == target => {
+                            data.answered = Some(now - shown);
+                            data.reaction += now - shown;
+                            data.answers += 1;
+
+                            player.haptic(HapticPattern::TAP);
+                            None
+                        }
+
+                        Some(_) => Some(Cause::Mistake),
+                        None if deadline <= now => Some(Cause::Timeout),
+                        None => None,
+                    };
+
+                    if let Some(cause) = failed {
+                        eliminated.push(Elimination::new(player.id(), cause));
+                        return false;
+                    }
+
+                    player.color.set(target.color(theme));
+
+                    return true;
+                });
+
+                for elimination in eliminated {
+                    elimination.apply(world);
+                }
+
+                if deadline <= now || self.data.iter().all(|(_, data)| data.answered.is_some()) {
+                    self.phase = Some(Self::pause(now, world.rng));
+                }
+            }
+        }
+
+        if self.data.len() == 1 {
+            return Some(Outcome::Winners(self.data.keys().collect()).finish(world));
+        }
+
+        if self.data.len() == 0 {
+            // All remaining players failed at once - the ones reacting fastest so far win
+            let fastest = remaining.iter()
+                .filter_map(|(_, average)| *average)
+                .min();
+
+            // Nobody has answered yet to tell the fastest
+            if fastest.is_none() {
+                return Some(Outcome::Draw.finish(world));
+            }
+
+            let winners = remaining.iter()
+                .filter(|(_, average)| *average == fastest)
+                .map(|(id, _)| *id)
+                .collect::<HashSet<_>>();
+
+            return Some(Outcome::Winners(winners).finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        match self.phase.as_mut() {
+            Some(Phase::Pause { until }) => *until += paused,
+            Some(Phase::Prompt { shown, deadline, .. }) => {
+                *shown += paused;
+                *deadline += paused;
+            }
+            None => {}
+        }
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Simon {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, _world: &mut World) -> Self {
+        return Self {
+            data: PlayerData::init(players, Player::default),
+            phase: None,
+            prompts: 0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        assert_eq!(Simon::window(0), Simon::WINDOW_START);
+        assert!(Simon::window(5) < Simon::window(4));
+        assert_eq!(Simon::window(100), Simon::WINDOW_MIN);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use scarlet::color::RGBColor;
+use tracing::debug;
+
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{hues, reset_color, start};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    hue: f64,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "statues",
+    description: "The music stops at random - moving while it is silent eliminates",
+    min_players: 2,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Statues>(players, seconds, world),
+    teams: None,
+};
+
+/// The music stops at random - players moving while it is silent are eliminated
+pub struct Statues {
+    data: PlayerData<Player>,
+
+    // The time the music is paused or resumed next
+    toggle: Option<Instant>,
+
+    // The time the music was paused
+    paused: Option<Instant>,
+
+    music: Playback,
+}
+
+impl Statues {
+    // Minimum / maximum time the music plays or stays silent
+    const PLAYING_DUR: Range<Duration> = (Duration::from_secs(4) .. Duration::from_secs(12));
+    const PAUSED_DUR: Range<Duration> = (Duration::from_secs(2) .. Duration::from_secs(6));
+
+    // Time to come to a halt after the music stopped
+    const GRACE: Duration = Duration::from_millis(600);
+
+    // Movement eliminating a player while the music is stopped
+    const THRESHOLD: f32 = 0.15;
+}
+
+impl Game<Settings, Event, Scoring> for Statues {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+        let now = world.now;
+
+        // Pause and resume the music at random
+        if *self.toggle.get_or_insert_with(|| now + world.rng.gen_range(Self::PLAYING_DUR)) <= now {
+            let duration = if self.music.paused() {
+                self.music.resume();
+                self.paused = None;
+                world.rng.gen_range(Self::PLAYING_DUR)
+            } else {
+                self.music.pause();
+                self.paused = Some(now);
+                world.rng.gen_range(Self::PAUSED_DUR)
+            };
+
+            debug!("Music {} at {:?}", if self.music.paused() { "paused" } else { "resumed" }, self.music.position());
+            self.toggle = Some(now + duration);
+        }
+
+        // Movement only counts once the music is stopped for a moment
+        let checked = self.music.paused() && self.paused.map_or(false, |paused| now - paused >= Self::GRACE);
+
+        let mut eliminated = Vec::new();
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let accel = player.acceleration(true) / (Self::THRESHOLD * threshold * player.threshold());
+
+            if checked && accel >= 1.0 {
+                eliminated.push(Elimination::new(player.id(), Cause::Movement));
+
+                return false;
+            }
+
+            // Players show their movement while the music is stopped
+            player.color.set(theme.hue(data.hue, if checked { 1.0 - f32::sqrt(accel.min(1.0)) as f64 } else { 1.0 }));
+
+            return true;
+        });
+
+        for elimination in eliminated {
+            elimination.apply(world);
+        }
+
+        if self.data.len() == 1 {
+            return Some(Outcome::Winners(self.data.keys().collect()).finish(world));
+        }
+
+        if self.data.len() == 0 {
+            // The last players went out together
+            return Some(Outcome::Draw.finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        // The music might already be stopped as part of the game
+        if self.paused.is_none() {
+            self.music.pause();
+        }
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        self.toggle = self.toggle.map(|toggle| toggle + paused);
+        self.paused = self.paused.map(|since| since + paused);
+
+        if self.paused.is_none() {
+            self.music.resume();
+        }
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Statues {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        let hues = hues(players.len(), world.rng);
+
+        let players = PlayerData::init_with(players.into_iter()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            toggle: None,
+            paused: None,
+            music,
+        };
+    }
+}
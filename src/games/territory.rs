@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::gestures::Gesture;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{countdown, hues, Lineup, reset_color, start, teams};
+use crate::games::outcome::Outcome;
+use crate::games::registry::{Mode, Teams};
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    team: usize,
+    hue: f64,
+
+    // Time since the controller is lying still
+    still: Option<Instant>,
+
+    base: bool,
+
+    // Whether the player already raided in the current raid
+    raided: bool,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+pub static MODE: Mode = Mode {
+    name: "territory",
+    description: "Teams claim bases by placing controllers still and raid the bases of the others",
+    min_players: 4,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Territory>(players, seconds, world),
+    teams: Some(Teams {
+        min: 2,
+        max: 2,
+        create: |teams, _, seconds, world| countdown(Territory::with_teams(teams, world), seconds, world),
+    }),
+};
+
+/// Teams claim territory by placing controllers perfectly still - each of these bases scores for
+/// its team while the players still holding their controller raid the bases of other teams by
+/// shaking when the raid cue plays
+pub struct Territory {
+    data: PlayerData<Player>,
+
+    // Points of each team
+    points: Vec<f32>,
+
+    // The start of the next raid and the end of the current raid
+    next_raid: Option<Instant>,
+    raiding: Option<Instant>,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Territory {
+    const ROUND: Duration = Duration::from_secs(180);
+
+    // Movement still considered lying still and the time until a still controller becomes a base
+    const STILL_THRESHOLD: f32 = 0.02;
+    const STILL_DUR: Duration = Duration::from_secs(5);
+
+    // Points per second for each base
+    const BASE_POINTS: f32 = 1.0;
+
+    // Time between raids and the time a raid lasts
+    const RAID_INTERVAL: Duration = Duration::from_secs(20);
+    const RAID_DUR: Duration = Duration::from_secs(4);
+
+    const RAID_TONE: f32 = 880.0;
+
+    /// Knocks out a random base of another team
+    fn raid(&mut self, team: usize, world: &mut World) {
+        // Sorted to pick the same base for the same seed
+        let mut bases = self.data.iter()
+            .filter(|(_, data)| data.base && data.team != team)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        bases.sort();
+
+        let target = bases.choose(world.rng).copied();
+
+        let (target, data) = match target.and_then(|target| Some((target, self.data.get_mut(target)?))) {
+            Some(target) => target,
+            None => return,
+        };
+
+        data.base = false;
+        data.still = None;
+
+        if let Some(player) = world.players.get_mut(target) {
+            player.haptic(HapticPattern::BUZZ);
+        }
+    }
+
+    /// Creates a game between the given teams
+    fn with_teams(teams: Vec<Lineup>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        let players = PlayerData::init_with(teams.iter()
+            .enumerate()
+            .flat_map(|(team, lineup)| lineup.roster.iter().map(move |id| (*id, Player {
+                team,
+                hue: lineup.hue,
+                still: None,
+                base: false,
+                raided: false,
+            })))
+            .collect());
+
+        return Self {
+            data: players,
+            points: vec![0.0; teams.len()],
+            next_raid: None,
+            raiding: None,
+            music,
+        };
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Territory {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, duration: Duration, session: &Session) -> Option<State> {
+        let now = world.now;
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+
+        // Start the next raid with a cue
+        if *self.next_raid.get_or_insert(now + Self::RAID_INTERVAL) <= now {
+            world.sound.tone(Self::RAID_TONE, Duration::from_millis(300));
+            world.announce("raid");
+
+            for (_, data) in self.data.iter_mut() {
+                data.raided = false;
+            }
+
+            self.raiding = Some(now + Self::RAID_DUR);
+            self.next_raid = Some(now + Self::RAID_INTERVAL);
+        }
+
+        let raiding = self.raiding.map_or(false, |ends| ends > now);
+
+        // Detect controllers lying still and players raiding
+        let mut raiders = Vec::new();
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let accel = player.acceleration(true) / (threshold * player.threshold());
+
+            if accel >= Self::STILL_THRESHOLD {
+                if data.base {
+                    player.haptic(HapticPattern::TAP);
+                }
+
+                data.still = None;
+                data.base = false;
+            } else {
+                let still = *data.still.get_or_insert(now);
+                if !data.base && now - still >= Self::STILL_DUR {
+                    data.base = true;
+                }
+            }
+
+            if raiding && !data.base && !data.raided && player.gestures().contains(&Gesture::Shake) {
+                data.raided = true;
+                raiders.push(data.team);
+            }
+
+            // Bases show the team color while the others show the time left to become a base -
+            // flashing white while raiding
+            player.color.set(match (data.base, data.still) {
+                (true, _) => theme.hue(data.hue, 1.0),
+                (false, _) if raiding && !data.raided => RGBColor { r: 1.0, g: 1.0, b: 1.0 },
+                (false, Some(still)) => theme.hue(data.hue, 0.2 + 0.5 * ((now - still).as_secs_f64() / Self::STILL_DUR.as_secs_f64())),
+                (false, None) => theme.hue(data.hue, 0.2),
+            });
+
+            return true;
+        });
+
+        for team in raiders {
+            self.raid(team, world);
+        }
+
+        // Bases score for their team
+        for (_, data) in self.data.iter() {
+            if data.base {
+                self.points[data.team] += Self::BASE_POINTS * duration.as_secs_f32();
+            }
+        }
+
+        if session.remaining(now, Self::ROUND).is_zero() {
+            let best = self.points.iter().copied().fold(0.0, f32::max);
+
+            let winners = self.data.iter()
+                .filter(|(_, data)| self.points[data.team] >= best)
+                .map(|(id, _)| id)
+                .collect();
+
+            return Some(Outcome::Winners(winners).finish(world));
+        }
+
+        return None;
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        self.next_raid = self.next_raid.map(|next| next + paused);
+        self.raiding = self.raiding.map(|until| until + paused);
+
+        for (_, data) in self.data.iter_mut() {
+            data.still = data.still.map(|still| still + paused);
+        }
+
+        self.music.resume();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Territory {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        // Sorted to shuffle the same way for the same seed
+        let mut order = players.iter().copied().collect::<Vec<_>>();
+        order.sort();
+        order.shuffle(world.rng);
+
+        let hues = hues(2, world.rng);
+
+        let teams = teams(&order, 2).into_iter()
+            .zip(hues)
+            .map(|(roster, hue)| Lineup {
+                hue,
+                roster,
+            })
+            .collect();
+
+        return Self::with_teams(teams, world);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use tracing::debug;
+
+use hastilude_core::engine::players::{PlayerId, Players};
+use hastilude_core::game::Scores;
+
+use crate::games::{GameMode, GameOptions};
+use crate::games::mutators::Mutators;
+use crate::meta::celebration::Celebration;
+use crate::snapshot::{self, Goal, Progress};
+use crate::state::{JoinPlayerError, PauseGameError, State, World};
+
+/// The game mode and number of rounds of a tournament
+pub struct Setup {
+    /// Game mode played in each round - uses the selected game mode if missing
+    pub mode: Option<GameMode>,
+
+    pub rounds: usize,
+
+    /// Players taking part instead of the ready players
+    pub players: Option<HashSet<PlayerId>>,
+}
+
+/// Chains multiple rounds of a game mode and crowns the players winning the most rounds
+pub struct Tournament {
+    mode: GameMode,
+
+    // Options of the game mode for all rounds as set when starting the tournament
+    options: GameOptions,
+
+    rounds: usize,
+
+    players: HashSet<PlayerId>,
+
+    // Rounds won by each player
+    standings: Scores<u32>,
+
+    // Rounds finished so far
+    played: usize,
+
+    round: Box<State>,
+
+    // Waiting for the players to reconnect after a restart - the round is a placeholder until then
+    resuming: bool,
+}
+
+impl Tournament {
+    pub fn start(mode: GameMode, options: GameOptions, rounds: usize, players: HashSet<PlayerId>, world: &mut World) -> State {
+        debug!("Starting tournament of {} rounds {:?} with players {:?}", rounds, mode, players);
+
+        let round = mode.create(players.clone(), &options, world);
+
+        return State::Tournament(Self {
+            mode,
+            options,
+            rounds,
+            standings: Scores::new(players.iter().copied()),
+            players,
+            played: 0,
+            round: Box::new(round),
+            resuming: false,
+        });
+    }
+
+    /// Continues a tournament kept over a restart with the next round
+    pub fn restore(progress: Progress, rounds: usize, players: &mut Players) -> Self {
+        debug!("Restoring tournament {:?} after round {} of {}", progress.mode, progress.played, rounds);
+
+        return Self {
+            mode: progress.mode,
+            options: progress.options,
+            rounds,
+            players: progress.players,
+            standings: progress.standings,
+            played: progress.played,
+            round: Box::new(State::lobby(players)),
+            resuming: true,
+        };
+    }
+
+    pub fn update(mut self, world: &mut World, duration: Duration) -> State {
+        if self.resuming {
+            if !snapshot::resumable(&self.players, world) {
+                return State::Tournament(self);
+            }
+
+            self.resuming = false;
+            return self.next_round(world);
+        }
+
+        let celebrating = matches!(*self.round, State::Celebration(_));
+        let round = (*self.round).update(world, duration);
+
+        match round {
+            // The winners of a round score a point as soon as it is finished
+            State::Celebration(ref celebration) if !celebrating => {
+                for winner in celebration.winners() {
+                    self.standings.add(*winner, 1);
+                }
+
+                self.played += 1;
+                debug!("Finished tournament round {} of {}", self.played, self.rounds);
+            }
+
+            State::Lobby(_) => {
+                // Aborted rounds count as played without scoring
+                if !celebrating {
+                    self.played += 1;
+                    debug!("Aborted tournament round {} of {}", self.played, self.rounds);
+                }
+
+                self.round = Box::new(round);
+                return self.next_round(world);
+            }
+
+            _ => {}
+        }
+
+        self.round = Box::new(round);
+        return State::Tournament(self);
+    }
+
+    /// Starts the next round or crowns the winners if all rounds are played
+    fn next_round(mut self, world: &mut World) -> State {
+        // Only players still connected take part in the next round
+        let players = self.players.iter()
+            .copied()
+            .filter(|player| world.players.get(*player).is_some())
+            .collect::<HashSet<_>>();
+
+        if self.played >= self.rounds || players.len() < 2 || !self.mode.accepts(players.len()) {
+            return State::Celebration(Celebration::tournament(self.standings.leaders(), world));
+        }
+
+        self.round = Box::new(self.mode.create(players, &self.options, world));
+        return State::Tournament(self);
+    }
+
+    /// Removes a player from the tournament and the current round. Returns whether the player was
+    /// part of the tournament.
+    pub fn kick_player(mut self, player: PlayerId, world: &mut World) -> (State, bool) {
+        if !self.players.remove(&player) {
+            return (State::Tournament(self), false);
+        }
+
+        // Players gone can not be crowned
+        self.standings.remove(player);
+
+        // The player might already be out of the current round
+        let (round, _) = (*self.round).kick_player(player, world);
+        self.round = Box::new(round);
+
+        return (State::Tournament(self), true);
+    }
+
+    pub fn pause(mut self, world: &mut World) -> (State, Result<(), PauseGameError>) {
+        let (round, result) = (*self.round).pause(world);
+        self.round = Box::new(round);
+
+        return (State::Tournament(self), result);
+    }
+
+    pub fn resume(mut self, world: &mut World) -> (State, Result<(), PauseGameError>) {
+        let (round, result) = (*self.round).resume(world);
+        self.round = Box::new(round);
+
+        return (State::Tournament(self), result);
+    }
+
+    /// Whether the current round is paused
+    pub fn paused(&self) -> bool {
+        return self.round.paused();
+    }
+
+    /// Adds a player arriving late to the current round and all following rounds
+    pub fn join_player(mut self, player: PlayerId, world: &mut World) -> (State, Result<(), JoinPlayerError>) {
+        let (round, result) = (*self.round).join_player(player, world);
+        self.round = Box::new(round);
+
+        if result.is_ok() {
+            self.players.insert(player);
+        }
+
+        return (State::Tournament(self), result);
+    }
+
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return self.round.participants();
+    }
+
+    pub fn mutators(&self) -> Option<&Mutators> {
+        return self.round.mutators();
+    }
+
+    /// Name of the state of the current round
+    pub fn phase(&self) -> String {
+        if self.resuming {
+            return "resuming".to_owned();
+        }
+
+        return self.round.name();
+    }
+
+    pub fn mode(&self) -> GameMode {
+        return self.mode;
+    }
+
+    pub fn rounds(&self) -> usize {
+        return self.rounds;
+    }
+
+    pub fn played(&self) -> usize {
+        return self.played;
+    }
+
+    pub fn standings(&self) -> HashMap<PlayerId, u32> {
+        return self.players.iter()
+            .map(|player| (*player, self.standings.get(*player)))
+            .collect();
+    }
+
+    pub fn progress(&self) -> Progress {
+        return Progress {
+            mode: self.mode,
+            options: self.options.clone(),
+            goal: Goal::Rounds(self.rounds),
+            players: self.players.clone(),
+            standings: self.standings.clone(),
+            played: self.played,
+        };
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use scarlet::color::RGBColor;
+
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
+use hastilude_core::engine::sound::Playback;
+use hastilude_core::game::{Game, GameData, Session};
+
+use crate::events::Event;
+use crate::games::{hues, reset_color, start};
+use crate::games::elimination::{Cause, Elimination};
+use crate::games::outcome::Outcome;
+use crate::games::registry::Mode;
+use crate::games::scoring::Scoring;
+use crate::meta::countdown::PlayerColor;
+use crate::palette::Theme;
+use crate::state::{Settings, State, World};
+
+pub struct Player {
+    hue: f64,
+
+    traitor: bool,
+
+    // The suspect currently selected and the vote cast in this voting phase
+    candidate: usize,
+    vote: Option<PlayerId>,
+}
+
+impl PlayerColor for Player {
+    fn color(&self, theme: Theme) -> RGBColor {
+        return theme.hue(self.hue, 1.0);
+    }
+}
+
+/// The suspect with the most votes - `None` on a tie or if nobody voted
+fn tally(votes: impl IntoIterator<Item=PlayerId>) -> Option<PlayerId> {
+    let mut counts = HashMap::new();
+    for vote in votes {
+        *counts.entry(vote).or_insert(0usize) += 1;
+    }
+
+    let max = counts.values().copied().max()?;
+
+    let mut leaders = counts.into_iter()
+        .filter(|(_, count)| *count == max)
+        .map(|(player, _)| player);
+
+    return match (leaders.next(), leaders.next()) {
+        (Some(leader), None) => Some(leader),
+        _ => None,
+    };
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Phase {
+    /// Players joust until the round ends
+    Round,
+
+    /// Players select a suspect with the move button and vote for it by pressing cross while
+    /// holding the trigger
+    Voting,
+}
+
+pub static MODE: Mode = Mode {
+    name: "traitor",
+    description: "A joust with a secret traitor tolerating more movement - vote to eject suspects",
+    min_players: 3,
+    max_players: None,
+    options: None,
+    create: |players, _, seconds, world| start::<Traitor>(players, seconds, world),
+    teams: None,
+};
+
+/// A joust with a secret traitor tolerating more movement - the other players eject suspects by
+/// voting between the rounds
+pub struct Traitor {
+    data: PlayerData<Player>,
+
+    // The current phase and its end - starts with the first update
+    phase: Option<(Phase, Instant)>,
+
+    // Plays for as long as the game lasts
+    music: Playback,
+}
+
+impl Traitor {
+    const ROUND_DUR: Duration = Duration::from_secs(45);
+    const VOTING_DUR: Duration = Duration::from_secs(20);
+
+    // Movement eliminating a player
+    const THRESHOLD: f32 = 0.6;
+
+    // Additional movement tolerated for the traitor
+    const TRAITOR_TOLERANCE: f32 = 1.3;
+
+    fn traitor(&self) -> Option<PlayerId> {
+        return self.data.iter()
+            .find(|(_, data)| data.traitor)
+            .map(|(id, _)| id);
+    }
+
+    fn eliminate(&mut self, player: PlayerId, world: &mut World) {
+        self.data.remove(player);
+        Elimination::new(player, Cause::Vote).apply(world);
+    }
+
+    fn round(&mut self, world: &mut World) {
+        let mut eliminated = Vec::new();
+        let threshold = world.threshold();
+        let theme = world.settings.theme;
+
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let tolerance = if data.traitor { Self::TRAITOR_TOLERANCE } else { 1.0 };
+            let accel = player.acceleration(true) / (Self::THRESHOLD * threshold * tolerance * player.threshold());
+
+            if accel >= 1.0 {
+                eliminated.push(Elimination::new(player.id(), Cause::Movement));
+
+                return false;
+            }
+
+            player.color.set(theme.hue(data.hue, 1.0 - f32::sqrt(accel) as f64));
+
+            return true;
+        });
+
+        for elimination in eliminated {
+            elimination.apply(world);
+        }
+    }
+
+    fn voting(&mut self, world: &mut World) {
+        let mut suspects = self.data.iter()
+            .map(|(id, data)| (id, data.hue))
+            .collect::<Vec<_>>();
+        suspects.sort_by_key(|(id, _)| *id);
+
+        let theme = world.settings.theme;
+
+        world.players.with_data(&mut self.data).update(|player, data| {
+            let candidates = suspects.iter()
+                .filter(|(id, _)| *id != player.id())
+                .collect::<Vec<_>>();
+
+            let buttons = &player.input().buttons;
+            if buttons.swoosh.pressed() {
+                data.candidate += 1;
+            }
+
+            if let Some((candidate, hue)) = candidates.get(data.candidate % candidates.len().max(1)) {
+                if buttons.trigger.0.is_down() && buttons.cross.pressed() {
+                    data.vote = Some(*candidate);
+                    player.haptic(HapticPattern::TAP);
+                }
+
+                // Show the selected suspect and dim it once the vote is cast
+                player.color.set(theme.hue(*hue, if data.vote.is_some() { 0.3 } else { 1.0 }));
+            }
+
+            return true;
+        });
+    }
+}
+
+impl Game<Settings, Event, Scoring> for Traitor {
+    type Next = State;
+
+    fn update(&mut self, world: &mut World, _duration: Duration, _session: &Session) -> Option<State> {
+        let now = world.now;
+
+        // Only the traitor learns about its role
+        if self.phase.is_none() {
+            if let Some(player) = self.traitor().and_then(|traitor| world.players.get_mut(traitor)) {
+                player.haptic(HapticPattern::HEARTBEAT);
+            }
+        }
+
+        let (phase, ends) = *self.phase.get_or_insert((Phase::Round, now + Self::ROUND_DUR));
+
+        match phase {
+            Phase::Round => self.round(world),
+            Phase::Voting => self.voting(world),
+        }
+
+        if ends <= now {
+            match phase {
+                Phase::Round => {
+                    for (_, data) in self.data.iter_mut() {
+                        data.vote = None;
+                    }
+
+                    self.phase = Some((Phase::Voting, now + Self::VOTING_DUR));
+                    world.announce("vote");
+                }
+
+                Phase::Voting => {
+                    let votes = self.data.iter()
+                        .filter_map(|(_, data)| data.vote)
+                        .collect::<Vec<_>>();
+
+                    if let Some(ejected) = tally(votes) {
+                        self.eliminate(ejected, world);
+                    }
+
+                    self.phase = Some((Phase::Round, now + Self::ROUND_DUR));
+                }
+            }
+        }
+
+        if self.data.len() == 0 {
+            // The last players went out together
+            return Some(Outcome::Draw.finish(world));
+        }
+
+        // The others win once the traitor is gone while the traitor wins by outlasting them
+        return match self.traitor() {
+            None => Some(Outcome::Winners(self.data.keys().collect()).finish(world)),
+            Some(traitor) if self.data.len() <= 2 => Some(Outcome::Winners(HashSet::from([traitor])).finish(world)),
+            Some(_) => None,
+        };
+    }
+
+    fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        if self.data.remove(player) {
+            reset_color(player, world);
+
+            return true;
+        }
+
+        return false;
+    }
+
+    fn suspend(&mut self, _world: &mut World) {
+        self.music.pause();
+    }
+
+    fn resume(&mut self, paused: Duration, _world: &mut World) {
+        if let Some((_, end)) = self.phase.as_mut() {
+            *end += paused;
+        }
+
+        self.music.resume();
+    }
+}
+
+impl GameData<Settings, Event, Scoring> for Traitor {
+    type Data = Player;
+
+    fn data(&mut self) -> &mut PlayerData<Player> {
+        return &mut self.data;
+    }
+
+    fn create(players: HashSet<PlayerId>, world: &mut World) -> Self {
+        let music = world.sound.music(world.assets.music.random(world.rng));
+
+        // Sorted to pick the same traitor for the same seed
+        let mut players = players.into_iter().collect::<Vec<_>>();
+        players.sort();
+
+        let traitor = players.choose(world.rng).copied();
+
+        let hues = hues(players.len(), world.rng);
+
+        let players = PlayerData::init_with(players.into_iter()
+            .zip(hues)
+            .map(|(id, hue)| (id, Player {
+                hue,
+                traitor: Some(id) == traitor,
+                candidate: 0,
+                vote: None,
+            }))
+            .collect());
+
+        return Self {
+            data: players,
+            phase: None,
+            music,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tally() {
+        assert_eq!(tally([]), None);
+        assert_eq!(tally([1, 2, 1]), Some(1));
+        assert_eq!(tally([1, 2, 2, 1]), None);
+    }
+}
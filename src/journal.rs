@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::{info, instrument, warn};
+
+use crate::events::Event;
+use crate::state::State;
+
+/// A single line of an event log
+///
+/// The time is given in seconds since the session was started alongside the wall clock time.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entry {
+    State {
+        time: f64,
+        wall: String,
+        state: String,
+    },
+
+    Event {
+        time: f64,
+        wall: String,
+        event: Event,
+    },
+}
+
+/// Appends every state transition and game event of a session to an event log in the data
+/// directory
+pub struct Journal {
+    path: PathBuf,
+    file: BufWriter<File>,
+
+    started: Instant,
+
+    events: broadcast::Receiver<Event>,
+
+    // Last written state used to write transitions only
+    state: Option<String>,
+}
+
+impl Journal {
+    /// Creates a new event log for the session of the named arena
+    #[instrument(level = "debug", skip(events))]
+    pub fn create(directory: &Path, arena: &str, events: broadcast::Receiver<Event>) -> Result<Self> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create data directory: {:?}", directory))?;
+
+        let started = humantime::format_rfc3339_seconds(SystemTime::now());
+        let path = directory.join(format!("{}-{}.jsonl", arena, started));
+
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create event log: {:?}", path))?;
+
+        info!("Writing event log to {:?}", path);
+
+        return Ok(Self {
+            path,
+            file: BufWriter::new(file),
+            started: Instant::now(),
+            events,
+            state: None,
+        });
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    /// Writes all events emitted and the state entered since the last frame
+    pub fn record(&mut self, state: &State, now: Instant) -> Result<()> {
+        let time = now.saturating_duration_since(self.started).as_secs_f64();
+        let wall = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+
+        let mut written = false;
+
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => {
+                    self.write(&Entry::Event { time, wall: wall.clone(), event })?;
+                    written = true;
+                }
+                Err(broadcast::error::TryRecvError::Lagged(n)) => warn!("Event log missed {} events", n),
+                Err(_) => break,
+            }
+        }
+
+        let name = state.name();
+        if self.state.as_ref() != Some(&name) {
+            self.write(&Entry::State { time, wall, state: name.clone() })?;
+            self.state = Some(name);
+            written = true;
+        }
+
+        // Keep everything written so far in case of a crash
+        if written {
+            self.file.flush()?;
+        }
+
+        return Ok(());
+    }
+
+    fn write(&mut self, entry: &Entry) -> Result<()> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")?;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use hastilude_core::engine::players::Players;
+
+    use crate::events::Events;
+
+    #[test]
+    fn test_record() {
+        let directory = std::env::temp_dir().join(format!("hastilude-journal-{}", std::process::id()));
+
+        let events = Events::new();
+        let mut journal = Journal::create(&directory, "test", events.subscribe()).unwrap();
+
+        let state = State::lobby(&mut Players::new());
+        let now = Instant::now();
+
+        events.emit(Event::GameFinished { winners: HashSet::from([1]) });
+        journal.record(&state, now).unwrap();
+
+        // Staying in the same state is not written again
+        journal.record(&state, now).unwrap();
+
+        let lines = std::fs::read_to_string(journal.path()).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "event");
+        assert_eq!(lines[0]["event"]["type"], "GameFinished");
+        assert_eq!(lines[1]["type"], "state");
+        assert_eq!(lines[1]["state"], "lobby");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use hastilude_core::engine::players::PlayerId;
+
+/// Counts the wins of each player
+#[derive(Default)]
+pub struct Leaderboard {
+    wins: HashMap<PlayerId, usize>,
+}
+
+impl Leaderboard {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// The best `n` players by number of wins
+    pub fn ranking(&self, n: usize) -> Vec<(PlayerId, usize)> {
+        let mut ranking = self.wins.iter()
+            .map(|(player, wins)| (*player, *wins))
+            .collect::<Vec<_>>();
+        ranking.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranking.truncate(n);
+        return ranking;
+    }
+
+    /// Records the winners of a round and returns whether the top `n` ranking has changed
+    pub fn record(&mut self, winners: impl IntoIterator<Item=PlayerId>, n: usize) -> bool {
+        let players = |ranking: Vec<(PlayerId, usize)>| ranking.into_iter()
+            .map(|(player, _)| player)
+            .collect::<Vec<_>>();
+
+        let before = players(self.ranking(n));
+
+        for winner in winners {
+            *self.wins.entry(winner).or_default() += 1;
+        }
+
+        return before != players(self.ranking(n));
+    }
+}
@@ -0,0 +1,22 @@
+pub mod arena;
+pub mod capture;
+pub mod chat;
+pub mod config;
+pub mod discord;
+pub mod dmx;
+pub mod estop;
+pub mod events;
+pub mod games;
+pub mod journal;
+pub mod leaderboard;
+pub mod web;
+pub mod meta;
+pub mod mqtt;
+pub mod osc;
+pub mod palette;
+pub mod profiles;
+pub mod recorder;
+pub mod scoreboard;
+pub mod simulation;
+pub mod snapshot;
+pub mod state;
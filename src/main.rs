@@ -1,29 +1,129 @@
-#![feature(type_alias_impl_trait)]
-#![feature(iter_intersperse)]
-#![feature(result_flattening)]
-#![feature(drain_filter)]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use std::time::Instant;
-
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
 use futures::task::Poll;
+use tracing::error;
+
+use hastilude_core::controller::{emulated, faults, pairing, playback};
+use hastilude_core::controller::calibration::Store;
+use hastilude_core::controller::rate::{self, Rates};
+use hastilude_core::engine::assets::Assets;
+use hastilude_core::engine::hotplug::{self, Hotplug};
+use hastilude_core::engine::timing::Pacer;
+
+use hastilude::{arena, chat, discord, dmx, estop, mqtt, osc, profiles, scoreboard, web};
+use hastilude::arena::{Arena, Arenas};
+use hastilude::config::{self, Config};
+use hastilude::profiles::Profiles;
+use hastilude::state::Settings;
+
+/// Party games played with motion controllers
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// TOML file with the setup of the venue - options given on the command line take precedence
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Write a capture of the session in the first arena for offline replay to this file
+    #[arg(long)]
+    capture: Option<PathBuf>,
+
+    /// Keeps the session of the first arena in this file and continues it from there after a restart
+    #[arg(long, value_name = "PATH")]
+    snapshot: Option<PathBuf>,
+
+    /// Directory to keep an event log of every session in
+    #[arg(long, value_name = "PATH")]
+    data_dir: Option<PathBuf>,
+
+    /// Powers off controllers idle in the lobby for this long
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    idle_power_off: Option<Duration>,
+
+    /// Shows a rainbow and plays music after the lobby was idle for this long
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    attract_after: Option<Duration>,
+
+    /// Starts games automatically once enough players are ready and nobody got ready for this long
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    auto_start: Option<Duration>,
+
+    /// Maximum number of controllers receiving LED and rumble updates per frame - the others are
+    /// deferred to the following frames to avoid congesting the bluetooth adapters
+    #[arg(long, value_name = "WRITES", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    feedback_budget: Option<usize>,
+
+    /// Blinks controllers orange from time to time if their battery drops to this level (0.0 - 1.0)
+    #[arg(long, value_name = "LEVEL")]
+    low_battery_warning: Option<f32>,
+
+    /// Seed of the random number generator of the games - replaying a session with the seed and the
+    /// recorded controller input reproduces it
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// JSON file with calibration overrides by controller address
+    #[arg(long, value_name = "PATH")]
+    calibration: Option<PathBuf>,
+
+    /// Number of updates per second the games are simulated with
+    #[arg(long, value_name = "HZ", default_value_t = 100)]
+    tick_rate: u32,
+
+    #[command(flatten)]
+    arena: arena::Config,
+
+    #[command(flatten)]
+    profiles: profiles::Config,
+
+    #[command(flatten)]
+    hotplug: hotplug::Config,
 
-use crate::engine::assets::Assets;
-use crate::engine::players::Players;
-use crate::engine::sound::Sound;
-use crate::engine::World;
-use crate::state::{Settings, State};
-use crate::web::StateDTO;
+    #[command(flatten)]
+    emulated: emulated::Config,
 
-pub mod controller;
-pub mod engine;
-pub mod games;
-pub mod web;
-pub mod meta;
-pub mod state;
+    #[command(flatten)]
+    playback: playback::Config,
+
+    #[command(flatten)]
+    rate: rate::Config,
+
+    #[command(flatten)]
+    faults: faults::Config,
+
+    #[command(flatten)]
+    pairing: pairing::Config,
+
+    #[command(flatten)]
+    mqtt: mqtt::Config,
+
+    #[command(flatten)]
+    osc: osc::Config,
+
+    #[command(flatten)]
+    dmx: dmx::Config,
+
+    #[command(flatten)]
+    chat: chat::Config,
+
+    #[command(flatten)]
+    discord: discord::Config,
+
+    #[command(flatten)]
+    scoreboard: scoreboard::Config,
+
+    #[command(flatten)]
+    estop: estop::Config,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+
     tracing_subscriber::fmt()
         .with_env_filter("hyper=INFO,DEBUG")
         .with_ansi(true)
@@ -31,63 +131,155 @@ async fn main() -> Result<()> {
         .compact()
         .init();
 
-    let mut players = Players::init().await
-        .context("Failed to initialize players")?;
+    let config = match args.config {
+        Some(ref path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    let calibration = args.calibration.as_deref()
+        .map(Store::load)
+        .transpose()
+        .context("Failed to load calibration")?;
 
-    let mut sound = Sound::init()
-        .context("Failed to initialize sound")?;
+    // Emulated and replayed controllers replace the hardware
+    let (mut hotplug, controllers) = if args.emulated.enabled() {
+        let controllers = emulated::spawn(args.emulated)
+            .context("Failed to initialize emulated controllers")?;
+        (None, controllers)
+    } else if args.playback.enabled() {
+        let controllers = playback::spawn(args.playback)
+            .context("Failed to initialize replayed controllers")?;
+        (None, controllers)
+    } else {
+        let (hotplug, controllers) = Hotplug::init(args.hotplug, calibration.clone(), Rates::new(&args.rate), args.faults, args.pairing).await
+            .context("Failed to initialize controllers")?;
+        (Some(hotplug), controllers)
+    };
 
-    let assets = Assets::init(std::env::current_dir()?.join("assets"))
+    let assets = Assets::init(std::env::current_dir()?.join(config.assets()))
         .context("Failed to initialize assets")?;
 
-    // Initialize fresh state machine
-    let mut state = State::lobby(&mut players);
+    let profiles = Arc::new(Profiles::load(args.profiles.profiles.as_deref())?);
+
+    // The initial settings
+    let mut settings = Settings::default();
+    config.apply(&mut settings);
+    settings.calibration = calibration;
+    settings.idle_power_off = args.idle_power_off.or(settings.idle_power_off);
+    settings.attract_after = args.attract_after.or(settings.attract_after);
+    settings.auto_start = args.auto_start.or(settings.auto_start);
+    settings.feedback_budget = args.feedback_budget.or(settings.feedback_budget);
+    settings.low_battery_warning = args.low_battery_warning.or(settings.low_battery_warning);
+    settings.seed = args.seed.or(settings.seed);
+    if let Some(ref name) = args.profiles.profile {
+        profiles.get(name)
+            .ok_or_else(|| anyhow!("No such profile: {}", name))?
+            .apply(&mut settings);
+    }
+
+    let mut arenas = Arenas::new(args.arena, settings)?;
+    for controller in controllers {
+        arenas.add(controller);
+    }
+
+    if let Some(path) = args.capture {
+        arenas.primary_mut().capture(&path)?;
+    }
+
+    if let Some(ref directory) = args.data_dir {
+        for arena in arenas.iter_mut() {
+            arena.journal(directory)?;
+        }
+    }
+
+    if let Some(path) = args.snapshot {
+        arenas.primary_mut().snapshot(&path)?;
+    }
+
+    // Integrations are bound to the first arena
+    let stub = arenas.primary().stub();
+    let info = arenas.primary().info();
+    let events = arenas.primary().events();
 
     // Start web interface
-    let (web, mut requests, mut info) = web::serve()?;
+    let web = web::serve(config.bind(), arenas.iter().map(Arena::handle).collect(), profiles)?;
     let mut web = tokio::spawn(web);
 
-    // The initial settings
-    let mut settings = Settings::default();
+    // Keep settings changed at runtime in the config file
+    if let Some(path) = args.config {
+        tokio::spawn(config::persist(path, config, arenas.primary().settings()));
+    }
 
-    let mut last = Instant::now();
-    loop {
-        // Calculate last frame duration
-        let now = Instant::now();
-        let duration = now - last;
+    // Watch emergency stop buttons
+    if args.estop.enabled() {
+        estop::run(args.estop, arenas.iter().map(Arena::stub).collect());
+    }
 
-        // Handle failures from the web server
-        if let Poll::Ready(result) = futures::poll!(&mut web) {
-            return result.map_err(Into::into);
-        };
+    // Start MQTT bridge if configured
+    if args.mqtt.host.is_some() {
+        tokio::spawn(mqtt::run(args.mqtt, stub.clone(), info.subscribe(), events.subscribe()));
+    }
 
-        // Update controller information
-        players.update(duration).await
-            .context("Failed to update players")?;
+    // Start OSC output if configured
+    if args.osc.target.is_some() {
+        let osc = osc::run(args.osc, events.subscribe());
+        tokio::spawn(async move {
+            if let Err(err) = osc.await {
+                error!("OSC output failed: {}", err);
+            }
+        });
+    }
 
-        let mut world = World {
-            now,
-            players: &mut players,
-            sound: &mut sound,
-            assets: &assets,
-            settings: &mut settings,
-        };
+    // Start DMX lighting if configured
+    if args.dmx.target.is_some() {
+        let dmx = dmx::run(args.dmx, events.subscribe());
+        tokio::spawn(async move {
+            if let Err(err) = dmx.await {
+                error!("DMX output failed: {}", err);
+            }
+        });
+    }
 
-        // Handle requests
-        state = state.handle(&mut requests, &mut world).await;
+    // Start chat integration if configured
+    if args.chat.channel.is_some() {
+        tokio::spawn(chat::run(args.chat, stub.clone(), events.subscribe()));
+    }
 
-        // Play the game
-        state = state.update(&mut world, duration);
+    // Start Discord announcements if configured
+    if args.discord.webhook.is_some() {
+        tokio::spawn(discord::run(args.discord, events.subscribe()));
+    }
 
-        // Publish updated status info
-        info.publish(StateDTO {
-            mode: settings.game_mode.into(),
-            state: (&state).into(),
-            devices: players.iter()
-                .map(|player| player.controller().into())
-                .collect(),
+    // Show scoreboard on the local display if enabled - the window requires its own thread
+    if args.scoreboard.enabled {
+        let (info, events) = (info.subscribe(), events.subscribe());
+        std::thread::spawn(move || {
+            if let Err(err) = scoreboard::run(args.scoreboard, info, events) {
+                error!("Scoreboard failed: {}", err);
+            }
         });
+    }
+
+    let mut pacer = Pacer::new(args.tick_rate, Instant::now());
+    loop {
+        // Wait for the next tick instead of spinning
+        let ticks = pacer.wait().await;
+
+        // Handle failures from the web server
+        if let Poll::Ready(result) = futures::poll!(&mut web) {
+            return result.map_err(Into::into);
+        };
+
+        // Dispatch connected and disconnected controllers to the arenas
+        if let Some(ref mut hotplug) = hotplug {
+            if let Some(change) = hotplug.poll().await? {
+                arenas.apply(change);
+            }
+        }
 
-        last = now;
+        // Ticks are simulated one by one to keep the time step fixed
+        for _ in 0..ticks {
+            arenas.update(&assets, pacer.step(), pacer.tick()).await?;
+        }
     }
 }
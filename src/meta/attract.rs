@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use scarlet::color::{Color, RGBColor};
+use scarlet::colors::HSVColor;
+use tracing::debug;
+
+use hastilude_core::engine::sound::Playback;
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::meta::lobby::Lobby;
+use crate::state::{State, World};
+
+/// Shows off the controllers while nobody is playing - a rainbow rotates across all controllers
+/// while ambient music plays until any button is pressed
+pub struct Attract {
+    // The lobby to return to with its ready players
+    lobby: Lobby,
+
+    elapsed: Duration,
+
+    // Keeps playing until the attract mode ends
+    _music: Playback,
+}
+
+impl Attract {
+    // Time for the rainbow to make a full rotation
+    const PERIOD: Duration = Duration::from_secs(8);
+
+    // Volume of the music relative to the music of the games
+    const VOLUME: f32 = 0.3;
+
+    pub fn new(lobby: Lobby, world: &mut World) -> Self {
+        debug!("Lobby idle - starting attract mode");
+
+        let music = world.sound.ambient(world.assets.music.random(world.rng), Self::VOLUME);
+
+        return Self {
+            lobby,
+            elapsed: Duration::ZERO,
+            _music: music,
+        };
+    }
+
+    /// Returns to the lobby the attract mode was started from
+    pub fn leave(self) -> Lobby {
+        return self.lobby.resume();
+    }
+
+    pub fn kick_player(&mut self, player: PlayerId) -> bool {
+        return self.lobby.kick_player(player);
+    }
+
+    pub fn remembered(&self) -> HashSet<PlayerId> {
+        return self.lobby.remembered();
+    }
+
+    /// Hue of the controller at `position` between 0 and 1 along the rainbow
+    fn hue(elapsed: Duration, position: f64) -> f64 {
+        return (elapsed.as_secs_f64() / Self::PERIOD.as_secs_f64() + position) * 360.0 % 360.0;
+    }
+
+    pub fn update(mut self, world: &mut World, duration: Duration) -> State {
+        if world.players.iter().any(|player| player.input().buttons.all().iter().any(|button| button.pressed())) {
+            debug!("Button pressed - leaving attract mode");
+            return State::Lobby(self.leave());
+        }
+
+        self.elapsed += duration;
+
+        let count = world.players.iter().len().max(1);
+        for (i, player) in world.players.iter_mut().enumerate() {
+            player.color.set(HSVColor {
+                h: Self::hue(self.elapsed, i as f64 / count as f64),
+                s: 1.0,
+                v: 1.0,
+            }.convert::<RGBColor>());
+        }
+
+        return State::Attract(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hastilude_core::engine::assets::Assets;
+
+    use crate::simulation::Sandbox;
+    use crate::state::Settings;
+
+    #[test]
+    fn test_hue() {
+        assert_eq!(Attract::hue(Duration::ZERO, 0.0), 0.0);
+        assert_eq!(Attract::hue(Duration::ZERO, 0.5), 180.0);
+
+        // The rainbow rotates across the controllers
+        assert_eq!(Attract::hue(Attract::PERIOD / 4, 0.0), 90.0);
+        assert_eq!(Attract::hue(Attract::PERIOD / 2, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_keeps_ready() {
+        let assets = Assets::empty();
+        let mut sandbox = Sandbox::new(&assets, Settings {
+            attract_after: Some(Duration::from_secs(1)),
+            ..Settings::default()
+        }, Some(1));
+
+        let lobby = Lobby::restore(&mut sandbox.players, HashSet::from([1, 2]));
+        let attract = match lobby.update(&mut sandbox.world(), Duration::from_secs(2)) {
+            State::Attract(attract) => attract,
+            _ => panic!("Expected the attract mode"),
+        };
+        assert_eq!(attract.remembered(), HashSet::from([1, 2]));
+
+        // The players stay ready when returning to the lobby
+        let lobby = attract.leave();
+        assert_eq!(lobby.remembered(), HashSet::from([1, 2]));
+        assert!(matches!(lobby.update(&mut sandbox.world(), Duration::from_millis(10)), State::Lobby(_)));
+    }
+}
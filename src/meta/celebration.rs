@@ -2,26 +2,231 @@ use std::collections::HashSet;
 use std::time::Duration;
 
 use rand::Rng;
-use scarlet::color::{Color, RGBColor};
-use scarlet::colors::HSVColor;
+use scarlet::color::RGBColor;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::{keyframe, keyframes};
-use crate::engine::players::{PlayerData, PlayerId};
-use crate::state::{State, World};
+use hastilude_core::{keyframe, keyframes};
+use hastilude_core::engine::animation::Keyframe;
+use hastilude_core::engine::players::{PlayerData, PlayerId};
 
+use crate::events::Event;
+use crate::games::seconds;
+use crate::palette::Theme;
+use crate::state::{InvalidOptionsError, State, World};
+
+/// Choreography shown on the controllers of the winners
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    /// Random colorful bursts
+    Fireworks,
+
+    /// Slow golden pulse for a single winner
+    Podium,
+
+    /// Colors washing over the winning team in sync
+    Wash,
+
+    /// White strobe speeding up towards the end
+    Strobe,
+}
+
+impl Style {
+    const GOLD: (u8, u8, u8) = (0xFF, 0xB0, 0x00);
+    const GOLD_DIM: (u8, u8, u8) = (0x30, 0x20, 0x00);
+
+    /// The style matching the number of winners of a game
+    fn select(winners: usize) -> Self {
+        return match winners {
+            1 => Style::Podium,
+            _ => Style::Wash,
+        };
+    }
+
+    /// The color animation of a single winner lasting for the given time
+    fn keyframes(self, time: Duration, theme: Theme, rng: &mut impl Rng) -> Vec<Keyframe<RGBColor>> {
+        let mut keyframes = Vec::new();
+        let mut elapsed = Duration::ZERO;
+
+        while elapsed < time {
+            match self {
+                Style::Fireworks => {
+                    let duration = Duration::from_millis(rng.gen_range(100..700));
+                    let color = theme.hue(rng.gen(), 1.0);
+
+                    keyframes.push(keyframe!(duration => { color }));
+                    keyframes.push(keyframe!(0.2 => { (0, 0, 0) } @ quadratic_out));
+                    elapsed += duration + Duration::from_millis(200);
+                }
+
+                Style::Podium => {
+                    keyframes.push(keyframe!(0.6 => { Self::GOLD } @ quadratic_in_out));
+                    keyframes.push(keyframe!(0.6 => { Self::GOLD_DIM } @ quadratic_in_out));
+                    elapsed += Duration::from_millis(1200);
+                }
+
+                Style::Wash => {
+                    // Sweeps the colors of the theme every six seconds
+                    let color = theme.hue(elapsed.as_secs_f64() / 6.0, 1.0);
+
+                    keyframes.push(keyframe!(0.5 => { color } @ linear));
+                    elapsed += Duration::from_millis(500);
+                }
+
+                Style::Strobe => {
+                    // Pauses between the flashes shrink from half a second to nothing
+                    let pause = 0.5 * (1.0 - elapsed.as_secs_f64() / time.as_secs_f64());
+
+                    keyframes.push(keyframe!(0.05 => { (255, 255, 255) }));
+                    keyframes.push(keyframe!(pause.max(0.05) => { (0, 0, 0) }));
+                    elapsed += Duration::from_secs_f64(0.05 + pause.max(0.05));
+                }
+            }
+        }
+
+        return keyframes;
+    }
+}
+
+/// Settings of the celebration after a game
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Options {
+    /// Time in seconds before returning to the lobby
+    pub duration: f32,
+
+    /// Choreography shown instead of the one matching the outcome
+    pub style: Option<Style>,
+}
+
+impl Options {
+    // Longer celebrations hold up the next game and take ever more keyframes
+    const MAX_DURATION: f32 = 60.0;
+
+    pub fn validate(&self) -> Result<(), InvalidOptionsError> {
+        seconds("celebration.duration", self.duration)?;
+
+        if self.duration > Self::MAX_DURATION {
+            return Err(InvalidOptionsError { field: "celebration.duration", reason: "must be at most 60 seconds" });
+        }
+
+        return Ok(());
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Self {
+            duration: 10.0,
+            style: None,
+        };
+    }
+}
+
+// The result celebrated
+enum Kind {
+    Game,
+    Draw,
+    Tournament,
+    Match,
+}
+
+/// Celebrates the result of a game once it is updated for the first time
+///
+/// The rounds of a match are decided without ever updating their celebration, so they are neither
+/// announced nor reported as finished games.
 pub struct Celebration {
+    kind: Kind,
+    winners: HashSet<PlayerId>,
+    style: Style,
+    time: Duration,
     elapsed: Duration,
+    started: bool,
 }
 
 impl Celebration {
-    const TIME: Duration = Duration::from_secs(10);
-
     pub fn new(winners: HashSet<PlayerId>, world: &mut World) -> Self {
-        debug!("Celebrating winners: {:?}", winners);
+        let style = Style::select(winners.len());
+        return Self::of(Kind::Game, winners, style, world);
+    }
+
+    /// Ends a game without winners by flashing all controllers
+    pub fn draw(world: &mut World) -> Self {
+        return Self::of(Kind::Draw, HashSet::new(), Style::Strobe, world);
+    }
+
+    /// Celebrates the champions of a whole tournament instead of a single game
+    pub fn tournament(champions: HashSet<PlayerId>, world: &mut World) -> Self {
+        return Self::of(Kind::Tournament, champions, Style::Fireworks, world);
+    }
+
+    /// Celebrates the players deciding a match by winning the required number of rounds
+    pub fn match_winners(champions: HashSet<PlayerId>, world: &mut World) -> Self {
+        return Self::of(Kind::Match, champions, Style::Fireworks, world);
+    }
+
+    fn of(kind: Kind, winners: HashSet<PlayerId>, style: Style, world: &mut World) -> Self {
+        let options = &world.settings.options.celebration;
+
+        return Self {
+            kind,
+            winners,
+            style: options.style.unwrap_or(style),
+            time: Duration::try_from_secs_f32(options.duration).unwrap_or_default(),
+            elapsed: Duration::ZERO,
+            started: false,
+        };
+    }
+
+    /// Reports and announces the result and starts the choreography
+    fn start(&mut self, world: &mut World) {
+        match self.kind {
+            Kind::Game => {
+                debug!("Celebrating winners: {:?}", self.winners);
+                world.events.emit(Event::GameFinished {
+                    winners: self.winners.clone(),
+                });
+                world.announce("winner");
+            }
+
+            Kind::Draw => {
+                debug!("Celebrating a draw");
+                world.events.emit(Event::GameDrawn);
+                world.announce("draw");
+            }
+
+            Kind::Tournament => {
+                debug!("Celebrating tournament champions: {:?}", self.winners);
+                world.events.emit(Event::TournamentFinished {
+                    champions: self.winners.clone(),
+                });
+                world.announce("winner");
+            }
 
-        let mut winners = PlayerData::init(winners, || ());
-        world.players.with_data(&mut winners).update(|player, _| {
+            Kind::Match => {
+                debug!("Celebrating match champions: {:?}", self.winners);
+                world.events.emit(Event::MatchFinished {
+                    champions: self.winners.clone(),
+                });
+                world.announce("winner");
+            }
+        }
+
+        let (style, time) = (self.style, self.time);
+        let theme = world.settings.theme;
+
+        // All winners share the same animation to keep them in sync - except for the fireworks
+        let keyframes = style.keyframes(time, theme, world.rng);
+
+        // A draw flashes everybody
+        let players = match self.kind {
+            Kind::Draw => world.players.keys().collect(),
+            _ => self.winners.clone(),
+        };
+
+        let mut data = PlayerData::init(players, || ());
+        world.players.with_data(&mut data).update(|player, _| {
             player.rumble.animate(keyframes![
                 0.0 => 0   @ quadratic_in_out,
                 0.8 => 200 @ quadratic_in_out,
@@ -36,42 +241,38 @@ impl Celebration {
                 0.2 => 0   @ quadratic_in_out,
             ]);
 
-            // Generate fireworks animation
-            let fireworks = std::iter::from_fn({
-                let mut elapsed = Duration::ZERO;
-
-                move || {
-                    if elapsed >= Self::TIME {
-                        return None;
-                    }
-
-                    let duration = Duration::from_millis(rand::thread_rng().gen_range(100..700));
-                    let color = HSVColor {
-                        h: rand::thread_rng().gen_range(0.0..360.0),
-                        s: 1.0,
-                        v: 1.0,
-                    }.convert::<RGBColor>();
-
-                    elapsed += duration;
-
-                    return Some(keyframe!(duration => { color }));
-                }
-            }).intersperse(keyframe!(0.2 => { (0,0,0) } @ quadratic_out));
-
-            player.color.animate(fireworks);
+            player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+            player.color.animate(match style {
+                Style::Fireworks => style.keyframes(time, theme, world.rng),
+                _ => keyframes.clone(),
+            });
 
             return true;
         });
+    }
 
-        return Self {
-            elapsed: Duration::ZERO,
-        };
+    pub fn winners(&self) -> &HashSet<PlayerId> {
+        return &self.winners;
+    }
+
+    /// Whether the game ended in a draw - the winners are empty then
+    pub fn is_draw(&self) -> bool {
+        return matches!(self.kind, Kind::Draw);
+    }
+
+    pub fn style(&self) -> Style {
+        return self.style;
     }
 
     pub fn update(mut self, world: &mut World, duration: Duration) -> State {
+        if !self.started {
+            self.start(world);
+            self.started = true;
+        }
+
         self.elapsed += duration;
 
-        if self.elapsed >= Duration::from_secs(10) {
+        if self.elapsed >= self.time {
             debug!("Enough partying - back to lobby");
             return State::lobby(world.players);
         }
@@ -79,3 +280,15 @@ impl Celebration {
         return State::Celebration(self);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        assert_eq!(Style::select(1), Style::Podium);
+        assert_eq!(Style::select(2), Style::Wash);
+        assert_eq!(Style::select(5), Style::Wash);
+    }
+}
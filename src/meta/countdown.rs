@@ -1,68 +1,129 @@
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use scarlet::color::RGBColor;
 use tracing::debug;
 
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::keyframes;
+
 use crate::games::{Game, GameData, GameState};
-use crate::keyframes;
+use crate::games::mutators::Mutators;
+use crate::palette::Theme;
 use crate::state::{State, World};
 
 pub trait PlayerColor {
-    fn color(&self) -> RGBColor;
+    fn color(&self, theme: Theme) -> RGBColor;
 }
 
 pub struct Countdown {
     game: Box<dyn Game>,
+    players: HashSet<PlayerId>,
+
+    // The color of each player flashed with every beep
+    colors: HashMap<PlayerId, RGBColor>,
+
+    // Length of the countdown and the number of beeps played so far
+    seconds: u32,
+    beeps: u32,
+
     elapsed: Duration,
 }
 
 impl Countdown {
-    pub fn new<T>(mut game: T, world: &mut World) -> Self
+    // Beep played at every second and the tone starting the game
+    const BEEP_TONE: f32 = 880.0;
+    const BEEP_DUR: Duration = Duration::from_millis(150);
+    const START_TONE: f32 = 1760.0;
+    const START_DUR: Duration = Duration::from_millis(600);
+
+    // Brightness the player colors fade to between the beeps
+    const FLASH_DIM: f64 = 0.1;
+
+    pub fn new<T>(mut game: T, seconds: u32, world: &mut World) -> Self
         where
             T: Game + GameData + 'static,
             T::Data: PlayerColor,
     {
-        debug!("Start countdown");
+        debug!("Start countdown of {} seconds", seconds);
+        world.announce("countdown");
+
+        let colors = game.data().iter()
+            .map(|(id, data)| (id, data.color(world.settings.theme)))
+            .collect::<HashMap<_, _>>();
 
         // Short initial buzz for all players
-        world.players.with_data(game.data()).update(|player, data| {
+        world.players.with_data(game.data()).update(|player, _| {
             player.rumble.animate(keyframes![
                 0.0 => 127,
                 0.1 => 0,
             ]);
 
-            player.color.animate(keyframes![
-                0.0 => { (0, 0, 0) },
-
-                0.75 => { data.color() } @ end,
-
-                0.10 => { (0, 0, 0) } @ linear,
-                0.65 => { data.color() } @ end,
-
-                0.20 => { (0, 0, 0) } @ linear,
-                0.55 => { data.color() } @ end,
-
-                0.30 => { (0, 0, 0) } @ linear,
-                0.45 => { data.color() } @ end,
-            ]);
+            player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
 
             return true;
         });
 
         return Self {
             game: Box::new(game),
+            players: colors.keys().copied().collect(),
+            colors,
+            seconds,
+            beeps: 0,
             elapsed: Duration::ZERO,
         };
     }
 
-    pub fn update(mut self, _: &mut World, duration: Duration) -> State {
-        self.elapsed += duration;
-
-        if self.elapsed >= Duration::from_secs(3) {
+    pub fn update(mut self, world: &mut World, duration: Duration) -> State {
+        if self.elapsed >= Duration::from_secs(self.seconds as u64) {
             debug!("Countdown finished - start game");
-            return State::Playing(GameState::new(self.game));
+            world.sound.tone(Self::START_TONE, Self::START_DUR);
+            world.announce("go");
+
+            for (id, color) in self.colors.iter() {
+                if let Some(player) = world.players.get_mut(*id) {
+                    player.color.set(*color);
+                }
+            }
+
+            // Timers of the previous game do not carry over
+            world.timers.clear();
+
+            let mutators = Mutators::new(&world.settings.mutators);
+            return State::Playing(GameState::new(self.game, self.players, mutators, world.now));
+        }
+
+        // Beep and flash all players in sync at the start of each second
+        if self.elapsed >= Duration::from_secs(self.beeps as u64) {
+            self.beeps += 1;
+            world.sound.tone(Self::BEEP_TONE, Self::BEEP_DUR);
+
+            for (id, color) in self.colors.iter() {
+                let dimmed = RGBColor {
+                    r: color.r * Self::FLASH_DIM,
+                    g: color.g * Self::FLASH_DIM,
+                    b: color.b * Self::FLASH_DIM,
+                };
+
+                if let Some(player) = world.players.get_mut(*id) {
+                    player.color.set_and_animate(*color, keyframes![
+                        0.7 => { dimmed } @ linear,
+                    ]);
+                }
+            }
         }
 
+        self.elapsed += duration;
+
         return State::Countdown(self);
     }
+
+    /// The players taking part in the game if the others show the idle animation
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return self.game.idle_animation().then_some(&self.players);
+    }
+
+    pub fn reconfigure(&mut self, world: &mut World) {
+        self.game.reconfigure(world);
+    }
 }
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use scarlet::color::RGBColor;
+use tracing::debug;
+
+use hastilude_core::engine::animation::{Completion, Keyframe};
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::game::Scores;
+use hastilude_core::keyframe;
+
+use crate::state::World;
+
+/// Short break between the rounds of a match - each controller blinks once for every round won by
+/// its player with the round just won in gold
+pub struct Intermission {
+    // Players still showing their wins
+    blinking: Vec<(PlayerId, Completion)>,
+
+    // Time spent since all players have finished blinking
+    elapsed: Duration,
+}
+
+impl Intermission {
+    // Time a controller is lit and dark for every win
+    const BLINK: f64 = 0.3;
+
+    // Time to get ready after the longest blinking sequence
+    const PAUSE: Duration = Duration::from_secs(2);
+
+    const GOLD: (u8, u8, u8) = (0xFF, 0xB0, 0x00);
+
+    pub fn new(players: &HashSet<PlayerId>, standings: &Scores<u32>, winners: &HashSet<PlayerId>, world: &mut World) -> Self {
+        debug!("Intermission with standings: {:?}", standings);
+
+        let mut blinking = Vec::new();
+        for id in players.iter() {
+            if let Some(player) = world.players.get_mut(*id) {
+                player.rumble.set(0);
+                player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
+                blinking.push((*id, player.color.animate(Self::keyframes(standings.get(*id), winners.contains(id)))));
+            }
+        }
+
+        return Self {
+            blinking,
+            elapsed: Duration::ZERO,
+        };
+    }
+
+    fn keyframes(wins: u32, won: bool) -> Vec<Keyframe<RGBColor>> {
+        return (0..wins)
+            .flat_map(|i| [
+                if won && i + 1 == wins {
+                    keyframe!(Self::BLINK => { Self::GOLD })
+                } else {
+                    keyframe!(Self::BLINK => { (255, 255, 255) })
+                },
+                keyframe!(Self::BLINK => { (0, 0, 0) }),
+            ])
+            .collect();
+    }
+
+    /// Advances the intermission - returns whether the next round can start
+    pub fn update(&mut self, world: &World, duration: Duration) -> bool {
+        // Players gone in the meantime do not hold up the match
+        self.blinking.retain(|(id, completion)| world.players.get(*id)
+            .map_or(false, |player| !player.color.completed(*completion)));
+        if !self.blinking.is_empty() {
+            return false;
+        }
+
+        self.elapsed += duration;
+        return self.elapsed >= Self::PAUSE;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hastilude_core::engine::assets::Assets;
+
+    use crate::simulation::Sandbox;
+    use crate::state::Settings;
+
+    #[test]
+    fn test_keyframes() {
+        let white = RGBColor::from((255, 255, 255));
+        let gold = RGBColor::from(Intermission::GOLD);
+
+        let keyframes = Intermission::keyframes(2, false);
+        assert_eq!(keyframes.len(), 4);
+        assert_eq!((keyframes[0].value, keyframes[2].value), (white, white));
+
+        // Only the round just won is shown in gold
+        let keyframes = Intermission::keyframes(2, true);
+        assert_eq!((keyframes[0].value, keyframes[2].value), (white, gold));
+    }
+
+    #[test]
+    fn test_disconnected() {
+        let assets = Assets::empty();
+        let mut sandbox = Sandbox::new(&assets, Settings::default(), Some(1));
+
+        // Players gone do not hold up the next round
+        let standings = Scores::new([1, 2]);
+        let mut intermission = Intermission::new(&HashSet::from([1, 2]), &standings, &HashSet::new(), &mut sandbox.world());
+        assert!(!intermission.update(&sandbox.world(), Intermission::PAUSE / 2));
+        assert!(intermission.update(&sandbox.world(), Intermission::PAUSE / 2));
+    }
+}
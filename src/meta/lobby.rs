@@ -1,18 +1,108 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::TAU;
+use std::time::Duration;
 
 use scarlet::color::RGBColor;
 use tracing::debug;
 
-use crate::keyframes;
-use crate::engine::players::{PlayerId, Players};
-use crate::games::debug;
-use crate::state::{State, World};
+use hastilude_core::keyframes;
+use hastilude_core::engine::players::{PlayerId, Players};
+
+use crate::games::{debug, GameMode};
+use crate::games::best_of::{self, Match};
+use crate::games::tournament::{Setup, Tournament};
+use crate::meta::attract::Attract;
+use crate::meta::teams::TeamSelection;
+use crate::palette::Theme;
+use crate::state::{StartGameError, State, World};
 
 pub struct Lobby {
     ready: HashSet<PlayerId>,
+
+    // Players ready before a restart - they are ready again as soon as they reconnect
+    restored: HashSet<PlayerId>,
+
+    // Time since any button was pressed
+    idle: Duration,
+
+    // Time the automatic start is counting down and whether it was cancelled for the ready players
+    auto_start: Option<Duration>,
+    cancelled: bool,
+
+    // Players having picked a game mode while holding select - they stay ready
+    selecting: HashSet<PlayerId>,
+
+    // Time players have been holding select without picking a game mode
+    holding: HashMap<PlayerId, Duration>,
+
+    // Time the color code of a newly selected game mode is still shown
+    flash: Duration,
 }
 
 impl Lobby {
+    // Per-player thresholds cycled through with the triangle button - shown as levels of the theme
+    const THRESHOLDS: [f32; 3] = [1.0, 1.5, 2.0];
+
+    /// The next threshold after the given one
+    fn next_threshold(threshold: f32) -> f32 {
+        return Self::THRESHOLDS.iter()
+            .copied()
+            .find(|level| *level > threshold)
+            .unwrap_or(Self::THRESHOLDS[0]);
+    }
+
+    /// The highest threshold level reached by the given threshold
+    fn threshold_level(threshold: f32) -> usize {
+        return Self::THRESHOLDS.iter()
+            .filter(|level| **level <= threshold)
+            .count()
+            .saturating_sub(1);
+    }
+
+    // Time players must hold select to leave the ready players - long enough to pick a mode first
+    const UNREADY_HOLD: Duration = Duration::from_secs(2);
+
+    // Time all controllers show the color code of a newly selected game mode
+    const SELECTED_FLASH: Duration = Duration::from_millis(1500);
+
+    /// The modes players can pick from their controllers - the debug mode can only be left from
+    /// the web interface
+    pub fn selectable() -> impl Iterator<Item=GameMode> {
+        return GameMode::all().filter(|mode| *mode != GameMode::of(&debug::MODE));
+    }
+
+    /// The mode `step` places away from the given one in the list of selectable modes
+    fn cycle(mode: GameMode, step: isize) -> GameMode {
+        let modes = Self::selectable().collect::<Vec<_>>();
+        let index = modes.iter().position(|other| *other == mode).unwrap_or(0) as isize;
+        return modes[(index + step).rem_euclid(modes.len() as isize) as usize];
+    }
+
+    /// Hue of the color code of a mode - the modes are spread evenly around the color wheel
+    fn mode_hue(mode: GameMode) -> f64 {
+        let count = Self::selectable().count();
+        let index = Self::selectable().position(|other| other == mode).unwrap_or(0);
+        return index as f64 * 360.0 / count as f64;
+    }
+
+    fn mode_color(mode: GameMode, theme: Theme) -> RGBColor {
+        return theme.hue(Self::mode_hue(mode) / 360.0, 1.0);
+    }
+
+    // Frequency of the pulse of the ready players at the begin and the end of the automatic start
+    const PULSE_SLOW: f64 = 0.5;
+    const PULSE_FAST: f64 = 4.0;
+
+    /// Brightness of the ready players after the automatic start counted down for `elapsed`
+    fn pulse(elapsed: Duration, total: Duration) -> f64 {
+        let elapsed = elapsed.as_secs_f64();
+        let total = total.as_secs_f64().max(f64::EPSILON);
+
+        // The frequency rises linearly - the phase is its integral
+        let phase = Self::PULSE_SLOW * elapsed + (Self::PULSE_FAST - Self::PULSE_SLOW) * elapsed * elapsed / (2.0 * total);
+        return 0.6 + 0.4 * (phase * TAU).cos();
+    }
+
     pub fn new(players: &mut Players) -> Self {
         // Reset all controllers
         for player in players.iter_mut() {
@@ -22,16 +112,87 @@ impl Lobby {
 
         return Self {
             ready: HashSet::new(),
+            restored: HashSet::new(),
+            idle: Duration::ZERO,
+            auto_start: None,
+            cancelled: false,
+            selecting: HashSet::new(),
+            holding: HashMap::new(),
+            flash: Duration::ZERO,
+        };
+    }
+
+    /// Creates the lobby with the players ready before a restart
+    pub fn restore(players: &mut Players, ready: HashSet<PlayerId>) -> Self {
+        return Self {
+            restored: ready,
+            ..Self::new(players)
         };
     }
 
-    pub fn update(mut self, world: &mut World) -> State {
+    /// Continues the lobby after the attract mode keeping the ready players
+    pub fn resume(mut self) -> Self {
+        self.idle = Duration::ZERO;
+        return self;
+    }
+
+    pub fn update(mut self, world: &mut World, duration: Duration) -> State {
         // Players can start the game by pressing the start button. But only if more than one player
         // is ready. By this they will become ready themself.
         let mut start = false;
 
+        // The player pressing start is told if the game can not be started
+        let mut starter = None;
+
+        let ready = self.ready.len();
+        let brightness = match (self.auto_start, world.settings.auto_start) {
+            (Some(elapsed), Some(total)) => Self::pulse(elapsed, total),
+            _ => 1.0,
+        };
+
         for player in world.players.iter_mut() {
-            if !self.ready.contains(&player.id()) && player.input().buttons.trigger.0 {
+            // Charging controllers only show their battery state
+            if player.charging_only() {
+                self.ready.remove(&player.id());
+                player.color.set(debug::battery_to_color(player.battery()));
+                continue;
+            }
+
+            if self.restored.remove(&player.id()) {
+                debug!("Player {} ready again after restart", player.id());
+                self.ready.insert(player.id());
+            }
+
+            // Holding select turns square and circle into picking the previous or next game mode
+            let buttons = &player.input().buttons;
+            let selecting = buttons.select.is_down();
+            if !selecting {
+                self.selecting.remove(&player.id());
+            } else if [&buttons.square, &buttons.circle, &buttons.triangle, &buttons.cross].iter().any(|button| button.is_down()) {
+                // Players combining select with another button are not leaving
+                self.selecting.insert(player.id());
+            }
+
+            let step = match (selecting, player.input().buttons.square.pressed(), player.input().buttons.circle.pressed()) {
+                (true, true, _) => Some(-1),
+                (true, _, true) => Some(1),
+                _ => None,
+            };
+
+            if let Some(step) = step {
+                world.settings.game_mode = Self::cycle(world.settings.game_mode, step);
+                self.selecting.insert(player.id());
+                self.flash = Self::SELECTED_FLASH;
+
+                debug!("Player {} selected game mode {:?}", player.id(), world.settings.game_mode);
+
+                player.rumble.animate(keyframes![
+                    0.00 => 64,
+                    0.05 => 0,
+                ]);
+            }
+
+            if !self.ready.contains(&player.id()) && player.input().buttons.trigger.0.pressed() {
                 self.ready.insert(player.id());
 
                 debug!("Player {} ready ({})", player.id(), self.ready.len());
@@ -42,48 +203,337 @@ impl Lobby {
                 ]);
             }
 
-            if self.ready.len() >= 2 && player.input().buttons.start {
+            let held = if selecting && !self.selecting.contains(&player.id()) {
+                let held = self.holding.entry(player.id()).or_default();
+                *held += duration;
+                *held
+            } else {
+                self.holding.remove(&player.id());
+                Duration::ZERO
+            };
+
+            // Players changing their mind get a double buzz to tell it apart from getting ready
+            if self.ready.contains(&player.id()) && held >= Self::UNREADY_HOLD {
+                self.ready.remove(&player.id());
+
+                debug!("Player {} not ready anymore ({})", player.id(), self.ready.len());
+
+                player.rumble.animate(keyframes![
+                    0.00 => 128,
+                    0.10 => 0,
+                    0.10 => 128,
+                    0.10 => 0,
+                ]);
+            }
+
+            // Ready players can stop the automatic start
+            if self.auto_start.is_some() && self.ready.contains(&player.id()) && !selecting && player.input().buttons.cross.pressed() {
+                debug!("Player {} cancelled the automatic start", player.id());
+                self.cancel();
+
+                player.rumble.animate(keyframes![
+                    0.00 => 128,
+                    0.20 => 0,
+                ]);
+            }
+
+            if self.ready.len() >= 2 && player.input().buttons.start.pressed() {
                 self.ready.insert(player.id());
                 start = true;
+                starter = Some(player.id());
                 debug!("Starting on player {} request", player.id());
             }
 
-            if player.input().buttons.circle {
+            // Players can make the games more forgiving for themself
+            if !selecting && player.input().buttons.triangle.pressed() {
+                player.set_threshold(Self::next_threshold(player.threshold()));
+                debug!("Player {} threshold set to {}", player.id(), player.threshold());
+            }
+
+            if !self.flash.is_zero() {
+                player.color.set(Self::mode_color(world.settings.game_mode, world.settings.theme));
+            } else if player.input().buttons.circle.is_down() {
                 player.color.set(debug::battery_to_color(player.battery()));
+            } else if player.input().buttons.triangle.is_down() {
+                player.color.set(world.settings.theme.level(Self::threshold_level(player.threshold())));
             } else if self.ready.contains(&player.id()) {
-                player.color.set(RGBColor { r: 1.0, g: 1.0, b: 1.0 });
+                player.color.set(RGBColor { r: brightness, g: brightness, b: brightness });
             } else {
                 player.color.set(RGBColor { r: 0.0, g: 0.0, b: 0.0 });
             }
         }
 
-        if self.ready.len() >= 2 && self.ready.len() >= world.players.count() {
+        self.flash = self.flash.saturating_sub(duration);
+
+        if world.players.iter().any(|player| player.input().buttons.all().iter().any(|button| button.pressed())) {
+            self.idle = Duration::ZERO;
+        } else {
+            self.idle += duration;
+        }
+
+        if world.settings.attract_after.map_or(false, |after| self.idle >= after) {
+            return State::Attract(Attract::new(self, world));
+        }
+
+        // More players getting ready restart the automatic start to give others time to join
+        if self.ready.len() > ready {
+            self.auto_start = None;
+            self.cancelled = false;
+        }
+
+        if let Some(total) = world.settings.auto_start {
+            if self.cancelled || Self::check(world.settings.game_mode, self.ready.len()).is_err() {
+                self.auto_start = None;
+            } else {
+                let elapsed = self.auto_start.get_or_insert(Duration::ZERO);
+                *elapsed += duration;
+
+                if *elapsed >= total {
+                    debug!("Starting automatically");
+                    start = true;
+                }
+            }
+        }
+
+        let playable = world.players.iter()
+            .filter(|player| !player.charging_only())
+            .count();
+
+        // Waits for the players ready before a restart instead of starting with the first ones back
+        if self.ready.len() >= 2 && self.ready.len() >= playable && self.restored.is_empty() {
             debug!("Starting as all players are ready");
             start = true;
         }
 
         if start {
+            if let Err(err) = Self::check(world.settings.game_mode, self.ready.len()) {
+                debug!("Not starting: {}", err);
+
+                if let Some(player) = starter.and_then(|starter| world.players.get_mut(starter)) {
+                    player.rumble.animate(keyframes![
+                        0.00 => 255,
+                        0.40 => 0,
+                    ]);
+                }
+
+                return State::Lobby(self);
+            }
+
             debug!("Starting game {:?}", world.settings.game_mode);
-            return world.settings.game_mode.create(self.ready, world);
+            return Self::launch(self.ready, world);
         }
 
         return State::Lobby(self);
     }
 
-    pub fn start(self, world: &mut World) -> (State, bool) {
-        if self.ready.len() >= 2 {
-            debug!("Starting game {:?} by external event", world.settings.game_mode);
-            return (world.settings.game_mode.create(self.ready, world), true);
-        } else {
-            return (State::Lobby(self), false);
+    /// The players to start with - the ready players if none are given
+    fn players(&self, world: &World, mode: GameMode, players: Option<HashSet<PlayerId>>) -> Result<HashSet<PlayerId>, StartGameError> {
+        let players = match players {
+            Some(players) => {
+                if let Some(player) = players.iter().find(|player| world.players.get(**player).is_none()) {
+                    return Err(StartGameError::NoSuchPlayer(*player));
+                }
+
+                if let Some(player) = players.iter().find(|player| world.players.get(**player).map_or(false, |player| player.charging_only())) {
+                    return Err(StartGameError::ChargingOnly(*player));
+                }
+
+                players
+            }
+            None => self.ready.clone(),
+        };
+
+        Self::check(mode, players.len())?;
+
+        return Ok(players);
+    }
+
+    /// Checks if the game mode can be played by the given number of players
+    fn check(mode: GameMode, players: usize) -> Result<(), StartGameError> {
+        if !mode.accepts(players) {
+            return Err(StartGameError::PlayerCount {
+                mode,
+                players,
+                min: mode.min_players,
+                max: mode.max_players,
+            });
+        }
+
+        return Ok(());
+    }
+
+    /// Starts the game with the ready players or, if given, with an explicit set of players
+    pub fn start(self, world: &mut World, players: Option<HashSet<PlayerId>>) -> (State, Result<(), StartGameError>) {
+        let players = match self.players(world, world.settings.game_mode, players) {
+            Ok(players) => players,
+            Err(err) => return (State::Lobby(self), Err(err)),
+        };
+
+        debug!("Starting game {:?} with players {:?} by external event", world.settings.game_mode, players);
+        return (Self::launch(players, world), Ok(()));
+    }
+
+    /// Creates a game of the selected mode - the players of modes played in teams pick their
+    /// teams first
+    fn launch(players: HashSet<PlayerId>, world: &mut World) -> State {
+        let mode = world.settings.game_mode;
+        if mode.teams.is_some() {
+            return State::Teams(TeamSelection::new(mode, players, world));
+        }
+
+        let options = world.settings.options.clone();
+        return mode.create(players, &options, world);
+    }
+
+    pub fn start_tournament(self, world: &mut World, setup: Setup) -> (State, Result<(), StartGameError>) {
+        if setup.rounds == 0 {
+            return (State::Lobby(self), Err(StartGameError::NoRounds));
+        }
+
+        let mode = setup.mode.unwrap_or(world.settings.game_mode);
+
+        let players = match self.players(world, mode, setup.players) {
+            Ok(players) => players,
+            Err(err) => return (State::Lobby(self), Err(err)),
+        };
+
+        let options = world.settings.options.clone();
+        return (Tournament::start(mode, options, setup.rounds, players, world), Ok(()));
+    }
+
+    pub fn start_match(self, world: &mut World, setup: best_of::Setup) -> (State, Result<(), StartGameError>) {
+        if setup.wins == 0 {
+            return (State::Lobby(self), Err(StartGameError::NoWins));
         }
+
+        let mode = setup.mode.unwrap_or(world.settings.game_mode);
+
+        let players = match self.players(world, mode, setup.players) {
+            Ok(players) => players,
+            Err(err) => return (State::Lobby(self), Err(err)),
+        };
+
+        let options = world.settings.options.clone();
+        return (Match::start(mode, options, setup.wins, players, world), Ok(()));
+    }
+
+    /// Stops the automatic start until more players get ready. Returns whether it was counting
+    /// down.
+    pub fn cancel(&mut self) -> bool {
+        let running = self.auto_start.take().is_some();
+        self.cancelled |= running;
+        return running;
     }
 
     pub fn kick_player(&mut self, player: PlayerId) -> bool {
+        self.restored.remove(&player);
         return self.ready.remove(&player);
     }
 
     pub fn ready(&self) -> &HashSet<PlayerId> {
         return &self.ready;
     }
-}
\ No newline at end of file
+
+    /// The ready players including the ones not reconnected since a restart
+    pub fn remembered(&self) -> HashSet<PlayerId> {
+        return self.ready.union(&self.restored).copied().collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hastilude_core::controller::{Battery, Button, Buttons, Controller, feed, Input, Model, Reading};
+    use hastilude_core::engine::assets::Assets;
+
+    use crate::games::{practice, relay};
+    use crate::simulation::Sandbox;
+    use crate::state::Settings;
+
+    #[test]
+    fn test_next_threshold() {
+        assert_eq!(Lobby::next_threshold(1.0), 1.5);
+        assert_eq!(Lobby::next_threshold(2.0), 1.0);
+
+        // Thresholds set from the web continue with the next level
+        assert_eq!(Lobby::next_threshold(1.2), 1.5);
+        assert_eq!(Lobby::threshold_level(1.2), 0);
+        assert_eq!(Lobby::threshold_level(2.5), 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let relay = GameMode::of(&relay::MODE);
+        assert!(Lobby::check(relay, 4).is_ok());
+        assert!(matches!(Lobby::check(relay, 3), Err(StartGameError::PlayerCount { players: 3, min: 4, max: None, .. })));
+
+        let practice = GameMode::of(&practice::MODE);
+        assert!(Lobby::check(practice, 1).is_ok());
+        assert!(Lobby::check(practice, 0).is_err());
+    }
+
+    #[test]
+    fn test_cycle() {
+        let first = Lobby::selectable().next().unwrap();
+        let last = Lobby::selectable().last().unwrap();
+
+        assert_eq!(Lobby::cycle(last, 1), first);
+        assert_eq!(Lobby::cycle(first, -1), last);
+        assert_eq!(Lobby::cycle(Lobby::cycle(first, 1), -1), first);
+
+        // Every mode has its own color
+        let hues = Lobby::selectable().map(Lobby::mode_hue).collect::<Vec<_>>();
+        assert!(hues.windows(2).all(|pair| pair[0] < pair[1] && pair[1] < 360.0));
+
+        // The debug mode can not be picked from the controllers
+        let debug = GameMode::of(&debug::MODE);
+        assert!((0..GameMode::all().count() as isize).all(|step| Lobby::cycle(first, step) != debug));
+    }
+
+    #[test]
+    fn test_pulse() {
+        let total = Duration::from_secs(10);
+        assert_eq!(Lobby::pulse(Duration::ZERO, total), 1.0);
+
+        for ms in (0..10_000).step_by(100) {
+            let brightness = Lobby::pulse(Duration::from_millis(ms), total);
+            assert!((0.2 - 1e-9..=1.0 + 1e-9).contains(&brightness));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unready() {
+        let assets = Assets::empty();
+        let mut sandbox = Sandbox::new(&assets, Settings::default(), Some(1));
+
+        let (feed, device) = feed::channel(Sandbox::address(0), Model::CECH_ZCM1);
+        sandbox.players.add(Controller::new(Box::new(device)));
+
+        let id = sandbox.players.keys().next().unwrap();
+        let mut lobby = Lobby::restore(&mut sandbox.players, HashSet::from([id]));
+
+        // The hold is timed by the frames - the frames pass much faster than the wall clock here
+        let frame = Duration::from_millis(500);
+        for frames in 1..=4 {
+            feed.push(Reading {
+                input: Input {
+                    buttons: Buttons {
+                        select: Button::new(true),
+                        ..Buttons::default()
+                    },
+                    ..Input::default()
+                },
+                battery: Battery::Charged,
+                temperature: None,
+            });
+            sandbox.players.update(frame).await.unwrap();
+
+            lobby = match lobby.update(&mut sandbox.world(), frame) {
+                State::Lobby(lobby) => lobby,
+                _ => panic!("Expected to stay in the lobby"),
+            };
+            assert_eq!(lobby.ready().contains(&id), frames < 4);
+        }
+    }
+}
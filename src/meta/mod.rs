@@ -1,3 +1,7 @@
+pub mod attract;
 pub mod celebration;
 pub mod countdown;
-pub mod lobby;
\ No newline at end of file
+pub mod intermission;
+pub mod lobby;
+pub mod pause;
+pub mod teams;
\ No newline at end of file
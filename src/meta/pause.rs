@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use tracing::debug;
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::events::Event;
+use crate::games::GameState;
+use crate::state::{JoinPlayerError, State, World};
+
+/// Whether any of the players asks to pause or resume the game by holding select while pressing
+/// start
+pub fn requested(world: &World, players: &HashSet<PlayerId>) -> bool {
+    return world.players.iter()
+        .filter(|player| players.contains(&player.id()))
+        .any(|player| player.input().buttons.select.is_down() && player.input().buttons.start.pressed());
+}
+
+/// A running game halted until it is resumed
+pub struct Paused {
+    game: GameState,
+}
+
+impl Paused {
+    /// Brightness of all controllers while the game is paused
+    pub const BRIGHTNESS: f32 = 0.2;
+
+    pub fn new(mut game: GameState, world: &mut World) -> Self {
+        debug!("Pausing game");
+
+        game.suspend(world);
+
+        world.events.emit(Event::GamePaused);
+        world.announce("pause");
+
+        return Self {
+            game,
+        };
+    }
+
+    pub fn resume(mut self, world: &mut World) -> State {
+        debug!("Resuming game");
+
+        self.game.resume(world);
+
+        world.events.emit(Event::GameResumed);
+        world.announce("resume");
+
+        return State::Playing(self.game);
+    }
+
+    pub fn update(self, world: &mut World) -> State {
+        if requested(world, self.game.players()) {
+            return self.resume(world);
+        }
+
+        return State::Paused(self);
+    }
+
+    pub fn kick_player(&mut self, player: PlayerId, world: &mut World) -> bool {
+        return self.game.kick_player(player, world);
+    }
+
+    pub fn join_player(&mut self, player: PlayerId, world: &mut World) -> Result<(), JoinPlayerError> {
+        return self.game.join_player(player, world);
+    }
+
+    /// The players taking part in the game if the others show the idle animation
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return self.game.participants();
+    }
+
+    pub fn reconfigure(&mut self, world: &mut World) {
+        self.game.reconfigure(world);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+
+use scarlet::color::RGBColor;
+use tracing::debug;
+
+use hastilude_core::controller::{Button, Buttons};
+use hastilude_core::engine::players::PlayerId;
+use hastilude_core::keyframes;
+
+use crate::games::{GameMode, Lineup};
+use crate::palette::Theme;
+use crate::state::{JoinPlayerError, StartGameError, State, World};
+
+/// Lets the players of a mode played in teams pick their team with the face buttons before the
+/// countdown starts
+pub struct TeamSelection {
+    mode: GameMode,
+
+    players: HashSet<PlayerId>,
+
+    // The team picked by each player
+    picks: HashMap<PlayerId, usize>,
+}
+
+impl TeamSelection {
+    // Hue of the team picked by each face button - square, cross, circle and triangle
+    const HUES: [f64; 4] = [0.0, 2.0 / 3.0, 1.0 / 3.0, 1.0 / 6.0];
+
+    // Brightness of players not having picked a team yet
+    const UNPICKED_DIM: f64 = 0.2;
+
+    pub fn new(mode: GameMode, players: HashSet<PlayerId>, world: &mut World) -> Self {
+        debug!("Picking teams for {:?} with players {:?}", mode, players);
+        world.announce("teams");
+
+        return Self {
+            mode,
+            players,
+            picks: HashMap::new(),
+        };
+    }
+
+    /// The team picked by pressing a face button
+    fn picked(buttons: &Buttons) -> Option<usize> {
+        return [&buttons.square, &buttons.cross, &buttons.circle, &buttons.triangle].iter()
+            .position(|button| button.pressed());
+    }
+
+    /// Holds down the face button picking the team - used by virtual players
+    pub fn press(buttons: &mut Buttons, team: usize) {
+        let button = match team {
+            0 => &mut buttons.square,
+            1 => &mut buttons.cross,
+            2 => &mut buttons.circle,
+            _ => &mut buttons.triangle,
+        };
+
+        *button = Button::new(true);
+    }
+
+    fn color(team: usize, theme: Theme) -> RGBColor {
+        return theme.hue(Self::HUES[team], 1.0);
+    }
+
+    /// Number of teams the players can pick from
+    fn teams(&self) -> usize {
+        return self.mode.teams.as_ref().map_or(0, |teams| teams.max).min(Self::HUES.len());
+    }
+
+    pub fn update(mut self, world: &mut World) -> State {
+        // Players gone for good do not hold up the others
+        let gone = self.players.iter()
+            .copied()
+            .filter(|player| world.players.get(*player).is_none() && !world.players.is_disconnected(*player))
+            .collect::<Vec<_>>();
+        for player in gone {
+            debug!("Player {} left while picking teams", player);
+            self.kick_player(player);
+        }
+
+        if !self.mode.accepts(self.players.len()) {
+            debug!("Not enough players left to pick teams");
+            return State::lobby(world.players);
+        }
+
+        let mut start = None;
+
+        let theme = world.settings.theme;
+        for player in world.players.iter_mut() {
+            if !self.players.contains(&player.id()) {
+                continue;
+            }
+
+            if let Some(team) = Self::picked(&player.input().buttons).filter(|team| *team < self.teams()) {
+                debug!("Player {} picked team {}", player.id(), team);
+                self.picks.insert(player.id(), team);
+
+                player.rumble.animate(keyframes![
+                    0.00 => 64,
+                    0.05 => 0,
+                ]);
+            }
+
+            if player.input().buttons.start.pressed() {
+                start = Some(player.id());
+            }
+
+            player.color.set(match self.picks.get(&player.id()) {
+                Some(team) => Self::color(*team, theme),
+                None => RGBColor { r: Self::UNPICKED_DIM, g: Self::UNPICKED_DIM, b: Self::UNPICKED_DIM },
+            });
+        }
+
+        if let Some(starter) = start {
+            return match self.start(world) {
+                (state, Ok(())) => state,
+                (state, Err(err)) => {
+                    debug!("Not starting: {}", err);
+
+                    if let Some(player) = world.players.get_mut(starter) {
+                        player.rumble.animate(keyframes![
+                            0.00 => 255,
+                            0.40 => 0,
+                        ]);
+                    }
+
+                    state
+                }
+            };
+        }
+
+        return State::Teams(self);
+    }
+
+    /// The picked teams if all players have picked one and the teams are balanced
+    fn lineups(&self) -> Result<Vec<Lineup>, StartGameError> {
+        let unpicked = self.players.iter()
+            .copied()
+            .filter(|player| !self.picks.contains_key(player))
+            .collect::<HashSet<_>>();
+
+        if !unpicked.is_empty() {
+            return Err(StartGameError::TeamMissing { players: unpicked });
+        }
+
+        let lineups = Self::HUES.iter()
+            .enumerate()
+            .map(|(team, hue)| Lineup {
+                hue: *hue,
+                roster: self.picks.iter()
+                    .filter(|(_, picked)| **picked == team)
+                    .map(|(player, _)| *player)
+                    .collect(),
+            })
+            .filter(|lineup| !lineup.roster.is_empty())
+            .collect::<Vec<_>>();
+
+        let (min, max) = self.mode.teams.as_ref().map_or((0, 0), |teams| (teams.min, teams.max));
+        if lineups.len() < min || lineups.len() > max {
+            return Err(StartGameError::TeamCount {
+                mode: self.mode,
+                teams: lineups.len(),
+                min,
+                max,
+            });
+        }
+
+        let sizes = lineups.iter().map(|lineup| lineup.roster.len()).collect::<Vec<_>>();
+        if sizes.iter().max().unwrap_or(&0) - sizes.iter().min().unwrap_or(&0) > 1 {
+            return Err(StartGameError::TeamBalance { sizes });
+        }
+
+        return Ok(lineups);
+    }
+
+    /// Starts the game with the picked teams
+    pub fn start(self, world: &mut World) -> (State, Result<(), StartGameError>) {
+        return match self.lineups() {
+            Ok(lineups) => {
+                let options = world.settings.options.clone();
+                (self.mode.create_teams(lineups, &options, world), Ok(()))
+            }
+            Err(err) => (State::Teams(self), Err(err)),
+        };
+    }
+
+    pub fn kick_player(&mut self, player: PlayerId) -> bool {
+        self.picks.remove(&player);
+        return self.players.remove(&player);
+    }
+
+    pub fn join_player(&mut self, player: PlayerId) -> Result<(), JoinPlayerError> {
+        if !self.players.insert(player) {
+            return Err(JoinPlayerError::AlreadyPlaying(player));
+        }
+
+        return Ok(());
+    }
+
+    /// The players picking teams - the others show the idle animation
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
+        return Some(&self.players);
+    }
+
+    pub fn picks(&self) -> &HashMap<PlayerId, usize> {
+        return &self.picks;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use hastilude_core::controller::{Controller, feed, Model};
+    use hastilude_core::engine::assets::Assets;
+
+    use crate::games::territory;
+    use crate::simulation::Sandbox;
+    use crate::state::Settings;
+
+    #[test]
+    fn test_lineups() {
+        let mut selection = TeamSelection {
+            mode: GameMode::of(&territory::MODE),
+            players: HashSet::from([1, 2, 3, 4]),
+            picks: HashMap::from([(1, 0), (2, 1), (3, 0)]),
+        };
+        assert!(matches!(selection.lineups(), Err(StartGameError::TeamMissing { .. })));
+
+        selection.picks.insert(4, 0);
+        assert!(matches!(selection.lineups(), Err(StartGameError::TeamBalance { .. })));
+
+        selection.picks.insert(4, 2);
+        assert!(matches!(selection.lineups(), Err(StartGameError::TeamCount { teams: 3, .. })));
+
+        selection.picks.insert(4, 1);
+        let lineups = selection.lineups().unwrap();
+        assert_eq!(lineups.len(), 2);
+        assert_eq!(lineups[0].hue, TeamSelection::HUES[0]);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let assets = Assets::empty();
+        let mut sandbox = Sandbox::new(&assets, Settings::default(), Some(1));
+
+        for i in 0..5 {
+            sandbox.players.add(Controller::new(Box::new(feed::channel(Sandbox::address(i), Model::CECH_ZCM1).1)));
+        }
+
+        let mut ids = sandbox.players.keys().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        let mut selection = TeamSelection::new(GameMode::of(&territory::MODE), ids.iter().copied().collect(), &mut sandbox.world());
+        selection.picks.extend([(ids[0], 0), (ids[1], 1), (ids[2], 0), (ids[3], 1)]);
+
+        // The player who did not pick a team is gone
+        sandbox.players.remove(ids[4]);
+        let selection = match selection.update(&mut sandbox.world()) {
+            State::Teams(selection) => selection,
+            _ => panic!("Expected to keep picking teams"),
+        };
+        assert_eq!(selection.players.len(), 4);
+        assert!(selection.lineups().is_ok());
+
+        // Too few players are left for the mode
+        sandbox.players.remove(ids[3]);
+        assert!(matches!(selection.update(&mut sandbox.world()), State::Lobby(_)));
+    }
+}
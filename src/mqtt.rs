@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event as MqttEvent, MqttOptions, Packet, QoS};
+use serde_json::json;
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, instrument, warn};
+
+use hastilude_core::controller::Address;
+use hastilude_core::engine::players::PlayerId;
+
+use crate::events::Event;
+use crate::state::request::Stub;
+use crate::web::StateDTO;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "mqtt")]
+pub struct Config {
+    /// MQTT broker to connect to - the bridge is disabled if not set
+    #[arg(long = "mqtt-host")]
+    pub host: Option<String>,
+
+    #[arg(long = "mqtt-port", default_value_t = 1883)]
+    pub port: u16,
+
+    /// Prefix for all published and subscribed topics
+    #[arg(long = "mqtt-prefix", default_value = "hastilude")]
+    pub prefix: String,
+
+    /// Client id to connect with - defaults to the topic prefix, so every instance needs its own
+    /// prefix or client id
+    #[arg(long = "mqtt-client-id")]
+    pub client_id: Option<String>,
+
+    /// Announce entities to Home Assistant using this discovery prefix
+    #[arg(long = "mqtt-discovery")]
+    pub discovery: Option<String>,
+}
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+fn publish(client: &AsyncClient, topic: String, retain: bool, payload: impl Into<Vec<u8>>) {
+    if let Err(err) = client.try_publish(&topic, QoS::AtLeastOnce, retain, payload) {
+        warn!("Failed to publish to {}: {}", topic, err);
+    }
+}
+
+fn publish_state(client: &AsyncClient, prefix: &str, state: &StateDTO) {
+    publish(client, format!("{}/state", prefix), true, serde_json::to_vec(state)
+        .expect("Failed to serialize state"));
+
+    for device in state.devices.iter() {
+        publish(client, format!("{}/battery/{}", prefix, device.address.as_string()), true, serde_json::to_vec(&device.battery)
+            .expect("Failed to serialize battery"));
+    }
+}
+
+/// Home Assistant MQTT discovery announcements
+struct Discovery {
+    prefix: String,
+
+    // Controllers for which battery sensors have been announced
+    announced: HashSet<Address>,
+}
+
+impl Discovery {
+    fn device() -> serde_json::Value {
+        return json!({
+            "identifiers": ["hastilude"],
+            "name": "Hastilude",
+        });
+    }
+
+    fn announce(&self, client: &AsyncClient, component: &str, object: &str, config: serde_json::Value) {
+        publish(client, format!("{}/{}/hastilude/{}/config", self.prefix, component, object), true, config.to_string());
+    }
+
+    /// Announces the static entities
+    fn announce_game(&mut self, client: &AsyncClient, prefix: &str) {
+        self.announced.clear();
+
+        self.announce(client, "sensor", "state", json!({
+            "name": "Game state",
+            "unique_id": "hastilude_state",
+            "state_topic": format!("{}/state", prefix),
+            "value_template": "{{ 'running' if 'Running' in value_json.state else 'waiting' }}",
+            "device": Self::device(),
+        }));
+
+        self.announce(client, "sensor", "players", json!({
+            "name": "Players",
+            "unique_id": "hastilude_players",
+            "state_topic": format!("{}/state", prefix),
+            "value_template": "{{ value_json.devices | length }}",
+            "device": Self::device(),
+        }));
+
+        for (command, name) in [("start", "Start game"), ("cancel", "Cancel game")] {
+            self.announce(client, "button", command, json!({
+                "name": name,
+                "unique_id": format!("hastilude_{}", command),
+                "command_topic": format!("{}/command/{}", prefix, command),
+                "payload_press": "",
+                "device": Self::device(),
+            }));
+        }
+    }
+
+    /// Announces battery sensors for controllers seen for the first time
+    fn announce_batteries(&mut self, client: &AsyncClient, prefix: &str, state: &StateDTO) {
+        for device in state.devices.iter() {
+            if !self.announced.insert(device.address) {
+                continue;
+            }
+
+            let address = device.address.as_string();
+            let object = address.replace(':', "").to_lowercase();
+
+            self.announce(client, "sensor", &format!("battery_{}", object), json!({
+                "name": format!("Battery {}", address),
+                "unique_id": format!("hastilude_battery_{}", object),
+                "state_topic": format!("{}/battery/{}", prefix, address),
+                "value_template": "{{ (value_json.Draining * 100) | round(0) if value_json is mapping else 'unknown' }}",
+                "device_class": "battery",
+                "unit_of_measurement": "%",
+                "device": Self::device(),
+            }));
+        }
+    }
+}
+
+async fn command(stub: &mut Stub, command: &str, payload: &[u8]) {
+    match command {
+        "start" => {
+            // Starts with the ready players without a payload
+            let players = if payload.is_empty() {
+                None
+            } else {
+                match serde_json::from_slice::<Vec<PlayerId>>(payload) {
+                    Ok(players) => Some(players.into_iter().collect()),
+                    Err(err) => {
+                        warn!("Invalid players to start with: {}", err);
+                        return;
+                    }
+                }
+            };
+
+            if let Err(err) = stub.start_game(players).await {
+                warn!("Failed to start game: {}", err);
+            }
+        }
+
+        "cancel" => {
+            if let Err(err) = stub.cancel_game().await {
+                warn!("Failed to cancel game: {}", err);
+            }
+        }
+
+        "mode" => {
+            match String::from_utf8_lossy(payload).trim().parse() {
+                Ok(mode) => stub.game_mode(mode).await,
+                Err(err) => warn!("Invalid game mode: {}", err),
+            }
+        }
+
+        _ => {
+            warn!("Unknown command: {}", command);
+        }
+    }
+}
+
+/// Publishes state and events to the broker and forwards received commands to the game
+#[instrument(level = "debug", skip(stub, info, events))]
+pub async fn run(config: Config,
+                 mut stub: Stub,
+                 mut info: watch::Receiver<StateDTO>,
+                 mut events: broadcast::Receiver<Event>) {
+    let host = config.host.clone().expect("MQTT host not configured");
+
+    let client_id = config.client_id.as_deref().unwrap_or(&config.prefix);
+
+    let mut options = MqttOptions::new(client_id, &host, config.port);
+    options.set_keep_alive(Duration::from_secs(10));
+
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+
+    let commands = format!("{}/command/", config.prefix);
+
+    let mut discovery = config.discovery.clone().map(|prefix| Discovery {
+        prefix,
+        announced: HashSet::new(),
+    });
+
+    info!("MQTT bridge connecting to {}:{}", host, config.port);
+
+    loop {
+        tokio::select! {
+            notification = eventloop.poll() => match notification {
+                Ok(MqttEvent::Incoming(Packet::ConnAck(_))) => {
+                    debug!("MQTT connected");
+
+                    // Subscriptions do not survive reconnects
+                    if let Err(err) = client.try_subscribe(format!("{}#", commands), QoS::AtLeastOnce) {
+                        warn!("Failed to subscribe to commands: {}", err);
+                    }
+
+                    // Retained messages may be outdated after reconnecting
+                    let state = info.borrow_and_update().clone();
+
+                    if let Some(discovery) = discovery.as_mut() {
+                        discovery.announce_game(&client, &config.prefix);
+                        discovery.announce_batteries(&client, &config.prefix, &state);
+                    }
+
+                    publish_state(&client, &config.prefix, &state);
+                }
+
+                Ok(MqttEvent::Incoming(Packet::Publish(message))) => {
+                    if let Some(name) = message.topic.strip_prefix(&commands) {
+                        command(&mut stub, name, &message.payload).await;
+                    }
+                }
+
+                Ok(_) => {}
+
+                Err(err) => {
+                    warn!("MQTT connection failed: {}", err);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            },
+
+            changed = info.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+
+                let state = info.borrow_and_update().clone();
+
+                if let Some(discovery) = discovery.as_mut() {
+                    discovery.announce_batteries(&client, &config.prefix, &state);
+                }
+
+                publish_state(&client, &config.prefix, &state);
+            }
+
+            event = events.recv() => match event {
+                Ok(event) => {
+                    publish(&client, format!("{}/event", config.prefix), false, serde_json::to_vec(&event)
+                        .expect("Failed to serialize event"));
+                }
+
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("MQTT bridge skipped {} events", skipped);
+                }
+
+                Err(broadcast::error::RecvError::Closed) => {
+                    break;
+                }
+            },
+        }
+    }
+}
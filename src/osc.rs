@@ -0,0 +1,275 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::net::UdpSocket;
+use tokio::sync::broadcast;
+use tracing::{info, instrument, warn};
+
+use crate::events::Event;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "osc")]
+pub struct Config {
+    /// Host to send OSC messages to - output is disabled if not set
+    #[arg(long = "osc-target", id = "osc-target")]
+    pub target: Option<SocketAddr>,
+
+    /// Prefix for all OSC addresses
+    #[arg(long = "osc-prefix", id = "osc-prefix", default_value = "/hastilude")]
+    pub prefix: String,
+
+    /// Tempo of the beat cues at normal music speed
+    #[arg(long = "osc-bpm", default_value_t = 120.0, value_parser = bpm)]
+    pub bpm: f32,
+}
+
+fn bpm(value: &str) -> Result<f32, String> {
+    let bpm: f32 = value.parse().map_err(|err| format!("{}", err))?;
+    if !(bpm > 0.0 && bpm.is_finite()) {
+        return Err(format!("tempo must be positive: {}", bpm));
+    }
+
+    return Ok(bpm);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Int(i32),
+    Float(f32),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub address: String,
+    pub arguments: Vec<Argument>,
+}
+
+impl Message {
+    pub fn new(address: impl Into<String>) -> Self {
+        return Self {
+            address: address.into(),
+            arguments: Vec::new(),
+        };
+    }
+
+    pub fn arg(mut self, argument: Argument) -> Self {
+        self.arguments.push(argument);
+        return self;
+    }
+
+    /// Encodes the message into an OSC 1.0 packet
+    pub fn encode(&self) -> Vec<u8> {
+        fn string(buffer: &mut Vec<u8>, s: &str) {
+            buffer.extend_from_slice(s.as_bytes());
+
+            // Strings are null terminated and padded to a multiple of four bytes
+            let padding = 4 - s.len() % 4;
+            buffer.extend(std::iter::repeat(0).take(padding));
+        }
+
+        let mut buffer = Vec::new();
+
+        string(&mut buffer, &self.address);
+
+        let tags = std::iter::once(',')
+            .chain(self.arguments.iter().map(|argument| match argument {
+                Argument::Int(_) => 'i',
+                Argument::Float(_) => 'f',
+                Argument::String(_) => 's',
+            }))
+            .collect::<String>();
+        string(&mut buffer, &tags);
+
+        for argument in self.arguments.iter() {
+            match argument {
+                Argument::Int(value) => buffer.extend_from_slice(&value.to_be_bytes()),
+                Argument::Float(value) => buffer.extend_from_slice(&value.to_be_bytes()),
+                Argument::String(value) => string(&mut buffer, value),
+            }
+        }
+
+        return buffer;
+    }
+}
+
+fn messages(prefix: &str, event: &Event) -> Vec<Message> {
+    return match event {
+        Event::GameStarted { mode, players } => vec![
+            Message::new(format!("{}/game/start", prefix))
+                .arg(Argument::String(mode.to_string()))
+                .arg(Argument::Int(players.len() as i32)),
+        ],
+
+        Event::GameCancelled => vec![
+            Message::new(format!("{}/game/cancel", prefix)),
+        ],
+
+        Event::GamePaused => vec![
+            Message::new(format!("{}/game/pause", prefix)),
+        ],
+
+        Event::GameResumed => vec![
+            Message::new(format!("{}/game/resume", prefix)),
+        ],
+
+        Event::EmergencyStop => vec![
+            Message::new(format!("{}/emergency-stop", prefix)),
+        ],
+
+        Event::PlayerJoined { player } => vec![
+            Message::new(format!("{}/player/joined", prefix))
+                .arg(Argument::String(player.to_string())),
+        ],
+
+        Event::PlayerEliminated { player, .. } => vec![
+            Message::new(format!("{}/player/eliminated", prefix))
+                .arg(Argument::String(player.to_string())),
+        ],
+
+        Event::GameFinished { winners } => vec![
+            winners.iter().fold(Message::new(format!("{}/game/finish", prefix))
+                                    .arg(Argument::Int(winners.len() as i32)),
+                                |message, winner| message.arg(Argument::String(winner.to_string()))),
+        ],
+
+        Event::GameDrawn => vec![
+            Message::new(format!("{}/game/draw", prefix)),
+        ],
+
+        Event::GameAborted => vec![
+            Message::new(format!("{}/game/abort", prefix)),
+        ],
+
+        Event::RoundFinished { winners } => vec![
+            winners.iter().fold(Message::new(format!("{}/match/round", prefix))
+                                    .arg(Argument::Int(winners.len() as i32)),
+                                |message, winner| message.arg(Argument::String(winner.to_string()))),
+        ],
+
+        Event::TournamentFinished { champions } => vec![
+            champions.iter().fold(Message::new(format!("{}/tournament/finish", prefix))
+                                      .arg(Argument::Int(champions.len() as i32)),
+                                  |message, champion| message.arg(Argument::String(champion.to_string()))),
+        ],
+
+        Event::MatchFinished { champions } => vec![
+            champions.iter().fold(Message::new(format!("{}/match/finish", prefix))
+                                      .arg(Argument::Int(champions.len() as i32)),
+                                  |message, champion| message.arg(Argument::String(champion.to_string()))),
+        ],
+
+        Event::MusicSpeed { speed } => vec![
+            Message::new(format!("{}/music/speed", prefix))
+                .arg(Argument::Float(*speed)),
+        ],
+    };
+}
+
+/// Sends OSC messages for game events and beat cues while a game is running
+#[instrument(level = "debug", skip(events))]
+pub async fn run(config: Config, mut events: broadcast::Receiver<Event>) -> Result<()> {
+    let target = config.target.expect("OSC target not configured");
+
+    let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+
+    info!("Sending OSC messages to {}", target);
+
+    let send = |message: Message| {
+        let socket = &socket;
+        async move {
+            if let Err(err) = socket.send_to(&message.encode(), target).await {
+                warn!("Failed to send OSC message {}: {}", message.address, err);
+            }
+        }
+    };
+
+    let beat = |speed: f32| Duration::from_secs_f32(60.0 / (config.bpm * speed));
+
+    // Beats are only generated while a game is running
+    let mut running = false;
+    let mut speed = 1.0;
+    let mut count = 0;
+
+    let mut ticker = tokio::time::interval(beat(speed));
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick(), if running => {
+                count += 1;
+                send(Message::new(format!("{}/beat", config.prefix))
+                    .arg(Argument::Int(count))).await;
+            }
+
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("OSC output skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Ok(());
+                    }
+                };
+
+                match event {
+                    Event::GameStarted { .. } => {
+                        running = true;
+                        speed = 1.0;
+                        count = 0;
+                        ticker = tokio::time::interval(beat(speed));
+                    }
+
+                    Event::GameCancelled | Event::EmergencyStop | Event::GameFinished { .. } | Event::GameDrawn | Event::GameAborted => {
+                        running = false;
+                    }
+
+                    // Beats continue counting where they stopped
+                    Event::GamePaused => running = false,
+                    Event::GameResumed => running = true,
+
+                    Event::MusicSpeed { speed: changed } => {
+                        speed = changed;
+                        ticker = tokio::time::interval(beat(speed));
+                    }
+
+                    _ => {}
+                }
+
+                for message in messages(&config.prefix, &event) {
+                    send(message).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_without_arguments() {
+        let message = Message::new("/test");
+
+        assert_eq!(message.encode(), b"/test\0\0\0,\0\0\0");
+    }
+
+    #[test]
+    fn test_encode_arguments() {
+        let message = Message::new("/abc")
+            .arg(Argument::Int(1))
+            .arg(Argument::Float(1.0))
+            .arg(Argument::String("xy".to_owned()));
+
+        assert_eq!(message.encode(), [
+            b"/abc\0\0\0\0".as_slice(),
+            b",ifs\0\0\0\0".as_slice(),
+            &[0x00, 0x00, 0x00, 0x01],
+            &[0x3F, 0x80, 0x00, 0x00],
+            b"xy\0\0".as_slice(),
+        ].concat());
+    }
+}
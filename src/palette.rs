@@ -0,0 +1,119 @@
+use scarlet::color::{Color, RGBColor};
+use scarlet::colors::HSVColor;
+use serde::{Deserialize, Serialize};
+
+/// Set of colors shown on the controllers
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Fully saturated colors from all around the color wheel
+    #[default]
+    Standard,
+
+    /// Colors distinguishable with all common forms of color blindness (Okabe-Ito)
+    ColorBlind,
+
+    /// Few colors as far apart from each other as possible
+    HighContrast,
+}
+
+impl Theme {
+    const COLOR_BLIND: [RGBColor; 7] = [
+        RGBColor { r: 0.90, g: 0.62, b: 0.00 },
+        RGBColor { r: 0.34, g: 0.71, b: 0.91 },
+        RGBColor { r: 0.00, g: 0.62, b: 0.45 },
+        RGBColor { r: 0.94, g: 0.89, b: 0.26 },
+        RGBColor { r: 0.00, g: 0.45, b: 0.70 },
+        RGBColor { r: 0.84, g: 0.37, b: 0.00 },
+        RGBColor { r: 0.80, g: 0.47, b: 0.65 },
+    ];
+
+    const HIGH_CONTRAST: [RGBColor; 6] = [
+        RGBColor { r: 1.0, g: 0.0, b: 0.0 },
+        RGBColor { r: 0.0, g: 0.0, b: 1.0 },
+        RGBColor { r: 1.0, g: 1.0, b: 0.0 },
+        RGBColor { r: 1.0, g: 1.0, b: 1.0 },
+        RGBColor { r: 0.0, g: 1.0, b: 0.0 },
+        RGBColor { r: 1.0, g: 0.0, b: 1.0 },
+    ];
+
+    // Colors of the steps of a scale from low to high
+    const LEVELS: [RGBColor; 3] = [
+        RGBColor { r: 0.0, g: 1.0, b: 0.0 },
+        RGBColor { r: 1.0, g: 1.0, b: 0.0 },
+        RGBColor { r: 1.0, g: 0.4, b: 0.0 },
+    ];
+    const COLOR_BLIND_LEVELS: [RGBColor; 3] = [
+        RGBColor { r: 0.00, g: 0.45, b: 0.70 },
+        RGBColor { r: 0.94, g: 0.89, b: 0.26 },
+        RGBColor { r: 0.84, g: 0.37, b: 0.00 },
+    ];
+    const HIGH_CONTRAST_LEVELS: [RGBColor; 3] = [
+        RGBColor { r: 0.0, g: 0.0, b: 1.0 },
+        RGBColor { r: 1.0, g: 1.0, b: 1.0 },
+        RGBColor { r: 1.0, g: 0.0, b: 0.0 },
+    ];
+
+    /// The colors of the theme - the standard theme uses the whole color wheel instead
+    fn colors(self) -> Option<&'static [RGBColor]> {
+        return match self {
+            Theme::Standard => None,
+            Theme::ColorBlind => Some(&Self::COLOR_BLIND),
+            Theme::HighContrast => Some(&Self::HIGH_CONTRAST),
+        };
+    }
+
+    /// The color at the given position around the color wheel (0.0 - 1.0) with the given brightness
+    ///
+    /// Themes with few colors pick the color covering the position - spreading positions evenly
+    /// gives distinct colors as long as there are enough of them.
+    pub fn hue(self, hue: f64, brightness: f64) -> RGBColor {
+        let hue = hue.rem_euclid(1.0);
+
+        let color = match self.colors() {
+            Some(colors) => colors[(hue * colors.len() as f64) as usize % colors.len()],
+            None => return HSVColor {
+                h: hue * 360.0,
+                s: 1.0,
+                v: brightness,
+            }.convert::<RGBColor>(),
+        };
+
+        return RGBColor {
+            r: color.r * brightness,
+            g: color.g * brightness,
+            b: color.b * brightness,
+        };
+    }
+
+    /// The color of a step on a scale from low to high - steps beyond the last one get its color
+    pub fn level(self, level: usize) -> RGBColor {
+        let levels = match self {
+            Theme::Standard => &Self::LEVELS,
+            Theme::ColorBlind => &Self::COLOR_BLIND_LEVELS,
+            Theme::HighContrast => &Self::HIGH_CONTRAST_LEVELS,
+        };
+
+        return levels[level.min(levels.len() - 1)];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hue() {
+        // Evenly spread positions get distinct colors
+        let colors = (0..6)
+            .map(|i| Theme::HighContrast.hue(i as f64 / 6.0, 1.0))
+            .collect::<Vec<_>>();
+        assert_eq!(colors, Theme::HIGH_CONTRAST.to_vec());
+
+        // Positions wrap around
+        assert_eq!(Theme::ColorBlind.hue(1.25, 1.0), Theme::ColorBlind.hue(0.25, 1.0));
+        assert_eq!(Theme::ColorBlind.hue(0.0, 0.5), RGBColor { r: 0.45, g: 0.31, b: 0.0 });
+
+        assert_eq!(Theme::ColorBlind.level(5), Theme::COLOR_BLIND_LEVELS[2]);
+    }
+}
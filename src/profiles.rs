@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::games::{GameMode, joust};
+use crate::state::Settings;
+
+#[derive(clap::Args, Debug)]
+#[group(id = "profile-settings")]
+pub struct Config {
+    /// JSON file with additional settings profiles by name
+    #[arg(long)]
+    pub profiles: Option<PathBuf>,
+
+    /// Settings profile to start with
+    #[arg(long)]
+    pub profile: Option<String>,
+}
+
+/// Settings tuned for a venue
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub game_mode: GameMode,
+    pub threshold: f32,
+    pub brightness: f32,
+    pub volume: f32,
+}
+
+impl Profile {
+    /// Checks that the settings can be used by the games and the engine
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.threshold > 0.0 && self.threshold.is_finite(), "Threshold must be positive: {}", self.threshold);
+        ensure!((0.0..=1.0).contains(&self.brightness), "Brightness must be within 0.0 - 1.0: {}", self.brightness);
+        ensure!((0.0..=1.0).contains(&self.volume), "Volume must be within 0.0 - 1.0: {}", self.volume);
+        return Ok(());
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        settings.game_mode = self.game_mode;
+        settings.threshold = self.threshold;
+        settings.brightness = self.brightness;
+        settings.volume = self.volume;
+    }
+}
+
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Profiles {
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    /// The built-in profiles
+    pub fn builtin() -> Self {
+        let mut profiles = BTreeMap::new();
+
+        profiles.insert("default".to_owned(), Profile {
+            game_mode: GameMode::of(&joust::MODE),
+            threshold: 1.0,
+            brightness: 1.0,
+            volume: 1.0,
+        });
+
+        // Players are close to each other and to the guests
+        profiles.insert("small-bar".to_owned(), Profile {
+            game_mode: GameMode::of(&joust::MODE),
+            threshold: 0.9,
+            brightness: 0.6,
+            volume: 0.5,
+        });
+
+        // Lots of space, bright light and loud surroundings
+        profiles.insert("festival-hall".to_owned(), Profile {
+            game_mode: GameMode::of(&joust::MODE),
+            threshold: 1.1,
+            brightness: 1.0,
+            volume: 1.0,
+        });
+
+        // Kids are moving a lot
+        profiles.insert("kids-party".to_owned(), Profile {
+            game_mode: GameMode::of(&joust::MODE),
+            threshold: 1.4,
+            brightness: 0.8,
+            volume: 0.6,
+        });
+
+        return Self { profiles };
+    }
+
+    /// Loads the built-in profiles and the ones from the given file, which take precedence
+    #[instrument(level = "debug")]
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut profiles = Self::builtin();
+
+        if let Some(path) = path {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open profiles: {:?}", path))?;
+
+            let custom: BTreeMap<String, Profile> = serde_json::from_reader(file)
+                .with_context(|| format!("Failed to parse profiles: {:?}", path))?;
+
+            for (name, profile) in custom.iter() {
+                profile.validate()
+                    .with_context(|| format!("Invalid profile {} in {:?}", name, path))?;
+            }
+
+            profiles.profiles.extend(custom);
+        }
+
+        return Ok(profiles);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        return self.profiles.get(name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        for (name, profile) in Profiles::builtin().profiles.iter() {
+            assert!(profile.validate().is_ok(), "{}", name);
+        }
+
+        let profile = Profile {
+            threshold: 0.0,
+            ..Profiles::builtin().profiles["default"].clone()
+        };
+        assert!(profile.validate().is_err());
+    }
+}
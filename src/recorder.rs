@@ -0,0 +1,111 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::events::Event;
+
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Time since the recorder has been started
+    pub monotonic: Duration,
+
+    /// Wall clock time as used for syncing with external recordings
+    pub wall: SystemTime,
+
+    pub event: Event,
+}
+
+#[derive(Serialize)]
+struct RecordDTO<'a> {
+    monotonic: f64,
+    wall: String,
+    event: &'a Event,
+}
+
+impl<'a> From<&'a Record> for RecordDTO<'a> {
+    fn from(record: &'a Record) -> Self {
+        return Self {
+            monotonic: record.monotonic.as_secs_f64(),
+            wall: humantime::format_rfc3339_millis(record.wall).to_string(),
+            event: &record.event,
+        };
+    }
+}
+
+/// Keeps all events of the session with timestamps
+#[derive(Clone)]
+pub struct Recorder {
+    records: Arc<Mutex<Vec<Record>>>,
+}
+
+impl Recorder {
+    /// Starts recording all events published from now on
+    pub fn start(mut events: broadcast::Receiver<Event>) -> Self {
+        let recorder = Self {
+            records: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let records = recorder.records.clone();
+        let started = Instant::now();
+
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => records.lock().expect("Recorder poisoned").push(Record {
+                        monotonic: started.elapsed(),
+                        wall: SystemTime::now(),
+                        event,
+                    }),
+
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Recorder missed {} events", skipped);
+                    }
+
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return;
+                    }
+                }
+            }
+        });
+
+        return recorder;
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        return self.records.lock().expect("Recorder poisoned").clone();
+    }
+
+    pub fn to_json(&self) -> String {
+        let records = self.records();
+        return serde_json::to_string(&records.iter()
+            .map(RecordDTO::from)
+            .collect::<Vec<_>>())
+            .expect("Failed to serialize records");
+    }
+
+    /// Exports the records as CSV with the event type and its remaining fields as JSON
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("monotonic,wall,type,details\n");
+
+        for record in self.records() {
+            let mut details = serde_json::to_value(&record.event)
+                .expect("Failed to serialize event");
+
+            let kind = details.as_object_mut()
+                .and_then(|details| details.remove("type"))
+                .and_then(|kind| kind.as_str().map(str::to_owned))
+                .unwrap_or_default();
+
+            csv.push_str(&format!("{:.3},{},{},\"{}\"\n",
+                                  record.monotonic.as_secs_f64(),
+                                  humantime::format_rfc3339_millis(record.wall),
+                                  kind,
+                                  details.to_string().replace('"', "\"\"")));
+        }
+
+        return csv;
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use minifb::{Window, WindowOptions};
+use tokio::sync::{broadcast, watch};
+use tracing::{info, instrument};
+
+use hastilude_core::engine::players::PlayerId;
+
+use crate::events::Event;
+use crate::games::debug::battery_to_color;
+use crate::leaderboard::Leaderboard;
+use crate::web::{GameStateDTO, StateDTO};
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "scoreboard")]
+pub struct Config {
+    /// Show a scoreboard on the local display
+    #[arg(long = "scoreboard")]
+    pub enabled: bool,
+
+    #[arg(long = "scoreboard-width", default_value_t = 1920)]
+    pub width: usize,
+
+    #[arg(long = "scoreboard-height", default_value_t = 1080)]
+    pub height: usize,
+}
+
+const COLOR_BACKGROUND: u32 = 0x101018;
+const COLOR_TEXT: u32 = 0xFFFFFF;
+const COLOR_HIGHLIGHT: u32 = 0xFFC000;
+
+/// Number of players shown in the leaderboard
+const LEADERBOARD_SIZE: usize = 5;
+
+/// Rows of a 3x5 pixel glyph with the most significant of the three bits on the left
+fn glyph(c: char) -> [u8; 5] {
+    return match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        _ => [0b000; 5],
+    };
+}
+
+struct Canvas {
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
+}
+
+impl Canvas {
+    fn clear(&mut self) {
+        self.buffer.fill(COLOR_BACKGROUND);
+    }
+
+    fn rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        for row in y.min(self.height)..(y + h).min(self.height) {
+            let line = &mut self.buffer[row * self.width..(row + 1) * self.width];
+            line[x.min(self.width)..(x + w).min(self.width)].fill(color);
+        }
+    }
+
+    /// Draws text with each glyph pixel being `scale` screen pixels wide
+    fn text(&mut self, x: usize, y: usize, scale: usize, color: u32, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * 4 * scale;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        self.rect(left + col * scale, y + row * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders a scoreboard in a local window
+#[instrument(level = "debug", skip(info, events))]
+pub fn run(config: Config, info: watch::Receiver<StateDTO>, mut events: broadcast::Receiver<Event>) -> Result<()> {
+    let mut window = Window::new("Hastilude", config.width, config.height, WindowOptions {
+        borderless: true,
+        ..WindowOptions::default()
+    }).map_err(|err| anyhow!("Failed to open scoreboard window: {}", err))?;
+    window.set_target_fps(30);
+
+    info!("Showing scoreboard on local display");
+
+    let mut canvas = Canvas {
+        buffer: vec![COLOR_BACKGROUND; config.width * config.height],
+        width: config.width,
+        height: config.height,
+    };
+
+    let mut leaderboard = Leaderboard::new();
+    let mut winners: Option<HashSet<PlayerId>> = None;
+
+    // Size of a glyph pixel
+    let unit = config.height / 60;
+
+    while window.is_open() {
+        loop {
+            match events.try_recv() {
+                Ok(Event::GameStarted { .. }) => winners = None,
+                Ok(Event::GameFinished { winners: finished }) => {
+                    leaderboard.record(finished.iter().copied(), LEADERBOARD_SIZE);
+                    winners = Some(finished);
+                }
+                Ok(Event::GameDrawn) => winners = Some(HashSet::new()),
+                Ok(Event::TournamentFinished { champions }) => winners = Some(champions),
+                Ok(Event::MatchFinished { champions }) => winners = Some(champions),
+                Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(broadcast::error::TryRecvError::Empty) => break,
+                Err(broadcast::error::TryRecvError::Closed) => return Ok(()),
+            }
+        }
+
+        let state = info.borrow().clone();
+
+        canvas.clear();
+
+        // Headline with mode and state
+        canvas.text(2 * unit, 2 * unit, unit * 2, COLOR_HIGHLIGHT, &state.mode.mode.to_string());
+
+        let status = match (&state.state, &winners) {
+            (GameStateDTO::Attract {}, _) => "PRESS ANY BUTTON".to_owned(),
+            (GameStateDTO::Teams { .. }, _) => "PICK YOUR TEAM".to_owned(),
+            (GameStateDTO::Running {}, _) => "PLAYING".to_owned(),
+            (GameStateDTO::Paused {}, _) => "PAUSED".to_owned(),
+            (GameStateDTO::Tournament { rounds, played, .. }, _) => format!("ROUND {}/{}", (played + 1).min(*rounds), rounds),
+            (GameStateDTO::Match { wins, played, .. }, _) => format!("ROUND {} - FIRST TO {}", played + 1, wins),
+            (GameStateDTO::Celebrating { draw: true, .. }, _) => "DRAW".to_owned(),
+            (GameStateDTO::Waiting { .. }, Some(winners)) if winners.is_empty() => "DRAW".to_owned(),
+            (GameStateDTO::Celebrating { winners, .. }, _) | (GameStateDTO::Waiting { .. }, Some(winners)) => format!("WINNER {}", winners.iter()
+                .map(|winner| format!("{:04X}", winner & 0xFFFF))
+                .collect::<Vec<_>>()
+                .join(" ")),
+            (GameStateDTO::Waiting { ready }, None) => format!("READY {}/{}", ready.len(), state.devices.len()),
+        };
+        canvas.text(2 * unit, 14 * unit, unit, COLOR_TEXT, &status);
+
+        // Leaderboard using the last four digits of the player IDs
+        for (i, (player, wins)) in leaderboard.ranking(LEADERBOARD_SIZE).into_iter().enumerate() {
+            canvas.text(2 * unit, (22 + i * 6) * unit, unit, COLOR_TEXT,
+                        &format!("{}  {:04X}  {}", i + 1, player & 0xFFFF, wins));
+        }
+
+        // Battery state of all controllers
+        for (i, device) in state.devices.iter().enumerate() {
+            let (r, g, b) = battery_to_color(device.battery).int_rgb_tup();
+            canvas.rect((2 + i * 4) * unit, 54 * unit, 3 * unit, 3 * unit,
+                        (r as u32) << 16 | (g as u32) << 8 | b as u32);
+        }
+
+        window.update_with_buffer(&canvas.buffer, canvas.width, canvas.height)
+            .map_err(|err| anyhow!("Failed to update scoreboard: {}", err))?;
+    }
+
+    return Ok(());
+}
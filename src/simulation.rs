@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cgmath::Vector3;
+use rand::Rng;
+use tracing::{debug, instrument, warn};
+
+use hastilude_core::controller::{Address, Battery, Buttons, Controller, faults, feed, Input, Model, Reading};
+use hastilude_core::engine::assets::Assets;
+use hastilude_core::engine::players::{PlayerId, Players};
+use hastilude_core::engine::random::Random;
+use hastilude_core::engine::scope::Scope;
+use hastilude_core::engine::sound::Sound;
+use hastilude_core::engine::timer::Timers;
+use hastilude_core::engine::timing::{Percentiles, Timing};
+
+use crate::events::{Event, Events};
+use crate::games::GameMode;
+use crate::games::scoring::Scoring;
+use crate::meta::teams::TeamSelection;
+use crate::state::{Settings, State, World};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub mode: GameMode,
+
+    /// Number of virtual players
+    pub players: usize,
+
+    /// Number of rounds to play
+    pub rounds: usize,
+
+    /// Simulated duration of a single frame
+    pub step: Duration,
+
+    /// Rounds taking longer are cancelled
+    pub max_round: Duration,
+
+    /// Faults injected into the virtual controllers
+    pub faults: faults::Config,
+
+    /// Seed of the random number generator driving the games and the virtual players
+    pub seed: Option<u64>,
+}
+
+/// A virtual player moving randomly
+///
+/// Players are mostly holding still but sometimes move abruptly. The more restless a player is, the
+/// more often this happens.
+struct Bot {
+    feed: feed::Feed,
+
+    /// Average number of abrupt movements per second
+    restlessness: f32,
+
+    // Remaining time and strength of the current abrupt movement
+    jolt: Option<(Duration, Vector3<f32>)>,
+
+    // Buttons held down with the next reading
+    buttons: Buttons,
+}
+
+impl Bot {
+    // Duration of a single abrupt movement
+    const JOLT_DURATION: Duration = Duration::from_millis(200);
+
+    // Maximum strength of an abrupt movement
+    const JOLT_STRENGTH: f32 = 2.0;
+
+    // Noise while holding still
+    const NOISE: f32 = 0.05;
+
+    fn step(&mut self, rng: &mut impl Rng, step: Duration) {
+        if self.jolt.is_none() && rng.gen::<f32>() < self.restlessness * step.as_secs_f32() {
+            let direction = Vector3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            self.jolt = Some((Self::JOLT_DURATION, direction * rng.gen_range(0.0..Self::JOLT_STRENGTH)));
+        }
+
+        let mut accelerometer = Vector3::new(
+            rng.gen_range(-Self::NOISE..Self::NOISE),
+            rng.gen_range(-Self::NOISE..Self::NOISE),
+            1.0 + rng.gen_range(-Self::NOISE..Self::NOISE),
+        );
+
+        if let Some((remaining, strength)) = self.jolt {
+            accelerometer += strength;
+            self.jolt = remaining.checked_sub(step)
+                .filter(|remaining| !remaining.is_zero())
+                .map(|remaining| (remaining, strength));
+        }
+
+        self.feed.push(Reading {
+            input: Input {
+                accelerometer,
+                buttons: std::mem::take(&mut self.buttons),
+                ..Input::default()
+            },
+            battery: Battery::Charged,
+            temperature: None,
+        });
+    }
+}
+
+/// Engine parts running a game without any hardware or sound output
+pub struct Sandbox<'a> {
+    pub now: Instant,
+
+    pub players: Players,
+    pub settings: Settings,
+    pub rng: Random,
+    pub events: Events,
+
+    sound: Sound,
+    assets: &'a Assets,
+    timing: Timing,
+    timers: Timers,
+    scope: Scope,
+    scoring: Scoring,
+}
+
+impl<'a> Sandbox<'a> {
+    pub fn new(assets: &'a Assets, settings: Settings, seed: Option<u64>) -> Self {
+        let events = Events::new();
+        let scoring = Scoring::new(events.subscribe());
+
+        return Self {
+            now: Instant::now(),
+            players: Players::new(),
+            settings,
+            rng: Random::new(seed),
+            events,
+            sound: Sound::silent(),
+            assets,
+            timing: Timing::default(),
+            timers: Timers::new(),
+            scope: Scope::new(),
+            scoring,
+        };
+    }
+
+    /// Address of the virtual controller with the given index
+    pub fn address(index: usize) -> Address {
+        return format!("02:00:00:00:{:02X}:{:02X}", (index >> 8) & 0xFF, index & 0xFF).parse()
+            .expect("Valid address");
+    }
+
+    pub fn world(&mut self) -> World<'_> {
+        return World {
+            now: self.now,
+            players: &mut self.players,
+            sound: &mut self.sound,
+            assets: self.assets,
+            settings: &mut self.settings,
+            timing: &self.timing,
+            timers: &mut self.timers,
+            rng: &mut self.rng,
+            scope: &self.scope,
+            events: &self.events,
+            scoring: &mut self.scoring,
+            threshold_scale: 1.0,
+        };
+    }
+}
+
+/// Outcome distributions of all simulated rounds
+#[derive(Debug, Default)]
+pub struct Report {
+    pub rounds: usize,
+
+    /// Rounds cancelled because they exceeded the maximum round length
+    pub timeouts: usize,
+
+    /// Rounds ending in a draw
+    pub draws: usize,
+
+    /// Time from the end of the countdown until a winner has been found
+    pub length: Percentiles<Duration>,
+
+    /// Time into the round at which players have been eliminated
+    pub eliminations: Percentiles<Duration>,
+
+    /// Time between the first and the last elimination of a round
+    pub spread: Percentiles<Duration>,
+
+    /// Number of wins by restlessness of the players
+    pub wins: Vec<(f32, usize)>,
+}
+
+/// Runs the game mode with virtual players as fast as possible
+#[instrument(level = "debug", skip(assets))]
+pub async fn run(config: Config, assets: &Assets) -> Result<Report> {
+    let mut sandbox = Sandbox::new(assets, Settings {
+        game_mode: config.mode,
+        ..Settings::default()
+    }, config.seed);
+
+    let mut emitted = sandbox.events.subscribe();
+
+    // Spread restlessness evenly across players
+    let mut bots = HashMap::new();
+    for i in 0..config.players {
+        let (feed, device) = feed::channel(Sandbox::address(i), Model::CECH_ZCM1);
+
+        let controller = Controller::new(Box::new(device))
+            .inject(&config.faults, sandbox.rng.gen());
+        bots.insert(controller.id(), Bot {
+            feed,
+            restlessness: 0.02 + 0.5 * i as f32 / config.players.max(2).saturating_sub(1) as f32,
+            jolt: None,
+            buttons: Buttons::default(),
+        });
+
+        sandbox.players.add(controller);
+    }
+
+    let mut report = Report::default();
+
+    let mut lengths = Vec::new();
+    let mut eliminations = Vec::new();
+    let mut spreads = Vec::new();
+    let mut wins: HashMap<PlayerId, usize> = HashMap::new();
+
+    for round in 0..config.rounds {
+        let mut state = State::lobby(&mut sandbox.players);
+
+        let (next, result) = state.start(&mut sandbox.world(), Some(bots.keys().copied().collect()));
+        result?;
+        state = next;
+
+        // Bots pick their teams in turns like players would
+        if let State::Teams(_) = state {
+            let teams = config.mode.teams.as_ref().map_or(1, |teams| teams.min.max(1));
+
+            let mut ids = bots.keys().copied().collect::<Vec<_>>();
+            ids.sort_unstable();
+            for (i, id) in ids.into_iter().enumerate() {
+                TeamSelection::press(&mut bots.get_mut(&id).expect("Bot exists").buttons, i % teams);
+            }
+        }
+
+        let begun = sandbox.now;
+        let mut started = None;
+        let mut eliminated = Vec::new();
+
+        loop {
+            sandbox.now += config.step;
+            let now = sandbox.now;
+
+            for bot in bots.values_mut() {
+                bot.step(&mut sandbox.rng, config.step);
+            }
+
+            sandbox.players.update(config.step).await?;
+
+            let mut world = sandbox.world();
+
+            state = state.update(&mut world, config.step);
+
+            // Start as soon as all bots left have picked their teams
+            if let State::Teams(ref selection) = state {
+                if selection.picks().len() == selection.participants().map_or(0, HashSet::len) {
+                    let (next, result) = state.start(&mut world, None);
+                    if let Err(err) = result {
+                        debug!("Bots failed to start with their teams: {}", err);
+                    }
+                    state = next;
+                }
+            }
+
+            // Rounds are measured from the end of the countdown
+            if started.is_none() && matches!(state, State::Playing(_)) {
+                started = Some(now);
+            }
+            let started = started.unwrap_or(now);
+
+            while let Ok(event) = emitted.try_recv() {
+                if let Event::PlayerEliminated { .. } = event {
+                    eliminated.push(now - started);
+                }
+            }
+
+            // The result is only reported once the celebration is updated
+            if let State::Celebration(ref celebration) = state {
+                let winners = celebration.winners().clone();
+
+                debug!("Round {} finished with winners {:?}", round, winners);
+
+                lengths.push(now - started);
+                if winners.is_empty() {
+                    report.draws += 1;
+                }
+
+                for winner in winners {
+                    *wins.entry(winner).or_default() += 1;
+                }
+
+                break;
+            }
+
+            if now - begun >= config.max_round {
+                warn!("Round {} exceeded the maximum round length", round);
+                report.timeouts += 1;
+
+                let (_, result) = state.cancel(&mut world);
+                result?;
+                break;
+            }
+        }
+
+        if let (Some(first), Some(last)) = (eliminated.first(), eliminated.last()) {
+            spreads.push(*last - *first);
+        }
+        eliminations.extend(eliminated);
+
+        report.rounds += 1;
+    }
+
+    report.length = Percentiles::of(lengths);
+    report.eliminations = Percentiles::of(eliminations);
+    report.spread = Percentiles::of(spreads);
+
+    report.wins = bots.iter()
+        .map(|(id, bot)| (bot.restlessness, wins.get(id).copied().unwrap_or(0)))
+        .collect();
+    report.wins.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    return Ok(report);
+}
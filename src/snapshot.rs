@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{debug, instrument, warn};
+
+use hastilude_core::engine::players::{PlayerId, Players};
+use hastilude_core::game::Scores;
+
+use crate::games::{GameMode, GameOptions};
+use crate::games::best_of::Match;
+use crate::games::scoring::ScoringReport;
+use crate::games::tournament::Tournament;
+use crate::state::{State, World};
+
+/// Everything about the session of an arena worth keeping over a restart
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub game_mode: GameMode,
+
+    /// Players ready in the lobby - kept while a game is running
+    pub ready: HashSet<PlayerId>,
+
+    /// The tournament or match currently played
+    pub progress: Option<Progress>,
+
+    pub scoring: ScoringReport,
+}
+
+/// The number of rounds or wins deciding a tournament or match
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    Rounds(usize),
+    Wins(u32),
+}
+
+/// Standings of a tournament or match - the round currently played is lost on restart
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub mode: GameMode,
+    pub options: GameOptions,
+    pub goal: Goal,
+    pub players: HashSet<PlayerId>,
+    pub standings: Scores<u32>,
+    pub played: usize,
+}
+
+impl Progress {
+    /// Continues the tournament or match once its players are back
+    pub fn restore(self, players: &mut Players) -> State {
+        return match self.goal {
+            Goal::Rounds(rounds) => State::Tournament(Tournament::restore(self, rounds, players)),
+            Goal::Wins(wins) => State::Match(Match::restore(self, wins)),
+        };
+    }
+}
+
+/// Whether a restored tournament or match can continue - either all of its players reconnected or
+/// one of them presses start to continue without the others
+pub fn resumable(players: &HashSet<PlayerId>, world: &World) -> bool {
+    let connected = players.iter()
+        .filter_map(|player| world.players.get(*player))
+        .collect::<Vec<_>>();
+
+    return connected.len() == players.len() || connected.iter().any(|player| player.input().buttons.start.pressed());
+}
+
+/// Keeps the latest snapshot of a session in a file
+///
+/// The file is written by a background task which skips snapshots superseded while writing.
+pub struct Store {
+    last: Option<Snapshot>,
+    updates: watch::Sender<Option<Snapshot>>,
+}
+
+impl Store {
+    /// Opens the file and reads the snapshot kept in there, if any
+    #[instrument(level = "debug")]
+    pub fn open(path: &Path) -> Result<Self> {
+        let last = if path.exists() {
+            let file = File::open(path)
+                .with_context(|| format!("Failed to open snapshot: {:?}", path))?;
+
+            Some(serde_json::from_reader(BufReader::new(file))
+                .with_context(|| format!("Failed to parse snapshot: {:?}", path))?)
+        } else {
+            None
+        };
+
+        let (updates, receiver) = watch::channel(None);
+        tokio::spawn(persist(path.to_owned(), receiver));
+
+        return Ok(Self {
+            last,
+            updates,
+        });
+    }
+
+    pub fn last(&self) -> Option<&Snapshot> {
+        return self.last.as_ref();
+    }
+
+    /// Writes the snapshot if anything changed since the last one
+    pub fn update(&mut self, snapshot: Snapshot) {
+        if self.last.as_ref() == Some(&snapshot) {
+            return;
+        }
+
+        self.last = Some(snapshot.clone());
+        self.updates.send_replace(Some(snapshot));
+    }
+}
+
+fn write(path: &Path, snapshot: &Snapshot) -> Result<()> {
+    debug!("Writing snapshot to {:?}", path);
+
+    // Replace the file at once to never leave a partial snapshot behind
+    let temp = path.with_extension("tmp");
+
+    let mut writer = BufWriter::new(File::create(&temp)
+        .with_context(|| format!("Failed to create snapshot: {:?}", temp))?);
+    serde_json::to_writer_pretty(&mut writer, snapshot)?;
+    writer.flush()?;
+
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("Failed to replace snapshot: {:?}", path))?;
+
+    return Ok(());
+}
+
+/// Writes the snapshots to the file as they are updated
+async fn persist(path: PathBuf, mut snapshots: watch::Receiver<Option<Snapshot>>) {
+    while snapshots.changed().await.is_ok() {
+        let snapshot = match snapshots.borrow_and_update().clone() {
+            Some(snapshot) => snapshot,
+            None => continue,
+        };
+
+        let path = path.clone();
+        match tokio::task::spawn_blocking(move || write(&path, &snapshot)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("Failed to write snapshot: {:#}", err),
+            Err(err) => warn!("Failed to write snapshot: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::games::joust;
+    use crate::games::scoring::Record;
+
+    #[tokio::test]
+    async fn test_store() {
+        let path = std::env::temp_dir().join(format!("hastilude-snapshot-{}.json", std::process::id()));
+
+        let mut standings = Scores::new([1, 2]);
+        standings.add(2, 1);
+
+        let snapshot = Snapshot {
+            game_mode: GameMode::of(&joust::MODE),
+            ready: HashSet::from([1, 2, 3]),
+            progress: Some(Progress {
+                mode: GameMode::of(&joust::MODE),
+                options: GameOptions::default(),
+                goal: Goal::Wins(3),
+                players: HashSet::from([1, 2]),
+                standings,
+                played: 1,
+            }),
+            scoring: ScoringReport {
+                rounds: 1,
+                leaderboard: vec![(2, Record { rounds: 1, wins: 1, ..Record::default() })],
+            },
+        };
+
+        let store = Store::open(&path).unwrap();
+        assert!(store.last().is_none());
+
+        // Dropping the sender finishes the writer once the latest snapshot is written
+        let (updates, receiver) = watch::channel(None);
+        updates.send_replace(Some(snapshot.clone()));
+        drop(updates);
+        persist(path.clone(), receiver).await;
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(store.last(), Some(&snapshot));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
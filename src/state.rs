@@ -1,26 +1,154 @@
+use std::collections::HashSet;
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tracing::{debug, warn};
 
-use crate::engine::players::{PlayerId, Players};
-use crate::games::{GameMode, GameState};
-use crate::keyframes;
+use hastilude_core::controller::calibration::Store;
+use hastilude_core::controller::pairing::{self, PairingResult};
+use hastilude_core::engine;
+use hastilude_core::engine::assets::Voices;
+use hastilude_core::engine::haptics::HapticPattern;
+use hastilude_core::engine::players::{PlayerId, Players};
+use hastilude_core::engine::sound::Priority;
+
+use crate::events::Event;
+use crate::games::{GameMode, GameOptions, GameState};
+use crate::games::best_of::{self, Match};
+use crate::games::mutators::{Mutator, Mutators};
+use crate::games::scoring::Scoring;
+use crate::games::tournament::{Setup, Tournament};
+use crate::meta::attract::Attract;
 use crate::meta::celebration::Celebration;
 use crate::meta::countdown::Countdown;
 use crate::meta::lobby::Lobby;
+use crate::meta::pause::Paused;
+use crate::meta::teams::TeamSelection;
+use crate::palette::Theme;
+use crate::profiles::Profile;
+use crate::snapshot::Progress;
+
+// Language of settings stored before announcements were localized
+fn default_language() -> String {
+    return Voices::DEFAULT.to_owned();
+}
 
-#[derive(Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Settings {
     pub game_mode: GameMode,
+
+    /// Language of voice announcements
+    #[serde(default = "default_language")]
+    pub language: String,
+
+    /// Scales movement thresholds of games - higher values tolerate more movement
+    pub threshold: f32,
+
+    /// LED brightness of all controllers
+    pub brightness: f32,
+
+    /// Volume of music and sounds
+    pub volume: f32,
+
+    pub power_save: PowerSave,
+
+    /// Colors shown on the controllers
+    #[serde(default)]
+    pub theme: Theme,
+
+    /// Settings of the individual game modes
+    #[serde(default)]
+    pub options: GameOptions,
+
+    /// Rule modifiers applied to the rounds started from now on
+    #[serde(default)]
+    pub mutators: HashSet<Mutator>,
+
+    /// Seed of the random number generator of the games - random on every start if not given
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Calibration overrides written by the calibration routine
+    #[serde(skip)]
+    pub calibration: Option<Store>,
+
+    /// Controllers idle in the lobby for this long are powered off
+    #[serde(skip)]
+    pub idle_power_off: Option<Duration>,
+
+    /// The lobby switches to the attract mode after nobody pressed a button for this long
+    #[serde(skip)]
+    pub attract_after: Option<Duration>,
+
+    /// Games start automatically once enough players are ready and nobody got ready for this long
+    #[serde(skip)]
+    pub auto_start: Option<Duration>,
+
+    /// Maximum number of controllers receiving feedback per frame
+    #[serde(skip)]
+    pub feedback_budget: Option<usize>,
+
+    /// Battery level below which controllers blink a warning
+    #[serde(skip)]
+    pub low_battery_warning: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSave {
+    Off,
+    On,
+
+    /// Saves power if many controllers are low on battery
+    Auto,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        return Self {
+            game_mode: GameMode::default(),
+            language: default_language(),
+            threshold: 1.0,
+            brightness: 1.0,
+            volume: 1.0,
+            power_save: PowerSave::Auto,
+            theme: Theme::default(),
+            options: GameOptions::default(),
+            mutators: HashSet::new(),
+            seed: None,
+            calibration: None,
+            idle_power_off: None,
+            attract_after: None,
+            auto_start: None,
+            feedback_budget: None,
+            low_battery_warning: None,
+        };
+    }
 }
 
-pub type World<'a> = crate::engine::World<'a, Settings>;
+pub type World<'a> = hastilude_core::engine::World<'a, Settings, Event, Scoring>;
+
+impl engine::Settings for Settings {
+    fn language(&self) -> &str {
+        return &self.language;
+    }
+
+    fn threshold(&self) -> f32 {
+        return self.threshold;
+    }
+}
 
 pub enum State {
     Lobby(Lobby),
+    Attract(Attract),
+    Teams(TeamSelection),
     Countdown(Countdown),
     Playing(GameState),
+    Paused(Paused),
     Celebration(Celebration),
+    Tournament(Tournament),
+    Match(Match),
 }
 
 impl State {
@@ -30,44 +158,160 @@ impl State {
 
     pub fn update(self, world: &mut World, duration: Duration) -> Self {
         return match self {
-            State::Lobby(lobby) => lobby.update(world),
+            State::Lobby(lobby) => lobby.update(world, duration),
+            State::Attract(attract) => attract.update(world, duration),
+            State::Teams(selection) => selection.update(world),
             State::Countdown(countdown) => countdown.update(world, duration),
             State::Playing(game) => game.update(world, duration),
+            State::Paused(paused) => paused.update(world),
             State::Celebration(celebration) => celebration.update(world, duration),
+            State::Tournament(tournament) => tournament.update(world, duration),
+            State::Match(game) => game.update(world, duration),
         };
     }
 
-    pub fn start(self, world: &mut World) -> (Self, Result<(), StartGameError>) {
+    /// The players taking part in the running game if controllers not taking part show the idle
+    /// animation
+    pub fn participants(&self) -> Option<&HashSet<PlayerId>> {
         return match self {
-            State::Lobby(lobby) => {
-                let (state, ok) = lobby.start(world);
-                if ok {
-                    (state, Ok(()))
-                } else {
-                    (state, Err(StartGameError::InsufficientPlayers))
-                }
-            }
+            State::Teams(selection) => selection.participants(),
+            State::Countdown(countdown) => countdown.participants(),
+            State::Playing(game) => game.participants(),
+            State::Paused(paused) => paused.participants(),
+            State::Tournament(tournament) => tournament.participants(),
+            State::Match(game) => game.participants(),
+            State::Lobby(_) | State::Attract(_) | State::Celebration(_) => None,
+        };
+    }
+
+    /// Short name of the state as written to the event log - tournaments and matches append the
+    /// state of the current round
+    pub fn name(&self) -> String {
+        return match self {
+            State::Lobby(_) => "lobby".to_owned(),
+            State::Attract(_) => "attract".to_owned(),
+            State::Teams(_) => "teams".to_owned(),
+            State::Countdown(_) => "countdown".to_owned(),
+            State::Playing(_) => "playing".to_owned(),
+            State::Paused(_) => "paused".to_owned(),
+            State::Celebration(_) => "celebration".to_owned(),
+            State::Tournament(tournament) => format!("tournament/{}", tournament.phase()),
+            State::Match(game) => format!("match/{}", game.phase()),
+        };
+    }
+
+    /// The mutators of the round currently played
+    pub fn mutators(&self) -> Option<&Mutators> {
+        return match self {
+            State::Playing(game) => Some(game.mutators()),
+            State::Tournament(tournament) => tournament.mutators(),
+            State::Match(game) => game.mutators(),
+            _ => None,
+        };
+    }
+
+    /// The tournament or match currently played
+    pub fn progress(&self) -> Option<Progress> {
+        return match self {
+            State::Tournament(tournament) => Some(tournament.progress()),
+            State::Match(game) => Some(game.progress()),
+            _ => None,
+        };
+    }
+
+    /// Whether the running game is paused
+    pub fn paused(&self) -> bool {
+        return match self {
+            State::Paused(_) => true,
+            State::Tournament(tournament) => tournament.paused(),
+            State::Match(game) => game.paused(),
+            _ => false,
+        };
+    }
+
+    pub fn start(self, world: &mut World, players: Option<HashSet<PlayerId>>) -> (Self, Result<(), StartGameError>) {
+        return match self {
+            State::Lobby(lobby) => lobby.start(world, players),
+            State::Attract(attract) => attract.leave().start(world, players),
+            State::Teams(selection) => selection.start(world),
+
+            State::Countdown(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Playing(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Paused(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Celebration(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Tournament(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Match(_) => (self, Err(StartGameError::AlreadyRunning)),
+        };
+    }
+
+    pub fn start_tournament(self, world: &mut World, setup: Setup) -> (Self, Result<(), StartGameError>) {
+        return match self {
+            State::Lobby(lobby) => lobby.start_tournament(world, setup),
+            State::Attract(attract) => attract.leave().start_tournament(world, setup),
+            State::Teams(_) => (self, Err(StartGameError::AlreadyRunning)),
 
             State::Countdown(_) => (self, Err(StartGameError::AlreadyRunning)),
             State::Playing(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Paused(_) => (self, Err(StartGameError::AlreadyRunning)),
             State::Celebration(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Tournament(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Match(_) => (self, Err(StartGameError::AlreadyRunning)),
+        };
+    }
+
+    pub fn start_match(self, world: &mut World, setup: best_of::Setup) -> (Self, Result<(), StartGameError>) {
+        return match self {
+            State::Lobby(lobby) => lobby.start_match(world, setup),
+            State::Attract(attract) => attract.leave().start_match(world, setup),
+            State::Teams(_) => (self, Err(StartGameError::AlreadyRunning)),
+
+            State::Countdown(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Playing(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Paused(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Celebration(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Tournament(_) => (self, Err(StartGameError::AlreadyRunning)),
+            State::Match(_) => (self, Err(StartGameError::AlreadyRunning)),
         };
     }
 
     pub fn cancel(self, world: &mut World) -> (Self, Result<(), CancelGameError>) {
         return match self {
-            State::Lobby(_) => (self, Err(CancelGameError::GameNotRunning)),
-            State::Countdown(_) => (Self::lobby(world.players), Ok(())),
-            State::Playing(_) => (Self::lobby(world.players), Ok(())),
+            State::Lobby(mut lobby) => {
+                let result = if lobby.cancel() { Ok(()) } else { Err(CancelGameError::GameNotRunning) };
+                (State::Lobby(lobby), result)
+            }
+            State::Attract(_) => (self, Err(CancelGameError::GameNotRunning)),
+            State::Teams(_) => (Self::lobby(world.players), Ok(())),
+            State::Countdown(_) | State::Playing(_) | State::Paused(_) | State::Tournament(_) | State::Match(_) => {
+                world.events.emit(Event::GameCancelled);
+                (Self::lobby(world.players), Ok(()))
+            }
             State::Celebration(_) => (self, Err(CancelGameError::GameNotRunning)),
         };
     }
 
+    pub fn pause(self, world: &mut World) -> (Self, Result<(), PauseGameError>) {
+        return match self {
+            State::Playing(game) => (State::Paused(Paused::new(game, world)), Ok(())),
+            State::Paused(_) => (self, Err(PauseGameError::AlreadyPaused)),
+            State::Tournament(tournament) => tournament.pause(world),
+            State::Match(game) => game.pause(world),
+            State::Lobby(_) | State::Attract(_) | State::Teams(_) | State::Countdown(_) | State::Celebration(_) => (self, Err(PauseGameError::GameNotRunning)),
+        };
+    }
+
+    pub fn resume(self, world: &mut World) -> (Self, Result<(), PauseGameError>) {
+        return match self {
+            State::Paused(paused) => (paused.resume(world), Ok(())),
+            State::Tournament(tournament) => tournament.resume(world),
+            State::Match(game) => game.resume(world),
+            _ => (self, Err(PauseGameError::NotPaused)),
+        };
+    }
+
     pub fn buzz_player(self, player: PlayerId, world: &mut World) -> (Self, Result<(), NoSuchPlayerError>) {
         if let Some(player) = world.players.get_mut(player) {
-            player.rumble.set_and_animate(0xFF, keyframes![
-                1.0 => 0x00 @ end,
-            ]);
+            player.haptic(HapticPattern::BUZZ);
             return (self, Ok(()));
         } else {
             return (self, Err(NoSuchPlayerError { player }));
@@ -82,16 +326,210 @@ impl State {
                 (self, Err(NoSuchPlayerError { player }))
             }
 
+            State::Attract(ref mut attract) => if attract.kick_player(player) {
+                (self, Ok(()))
+            } else {
+                (self, Err(NoSuchPlayerError { player }))
+            }
+
             State::Countdown(_) => (self, Err(NoSuchPlayerError { player })),
 
+            State::Teams(ref mut selection) => if selection.kick_player(player) {
+                (self, Ok(()))
+            } else {
+                (self, Err(NoSuchPlayerError { player }))
+            }
+
             State::Playing(ref mut game) => if game.kick_player(player, world) {
                 (self, Ok(()))
             } else {
                 (self, Err(NoSuchPlayerError { player }))
             }
 
-            State::Celebration(_) => (self, Err(NoSuchPlayerError { player }))
+            State::Paused(ref mut paused) => if paused.kick_player(player, world) {
+                (self, Ok(()))
+            } else {
+                (self, Err(NoSuchPlayerError { player }))
+            }
+
+            State::Celebration(_) => (self, Err(NoSuchPlayerError { player })),
+
+            State::Tournament(tournament) => match tournament.kick_player(player, world) {
+                (state, true) => (state, Ok(())),
+                (state, false) => (state, Err(NoSuchPlayerError { player })),
+            }
+
+            State::Match(game) => match game.kick_player(player, world) {
+                (state, true) => (state, Ok(())),
+                (state, false) => (state, Err(NoSuchPlayerError { player })),
+            }
+        };
+    }
+
+    pub fn join_player(mut self, player: PlayerId, world: &mut World) -> (Self, Result<(), JoinPlayerError>) {
+        match world.players.get(player) {
+            Some(device) if device.charging_only() => return (self, Err(JoinPlayerError::ChargingOnly(player))),
+            Some(_) => {}
+            None => return (self, Err(JoinPlayerError::NoSuchPlayer(player))),
+        }
+
+        return match self {
+            State::Playing(ref mut game) => {
+                let result = game.join_player(player, world);
+                (self, result)
+            }
+
+            State::Paused(ref mut paused) => {
+                let result = paused.join_player(player, world);
+                (self, result)
+            }
+
+            State::Teams(ref mut selection) => {
+                let result = selection.join_player(player);
+                (self, result)
+            }
+
+            State::Tournament(tournament) => tournament.join_player(player, world),
+            State::Match(game) => game.join_player(player, world),
+
+            State::Lobby(_) | State::Attract(_) | State::Countdown(_) | State::Celebration(_) => (self, Err(JoinPlayerError::GameNotRunning)),
+        };
+    }
+
+    /// Adjusts the movement thresholds for a player - only in the lobby to keep running games fair
+    pub fn player_threshold(self, player: PlayerId, threshold: f32, world: &mut World) -> (Self, Result<(), PlayerThresholdError>) {
+        if !matches!(self, State::Lobby(_)) {
+            return (self, Err(PlayerThresholdError::NotInLobby));
+        }
+
+        if let Some(player) = world.players.get_mut(player) {
+            player.set_threshold(threshold);
+            return (self, Ok(()));
+        } else {
+            return (self, Err(PlayerThresholdError::NoSuchPlayer(player)));
+        }
+    }
+
+    /// Stops everything immediately and returns to the lobby
+    pub fn emergency_stop(self, world: &mut World) -> Self {
+        warn!("Emergency stop");
+        world.events.emit(Event::EmergencyStop);
+
+        // Dropping the current state stops all running music
+        drop(self);
+
+        return Self::lobby(world.players);
+    }
+
+    /// Shakes things up by rumbling the controllers of the participants - or of everybody if no
+    /// game is running
+    pub fn chaos(self, world: &mut World) -> Self {
+        let participants = self.participants();
+        for player in world.players.iter_mut() {
+            if participants.map_or(true, |participants| participants.contains(&player.id())) {
+                player.haptic(HapticPattern::SHAKE);
+            }
+        }
+
+        return self;
+    }
+
+    pub fn test_audio(self, sfx: Option<String>, world: &mut World) -> (Self, Result<(), NoSuchAssetError>) {
+        if let Some(sfx) = sfx {
+            if let Some(asset) = world.assets.sfx.get(&sfx) {
+                world.sound.cue(world.now, Priority::Stinger, &sfx, asset);
+            } else {
+                return (self, Err(NoSuchAssetError { name: sfx }));
+            }
+        } else {
+            world.sound.tone(440.0, Duration::from_secs(1));
+        }
+
+        return (self, Ok(()));
+    }
+
+    pub fn language(self, language: String, world: &mut World) -> (Self, Result<(), NoSuchLanguageError>) {
+        if !world.assets.voice.has_language(&language) {
+            return (self, Err(NoSuchLanguageError { language }));
+        }
+
+        world.settings.language = language;
+        return (self, Ok(()));
+    }
+
+    pub fn power_save(self, power_save: PowerSave, world: &mut World) -> Self {
+        world.settings.power_save = power_save;
+        return self;
+    }
+
+    pub fn theme(self, theme: Theme, world: &mut World) -> Self {
+        world.settings.theme = theme;
+        return self;
+    }
+
+    pub fn brightness(self, brightness: f32, world: &mut World) -> Self {
+        world.settings.brightness = brightness.clamp(0.0, 1.0);
+        return self;
+    }
+
+    pub fn game_options(mut self, options: GameOptions, world: &mut World) -> (Self, Result<(), InvalidOptionsError>) {
+        if let Err(err) = options.validate() {
+            return (self, Err(err));
+        }
+
+        debug!("Applying game options: {:?}", options);
+        world.settings.options = options;
+
+        // Running games pick up the changed options right away
+        self.reconfigure(world);
+
+        return (self, Ok(()));
+    }
+
+    /// Applies changed settings to the running game
+    pub fn reconfigure(&mut self, world: &mut World) {
+        match self {
+            State::Countdown(countdown) => countdown.reconfigure(world),
+            State::Playing(game) => game.reconfigure(world),
+            State::Paused(paused) => paused.reconfigure(world),
+
+            // Tournaments and matches keep the options they were started with
+            State::Tournament(_) | State::Match(_) => {}
+
+            State::Lobby(_) | State::Attract(_) | State::Teams(_) | State::Celebration(_) => {}
+        }
+    }
+
+    pub fn enable_mutators(self, mutators: HashSet<Mutator>, world: &mut World) -> Self {
+        debug!("Enabling mutators: {:?}", mutators);
+        world.settings.mutators = mutators;
+        return self;
+    }
+
+    pub fn profile(self, profile: Profile, world: &mut World) -> Self {
+        debug!("Applying profile: {:?}", profile);
+        profile.apply(world.settings);
+        return self;
+    }
+
+    pub async fn power_off_player(self, player: PlayerId, world: &mut World<'_>) -> (Self, Result<(), PowerOffError>) {
+        let result = match world.players.get_mut(player) {
+            Some(player) => player.power_off().await
+                .map_err(|err| PowerOffError::Failed(format!("{:#}", err))),
+            None => Err(PowerOffError::NoSuchPlayer(player)),
         };
+
+        return (self, result);
+    }
+
+    pub async fn pair_controllers(self, world: &mut World<'_>) -> (Self, Result<Vec<PairingResult>, PairControllersError>) {
+        let host = match pairing::host_address() {
+            Ok(host) => host,
+            Err(err) => return (self, Err(PairControllersError::NoHostAddress(err.to_string()))),
+        };
+
+        let results = world.players.pair(host).await;
+        return (self, Ok(results));
     }
 }
 
@@ -101,6 +539,25 @@ pub struct NoSuchPlayerError {
     player: PlayerId,
 }
 
+#[derive(Error, Debug)]
+#[error("No such asset: {name}")]
+pub struct NoSuchAssetError {
+    name: String,
+}
+
+#[derive(Error, Debug)]
+#[error("No such language: {language}")]
+pub struct NoSuchLanguageError {
+    language: String,
+}
+
+#[derive(Error, Serialize, Debug)]
+#[error("Invalid game option {field}: {reason}")]
+pub struct InvalidOptionsError {
+    pub field: &'static str,
+    pub reason: &'static str,
+}
+
 #[derive(Error, Debug)]
 pub enum CancelGameError {
     #[error("Game not running")]
@@ -108,22 +565,126 @@ pub enum CancelGameError {
 }
 
 #[derive(Error, Debug)]
+pub enum JoinPlayerError {
+    #[error("Game not running")]
+    GameNotRunning,
+
+    #[error("Game does not accept late joins")]
+    NotSupported,
+
+    #[error("No such player: {0}")]
+    NoSuchPlayer(PlayerId),
+
+    #[error("Player is only charging: {0}")]
+    ChargingOnly(PlayerId),
+
+    #[error("Player already playing: {0}")]
+    AlreadyPlaying(PlayerId),
+}
+
+#[derive(Error, Debug)]
+pub enum PauseGameError {
+    #[error("Game not running")]
+    GameNotRunning,
+
+    #[error("Game already paused")]
+    AlreadyPaused,
+
+    #[error("Game not paused")]
+    NotPaused,
+}
+
+#[derive(Error, Debug)]
+pub enum PairControllersError {
+    #[error("Host address unavailable: {0}")]
+    NoHostAddress(String),
+}
+
+#[derive(Error, Debug)]
+pub enum PlayerThresholdError {
+    #[error("Not in the lobby")]
+    NotInLobby,
+
+    #[error("No such player: {0}")]
+    NoSuchPlayer(PlayerId),
+}
+
+#[derive(Error, Debug)]
+pub enum PowerOffError {
+    #[error("No such player: {0}")]
+    NoSuchPlayer(PlayerId),
+
+    #[error("Powering off failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Error, Serialize, Debug)]
+#[serde(tag = "error", content = "details", rename_all = "snake_case")]
 pub enum StartGameError {
     #[error("Game already running")]
     AlreadyRunning,
 
-    #[error("Insufficient players")]
-    InsufficientPlayers,
+    #[error("Game mode {mode:?} can not be played by {players} players")]
+    PlayerCount {
+        mode: GameMode,
+        players: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+
+    #[error("No such player: {0}")]
+    NoSuchPlayer(PlayerId),
+
+    #[error("Player is only charging: {0}")]
+    ChargingOnly(PlayerId),
+
+    #[error("Tournament needs at least one round")]
+    NoRounds,
+
+    #[error("Match needs at least one win")]
+    NoWins,
+
+    #[error("Players without a team: {players:?}")]
+    TeamMissing {
+        players: HashSet<PlayerId>,
+    },
+
+    #[error("Game mode {mode:?} can not be played by {teams} teams")]
+    TeamCount {
+        mode: GameMode,
+        teams: usize,
+        min: usize,
+        max: usize,
+    },
+
+    #[error("Teams are unbalanced: {sizes:?}")]
+    TeamBalance {
+        sizes: Vec<usize>,
+    },
 }
 
 pub mod request {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use futures::{SinkExt, StreamExt};
     use futures::channel::{mpsc, oneshot};
     use futures::task::Poll;
 
-    use crate::engine::players::PlayerId;
-    use crate::games::GameMode;
-    use super::{World, CancelGameError, NoSuchPlayerError, StartGameError};
+    use hastilude_core::controller::pairing::PairingResult;
+    use hastilude_core::engine::players::{BatteryReport, PlayerId};
+    use hastilude_core::engine::metrics::MetricsReport;
+    use hastilude_core::engine::timing::TimingReport;
+
+    use crate::games::{GameMode, GameOptions};
+    use crate::games::best_of;
+    use crate::games::mutators::Mutator;
+    use crate::games::scoring::ScoringReport;
+    use crate::games::tournament::Setup;
+    use crate::palette::Theme;
+    use crate::profiles::Profile;
+    use super::{World, CancelGameError, InvalidOptionsError, JoinPlayerError, NoSuchAssetError, NoSuchLanguageError, NoSuchPlayerError, PairControllersError, PauseGameError, PlayerThresholdError, PowerOffError, PowerSave, StartGameError};
 
     pub struct Action<Req, Res> {
         request: Req,
@@ -139,39 +700,124 @@ pub mod request {
 
     pub enum Actions {
         GameMode(Action<GameMode, ()>),
-        StartGame(Action<(), Result<(), StartGameError>>),
+        Language(Action<String, Result<(), NoSuchLanguageError>>),
+        Profile(Action<Profile, ()>),
+        PowerSave(Action<PowerSave, ()>),
+        Theme(Action<Theme, ()>),
+        Brightness(Action<f32, ()>),
+        GameOptions(Action<GameOptions, Result<(), InvalidOptionsError>>),
+        Mutators(Action<HashSet<Mutator>, ()>),
+        StartGame(Action<Option<HashSet<PlayerId>>, Result<(), StartGameError>>),
+        StartTournament(Action<Setup, Result<(), StartGameError>>),
+        StartMatch(Action<best_of::Setup, Result<(), StartGameError>>),
         CancelGame(Action<(), Result<(), CancelGameError>>),
+        PauseGame(Action<(), Result<(), PauseGameError>>),
+        ResumeGame(Action<(), Result<(), PauseGameError>>),
         BuzzPlayer(Action<PlayerId, Result<(), NoSuchPlayerError>>),
         KickPlayer(Action<PlayerId, Result<(), NoSuchPlayerError>>),
+        JoinPlayer(Action<PlayerId, Result<(), JoinPlayerError>>),
+        PlayerThreshold(Action<(PlayerId, f32), Result<(), PlayerThresholdError>>),
+        PowerOffPlayer(Action<PlayerId, Result<(), PowerOffError>>),
+        PairControllers(Action<(), Result<Vec<PairingResult>, PairControllersError>>),
+        Batteries(Action<(), Vec<BatteryReport>>),
+        Timing(Action<(), TimingReport>),
+        Metrics(Action<(), MetricsReport>),
+        Scoring(Action<(), ScoringReport>),
+        TestAudio(Action<Option<String>, Result<(), NoSuchAssetError>>),
+        Chaos(Action<(), ()>),
+        EmergencyStop(Action<(), ()>),
     }
 
     #[derive(Clone)]
-    pub struct Stub(mpsc::Sender<Actions>);
+    pub struct Stub {
+        tx: mpsc::Sender<Actions>,
+
+        // Number of requests sent but not yet received
+        pending: Arc<AtomicUsize>,
+    }
+
+    pub struct Requests {
+        rx: mpsc::Receiver<Actions>,
+        pending: Arc<AtomicUsize>,
+    }
+
+    impl Requests {
+        /// Number of requests waiting to be handled
+        pub fn backlog(&self) -> usize {
+            return self.pending.load(Ordering::Relaxed);
+        }
+    }
 
     impl Stub {
-        pub fn create() -> (Stub, mpsc::Receiver<Actions>) {
+        pub fn create() -> (Stub, Requests) {
             let (tx, rx) = mpsc::channel(1);
-            return (Stub(tx), rx);
+            let pending = Arc::new(AtomicUsize::new(0));
+            return (Stub { tx, pending: pending.clone() }, Requests { rx, pending });
         }
 
         async fn call<Args, Res>(&mut self, args: Args, f: impl Fn(Action<Args, Res>) -> Actions) -> Res {
             let (action, response) = Action::with_args(args);
-            self.0.send(f(action)).await.expect("Sending request");
+            self.pending.fetch_add(1, Ordering::Relaxed);
+            self.tx.send(f(action)).await.expect("Sending request");
             return response.await.expect("Receiving response");
         }
 
-        pub async fn game_mode(&mut self, mode: GameMode) -> () {
+        pub async fn game_mode(&mut self, mode: GameMode) {
             return self.call(mode, Actions::GameMode).await;
         }
 
-        pub async fn start_game(&mut self) -> Result<(), StartGameError> {
-            return self.call((), Actions::StartGame).await;
+        pub async fn language(&mut self, language: String) -> Result<(), NoSuchLanguageError> {
+            return self.call(language, Actions::Language).await;
+        }
+
+        pub async fn profile(&mut self, profile: Profile) {
+            return self.call(profile, Actions::Profile).await;
+        }
+
+        pub async fn power_save(&mut self, power_save: PowerSave) {
+            return self.call(power_save, Actions::PowerSave).await;
+        }
+
+        pub async fn theme(&mut self, theme: Theme) {
+            return self.call(theme, Actions::Theme).await;
+        }
+
+        pub async fn brightness(&mut self, brightness: f32) {
+            return self.call(brightness, Actions::Brightness).await;
+        }
+
+        pub async fn game_options(&mut self, options: GameOptions) -> Result<(), InvalidOptionsError> {
+            return self.call(options, Actions::GameOptions).await;
+        }
+
+        pub async fn mutators(&mut self, mutators: HashSet<Mutator>) {
+            return self.call(mutators, Actions::Mutators).await;
+        }
+
+        pub async fn start_game(&mut self, players: Option<HashSet<PlayerId>>) -> Result<(), StartGameError> {
+            return self.call(players, Actions::StartGame).await;
+        }
+
+        pub async fn start_tournament(&mut self, setup: Setup) -> Result<(), StartGameError> {
+            return self.call(setup, Actions::StartTournament).await;
+        }
+
+        pub async fn start_match(&mut self, setup: best_of::Setup) -> Result<(), StartGameError> {
+            return self.call(setup, Actions::StartMatch).await;
         }
 
         pub async fn cancel_game(&mut self) -> Result<(), CancelGameError> {
             return self.call((), Actions::CancelGame).await;
         }
 
+        pub async fn pause_game(&mut self) -> Result<(), PauseGameError> {
+            return self.call((), Actions::PauseGame).await;
+        }
+
+        pub async fn resume_game(&mut self) -> Result<(), PauseGameError> {
+            return self.call((), Actions::ResumeGame).await;
+        }
+
         pub async fn buzz_player(&mut self, player: PlayerId) -> Result<(), NoSuchPlayerError> {
             return self.call(player, Actions::BuzzPlayer).await;
         }
@@ -179,11 +825,57 @@ pub mod request {
         pub async fn kick_player(&mut self, player: PlayerId) -> Result<(), NoSuchPlayerError> {
             return self.call(player, Actions::KickPlayer).await;
         }
+
+        pub async fn join_player(&mut self, player: PlayerId) -> Result<(), JoinPlayerError> {
+            return self.call(player, Actions::JoinPlayer).await;
+        }
+
+        pub async fn player_threshold(&mut self, player: PlayerId, threshold: f32) -> Result<(), PlayerThresholdError> {
+            return self.call((player, threshold), Actions::PlayerThreshold).await;
+        }
+
+        pub async fn power_off_player(&mut self, player: PlayerId) -> Result<(), PowerOffError> {
+            return self.call(player, Actions::PowerOffPlayer).await;
+        }
+
+        pub async fn pair_controllers(&mut self) -> Result<Vec<PairingResult>, PairControllersError> {
+            return self.call((), Actions::PairControllers).await;
+        }
+
+        pub async fn batteries(&mut self) -> Vec<BatteryReport> {
+            return self.call((), Actions::Batteries).await;
+        }
+
+        pub async fn timing(&mut self) -> TimingReport {
+            return self.call((), Actions::Timing).await;
+        }
+
+        pub async fn metrics(&mut self) -> MetricsReport {
+            return self.call((), Actions::Metrics).await;
+        }
+
+        pub async fn scoring(&mut self) -> ScoringReport {
+            return self.call((), Actions::Scoring).await;
+        }
+
+        pub async fn test_audio(&mut self, sfx: Option<String>) -> Result<(), NoSuchAssetError> {
+            return self.call(sfx, Actions::TestAudio).await;
+        }
+
+        pub async fn chaos(&mut self) {
+            return self.call((), Actions::Chaos).await;
+        }
+
+        pub async fn emergency_stop(&mut self) {
+            return self.call((), Actions::EmergencyStop).await;
+        }
     }
 
     impl super::State {
-        pub async fn handle(self, requests: &mut mpsc::Receiver<Actions>, world: &mut World<'_>) -> Self {
-            if let Poll::Ready(Some(request)) = futures::poll!(requests.next()) {
+        pub async fn handle(self, requests: &mut Requests, world: &mut World<'_>) -> Self {
+            if let Poll::Ready(Some(request)) = futures::poll!(requests.rx.next()) {
+                requests.pending.fetch_sub(1, Ordering::Relaxed);
+
                 match request {
                     Actions::GameMode(action) => {
                         world.settings.game_mode = action.request;
@@ -191,8 +883,62 @@ pub mod request {
                         return self;
                     }
 
+                    Actions::Language(action) => {
+                        let (state, result) = self.language(action.request, world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Profile(action) => {
+                        let state = self.profile(action.request, world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::PowerSave(action) => {
+                        let state = self.power_save(action.request, world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Theme(action) => {
+                        let state = self.theme(action.request, world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Brightness(action) => {
+                        let state = self.brightness(action.request, world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::GameOptions(action) => {
+                        let (state, result) = self.game_options(action.request, world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Mutators(action) => {
+                        let state = self.enable_mutators(action.request, world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
                     Actions::StartGame(action) => {
-                        let (state, result) = self.start(world);
+                        let (state, result) = self.start(world, action.request);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::StartTournament(action) => {
+                        let (state, result) = self.start_tournament(world, action.request);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::StartMatch(action) => {
+                        let (state, result) = self.start_match(world, action.request);
                         action.response.send(result).expect("Sending response");
                         return state;
                     }
@@ -203,6 +949,18 @@ pub mod request {
                         return state;
                     }
 
+                    Actions::PauseGame(action) => {
+                        let (state, result) = self.pause(world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::ResumeGame(action) => {
+                        let (state, result) = self.resume(world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
                     Actions::BuzzPlayer(action) => {
                         let (state, result) = self.buzz_player(action.request, world);
                         action.response.send(result).expect("Sending response");
@@ -214,6 +972,69 @@ pub mod request {
                         action.response.send(result).expect("Sending response");
                         return state;
                     }
+
+                    Actions::JoinPlayer(action) => {
+                        let (state, result) = self.join_player(action.request, world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::PlayerThreshold(action) => {
+                        let (player, threshold) = action.request;
+                        let (state, result) = self.player_threshold(player, threshold, world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::PowerOffPlayer(action) => {
+                        let (state, result) = self.power_off_player(action.request, world).await;
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::PairControllers(action) => {
+                        let (state, result) = self.pair_controllers(world).await;
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Batteries(action) => {
+                        action.response.send(world.players.batteries()).expect("Sending response");
+                        return self;
+                    }
+
+                    Actions::Timing(action) => {
+                        action.response.send(MetricsReport::of(world.timing, world.players).timing).expect("Sending response");
+                        return self;
+                    }
+
+                    Actions::Metrics(action) => {
+                        action.response.send(MetricsReport::of(world.timing, world.players)).expect("Sending response");
+                        return self;
+                    }
+
+                    Actions::Scoring(action) => {
+                        action.response.send(world.scoring.report()).expect("Sending response");
+                        return self;
+                    }
+
+                    Actions::TestAudio(action) => {
+                        let (state, result) = self.test_audio(action.request, world);
+                        action.response.send(result).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::Chaos(action) => {
+                        let state = self.chaos(world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
+
+                    Actions::EmergencyStop(action) => {
+                        let state = self.emergency_stop(world);
+                        action.response.send(()).expect("Sending response");
+                        return state;
+                    }
                 }
             } else {
                 return self;
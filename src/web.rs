@@ -1,22 +1,41 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use futures::channel::mpsc;
 use futures::SinkExt;
 use rust_embed::RustEmbed;
-use serde::{Deserialize, Serialize, Serializer};
-use tokio::sync::watch;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use tokio::sync::{broadcast, watch};
 use tracing::info;
 use warp::{body, Filter, get, http, log, path, post, reject, Rejection, Reply};
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
 use warp::ws;
 
-use crate::controller::{Address, Battery, Controller, Model};
-use crate::engine::players::PlayerId;
-use crate::games::GameMode;
-use crate::state::{CancelGameError, NoSuchPlayerError, StartGameError, State};
-use crate::state::request::{Actions, Stub};
+use hastilude_core::controller::{Address, Battery, Controller, Firmware, Model};
+use hastilude_core::controller::pairing::{Outcome, PairingResult};
+use hastilude_core::engine::metrics::MetricsReport;
+use hastilude_core::engine::players::{BatteryReport, PlayerId};
+use hastilude_core::engine::scope::Scope;
+use hastilude_core::engine::timing::{Percentiles, TimingReport};
+
+use crate::events::{Event, Events};
+use crate::games::{GameMode, GameOptions};
+use crate::games::best_of;
+use crate::games::elimination::Cause;
+use crate::games::mutators::Mutator;
+use crate::games::scoring::{Record, ScoringReport};
+use crate::games::tournament::Setup;
+use crate::meta::celebration::Style;
+use crate::palette::Theme;
+use crate::profiles::Profiles;
+use crate::recorder::Recorder;
+use crate::state::{CancelGameError, InvalidOptionsError, JoinPlayerError, NoSuchAssetError, NoSuchLanguageError, NoSuchPlayerError, PairControllersError, PauseGameError, PlayerThresholdError, PowerOffError, PowerSave, Settings, StartGameError, State};
+use crate::state::request::Stub;
 
 #[derive(RustEmbed)]
 #[folder = "web/dist/"]
@@ -57,7 +76,42 @@ pub enum GameStateDTO {
         ready: HashSet<PlayerId>,
     },
 
+    /// Nobody is around - the controllers show off until a button is pressed
+    Attract {},
+
+    Teams {
+        /// The team picked by each player so far
+        picks: HashMap<PlayerId, usize>,
+    },
+
     Running {},
+
+    Paused {},
+
+    Celebrating {
+        winners: HashSet<PlayerId>,
+        draw: bool,
+        style: Style,
+    },
+
+    Tournament {
+        mode: GameMode,
+        rounds: usize,
+        played: usize,
+
+        /// Rounds won by each player
+        standings: HashMap<PlayerId, u32>,
+    },
+
+    Match {
+        mode: GameMode,
+        wins: u32,
+        played: usize,
+        intermission: bool,
+
+        /// Rounds won by each player
+        standings: HashMap<PlayerId, u32>,
+    },
 }
 
 impl From<&State> for GameStateDTO {
@@ -66,9 +120,31 @@ impl From<&State> for GameStateDTO {
             State::Lobby(lobby) => Self::Waiting {
                 ready: lobby.ready().clone(),
             },
+            State::Attract(_) => Self::Attract {},
+            State::Teams(selection) => Self::Teams {
+                picks: selection.picks().clone(),
+            },
             State::Countdown(_) => Self::Running {},
             State::Playing(_) => Self::Running {},
-            State::Celebration(_) => Self::Running {},
+            State::Paused(_) => Self::Paused {},
+            State::Celebration(celebration) => Self::Celebrating {
+                winners: celebration.winners().clone(),
+                draw: celebration.is_draw(),
+                style: celebration.style(),
+            },
+            State::Tournament(tournament) => Self::Tournament {
+                mode: tournament.mode(),
+                rounds: tournament.rounds(),
+                played: tournament.played(),
+                standings: tournament.standings(),
+            },
+            State::Match(game) => Self::Match {
+                mode: game.mode(),
+                wins: game.wins(),
+                played: game.played(),
+                intermission: game.intermission(),
+                standings: game.standings(),
+            },
         };
     }
 }
@@ -76,18 +152,36 @@ impl From<&State> for GameStateDTO {
 #[derive(Serialize, Clone, PartialEq)]
 pub struct ControllerInfoDTO {
     pub address: Address,
-    pub signal: f64,
+    pub signal: Option<i8>,
     pub battery: Battery,
+    pub charging_only: bool,
     pub model: Model,
+    pub firmware: Option<Firmware>,
+
+    /// Temperature in whole degrees Celsius
+    pub temperature: Option<f32>,
+
+    pub overheated: bool,
+    pub low_battery: bool,
+
+    /// Scale of the movement thresholds for the player of the controller
+    pub threshold: f32,
 }
 
 impl From<&Controller> for ControllerInfoDTO {
     fn from(controller: &Controller) -> Self {
         return Self {
             address: controller.serial(),
-            signal: 0.0,
+            signal: controller.signal(),
             battery: controller.battery(),
+            charging_only: controller.charging_only(),
             model: controller.model(),
+            firmware: controller.firmware(),
+            // Whole degrees keep the sensor jitter from changing the state on every report
+            temperature: controller.temperature().map(f32::round),
+            overheated: controller.overheated(),
+            low_battery: false,
+            threshold: 1.0,
         };
     }
 }
@@ -95,20 +189,20 @@ impl From<&Controller> for ControllerInfoDTO {
 #[derive(Serialize, Clone, PartialEq)]
 pub struct StateDTO {
     pub mode: GameModeDTO,
+    pub options: GameOptions,
+    pub mutators: HashSet<Mutator>,
+    pub theme: Theme,
     pub state: GameStateDTO,
     pub devices: Vec<ControllerInfoDTO>,
 }
 
-impl Serialize for Address {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        serializer.serialize_str(&self.as_string())
-    }
-}
-
 impl Default for StateDTO {
     fn default() -> Self {
         return Self {
             mode: Default::default(),
+            options: Default::default(),
+            mutators: Default::default(),
+            theme: Default::default(),
             state: GameStateDTO::Waiting {
                 ready: Default::default(),
             },
@@ -117,9 +211,30 @@ impl Default for StateDTO {
     }
 }
 
+/// Connects the web interface to a single arena
+#[derive(Clone)]
+pub struct ArenaHandle {
+    pub name: String,
+    pub stub: Stub,
+    pub info: watch::Receiver<StateDTO>,
+    pub recorder: Recorder,
+    pub events: Events,
+    pub scope: Scope,
+    pub settings: watch::Receiver<Settings>,
+}
+
 pub struct InfoPublisher(watch::Sender<StateDTO>);
 
 impl InfoPublisher {
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(StateDTO::default());
+        return Self(tx);
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<StateDTO> {
+        return self.0.subscribe();
+    }
+
     pub fn publish(&mut self, info: StateDTO) {
         if *self.0.borrow() != info {
             self.0.send_replace(info);
@@ -140,12 +255,381 @@ impl From<GameMode> for GameModeDTO {
     }
 }
 
+#[derive(Serialize)]
+pub struct ModeDTO {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub min_players: usize,
+    pub max_players: Option<usize>,
+    pub options: Option<serde_json::Value>,
+}
+
+impl From<GameMode> for ModeDTO {
+    fn from(mode: GameMode) -> Self {
+        return Self {
+            name: mode.name,
+            description: mode.description,
+            min_players: mode.min_players,
+            max_players: mode.max_players,
+            options: mode.options.as_ref().map(|options| (options.schema)()),
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct PairingResultDTO {
+    pub address: Address,
+    pub outcome: Option<Outcome>,
+    pub error: Option<String>,
+}
+
+impl From<PairingResult> for PairingResultDTO {
+    fn from(result: PairingResult) -> Self {
+        let (outcome, error) = match result.outcome {
+            Ok(outcome) => (Some(outcome), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        return Self {
+            address: result.controller,
+            outcome,
+            error,
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct BatteryReportDTO {
+    pub player: PlayerId,
+    pub address: Address,
+    pub battery: Battery,
+
+    /// Estimated remaining play time in seconds
+    pub remaining: Option<u64>,
+}
+
+impl From<BatteryReport> for BatteryReportDTO {
+    fn from(report: BatteryReport) -> Self {
+        return Self {
+            player: report.player,
+            address: report.address,
+            battery: report.battery,
+            remaining: report.remaining.map(|remaining| remaining.as_secs()),
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct PercentilesDTO<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+    pub max: T,
+}
+
+impl PercentilesDTO<f64> {
+    /// Converts duration percentiles to milliseconds
+    fn millis(percentiles: Percentiles<Duration>) -> Self {
+        return Self {
+            p50: percentiles.p50.as_secs_f64() * 1000.0,
+            p90: percentiles.p90.as_secs_f64() * 1000.0,
+            p99: percentiles.p99.as_secs_f64() * 1000.0,
+            max: percentiles.max.as_secs_f64() * 1000.0,
+        };
+    }
+}
+
+impl From<Percentiles<usize>> for PercentilesDTO<usize> {
+    fn from(percentiles: Percentiles<usize>) -> Self {
+        return Self {
+            p50: percentiles.p50,
+            p90: percentiles.p90,
+            p99: percentiles.p99,
+            max: percentiles.max,
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct TimingDTO {
+    pub frames: PercentilesDTO<f64>,
+    pub work: PercentilesDTO<f64>,
+    pub controllers: PercentilesDTO<f64>,
+    pub backlog: PercentilesDTO<usize>,
+}
+
+impl From<TimingReport> for TimingDTO {
+    fn from(report: TimingReport) -> Self {
+        return Self {
+            frames: PercentilesDTO::millis(report.frames),
+            work: PercentilesDTO::millis(report.work),
+            controllers: PercentilesDTO::millis(report.controllers),
+            backlog: report.backlog.into(),
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct MetricsDTO {
+    pub frames: u64,
+    pub timing: TimingDTO,
+    pub controllers: usize,
+
+    pub updates: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub feedback: u64,
+    pub held: u64,
+}
+
+impl From<MetricsReport> for MetricsDTO {
+    fn from(report: MetricsReport) -> Self {
+        return Self {
+            frames: report.frames,
+            timing: report.timing.into(),
+            controllers: report.controllers,
+            updates: report.counters.updates,
+            errors: report.counters.errors,
+            error_rate: report.counters.error_rate(),
+            feedback: report.counters.feedback,
+            held: report.counters.held,
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScoreDTO {
+    pub player: PlayerId,
+    pub rounds: usize,
+    pub wins: usize,
+    pub eliminations: usize,
+
+    /// Time survived over all rounds in seconds
+    pub survival: f64,
+}
+
+impl From<(PlayerId, Record)> for ScoreDTO {
+    fn from((player, record): (PlayerId, Record)) -> Self {
+        return Self {
+            player,
+            rounds: record.rounds,
+            wins: record.wins,
+            eliminations: record.eliminations,
+            survival: record.survival.as_secs_f64(),
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScoringDTO {
+    pub rounds: usize,
+    pub leaderboard: Vec<ScoreDTO>,
+}
+
+impl From<ScoringReport> for ScoringDTO {
+    fn from(report: ScoringReport) -> Self {
+        return Self {
+            rounds: report.rounds,
+            leaderboard: report.leaderboard.into_iter()
+                .map(ScoreDTO::from)
+                .collect(),
+        };
+    }
+}
+
+#[derive(Serialize)]
+pub struct EliminationDTO {
+    pub player: PlayerId,
+    pub cause: Cause,
+
+    /// Wall clock time of the elimination
+    pub time: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct StartGameDTO {
+    /// Players to start the game with instead of the ready players
+    #[serde(default)]
+    pub players: Option<HashSet<PlayerId>>,
+}
+
+#[derive(Deserialize)]
+pub struct StartTournamentDTO {
+    /// Game mode played in each round instead of the selected game mode
+    #[serde(default)]
+    pub mode: Option<GameMode>,
+
+    pub rounds: usize,
+
+    /// Players taking part instead of the ready players
+    #[serde(default)]
+    pub players: Option<HashSet<PlayerId>>,
+}
+
+impl From<StartTournamentDTO> for Setup {
+    fn from(dto: StartTournamentDTO) -> Self {
+        return Self {
+            mode: dto.mode,
+            rounds: dto.rounds,
+            players: dto.players,
+        };
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StartMatchDTO {
+    /// Game mode played in each round instead of the selected game mode
+    #[serde(default)]
+    pub mode: Option<GameMode>,
+
+    /// Rounds a player must win to win the match
+    pub wins: u32,
+
+    /// Players taking part instead of the ready players
+    #[serde(default)]
+    pub players: Option<HashSet<PlayerId>>,
+}
+
+impl From<StartMatchDTO> for best_of::Setup {
+    fn from(dto: StartMatchDTO) -> Self {
+        return Self {
+            mode: dto.mode,
+            wins: dto.wins,
+            players: dto.players,
+        };
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PowerSaveDTO {
+    pub mode: PowerSave,
+}
+
+#[derive(Deserialize)]
+pub struct ThemeDTO {
+    pub theme: Theme,
+}
+
+#[derive(Deserialize)]
+pub struct BrightnessDTO {
+    /// LED brightness of all controllers in percent
+    pub percent: f32,
+}
+
+#[derive(Deserialize)]
+pub struct ThresholdDTO {
+    /// Scale of the movement thresholds - higher values tolerate more movement
+    pub threshold: f32,
+}
+
+#[derive(Deserialize)]
+pub struct ProfileDTO {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct LanguageDTO {
+    pub language: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct AudioTestDTO {
+    /// Name of the sound effect to play - plays a test tone if missing
+    #[serde(default)]
+    pub sfx: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    JSON,
+    CSV,
+}
+
+#[derive(Deserialize)]
+pub struct ExportDTO {
+    pub format: Option<ExportFormat>,
+}
+
 impl reject::Reject for StartGameError {}
 
+impl reject::Reject for InvalidOptionsError {}
+
+#[derive(Serialize)]
+pub struct ErrorDTO<'a, E> {
+    pub message: String,
+
+    #[serde(flatten)]
+    pub error: &'a E,
+}
+
+impl<'a, E> From<&'a E> for ErrorDTO<'a, E>
+    where
+        E: std::error::Error,
+{
+    fn from(error: &'a E) -> Self {
+        return Self {
+            message: error.to_string(),
+            error,
+        };
+    }
+}
+
+/// Replies with the details of errors the UI explains to the user instead of a bare status
+async fn explain(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    if let Some(err) = rejection.find::<StartGameError>() {
+        return Ok(warp::reply::with_status(warp::reply::json(&ErrorDTO::from(err)), http::StatusCode::CONFLICT));
+    }
+
+    if let Some(err) = rejection.find::<InvalidOptionsError>() {
+        return Ok(warp::reply::with_status(warp::reply::json(&ErrorDTO::from(err)), http::StatusCode::BAD_REQUEST));
+    }
+
+    if let Some(InvalidBody(err)) = rejection.find::<InvalidBody>() {
+        return Ok(warp::reply::with_status(warp::reply::json(&serde_json::json!({ "message": err.to_string() })), http::StatusCode::BAD_REQUEST));
+    }
+
+    return Err(rejection);
+}
+
+/// A request body which is not valid JSON for the endpoint
+#[derive(Debug)]
+struct InvalidBody(serde_json::Error);
+
+impl reject::Reject for InvalidBody {}
+
+/// Parses the JSON body of the request - an empty body is the default value
+fn json_or_default<T>() -> impl Filter<Extract=(T,), Error=Rejection> + Clone
+    where
+        T: DeserializeOwned + Default + Send,
+{
+    return body::bytes().and_then(|bytes: warp::hyper::body::Bytes| async move {
+        if bytes.is_empty() {
+            return Ok(T::default());
+        }
+
+        return serde_json::from_slice(&bytes).map_err(|err| reject::custom(InvalidBody(err)));
+    });
+}
+
 impl reject::Reject for CancelGameError {}
 
+impl reject::Reject for PauseGameError {}
+
 impl reject::Reject for NoSuchPlayerError {}
 
+impl reject::Reject for JoinPlayerError {}
+
+impl reject::Reject for PairControllersError {}
+
+impl reject::Reject for PlayerThresholdError {}
+
+impl reject::Reject for PowerOffError {}
+
+impl reject::Reject for NoSuchAssetError {}
+
+impl reject::Reject for NoSuchLanguageError {}
+
 fn mode_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
     return post()
         .map(move || stub.clone())
@@ -157,12 +641,135 @@ fn mode_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Cl
         });
 }
 
+fn language_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("language"))
+        .and(body::json())
+        .and_then(|mut stub: Stub, body: LanguageDTO| async move {
+            return match stub.language(body.language).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn profile_set(stub: Stub, profiles: Arc<Profiles>) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || (stub.clone(), profiles.clone()))
+        .and(path!("profile"))
+        .and(body::json())
+        .and_then(|(mut stub, profiles): (Stub, Arc<Profiles>), body: ProfileDTO| async move {
+            let profile = profiles.get(&body.name)
+                .ok_or_else(reject::not_found)?;
+
+            stub.profile(profile.clone()).await;
+            return Ok::<_, Rejection>(http::StatusCode::OK);
+        });
+}
+
+fn profiles_list(profiles: Arc<Profiles>) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .and(path!("profiles"))
+        .map(move || warp::reply::json(profiles.as_ref()));
+}
+
+fn modes_list() -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .and(path!("modes"))
+        .map(|| warp::reply::json(&GameMode::all().map(ModeDTO::from).collect::<Vec<_>>()));
+}
+
+fn power_save_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("power-save"))
+        .and(body::json())
+        .then(|mut stub: Stub, body: PowerSaveDTO| async move {
+            stub.power_save(body.mode).await;
+            return http::StatusCode::OK;
+        });
+}
+
+fn theme_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("theme"))
+        .and(body::json())
+        .then(|mut stub: Stub, body: ThemeDTO| async move {
+            stub.theme(body.theme).await;
+            return http::StatusCode::OK;
+        });
+}
+
+fn brightness_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("brightness"))
+        .and(body::json())
+        .then(|mut stub: Stub, body: BrightnessDTO| async move {
+            stub.brightness(body.percent / 100.0).await;
+            return http::StatusCode::OK;
+        });
+}
+
+fn options_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("mode" / "options"))
+        .and(body::json())
+        .and_then(|mut stub: Stub, body: GameOptions| async move {
+            return match stub.game_options(body).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn mutators_set(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("mutators"))
+        .and(body::json())
+        .then(|mut stub: Stub, body: HashSet<Mutator>| async move {
+            stub.mutators(body).await;
+            return http::StatusCode::OK;
+        });
+}
+
 fn game_start(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
     return post()
         .map(move || stub.clone())
         .and(path!("game" / "start"))
-        .and_then(|mut stub: Stub| async move {
-            return match stub.start_game().await {
+        .and(json_or_default())
+        .and_then(|mut stub: Stub, body: StartGameDTO| async move {
+            return match stub.start_game(body.players).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn tournament_start(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("tournament" / "start"))
+        .and(body::json())
+        .and_then(|mut stub: Stub, body: StartTournamentDTO| async move {
+            return match stub.start_tournament(body.into()).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn match_start(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("match" / "start"))
+        .and(body::json())
+        .and_then(|mut stub: Stub, body: StartMatchDTO| async move {
+            return match stub.start_match(body.into()).await {
                 Ok(()) => Ok(http::StatusCode::OK),
                 Err(err) => Err(reject::custom(err)),
             };
@@ -181,6 +788,30 @@ fn game_cancel(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> +
         });
 }
 
+fn game_pause(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("game" / "pause"))
+        .and_then(|mut stub: Stub| async move {
+            return match stub.pause_game().await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn game_resume(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("game" / "resume"))
+        .and_then(|mut stub: Stub| async move {
+            return match stub.resume_game().await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
 fn player_buzz(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
     return post()
         .map(move || stub.clone())
@@ -205,6 +836,135 @@ fn player_kick(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> +
         });
 }
 
+fn player_join(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("game" / PlayerId / "join"))
+        .and_then(|mut stub: Stub, player_id: PlayerId| async move {
+            return match stub.join_player(player_id).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn player_threshold(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("player" / PlayerId / "threshold"))
+        .and(body::json())
+        .and_then(|mut stub: Stub, player_id: PlayerId, body: ThresholdDTO| async move {
+            return match stub.player_threshold(player_id, body.threshold).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn player_power_off(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("player" / PlayerId / "power-off"))
+        .and_then(|mut stub: Stub, player_id: PlayerId| async move {
+            return match stub.power_off_player(player_id).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn controller_pair(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("controller" / "pair"))
+        .and_then(|mut stub: Stub| async move {
+            return match stub.pair_controllers().await {
+                Ok(results) => Ok(warp::reply::json(&results.into_iter()
+                    .map(PairingResultDTO::from)
+                    .collect::<Vec<_>>())),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn batteries(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .map(move || stub.clone())
+        .and(path!("batteries"))
+        .then(|mut stub: Stub| async move {
+            return warp::reply::json(&stub.batteries().await.into_iter()
+                .map(BatteryReportDTO::from)
+                .collect::<Vec<_>>());
+        });
+}
+
+fn diagnostics_timing(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .map(move || stub.clone())
+        .and(path!("diagnostics" / "timing"))
+        .then(|mut stub: Stub| async move {
+            return warp::reply::json(&TimingDTO::from(stub.timing().await));
+        });
+}
+
+fn metrics(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .map(move || stub.clone())
+        .and(path!("metrics"))
+        .then(|mut stub: Stub| async move {
+            return warp::reply::json(&MetricsDTO::from(stub.metrics().await));
+        });
+}
+
+fn session_scores(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .map(move || stub.clone())
+        .and(path!("session" / "scores"))
+        .then(|mut stub: Stub| async move {
+            return warp::reply::json(&ScoringDTO::from(stub.scoring().await));
+        });
+}
+
+fn audio_test(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("audio" / "test"))
+        .and(json_or_default())
+        .and_then(|mut stub: Stub, body: AudioTestDTO| async move {
+            return match stub.test_audio(body.sfx).await {
+                Ok(()) => Ok(http::StatusCode::OK),
+                Err(err) => Err(reject::custom(err)),
+            };
+        });
+}
+
+fn emergency_stop(stub: Stub) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return post()
+        .map(move || stub.clone())
+        .and(path!("emergency-stop"))
+        .then(|mut stub: Stub| async move {
+            stub.emergency_stop().await;
+            return http::StatusCode::OK;
+        });
+}
+
+fn events_export(recorder: Recorder) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .map(move || recorder.clone())
+        .and(path!("events" / "export"))
+        .and(warp::query::<ExportDTO>())
+        .map(|recorder: Recorder, query: ExportDTO| {
+            let (content_type, body) = match query.format.unwrap_or(ExportFormat::JSON) {
+                ExportFormat::JSON => ("application/json", recorder.to_json()),
+                ExportFormat::CSV => ("text/csv", recorder.to_csv()),
+            };
+
+            return http::Response::builder()
+                .header("Content-Type", content_type)
+                .body(body);
+        });
+}
+
 fn state(rx: watch::Receiver<StateDTO>) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
     return ws()
         .and(path!("state"))
@@ -228,23 +988,147 @@ fn state(rx: watch::Receiver<StateDTO>) -> impl Filter<Extract=impl Reply, Error
         });
 }
 
-pub fn serve() -> Result<(impl Future<Output=()>, mpsc::Receiver<Actions>, InfoPublisher)> {
-    let addr: SocketAddr = "0.0.0.0:3000".parse()?;
+/// Sends the current settings and again whenever they are changed
+fn settings(rx: watch::Receiver<Settings>) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return ws()
+        .and(path!("settings"))
+        .map(move |ws: ws::Ws| {
+            let mut rx = rx.clone();
+            ws.on_upgrade(|mut ws| async move {
+                loop {
+                    let settings = rx.borrow_and_update().clone();
+                    let settings = serde_json::to_string(&settings)
+                        .expect("Failed to serialize settings message");
+
+                    if let Err(_) = ws.send(ws::Message::text(settings)).await {
+                        break;
+                    }
+
+                    if let Err(_) = rx.changed().await {
+                        break;
+                    }
+                }
+            })
+        });
+}
 
-    let (stub, requests) = Stub::create();
+/// Notifies about every player eliminated from now on
+fn eliminations(events: Events) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return ws()
+        .and(path!("eliminations"))
+        .map(move |ws: ws::Ws| {
+            let mut rx = events.subscribe();
+            ws.on_upgrade(|mut ws| async move {
+                loop {
+                    let (player, cause, time) = match rx.recv().await {
+                        Ok(Event::PlayerEliminated { player, cause, time }) => (player, cause, time),
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
 
-    let (info_publisher, info_watch) = watch::channel(StateDTO::default());
-    let info_publisher = InfoPublisher(info_publisher);
+                    let elimination = serde_json::to_string(&EliminationDTO {
+                        player,
+                        cause,
+                        time: humantime::format_rfc3339_millis(time).to_string(),
+                    }).expect("Failed to serialize elimination message");
 
-    let api = mode_set(stub.clone())
+                    if let Err(_) = ws.send(ws::Message::text(elimination)).await {
+                        break;
+                    }
+                }
+            })
+        });
+}
+
+/// Streams the sensor values of a controller while the debug game is running
+fn scope(scope: Scope) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return ws()
+        .and(path!("scope" / PlayerId))
+        .map(move |ws: ws::Ws, player: PlayerId| {
+            let mut rx = scope.subscribe();
+            ws.on_upgrade(move |mut ws| async move {
+                loop {
+                    let sample = match rx.recv().await {
+                        Ok(sample) if sample.player == player => sample,
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+
+                    let sample = serde_json::to_string(&sample).expect("Failed to serialize sample message");
+
+                    if let Err(_) = ws.send(ws::Message::text(sample)).await {
+                        break;
+                    }
+                }
+            })
+        });
+}
+
+fn arenas(names: Vec<String>) -> impl Filter<Extract=impl Reply, Error=Rejection> + Clone {
+    return get()
+        .and(path!("arenas"))
+        .map(move || warp::reply::json(&names));
+}
+
+fn arena(arena: ArenaHandle, profiles: Arc<Profiles>) -> BoxedFilter<(Response, )> {
+    let stub = arena.stub;
+
+    return mode_set(stub.clone())
+        .or(language_set(stub.clone()))
+        .or(profile_set(stub.clone(), profiles))
+        .or(power_save_set(stub.clone()))
+        .or(theme_set(stub.clone()))
+        .or(brightness_set(stub.clone()))
+        .or(options_set(stub.clone()))
+        .or(mutators_set(stub.clone()))
         .or(game_start(stub.clone()))
+        .or(tournament_start(stub.clone()))
+        .or(match_start(stub.clone()))
         .or(game_cancel(stub.clone()))
+        .or(game_pause(stub.clone()))
+        .or(game_resume(stub.clone()))
         .or(player_buzz(stub.clone()))
         .or(player_kick(stub.clone()))
-        .or(state(info_watch));
+        .or(player_join(stub.clone()))
+        .or(player_threshold(stub.clone()))
+        .or(player_power_off(stub.clone()))
+        .or(controller_pair(stub.clone()))
+        .or(batteries(stub.clone()))
+        .or(diagnostics_timing(stub.clone()))
+        .or(metrics(stub.clone()))
+        .or(session_scores(stub.clone()))
+        .or(audio_test(stub.clone()))
+        .or(emergency_stop(stub.clone()))
+        .or(events_export(arena.recorder))
+        .or(eliminations(arena.events))
+        .or(scope(arena.scope))
+        .or(state(arena.info))
+        .or(settings(arena.settings))
+        .map(Reply::into_response)
+        .boxed();
+}
+
+/// Serves the API of each arena below `/api/arena/<name>` and of the first arena below `/api`
+pub fn serve(addr: SocketAddr, arenas: Vec<ArenaHandle>, profiles: Arc<Profiles>) -> Result<impl Future<Output=()>> {
+    let primary = arena(arenas.first().expect("At least one arena").clone(), profiles.clone());
+
+    let scoped = arenas.iter()
+        .map(|handle| path("arena")
+            .and(path(handle.name.clone()))
+            .and(arena(handle.clone(), profiles.clone()))
+            .boxed())
+        .reduce(|a, b| a.or(b).unify().boxed())
+        .expect("At least one arena");
+
+    let api = self::arenas(arenas.iter().map(|handle| handle.name.clone()).collect())
+        .or(profiles_list(profiles))
+        .or(modes_list())
+        .or(scoped)
+        .or(primary);
 
     let api = path("api")
         .and(api)
+        .recover(explain)
         .with(log::log("api"));
 
     let routes = Filter::or(
@@ -255,5 +1139,5 @@ pub fn serve() -> Result<(impl Future<Output=()>, mpsc::Receiver<Actions>, InfoP
 
     info!("Web-Server listening on {}", addr);
 
-    return Ok((server, requests, info_publisher));
-}
\ No newline at end of file
+    return Ok(server);
+}